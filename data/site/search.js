@@ -0,0 +1,39 @@
+// The search box of the index page, run by search.wasm (built from wasm/ in the zngn repository) over
+// search-index.tsv. Browsers don't fetch files from file:// URLs, so the site has to be served over HTTP.
+(async function () {
+  const input = document.getElementById("search");
+  const list = document.getElementById("hits");
+  const fetched = (path) => fetch(path).then((response) => response.arrayBuffer());
+  const [wasm, index] = await Promise.all([fetched("search.wasm"), fetched("search-index.tsv")]);
+  const zngn = (await WebAssembly.instantiate(wasm)).instance.exports;
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+  // Copies bytes into the module's memory and calls `f` with where they are.
+  const passing = (bytes, f) => {
+    const ptr = zngn.alloc(bytes.length);
+    new Uint8Array(zngn.memory.buffer, ptr, bytes.length).set(bytes);
+    const result = f(ptr, bytes.length);
+    zngn.dealloc(ptr, bytes.length);
+    return result;
+  };
+  passing(new Uint8Array(index), zngn.load);
+  input.disabled = false;
+  input.addEventListener("input", () => {
+    list.textContent = "";
+    const query = input.value.trim();
+    if (query === "") {
+      return;
+    }
+    passing(encoder.encode(query), (ptr, len) => zngn.search_index(ptr, len, 100));
+    const output = decoder.decode(new Uint8Array(zngn.memory.buffer, zngn.output_ptr(), zngn.output_len()));
+    for (const line of output.split("\n").filter((line) => line !== "")) {
+      const [bankCode, branchCode, name, phonetic, bankName] = line.split("\t");
+      const link = document.createElement("a");
+      link.href = "banks/" + bankCode + ".html" + (branchCode ? "#b-" + branchCode : "");
+      link.textContent = branchCode ? bankName + " " + name + " " + bankCode + "-" + branchCode : name + " " + bankCode;
+      const item = document.createElement("li");
+      item.append(link, " ", phonetic);
+      list.append(item);
+    }
+  });
+})();
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::{Bank, BankCode, Error};
+
+// Former names of banks that renamed or merged, keyed by the code of the bank they became.
+const BUNDLED: &str = include_str!("../data/aliases.json");
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct Alias {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phonetic: Option<String>,
+    // Date (YYYY-MM-DD) the name stopped being used, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+}
+
+impl Alias {
+    pub fn matches(&self, query: &str) -> bool {
+        self.name.contains(query) || self.phonetic.as_deref().is_some_and(|p| p.contains(query))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Aliases(HashMap<BankCode, Vec<Alias>>);
+
+impl Aliases {
+    pub fn bundled() -> Self {
+        Self(serde_json::from_str(BUNDLED).unwrap_or_default())
+    }
+
+    // The bundled table extended with `aliases.json` in the output directory, if there is one.
+    pub fn load(layout: &Layout) -> Result<Self, Error> {
+        let mut aliases = Self::bundled();
+        let path = layout.aliases_file();
+        if path.exists() {
+            let file = File::open(&path).map_err(Error::file(&path))?;
+            let user: HashMap<BankCode, Vec<Alias>> = serde_json::from_reader(file).map_err(Error::invalid(&path))?;
+            for (code, entries) in user {
+                let known = aliases.0.entry(code).or_default();
+                for alias in entries {
+                    if !known.iter().any(|k| k.name == alias.name) {
+                        known.push(alias);
+                    }
+                }
+            }
+        }
+        Ok(aliases)
+    }
+
+    pub fn apply(&self, banks: &mut [Bank]) {
+        for bank in banks {
+            if let Some(aliases) = self.0.get(&bank.code) {
+                bank.aliases = aliases.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn bundled_aliases_test() {
+        use crate::aliases::Aliases;
+        use crate::search::{search, Hit};
+        use crate::Bank;
+
+        let mut banks = vec![Bank::new("ＰａｙＰａｙ銀行".to_owned(), "ﾍﾟｲﾍﾟｲ".to_owned(), "0033".to_owned(), "0x033".to_owned())];
+        Aliases::bundled().apply(&mut banks);
+        assert_eq!(banks[0].aliases[0].name, "ジャパンネット銀行");
+        assert_eq!(search(&banks, "ジャパンネット").collect::<Vec<Hit>>(), vec![Hit::Bank(&banks[0])]);
+    }
+}
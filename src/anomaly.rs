@@ -0,0 +1,59 @@
+use serde::Serialize;
+
+use crate::layout::Layout;
+use crate::{load_branch_file, Bank, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Counted {
+    Banks,
+    Branches,
+}
+
+// Fails when `current` is more than `max_drop` percent below `previous`. A site change that breaks
+// the parsers shows up as a crawl that finds far less than last time, and must not replace good data.
+pub fn check(counted: Counted, previous: usize, current: usize, max_drop: f64) -> Result<(), Error> {
+    let floor = previous as f64 * (1.0 - max_drop / 100.0);
+    if (current as f64) < floor {
+        return Err(Error::CountDropped {
+            counted,
+            previous,
+            current,
+        });
+    }
+    Ok(())
+}
+
+// Compares the branches about to be written with the files they replace. Banks without a saved
+// file are new and left out on both sides.
+pub fn check_branches(banks: &[Bank], layout: &Layout, max_drop: f64) -> Result<(), Error> {
+    let mut previous = 0;
+    let mut current = 0;
+    for bank in banks {
+        if let Ok(saved) = load_branch_file(layout, bank) {
+            previous += saved.branches.len();
+            current += bank.branches.len();
+        }
+    }
+    check(Counted::Branches, previous, current, max_drop)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_test() {
+        use crate::anomaly::{check, Counted};
+        use crate::Error;
+
+        assert!(check(Counted::Banks, 1000, 900, 10.0).is_ok());
+        assert!(check(Counted::Banks, 1000, 1200, 10.0).is_ok());
+        assert!(check(Counted::Banks, 0, 0, 10.0).is_ok());
+        match check(Counted::Branches, 1000, 899, 10.0) {
+            Err(Error::CountDropped { counted, previous, current }) => {
+                assert_eq!((counted, previous, current), (Counted::Branches, 1000, 899))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(check(Counted::Banks, 1000, 0, 100.0).is_ok());
+    }
+}
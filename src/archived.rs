@@ -0,0 +1,177 @@
+use std::fs::{self, File};
+
+use memmap2::Mmap;
+use rkyv::string::ArchivedString;
+
+use crate::layout::Layout;
+use crate::{Bank, BankCode, BranchCode, Error};
+
+#[derive(rkyv::Archive, rkyv::Serialize)]
+#[archive(check_bytes)]
+struct Dataset {
+    // Ordered by code, so lookups can bisect.
+    banks: Vec<BankRecord>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize)]
+#[archive(check_bytes)]
+struct BankRecord {
+    code: String,
+    name: String,
+    phonetic: String,
+    branches: Vec<BranchRecord>,
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize)]
+#[archive(check_bytes)]
+struct BranchRecord {
+    code: String,
+    name: String,
+    phonetic: String,
+}
+
+// Borrowed straight out of the mapped file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BankView<'a> {
+    pub code: &'a str,
+    pub name: &'a str,
+    pub phonetic: &'a str,
+    pub branches: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BranchView<'a> {
+    pub bank: BankView<'a>,
+    pub code: &'a str,
+    pub name: &'a str,
+    pub phonetic: &'a str,
+}
+
+fn bank_view(bank: &ArchivedBankRecord) -> BankView<'_> {
+    BankView {
+        code: &bank.code,
+        name: &bank.name,
+        phonetic: &bank.phonetic,
+        branches: bank.branches.len(),
+    }
+}
+
+// Writes the archive `MappedDataset` reads and returns its size.
+pub fn write(banks: &[Bank], layout: &Layout) -> Result<u64, Error> {
+    let mut banks = banks
+        .iter()
+        .map(|bank| BankRecord {
+            code: bank.code.0.clone(),
+            name: bank.name.clone(),
+            phonetic: bank.phonetic.clone(),
+            branches: bank
+                .branches
+                .iter()
+                .map(|branch| BranchRecord {
+                    code: branch.code.clone(),
+                    name: branch.name.to_string(),
+                    phonetic: branch.phonetic.to_string(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<BankRecord>>();
+    banks.sort_by(|a, b| a.code.cmp(&b.code));
+    let bytes = rkyv::to_bytes::<_, 4096>(&Dataset { banks })
+        .map_err(|e| Error::ArchivedDatasetFailed(e.to_string()))?;
+    fs::write(layout.archived_file(), &bytes).map_err(Error::file(layout.archived_file()))?;
+    Ok(bytes.len() as u64)
+}
+
+// The archived dataset mapped into memory. Nothing is deserialized: queries read the mapped bytes,
+// so opening costs one validation pass over the file and no allocations.
+pub struct MappedDataset {
+    mmap: Mmap,
+}
+
+impl MappedDataset {
+    // None when there is no archive, or it is older than the JSON snapshot.
+    pub fn open(layout: &Layout) -> Result<Option<Self>, Error> {
+        let path = layout.archived_file();
+        let archived_at = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return Ok(None),
+        };
+        let saved_at = fs::metadata(layout.banks_file()).and_then(|metadata| metadata.modified());
+        if saved_at.is_ok_and(|saved_at| saved_at > archived_at) {
+            return Ok(None);
+        }
+        let file = File::open(&path).map_err(Error::file(&path))?;
+        // Safety: the archive is only ever replaced as a whole by `write`, never modified in place.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(Error::file(&path))?;
+        rkyv::check_archived_root::<Dataset>(&mmap[..])
+            .map_err(|e| Error::ArchivedDatasetFailed(e.to_string()))?;
+        Ok(Some(Self { mmap }))
+    }
+
+    fn dataset(&self) -> &ArchivedDataset {
+        // Safety: validated in `open`, and the mapping is read only.
+        unsafe { rkyv::archived_root::<Dataset>(&self.mmap[..]) }
+    }
+
+    fn find(&self, code: &str) -> Option<&ArchivedBankRecord> {
+        let code = code.parse::<BankCode>().ok()?;
+        let banks = &self.dataset().banks;
+        banks
+            .binary_search_by(|bank| ArchivedString::as_str(&bank.code).cmp(&code.0))
+            .ok()
+            .map(|i| &banks[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.dataset().banks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn lookup_bank(&self, code: &str) -> Option<BankView<'_>> {
+        self.find(code).map(bank_view)
+    }
+
+    pub fn lookup_branch(&self, bank_code: &str, branch_code: &str) -> Option<BranchView<'_>> {
+        let bank = self.find(bank_code)?;
+        let branch_code = branch_code.parse::<BranchCode>().ok()?;
+        let branch = bank.branches.iter().find(|branch| branch.code == branch_code.0)?;
+        Some(BranchView {
+            bank: bank_view(bank),
+            code: &branch.code,
+            name: &branch.name,
+            phonetic: &branch.phonetic,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn mapped_lookup_test() {
+        use std::path::PathBuf;
+        use crate::archived::{write, MappedDataset};
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::{Bank, Branch};
+
+        let out = std::env::temp_dir().join(format!("zngn-archived-{}", std::process::id()));
+        std::fs::create_dir_all(&out).unwrap();
+        let layout = Layout::new(PathBuf::from(&out), DEFAULT_TEMPLATE.to_owned()).unwrap();
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        write(&[neko, inu], &layout).unwrap();
+
+        let dataset = MappedDataset::open(&layout).unwrap().unwrap();
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.lookup_bank("0111").unwrap().name, "いぬ銀行");
+        let branch = dataset.lookup_branch("0222", "001").unwrap();
+        assert_eq!((branch.bank.name, branch.name), ("ねこ銀行", "本店"));
+        assert!(dataset.lookup_bank("0333").is_none());
+        assert!(dataset.lookup_branch("0222", "002").is_none());
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+}
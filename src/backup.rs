@@ -0,0 +1,178 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Archive, Builder, Header};
+
+use crate::layout::{LOCK_FILE, MANIFEST_FILE, SIGNATURE_FILE};
+use crate::manifest::Manifest;
+use crate::Error;
+
+// The saved manifest.json, byte for byte so its signature still holds, when it describes `dir`;
+// otherwise a fresh manifest.
+fn manifest_of(dir: &Path) -> Result<(Manifest, Vec<u8>), Error> {
+    if let Ok(json) = fs::read(dir.join(MANIFEST_FILE)) {
+        if let Ok(manifest) = serde_json::from_slice::<Manifest>(&json) {
+            if manifest.verify(dir)?.is_empty() {
+                return Ok((manifest, json));
+            }
+        }
+    }
+    let manifest = Manifest::build(dir)?;
+    let json = serde_json::to_vec_pretty(&manifest).map_err(Error::SerializeFailed)?;
+    Ok((manifest, json))
+}
+
+// Writes `dir` to a gzipped tarball, with its manifest as the first entry. The signature comes
+// along when the saved manifest it signs is the one archived.
+pub fn backup(dir: &Path, archive: &Path) -> Result<Manifest, Error> {
+    let (manifest, json) = manifest_of(dir)?;
+    let signed = fs::read(dir.join(MANIFEST_FILE)).is_ok_and(|saved| saved == json);
+    let file = File::create(archive).map_err(Error::BackupFailed)?;
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut header = Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_FILE, &json[..]).map_err(Error::BackupFailed)?;
+    for path in manifest.files.keys() {
+        builder.append_path_with_name(dir.join(path), path).map_err(Error::BackupFailed)?;
+    }
+    if signed && dir.join(SIGNATURE_FILE).exists() {
+        builder
+            .append_path_with_name(dir.join(SIGNATURE_FILE), SIGNATURE_FILE)
+            .map_err(Error::BackupFailed)?;
+    }
+    builder.into_inner().and_then(|gz| gz.finish()).map_err(Error::BackupFailed)?;
+    Ok(manifest)
+}
+
+// A scratch directory next to `dir`, on the same file system so its entries can be renamed into it.
+pub fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    dir.with_file_name(format!(".{}.{}-{}", name, suffix, std::process::id()))
+}
+
+// Unpacks a backup into `dir`, replacing what was there. The archive is unpacked and checked next to
+// `dir` first, so a corrupt backup leaves `dir` untouched.
+pub fn restore(archive: &Path, dir: &Path) -> Result<Manifest, Error> {
+    let staging = sibling(dir, "restoring");
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging).map_err(Error::BackupFailed)?;
+    let unpacked = unpack(archive, &staging);
+    let manifest = match unpacked {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    swap_in(&staging, dir)?;
+    Ok(manifest)
+}
+
+// Replaces `dir` with `staging` by renaming whole directories, so `dir` is never a mix of the two.
+// The old copy is moved aside and only deleted once the new one is in place, and moved back if that
+// fails. No portable rename swaps two directories, so there is a moment without `dir` in between.
+pub fn swap_in(staging: &Path, dir: &Path) -> Result<(), Error> {
+    // The lock belongs to whoever is swapping, and stays held on the new copy.
+    let lock = dir.join(LOCK_FILE);
+    if lock.exists() {
+        fs::copy(&lock, staging.join(LOCK_FILE)).map_err(Error::BackupFailed)?;
+    }
+    if !dir.exists() {
+        if let Some(parent) = dir.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(Error::BackupFailed)?;
+        }
+        return fs::rename(staging, dir).map_err(Error::BackupFailed);
+    }
+    let replaced = sibling(dir, "replaced");
+    let _ = fs::remove_dir_all(&replaced);
+    fs::rename(dir, &replaced).map_err(Error::BackupFailed)?;
+    if let Err(e) = fs::rename(staging, dir) {
+        let _ = fs::rename(&replaced, dir);
+        return Err(Error::BackupFailed(e));
+    }
+    let _ = fs::remove_dir_all(&replaced);
+    Ok(())
+}
+
+fn unpack(archive: &Path, staging: &Path) -> Result<Manifest, Error> {
+    let file = File::open(archive).map_err(Error::BackupFailed)?;
+    Archive::new(GzDecoder::new(file)).unpack(staging).map_err(Error::BackupFailed)?;
+    let manifest = Manifest::load(&staging.join(MANIFEST_FILE))?;
+    let mismatches = manifest.verify(staging)?;
+    if !mismatches.is_empty() {
+        return Err(Error::ChecksumMismatch(mismatches));
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn backup_restore_test() {
+        use std::fs;
+        use crate::backup::{backup, restore};
+        use crate::Error;
+
+        let root = std::env::temp_dir().join(format!("zngn-backup-{}", std::process::id()));
+        let dir = root.join("dest");
+        fs::create_dir_all(dir.join("0")).unwrap();
+        fs::write(dir.join("banks.json"), r#"{"0001":{}}"#).unwrap();
+        fs::write(dir.join("0").join("0001.json"), "{}").unwrap();
+        let archive = root.join("dest.tar.gz");
+        let manifest = backup(&dir, &archive).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+
+        fs::write(dir.join("banks.json"), "{}").unwrap();
+        fs::write(dir.join("stray.json"), "{}").unwrap();
+        restore(&archive, &dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("banks.json")).unwrap(), r#"{"0001":{}}"#);
+        assert!(dir.join("0").join("0001.json").exists());
+        assert!(dir.join("manifest.json").exists());
+        assert!(!dir.join("stray.json").exists());
+
+        fs::write(&archive, "not a tarball").unwrap();
+        assert!(matches!(restore(&archive, &dir), Err(Error::BackupFailed(_))));
+        assert!(dir.join("banks.json").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn swap_in_test() {
+        use std::fs;
+        use crate::backup::{sibling, swap_in};
+        use crate::layout::LOCK_FILE;
+        use crate::Error;
+
+        let root = std::env::temp_dir().join(format!("zngn-swap-in-{}", std::process::id()));
+        let (dir, staging) = (root.join("dest"), root.join("staged"));
+        fs::create_dir_all(dir.join("0")).unwrap();
+        fs::write(dir.join("banks.json"), "old").unwrap();
+        fs::write(dir.join("0").join("0001.json"), "old").unwrap();
+        fs::write(dir.join(LOCK_FILE), "1234").unwrap();
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(staging.join("banks.json"), "new").unwrap();
+
+        swap_in(&staging, &dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("banks.json")).unwrap(), "new");
+        assert!(!dir.join("0").exists());
+        assert_eq!(fs::read_to_string(dir.join(LOCK_FILE)).unwrap(), "1234");
+        assert!(!staging.exists() && !sibling(&dir, "replaced").exists());
+
+        // A staging directory that can't be moved in leaves the old copy where it was.
+        fs::remove_file(dir.join(LOCK_FILE)).unwrap();
+        assert!(matches!(swap_in(&root.join("missing"), &dir), Err(Error::BackupFailed(_))));
+        assert_eq!(fs::read_to_string(dir.join("banks.json")).unwrap(), "new");
+
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(staging.join("banks.json"), "first").unwrap();
+        swap_in(&staging, &root.join("fresh")).unwrap();
+        assert_eq!(fs::read_to_string(root.join("fresh").join("banks.json")).unwrap(), "first");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
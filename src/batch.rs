@@ -0,0 +1,553 @@
+use std::io::{Read, Write};
+
+use csv::StringRecord;
+use serde::Serialize;
+
+use crate::charset;
+use crate::transfer::Problem;
+use crate::{Bank, BankCode, Branch, BranchCode, Error};
+
+// Headers looked for when a column isn't named explicitly, compared ignoring case.
+const BANK_CODE_HEADERS: [&str; 5] = ["bank_code", "bank", "金融機関コード", "銀行コード", "銀行番号"];
+const BRANCH_CODE_HEADERS: [&str; 4] = ["branch_code", "branch", "支店コード", "支店番号"];
+const BANK_NAME_HEADERS: [&str; 3] = ["bank_name", "金融機関名", "銀行名"];
+const BRANCH_NAME_HEADERS: [&str; 2] = ["branch_name", "支店名"];
+
+// Columns appended to every row of the output.
+pub const ADDED_HEADERS: [&str; 6] = [
+    "resolved_bank_code",
+    "resolved_bank_name",
+    "resolved_branch_code",
+    "resolved_branch_name",
+    "status",
+    "reason",
+];
+
+// A name closer than this to none of the records resolves to nothing.
+const MIN_SCORE: f32 = 0.5;
+// How far ahead of the runner-up a name that isn't an exact match must be to be taken as the one meant.
+const MARGIN: f32 = 0.2;
+// Candidates listed for a row left for review.
+const MAX_CANDIDATES: usize = 5;
+// What vendor master data adds to a bank name, and to a branch name.
+const BANK_NOISE: [&str; 4] = ["株式会社", "(株)", "㈱", " "];
+const BRANCH_NOISE: [&str; 4] = ["支店", "ｼﾃﾝ", "出張所", " "];
+
+// Headers of the columns to check, for files whose headers aren't among the ones recognized.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnNames {
+    pub bank_code: Option<String>,
+    pub branch_code: Option<String>,
+    pub bank_name: Option<String>,
+    pub branch_name: Option<String>,
+}
+
+// Positions of the checked columns in a row. A file needs the code or the name of both the bank and
+// the branch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Columns {
+    bank_code: Option<usize>,
+    branch_code: Option<usize>,
+    bank_name: Option<usize>,
+    branch_name: Option<usize>,
+}
+
+impl Columns {
+    fn find(headers: &StringRecord, names: &ColumnNames) -> Result<Self, Error> {
+        let position = |name: &Option<String>, known: &[&str]| match name {
+            Some(name) => headers
+                .iter()
+                .position(|header| header.trim() == name)
+                .ok_or_else(|| Error::BatchFailed(format!("no column {:?} in the header", name)))
+                .map(Some),
+            None => Ok(headers
+                .iter()
+                .position(|header| known.iter().any(|known| header.trim().eq_ignore_ascii_case(known)))),
+        };
+        let columns = Self {
+            bank_code: position(&names.bank_code, &BANK_CODE_HEADERS)?,
+            branch_code: position(&names.branch_code, &BRANCH_CODE_HEADERS)?,
+            bank_name: position(&names.bank_name, &BANK_NAME_HEADERS)?,
+            branch_name: position(&names.branch_name, &BRANCH_NAME_HEADERS)?,
+        };
+        for (code, name, what) in [
+            (columns.bank_code, columns.bank_name, "bank"),
+            (columns.branch_code, columns.branch_name, "branch"),
+        ]
+        .iter()
+        {
+            if code.is_none() && name.is_none() {
+                return Err(Error::BatchFailed(format!(
+                    "no {} code or name column in the header; name one with --{}-column or --{}-name-column",
+                    what, what, what
+                )));
+            }
+        }
+        Ok(columns)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    // The codes exist, but a name in the row is a former one, or a code was resolved from a name that
+    // only resembles the record's.
+    Warning,
+    // A name resembles several records about equally, so which one is meant is left to a person.
+    Review,
+    Error,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Review => "review",
+            Status::Error => "error",
+        }
+    }
+}
+
+// What `check` found for one row.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    pub bank_code: Option<BankCode>,
+    pub bank_name: Option<String>,
+    pub branch_code: Option<String>,
+    pub branch_name: Option<String>,
+    // Whether a code was missing from the row and found from a name.
+    pub resolved: bool,
+    pub status: Status,
+    pub problems: Vec<Problem>,
+}
+
+// A name as written in a payout file matches when it is the name or, in any kana, the reading.
+fn same_name(given: &str, name: &str, phonetic: &str) -> bool {
+    let given = given.trim();
+    given == name || charset::normalize(given) == phonetic
+}
+
+// A name with its kana in one form and without the words in `noise`, for comparing names written
+// differently.
+fn name_key(text: &str, noise: &[&str]) -> String {
+    let mut key = charset::normalize(text);
+    for word in noise {
+        key = key.replace(word, "");
+    }
+    key
+}
+
+fn bigrams(text: &str) -> Vec<(char, char)> {
+    let chars = text.chars().collect::<Vec<char>>();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+// How alike a given key is to a record's, from 0 to 1: 1 when they are the same, over 0.5 by the share
+// of the record's it makes up when it is an abbreviation of it, the share alone when it has the record's
+// with more, and otherwise the Dice coefficient of their character pairs.
+fn similarity(given: &str, key: &str) -> f32 {
+    if given.is_empty() || key.is_empty() {
+        return 0.0;
+    }
+    if given == key {
+        return 1.0;
+    }
+    let (given_length, key_length) = (given.chars().count(), key.chars().count());
+    let (short, long) = (given_length.min(key_length), given_length.max(key_length));
+    if key.contains(given) {
+        return 0.5 + 0.5 * short as f32 / long as f32;
+    }
+    if given.contains(key) {
+        return short as f32 / long as f32;
+    }
+    let (a, mut b) = (bigrams(given), bigrams(key));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let shared = a
+        .iter()
+        .filter(|pair| match b.iter().position(|other| other == *pair) {
+            Some(i) => {
+                b.swap_remove(i);
+                true
+            }
+            None => false,
+        })
+        .count();
+    2.0 * shared as f32 / total as f32
+}
+
+// What a name resolved to among a bank list or a bank's branches.
+enum Resolution<'a, T> {
+    Found(&'a T, f32),
+    Ambiguous(Vec<&'a T>),
+    NotFound,
+}
+
+// The record whose names are closest to `given`, when it is close enough and clearly closer than any
+// other; an exact match only has to be the only exact one.
+fn resolve<'a, T>(
+    records: &'a [T],
+    given: &str,
+    noise: &[&str],
+    names: impl Fn(&'a T) -> Vec<&'a str>,
+) -> Resolution<'a, T> {
+    let given = name_key(given, noise);
+    let mut ranked = records
+        .iter()
+        .map(|record| {
+            let score = names(record)
+                .into_iter()
+                .map(|name| similarity(&given, &name_key(name, noise)))
+                .fold(0.0, f32::max);
+            (score, record)
+        })
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .collect::<Vec<(f32, &T)>>();
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    let best = match ranked.first() {
+        Some((best, _)) => *best,
+        None => return Resolution::NotFound,
+    };
+    let margin = if best >= 1.0 { f32::EPSILON } else { MARGIN };
+    let close = ranked
+        .iter()
+        .take_while(|(score, _)| best - score < margin)
+        .map(|(_, record)| *record)
+        .collect::<Vec<&T>>();
+    if close.len() == 1 {
+        Resolution::Found(close[0], best)
+    } else {
+        Resolution::Ambiguous(close.into_iter().take(MAX_CANDIDATES).collect())
+    }
+}
+
+fn bank_names(bank: &Bank) -> Vec<&str> {
+    let aliases = bank
+        .aliases
+        .iter()
+        .flat_map(|alias| std::iter::once(alias.name.as_str()).chain(alias.phonetic.as_deref()));
+    vec![bank.name.as_str(), bank.phonetic.as_str()].into_iter().chain(aliases).collect()
+}
+
+fn branch_names(branch: &Branch) -> Vec<&str> {
+    vec![&*branch.name, &*branch.phonetic]
+}
+
+fn non_empty(text: Option<&str>) -> Option<&str> {
+    text.map(str::trim).filter(|text| !text.is_empty())
+}
+
+// Checks the codes of one row against the dataset, and the names too when the row has them. A row
+// without a code has it resolved from the name instead, by the closest bank or branch; names resembling
+// several about equally leave the row for review with them listed.
+pub fn check(
+    banks: &[Bank],
+    bank_code: Option<&str>,
+    branch_code: Option<&str>,
+    bank_name: Option<&str>,
+    branch_name: Option<&str>,
+) -> Annotation {
+    let (bank_code, branch_code) = (non_empty(bank_code), non_empty(branch_code));
+    let (bank_name, branch_name) = (non_empty(bank_name), non_empty(branch_name));
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    let mut review = Vec::new();
+    let bank = match (bank_code, bank_name) {
+        (Some(code), _) => match code.parse::<BankCode>() {
+            Ok(code) => {
+                let bank = banks.iter().find(|bank| bank.code == code);
+                if bank.is_none() {
+                    problems.push(Problem { field: "bank", message: format!("no bank with code {}", code) });
+                }
+                bank
+            }
+            Err(e) => {
+                problems.push(Problem { field: "bank", message: e });
+                None
+            }
+        },
+        (None, Some(name)) => match resolve(banks, name, &BANK_NOISE, bank_names) {
+            Resolution::Found(bank, score) => {
+                if score < 1.0 {
+                    warnings.push(Problem {
+                        field: "bank_name",
+                        message: format!("{} resolved to the similar {} {}", name, bank.code, bank.name),
+                    });
+                }
+                Some(bank)
+            }
+            Resolution::Ambiguous(candidates) => {
+                let candidates = candidates.iter().map(|bank| format!("{} {}", bank.code, bank.name));
+                review.push(Problem {
+                    field: "bank_name",
+                    message: format!("{} could be any of {}", name, candidates.collect::<Vec<String>>().join(", ")),
+                });
+                None
+            }
+            Resolution::NotFound => {
+                problems.push(Problem { field: "bank_name", message: format!("no bank named like {}", name) });
+                None
+            }
+        },
+        (None, None) => {
+            problems.push(Problem { field: "bank", message: "no bank code or name".to_owned() });
+            None
+        }
+    };
+    let branch = match (branch_code, branch_name) {
+        (Some(code), _) => match (bank, code.parse::<BranchCode>()) {
+            (_, Err(e)) => {
+                problems.push(Problem { field: "branch", message: e });
+                None
+            }
+            (Some(bank), Ok(code)) => {
+                let branch = bank.branches.iter().find(|branch| branch.code == code.0);
+                if branch.is_none() {
+                    let message = format!("no branch with code {} in bank {}", code, bank.code);
+                    problems.push(Problem { field: "branch", message });
+                }
+                branch
+            }
+            (None, Ok(_)) => None,
+        },
+        (None, Some(name)) => match bank.map(|bank| (bank, resolve(&bank.branches, name, &BRANCH_NOISE, branch_names))) {
+            Some((_, Resolution::Found(branch, score))) => {
+                if score < 1.0 {
+                    warnings.push(Problem {
+                        field: "branch_name",
+                        message: format!("{} resolved to the similar branch {} {}", name, branch.code, branch.name),
+                    });
+                }
+                Some(branch)
+            }
+            Some((_, Resolution::Ambiguous(candidates))) => {
+                let candidates = candidates.iter().map(|branch| format!("{} {}", branch.code, branch.name));
+                review.push(Problem {
+                    field: "branch_name",
+                    message: format!("{} could be any of {}", name, candidates.collect::<Vec<String>>().join(", ")),
+                });
+                None
+            }
+            Some((bank, Resolution::NotFound)) => {
+                let message = format!("no branch named like {} in bank {}", name, bank.code);
+                problems.push(Problem { field: "branch_name", message });
+                None
+            }
+            None => None,
+        },
+        (None, None) => {
+            problems.push(Problem { field: "branch", message: "no branch code or name".to_owned() });
+            None
+        }
+    };
+    // Names resolved to a code are checked by resolving them; the rest are compared with the record.
+    if let (Some(bank), Some(given)) = (bank, bank_name) {
+        if bank.aliases.iter().any(|alias| same_name(given, &alias.name, alias.phonetic.as_deref().unwrap_or_default())) {
+            warnings.push(Problem {
+                field: "bank_name",
+                message: format!("{} is a former name of {} {}", given, bank.code, bank.name),
+            });
+        } else if bank_code.is_some() && !same_name(given, &bank.name, &bank.phonetic) {
+            problems.push(Problem {
+                field: "bank_name",
+                message: format!("{} is not the name of {} {}", given, bank.code, bank.name),
+            });
+        }
+    }
+    if let (Some(branch), Some(given), Some(_)) = (branch, branch_name, branch_code) {
+        if !same_name(given, &branch.name, &branch.phonetic) {
+            problems.push(Problem {
+                field: "branch_name",
+                message: format!("{} is not the name of branch {} {}", given, branch.code, branch.name),
+            });
+        }
+    }
+    let status = if !problems.is_empty() {
+        Status::Error
+    } else if !review.is_empty() {
+        Status::Review
+    } else if !warnings.is_empty() {
+        Status::Warning
+    } else {
+        Status::Ok
+    };
+    problems.extend(review);
+    problems.extend(warnings);
+    Annotation {
+        bank_code: bank.map(|bank| bank.code.clone()),
+        bank_name: bank.map(|bank| bank.name.clone()),
+        branch_code: branch.map(|branch| branch.code.clone()),
+        branch_name: branch.map(|branch| branch.name.to_string()),
+        resolved: (bank.is_some() && bank_code.is_none()) || (branch.is_some() && branch_code.is_none()),
+        status,
+        problems,
+    }
+}
+
+// Rows read by `validate`, by the status they were given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub rows: usize,
+    pub ok: usize,
+    pub warnings: usize,
+    pub review: usize,
+    pub errors: usize,
+    // Rows with a code resolved from a name.
+    pub resolved: usize,
+}
+
+// Copies a CSV file with a header row from `reader` to `writer`, checking each row and appending the
+// resolved codes and names, a status and the reasons for it as `ADDED_HEADERS`. Rows are kept in order and as
+// they were, so the output lines up with the input.
+pub fn validate<R: Read, W: Write>(banks: &[Bank], reader: R, writer: W, names: &ColumnNames) -> Result<Summary, Error> {
+    let failed = |e: csv::Error| Error::BatchFailed(e.to_string());
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut headers = reader.headers().map_err(failed)?.clone();
+    // A byte order mark, as spreadsheet software writes, would hide the first header.
+    if let Some(first) = headers.get(0).and_then(|first| first.strip_prefix('\u{feff}')) {
+        let mut rest = headers.iter().skip(1).map(str::to_owned).collect::<Vec<String>>();
+        rest.insert(0, first.to_owned());
+        headers = StringRecord::from(rest);
+    }
+    let columns = Columns::find(&headers, names)?;
+    let mut output = headers.clone();
+    output.extend(ADDED_HEADERS.iter());
+    writer.write_record(&output).map_err(failed)?;
+    let mut summary = Summary::default();
+    for record in reader.records() {
+        let record = record.map_err(failed)?;
+        let field = |position: usize| record.get(position).unwrap_or_default();
+        let annotation = check(
+            banks,
+            columns.bank_code.map(field),
+            columns.branch_code.map(field),
+            columns.bank_name.map(field),
+            columns.branch_name.map(field),
+        );
+        summary.rows += 1;
+        if annotation.resolved {
+            summary.resolved += 1;
+        }
+        match annotation.status {
+            Status::Ok => summary.ok += 1,
+            Status::Warning => summary.warnings += 1,
+            Status::Review => summary.review += 1,
+            Status::Error => summary.errors += 1,
+        }
+        let reasons = annotation
+            .problems
+            .iter()
+            .map(|problem| problem.message.as_str())
+            .collect::<Vec<&str>>()
+            .join("; ");
+        let mut output = record.clone();
+        output.push_field(annotation.bank_code.as_ref().map(|code| code.0.as_str()).unwrap_or_default());
+        output.push_field(annotation.bank_name.as_deref().unwrap_or_default());
+        output.push_field(annotation.branch_code.as_deref().unwrap_or_default());
+        output.push_field(annotation.branch_name.as_deref().unwrap_or_default());
+        output.push_field(annotation.status.as_str());
+        output.push_field(&reasons);
+        writer.write_record(&output).map_err(failed)?;
+    }
+    writer.flush().map_err(|e| Error::BatchFailed(e.to_string()))?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn validate_test() {
+        use crate::aliases::Alias;
+        use crate::batch::{validate, ColumnNames, Summary};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        bank.aliases.push(Alias {
+            name: "富士銀行".to_owned(),
+            phonetic: Some("ﾌｼﾞ".to_owned()),
+            until: None,
+        });
+        let banks = [bank];
+
+        let input = "\u{feff}id,金融機関コード,支店コード,銀行名,amount\n\
+            1,1,1,みずほ銀行,1000\n\
+            2,0001,001,富士銀行,2000\n\
+            3,0001,002,,3000\n\
+            4,9999,001,ねこ銀行,4000\n\
+            5,0001,001,ミズホ,5000\n";
+        let mut output = Vec::new();
+        let summary = validate(&banks, input.as_bytes(), &mut output, &ColumnNames::default()).unwrap();
+        assert_eq!(summary, Summary { rows: 5, ok: 2, warnings: 1, review: 0, errors: 2, resolved: 0 });
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            lines[0],
+            "id,金融機関コード,支店コード,銀行名,amount,\
+             resolved_bank_code,resolved_bank_name,resolved_branch_code,resolved_branch_name,status,reason"
+        );
+        assert_eq!(lines[1], "1,1,1,みずほ銀行,1000,0001,みずほ銀行,001,東京営業部,ok,");
+        assert_eq!(
+            lines[2],
+            "2,0001,001,富士銀行,2000,0001,みずほ銀行,001,東京営業部,warning,富士銀行 is a former name of 0001 みずほ銀行"
+        );
+        assert_eq!(lines[3], "3,0001,002,,3000,0001,みずほ銀行,,,error,no branch with code 002 in bank 0001");
+        assert_eq!(lines[4], "4,9999,001,ねこ銀行,4000,,,,,error,no bank with code 9999");
+
+        let names = ColumnNames {
+            bank_code: Some("bank".to_owned()),
+            ..ColumnNames::default()
+        };
+        assert!(validate(&banks, input.as_bytes(), Vec::new(), &names).is_err());
+    }
+
+    #[test]
+    fn resolve_test() {
+        use crate::batch::{check, Status};
+        use crate::{Bank, Branch};
+
+        let mut mizuho = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned());
+        mizuho.append_branch(Branch::new("渋谷".to_owned(), "ｼﾌﾞﾔ".to_owned(), "135".to_owned()));
+        mizuho.append_branch(Branch::new("新宿".to_owned(), "ｼﾝｼﾞﾕｸ".to_owned(), "200".to_owned()));
+        mizuho.append_branch(Branch::new("新宿西口".to_owned(), "ｼﾝｼﾞﾕｸﾆｼｸﾞﾁ".to_owned(), "201".to_owned()));
+        let trust = Bank::new("みずほ信託銀行".to_owned(), "ﾐｽﾞﾎｼﾝﾀｸ".to_owned(), "0289".to_owned(), "x289".to_owned());
+        let mut mufg = Bank::new("三菱UFJ銀行".to_owned(), "ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ".to_owned(), "0005".to_owned(), "x5".to_owned());
+        mufg.append_branch(Branch::new("渋谷".to_owned(), "ｼﾌﾞﾔ".to_owned(), "135".to_owned()));
+        let star = Bank::new("東京スター銀行".to_owned(), "ﾄｳｷﾖｳｽﾀ-".to_owned(), "0526".to_owned(), "x526".to_owned());
+        let city = Bank::new("東京シティ信用金庫".to_owned(), "ﾄｳｷﾖｳｼﾃｲ".to_owned(), "1311".to_owned(), "x1311".to_owned());
+        let banks = [mizuho, trust, mufg, star, city];
+
+        // Exact names and readings, with what vendor data adds to them, resolve without a warning.
+        let found = check(&banks, None, None, Some("株式会社みずほ銀行"), Some("渋谷支店"));
+        assert_eq!(found.status, Status::Ok);
+        assert_eq!((found.bank_code.unwrap().0.as_str(), found.branch_code.as_deref()), ("0001", Some("135")));
+        assert!(found.resolved);
+        let found = check(&banks, Some(""), None, Some("ミズホ"), Some("シンジュク"));
+        assert_eq!((found.status, found.branch_code.as_deref()), (Status::Ok, Some("200")));
+
+        // A name that only resembles one record resolves with a warning.
+        let found = check(&banks, None, None, Some("三菱ＵＦＪ"), Some("しぶや"));
+        assert_eq!(found.status, Status::Warning);
+        assert_eq!(found.bank_code.unwrap().0, "0005");
+        assert_eq!(found.branch_code.as_deref(), Some("135"));
+
+        // One resembling several about equally is left for review, with the candidates given.
+        let found = check(&banks, None, Some("135"), Some("東京"), None);
+        assert_eq!(found.status, Status::Review);
+        assert_eq!(found.bank_code, None);
+        assert_eq!(found.problems[0].message, "東京 could be any of 0526 東京スター銀行, 1311 東京シティ信用金庫");
+        let found = check(&banks, Some("1"), None, None, Some("新宿西"));
+        assert_eq!((found.status, found.branch_code.as_deref()), (Status::Warning, Some("201")));
+        let found = check(&banks, None, None, Some("みずほ信託"), None);
+        assert_eq!(found.bank_code.unwrap().0, "0289");
+
+        let found = check(&banks, None, None, Some("ねこ銀行"), Some("本店"));
+        assert_eq!(found.status, Status::Error);
+        assert!(!found.resolved);
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+// A cheaply clonable flag an embedding application can trip to stop a crawl.
+// tokio 0.2 only ships its own token behind `tokio_unstable`.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+
+    pub fn cancel(&self) {
+        let _ = self.sender.broadcast(true);
+    }
+
+    // Clears a cancellation so the token can stop another run, as for strict mode between the runs
+    // of `crawl --every`.
+    pub fn reset(&self) {
+        let _ = self.sender.broadcast(false);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.clone();
+        while let Some(cancelled) = receiver.recv().await {
+            if cancelled {
+                return;
+            }
+        }
+        futures::future::pending::<()>().await
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn cancelled_test() {
+        use crate::cancel::CancellationToken;
+
+        let token = CancellationToken::new();
+        assert!(!token.is_cancelled());
+        let waiter = {
+            let token = token.clone();
+            tokio::spawn(async move { token.cancelled().await })
+        };
+        token.cancel();
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+        token.reset();
+        assert!(!token.is_cancelled());
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::Bank;
+
+// 業態: the kind of financial institution a bank is. The dataset doesn't record it, but zengin codes are
+// assigned in blocks by kind, and the few kinds sharing a block are told apart by their names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    // 都市銀行
+    City,
+    // 信託銀行
+    Trust,
+    // 地方銀行
+    Regional,
+    // 第二地方銀行
+    SecondRegional,
+    // 信用金庫
+    Shinkin,
+    // 信用組合
+    CreditUnion,
+    // 労働金庫
+    Labour,
+    // 農業協同組合 and their federations
+    Agricultural,
+    // 漁業協同組合 and their federations
+    Fishery,
+    // ゆうちょ銀行
+    Yucho,
+    // Net and other new banks, foreign banks, and anything else
+    Other,
+}
+
+const ALL: [Category; 11] = [
+    Category::City,
+    Category::Trust,
+    Category::Regional,
+    Category::SecondRegional,
+    Category::Shinkin,
+    Category::CreditUnion,
+    Category::Labour,
+    Category::Agricultural,
+    Category::Fishery,
+    Category::Yucho,
+    Category::Other,
+];
+
+impl Category {
+    pub fn of(bank: &Bank) -> Self {
+        let code = bank.code.0.parse::<u16>().unwrap_or_default();
+        if bank.name.contains("信託銀行") {
+            return Category::Trust;
+        }
+        if bank.name.contains("漁業協同組合") || bank.name.contains("漁連") || bank.name.contains("漁協") {
+            return Category::Fishery;
+        }
+        match code {
+            1..=32 => Category::City,
+            116..=199 => Category::Regional,
+            500..=599 => Category::SecondRegional,
+            1000..=1999 => Category::Shinkin,
+            2000..=2899 => Category::CreditUnion,
+            2950..=2999 => Category::Labour,
+            3000..=9899 => Category::Agricultural,
+            9900 => Category::Yucho,
+            _ => Category::Other,
+        }
+    }
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            Category::City => "city",
+            Category::Trust => "trust",
+            Category::Regional => "regional",
+            Category::SecondRegional => "second_regional",
+            Category::Shinkin => "shinkin",
+            Category::CreditUnion => "credit_union",
+            Category::Labour => "labour",
+            Category::Agricultural => "agricultural",
+            Category::Fishery => "fishery",
+            Category::Yucho => "yucho",
+            Category::Other => "other",
+        }
+    }
+
+    pub fn name_ja(self) -> &'static str {
+        match self {
+            Category::City => "都市銀行",
+            Category::Trust => "信託銀行",
+            Category::Regional => "地方銀行",
+            Category::SecondRegional => "第二地方銀行",
+            Category::Shinkin => "信用金庫",
+            Category::CreditUnion => "信用組合",
+            Category::Labour => "労働金庫",
+            Category::Agricultural => "農業協同組合",
+            Category::Fishery => "漁業協同組合",
+            Category::Yucho => "ゆうちょ銀行",
+            Category::Other => "その他",
+        }
+    }
+}
+
+// Accepts the Japanese name as well as the slug, e.g. 地方銀行 or regional.
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL.iter()
+            .copied()
+            .find(|category| category.slug() == s || category.name_ja() == s)
+            .ok_or_else(|| format!("unknown category: {}", s))
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name_ja())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn category_test() {
+        use crate::category::Category;
+        use crate::Bank;
+
+        let bank = |name: &str, code: &str| Bank::new(name.to_owned(), String::new(), code.to_owned(), String::new());
+        assert_eq!(Category::of(&bank("みずほ銀行", "0001")), Category::City);
+        assert_eq!(Category::of(&bank("三井住友信託銀行", "0294")), Category::Trust);
+        assert_eq!(Category::of(&bank("横浜銀行", "0138")), Category::Regional);
+        assert_eq!(Category::of(&bank("北洋銀行", "0501")), Category::SecondRegional);
+        assert_eq!(Category::of(&bank("城南信用金庫", "1344")), Category::Shinkin);
+        assert_eq!(Category::of(&bank("ＪＦマリンバンク（漁業協同組合）", "9450")), Category::Fishery);
+        assert_eq!(Category::of(&bank("ゆうちょ銀行", "9900")), Category::Yucho);
+        assert_eq!(Category::of(&bank("ＰａｙＰａｙ銀行", "0033")), Category::Other);
+
+        assert_eq!("地方銀行".parse::<Category>(), Ok(Category::Regional));
+        assert_eq!("shinkin".parse::<Category>(), Ok(Category::Shinkin));
+        assert!("bank".parse::<Category>().is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+use crate::kana::{self, PhoneticForm};
+
+const SMALL: &str = "ｧｨｩｪｫｬｭｮｯ";
+const SMALL_AS: &str = "ｱｲｳｴｵﾔﾕﾖﾂ";
+// Symbols allowed besides half-width kana, upper case letters, digits and spaces. JIS X 0201 has the
+// yen sign where ASCII has the backslash, so either may stand for it.
+const SYMBOLS: &str = "()-./\\¥,｢｣";
+// Characters commonly typed for one of the symbols, and the symbol they are written as.
+const LOOKALIKES: &[(char, char)] = &[
+    ('ｰ', '-'),
+    ('‐', '-'),
+    ('‑', '-'),
+    ('–', '-'),
+    ('—', '-'),
+    ('―', '-'),
+    ('−', '-'),
+    ('･', '.'),
+    ('､', ','),
+    ('｡', '.'),
+];
+
+// A character outside the zengin set, by its position in characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Disallowed {
+    pub position: usize,
+    pub character: char,
+}
+
+// Whether a character may appear in the kana fields of a zengin record, such as receiver names.
+pub fn is_allowed(c: char) -> bool {
+    matches!(c, 'ｦ' | 'ｱ'..='ﾝ' | 'ﾞ' | 'ﾟ' | 'A'..='Z' | '0'..='9' | ' ') || SYMBOLS.contains(c)
+}
+
+// Rewrites text in the zengin set where there is an obvious counterpart: kana of any script or width
+// become half-width katakana, small kana are written large, letters upper case, and dashes and
+// punctuation the symbol they stand for. Anything else, such as kanji, is left for `check` to report.
+pub fn normalize(text: &str) -> String {
+    kana::convert(text.trim(), PhoneticForm::HalfWidth)
+        .chars()
+        .map(|c| {
+            if let Some(i) = SMALL.chars().position(|small| small == c) {
+                return SMALL_AS.chars().nth(i).unwrap();
+            }
+            match LOOKALIKES.iter().find(|(lookalike, _)| *lookalike == c) {
+                Some((_, symbol)) => *symbol,
+                None => c.to_ascii_uppercase(),
+            }
+        })
+        .collect()
+}
+
+pub fn check(text: &str) -> Vec<Disallowed> {
+    text.chars()
+        .enumerate()
+        .filter(|(_, c)| !is_allowed(*c))
+        .map(|(position, character)| Disallowed { position, character })
+        .collect()
+}
+
+// The text normalized, or the characters that still aren't allowed after normalizing it.
+pub fn to_zengin(text: &str) -> Result<String, Vec<Disallowed>> {
+    let normalized = normalize(text);
+    let disallowed = check(&normalized);
+    if disallowed.is_empty() {
+        Ok(normalized)
+    } else {
+        Err(disallowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn normalize_test() {
+        use crate::charset::{check, normalize, to_zengin, Disallowed};
+
+        assert_eq!(normalize("やまだ　たろー"), "ﾔﾏﾀﾞ ﾀﾛ-");
+        assert_eq!(normalize("ｷｬﾉﾝ(ｶ"), "ｷﾔﾉﾝ(ｶ");
+        assert_eq!(normalize("ｴｽ･ｹｰ abc"), "ｴｽ.ｹ- ABC");
+        assert_eq!(normalize("カ）サトウ―ショウジ"), "ｶ)ｻﾄｳ-ｼﾖｳｼﾞ");
+        assert_eq!(check("ﾔﾏﾀﾞ ﾀﾛｳ"), vec![]);
+        assert_eq!(
+            to_zengin("山田ﾀﾛｳ"),
+            Err(vec![
+                Disallowed { position: 0, character: '山' },
+                Disallowed { position: 1, character: '田' },
+            ])
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use zngn::backup;
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::manifest::Manifest;
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{ExitCode, Report};
+
+#[derive(Debug, Serialize)]
+struct BackupSummary {
+    archive: PathBuf,
+    files: usize,
+    bytes: u64,
+}
+
+impl BackupSummary {
+    fn new(archive: PathBuf, manifest: &Manifest) -> Self {
+        Self {
+            archive,
+            files: manifest.files.len(),
+            bytes: manifest.files.values().map(|entry| entry.bytes).sum(),
+        }
+    }
+}
+
+pub fn backup(layout: &Layout, archive: PathBuf) -> Report {
+    match backup::backup(layout.out(), &archive) {
+        Ok(manifest) => {
+            let summary = BackupSummary::new(archive, &manifest);
+            let text = fill(Msg::BackedUp, &[&summary.files, &summary.bytes, &summary.archive.display()]);
+            Report::new(&summary, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+pub fn restore(layout: &Layout, archive: PathBuf, force: bool) -> Report {
+    if layout.banks_file().exists() && !force {
+        return Report::failed(ExitCode::Validation, fill(Msg::RestoreWouldReplace, &[&layout.out().display()]));
+    }
+    let _lock = match CrawlLock::acquire(layout) {
+        Ok(lock) => lock,
+        Err(e) => return Report::from_error(&e),
+    };
+    match backup::restore(&archive, layout.out()) {
+        Ok(manifest) => {
+            let summary = BackupSummary::new(archive, &manifest);
+            let text = fill(Msg::Restored, &[&summary.files, &summary.archive.display()]);
+            Report::new(&summary, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
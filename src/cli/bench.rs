@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::search;
+use zngn::{compiled, load_json_dataset, Bank};
+
+use crate::cli::{load, Report, Table};
+
+#[derive(Debug, StructOpt)]
+pub struct BenchOpt {
+    /// How many times the dataset is loaded from disk
+    #[structopt(long, default_value = "5")]
+    loads: usize,
+    /// How many lookups and searches are timed
+    #[structopt(long, default_value = "1000")]
+    queries: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Measurement {
+    name: &'static str,
+    runs: usize,
+    median_ns: u128,
+    p99_ns: u128,
+    // Runs per second over the whole measurement.
+    per_second: f64,
+}
+
+impl Measurement {
+    fn new(name: &'static str, mut timings: Vec<Duration>) -> Self {
+        timings.sort();
+        let total = timings.iter().sum::<Duration>().as_secs_f64();
+        let at = |fraction: f64| {
+            let i = ((timings.len() as f64 * fraction).ceil() as usize).saturating_sub(1);
+            timings.get(i).map_or(0, Duration::as_nanos)
+        };
+        Self {
+            name,
+            runs: timings.len(),
+            median_ns: at(0.5),
+            p99_ns: at(0.99),
+            per_second: if total > 0.0 { timings.len() as f64 / total } else { 0.0 },
+        }
+    }
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let value = f();
+    (value, started.elapsed())
+}
+
+fn human(nanos: u128) -> String {
+    match nanos {
+        0..=999 => format!("{}ns", nanos),
+        1_000..=999_999 => format!("{:.1}µs", nanos as f64 / 1e3),
+        _ => format!("{:.1}ms", nanos as f64 / 1e6),
+    }
+}
+
+// Codes and names spread evenly over the dataset, so every run asks the same questions.
+fn samples(banks: &[Bank], count: usize) -> Vec<(&str, &str, &str)> {
+    let branches = banks
+        .iter()
+        .flat_map(|bank| bank.branches.iter().map(move |branch| (bank, branch)))
+        .collect::<Vec<_>>();
+    if branches.is_empty() {
+        return Vec::new();
+    }
+    (0..count)
+        .map(|i| branches[i * branches.len() / count.max(1) % branches.len()])
+        .map(|(bank, branch)| {
+            let end = branch.name.char_indices().nth(2).map_or(branch.name.len(), |(i, _)| i);
+            (bank.code.0.as_str(), branch.code.as_str(), &branch.name[..end])
+        })
+        .collect()
+}
+
+pub fn run(opt: BenchOpt, layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let mut measurements = Vec::new();
+
+    let mut timings = Vec::new();
+    for _ in 0..opt.loads {
+        let (loaded, elapsed) = time(|| load_json_dataset(layout));
+        if let Err(e) = loaded {
+            return Report::from_error(&e);
+        }
+        timings.push(elapsed);
+    }
+    measurements.push(Measurement::new("load json", timings));
+
+    let mut timings = Vec::new();
+    for _ in 0..opt.loads {
+        match time(|| compiled::load(layout)) {
+            (Ok(Some(_)), elapsed) => timings.push(elapsed),
+            (Ok(None), _) => break,
+            (Err(e), _) => return Report::from_error(&e),
+        }
+    }
+    if !timings.is_empty() {
+        measurements.push(Measurement::new("load compiled", timings));
+    }
+
+    let samples = samples(&banks, opt.queries);
+    let timings = samples
+        .iter()
+        .map(|(bank_code, branch_code, _)| {
+            time(|| search::lookup_bank(&banks, bank_code).and_then(|bank| search::lookup_branch(bank, branch_code))).1
+        })
+        .collect();
+    measurements.push(Measurement::new("lookup", timings));
+
+    let timings = samples
+        .iter()
+        .map(|(_, _, query)| time(|| search::search(&banks, query).count()).1)
+        .collect();
+    measurements.push(Measurement::new("search", timings));
+
+    let mut table = Table::new(&["benchmark", "runs", "median", "p99", "per second"]);
+    for measurement in &measurements {
+        table.push(vec![
+            measurement.name.to_owned(),
+            measurement.runs.to_string(),
+            human(measurement.median_ns),
+            human(measurement.p99_ns),
+            format!("{:.0}", measurement.per_second),
+        ]);
+    }
+    Report::new(&measurements, table.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn measurement_test() {
+        use std::time::Duration;
+        use crate::cli::bench::Measurement;
+
+        let timings = (1..=100).rev().map(Duration::from_millis).collect();
+        let measurement = Measurement::new("load", timings);
+        assert_eq!(measurement.runs, 100);
+        assert_eq!(measurement.median_ns, Duration::from_millis(50).as_nanos());
+        assert_eq!(measurement.p99_ns, Duration::from_millis(99).as_nanos());
+        assert!((measurement.per_second - 100.0 / 5.05).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,587 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use structopt::StructOpt;
+use tokio::time::interval;
+use zngn::anomaly::{self, Counted};
+use zngn::cancel::CancellationToken;
+use zngn::client::{Source, ZnginClient};
+use zngn::compiled;
+use zngn::dedup::{self, Conflict, Policy};
+use zngn::diff;
+use zngn::keycount::{self, KeyCounter, KeyReport};
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::logging;
+use zngn::manifest::Manifest;
+use zngn::markup::Markup;
+use zngn::notify::{self, Summary};
+use zngn::plan::{self, Plan};
+use zngn::schedule::{self, Scheduled};
+use zngn::shard::Shard;
+use zngn::signing;
+use zngn::progress::ProgressObserver;
+use zngn::retry::{self, RetryQueue};
+use zngn::throttle::{parse_bandwidth, HostLimits, Window};
+use zngn::writer::BankWriter;
+use zngn::{
+    all_search_keys, load_banks, load_json_dataset, marker, pool, Bank, BankCode, Error,
+    ParseWarning, WarningKind,
+};
+
+use crate::cli::i18n::{fill, t, Msg};
+use crate::cli::query::change_cells;
+use crate::cli::{ExitCode, Report, Table};
+
+// Logs each bank as it starts. Given a plan, positions count the banks of the whole plan, banks done
+// by earlier runs included, rather than those of this run.
+#[derive(Default)]
+pub struct ConsoleProgress {
+    done: AtomicUsize,
+    planned: AtomicUsize,
+}
+
+impl ConsoleProgress {
+    pub fn plan(&self, plan: &Plan) {
+        let (_, banks) = plan.progress();
+        self.done.store(banks.done, Ordering::SeqCst);
+        self.planned.store(banks.total, Ordering::SeqCst);
+    }
+}
+
+impl ProgressObserver for ConsoleProgress {
+    fn bank_started(&self, bank: &Bank, position: usize, total: usize) {
+        let done = self.done.load(Ordering::SeqCst);
+        let total = self.planned.load(Ordering::SeqCst).max(done + total);
+        logging::info(&format!("[{}/{}] {} {}", done + position + 1, total, bank.code.0, bank.name));
+    }
+
+    fn request_failed(&self, search_key: char, error: &Error) {
+        logging::warn(&fill(Msg::RequestFailed, &[&search_key, error]));
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RowWarning {
+    search_key: char,
+    // The bank whose branch page had the row; None for rows of the bank list.
+    bank_code: Option<String>,
+    #[serde(flatten)]
+    warning: ParseWarning,
+}
+
+impl RowWarning {
+    // Warnings are grouped under the bank they concern; skipped rows of the bank list have no bank.
+    fn bank(&self) -> String {
+        self.bank_code
+            .clone()
+            .or_else(|| self.warning.code.clone())
+            .unwrap_or_else(|| "-".to_owned())
+    }
+}
+
+// Collects what the parsers flagged. In strict mode the first skipped row stops the crawl.
+struct ParseWarnings {
+    strict: bool,
+    cancel: CancellationToken,
+    collected: Mutex<Vec<RowWarning>>,
+}
+
+impl ParseWarnings {
+    fn take(&self) -> Vec<RowWarning> {
+        std::mem::take(&mut *self.collected.lock().unwrap())
+    }
+}
+
+impl ProgressObserver for ParseWarnings {
+    fn parse_warning(&self, search_key: char, bank_code: Option<&BankCode>, warning: &ParseWarning) {
+        self.collected.lock().unwrap().push(RowWarning {
+            search_key,
+            bank_code: bank_code.map(|code| code.0.clone()),
+            warning: warning.clone(),
+        });
+        if self.strict && warning.kind == WarningKind::MalformedRow {
+            self.cancel.cancel();
+        }
+    }
+}
+
+fn describe(warning: &RowWarning) -> String {
+    let skipped = warning.warning.kind == WarningKind::MalformedRow;
+    let (row, key, reason) = (&warning.warning.row, &warning.search_key, &warning.warning.reason);
+    match &warning.bank_code {
+        Some(code) => {
+            let msg = if skipped { Msg::SkippedBranchRow } else { Msg::KeptBranchRow };
+            fill(msg, &[row, code, key, reason])
+        }
+        None => fill(if skipped { Msg::SkippedBankRow } else { Msg::KeptBankRow }, &[row, key, reason]),
+    }
+}
+
+// The site as source.json describes it, at the base URL given on the command line if any.
+pub fn source(layout: &Layout, base: Option<&str>) -> Result<Source, Error> {
+    let source = Source::load(layout)?;
+    Ok(match base {
+        Some(base) => source.with_base(base),
+        None => source,
+    })
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct CrawlOpt {
+    /// Base URL of the site, e.g. that of a local `zngn mock-server`; the base in source.json, or
+    /// https://zengin.ajtw.net, when omitted
+    #[structopt(long)]
+    source: Option<String>,
+    /// Sleep a random duration up to this many milliseconds before each request
+    #[structopt(long, default_value = "0")]
+    jitter_ms: u64,
+    /// Cap the overall download rate, in bytes per second; no cap when omitted
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    max_bandwidth: Option<u64>,
+    /// Limits for one host instead of --jitter-ms and --max-bandwidth, repeatable, e.g.
+    /// mirror.example.com:jitter_ms=0,max_bandwidth=10000000,concurrency=32
+    #[structopt(long = "host-limit", number_of_values = 1)]
+    host_limits: Vec<HostLimits>,
+    /// Bank list requests in flight at the same time; unlimited when omitted
+    #[structopt(long)]
+    bank_concurrency: Option<usize>,
+    /// Branch list requests in flight at the same time; unlimited when omitted
+    #[structopt(long)]
+    branch_concurrency: Option<usize>,
+    /// Only send requests during this time of day, in Japan time, pausing outside it and resuming when
+    /// it opens again, e.g. 01:00-05:00
+    #[structopt(long)]
+    window: Option<Window>,
+    /// Keep running and crawl again every this many seconds, logging each run instead of printing it
+    #[structopt(long)]
+    every: Option<u64>,
+    /// Number of branch files written at the same time
+    #[structopt(long, default_value = "16")]
+    write_concurrency: usize,
+    /// Number of threads used to parse downloaded pages (defaults to the number of CPUs)
+    #[structopt(long)]
+    parse_threads: Option<usize>,
+    /// Stop issuing new requests after this many have been sent
+    #[structopt(long)]
+    max_requests: Option<usize>,
+    /// Only crawl part i of n of the banks, split by bank code the same way on every machine, e.g. 2/4;
+    /// `zngn merge` combines the parts
+    #[structopt(long)]
+    shard: Option<Shard>,
+    /// Carry on with the plan of the last crawl, fetching only the banks it has not saved yet
+    #[structopt(long)]
+    resume: bool,
+    /// Skip banks marked done within this many hours
+    #[structopt(long)]
+    freshness: Option<u64>,
+    /// What to do when a code is seen twice with different names: keep-first, keep-latest or fail
+    #[structopt(long, default_value = "keep-first")]
+    on_duplicate: Policy,
+    /// Fail the run on any malformed row instead of skipping it with a warning
+    #[structopt(long)]
+    strict: bool,
+    /// Keep the saved snapshot and fail when the crawl finds more than this many percent fewer banks or
+    /// branches than it has; 100 turns the check off
+    #[structopt(long, default_value = "10")]
+    max_drop: f64,
+    /// Sign the manifest.json written after the crawl with this private key from `zngn keygen`
+    #[structopt(long, parse(from_os_str), env = "ZNGN_SIGN_KEY")]
+    sign_key: Option<PathBuf>,
+    /// Post a summary of the run to this webhook, repeatable; Slack and Discord webhook URLs get a
+    /// formatted message, any other URL the summary as JSON
+    #[structopt(long = "notify", number_of_values = 1, env = "ZNGN_NOTIFY", use_delimiter = true)]
+    notify: Vec<String>,
+    /// Show how many banks and branches each search key yielded, next to the counts of the last full crawl
+    #[structopt(long)]
+    per_key_report: bool,
+    /// Also fetch each bank's own page where the bank list links one, and save the address and website
+    /// found there or in extra columns of the list, as set up in parser.json
+    #[structopt(long)]
+    enrich: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct CrawlSummary {
+    retried: usize,
+    banks: usize,
+    completed: usize,
+    files: usize,
+    bytes: usize,
+    queued_failures: usize,
+    stopped: Option<&'static str>,
+    conflicts: Vec<Conflict>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheduled: Option<Scheduled>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enriched: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    enrich_failed: Vec<BankCode>,
+}
+
+// Without --every this crawls once. With it, it keeps crawling, a run per period, and logs how each
+// went; Ctrl-C ends it, during a run once that run has stopped. The parse pool and the Ctrl-C
+// listener are set up once for all runs.
+pub async fn run(opt: CrawlOpt, layout: Layout) -> Report {
+    if let Some(parse_threads) = opt.parse_threads {
+        if let Err(e) = pool::configure(parse_threads) {
+            return Report::failed(ExitCode::Validation, e.to_string());
+        }
+    }
+    let cancel = CancellationToken::new();
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let cancel = cancel.clone();
+        let interrupted = interrupted.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.store(true, Ordering::SeqCst);
+                cancel.cancel();
+            }
+        });
+    }
+    let period = match opt.every {
+        Some(seconds) => Duration::from_secs(seconds.max(1)),
+        None => return run_once(opt, layout, cancel).await,
+    };
+    let mut ticks = interval(period);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = cancel.cancelled() => return Report::new(&(), String::new()),
+        }
+        let report = run_once(opt.clone(), layout.clone(), cancel.clone()).await;
+        if report.results.get("stopped").and_then(Value::as_str) == Some("cancelled") {
+            return report;
+        }
+        report.log();
+        // Strict mode cancels the run it finds a malformed row in, but not the runs after it.
+        cancel.reset();
+        if interrupted.load(Ordering::SeqCst) {
+            return Report::new(&(), String::new());
+        }
+    }
+}
+
+async fn run_once(opt: CrawlOpt, layout: Layout, cancel: CancellationToken) -> Report {
+    let notify = opt.notify.clone();
+    // The snapshot before the crawl, to list what changed in the notification.
+    let previous = if notify.is_empty() { None } else { load_json_dataset(&layout).ok() };
+    let notify_layout = layout.clone();
+    let previous_counts = keycount::load(&layout).ok().flatten();
+    // Counts of a run that skips banks aren't comparable with those of a full crawl.
+    let full = !opt.resume && opt.freshness.is_none() && opt.shard.is_none();
+    let per_key_report = opt.per_key_report;
+    let counter = Arc::new(KeyCounter::default());
+    let warnings = Arc::new(ParseWarnings {
+        strict: opt.strict,
+        cancel: cancel.clone(),
+        collected: Mutex::new(Vec::new()),
+    });
+    let strict = opt.strict;
+    let result = crawl(opt, layout, cancel, warnings.clone(), counter.clone()).await;
+    let warnings = warnings.take();
+    let malformed = warnings
+        .iter()
+        .filter(|warning| warning.warning.kind == WarningKind::MalformedRow)
+        .count();
+    if strict && malformed > 0 {
+        let mut report = Report::failed(ExitCode::Parse, fill(Msg::StrictMode, &[&malformed]));
+        for warning in &warnings {
+            report.warn(describe(warning));
+        }
+        return report;
+    }
+    let mut report = match result {
+        Ok(report) => report,
+        Err(Error::CountDropped { counted, previous, current }) => {
+            let msg = match counted {
+                Counted::Banks => Msg::BankCountDropped,
+                Counted::Branches => Msg::BranchCountDropped,
+            };
+            Report::failed(ExitCode::Anomaly, fill(msg, &[&current, &previous]))
+        }
+        Err(e) => Report::from_error(&e),
+    };
+    for warning in &warnings {
+        report.warn(describe(warning));
+    }
+    let counts = counter.counts();
+    let per_key = keycount::report(&counts, previous_counts.as_ref().filter(|_| full));
+    for line in per_key.iter().filter(|line| line.dropped) {
+        let before = line.previous.unwrap_or_default();
+        report.warn(fill(
+            Msg::KeyYieldDropped,
+            &[&line.search_key, &line.current.banks, &line.current.branches, &before.banks, &before.branches],
+        ));
+    }
+    if full && report.exit_code == ExitCode::Success {
+        if let Err(e) = keycount::save(&counts, &notify_layout) {
+            report.warn(fill(Msg::KeyCountsNotSaved, &[&e]));
+        }
+    }
+    if per_key_report {
+        report.text.push_str(&per_key_table(&per_key));
+        report.insert_result("per_key", &per_key);
+    }
+    let mut by_bank: BTreeMap<String, Vec<RowWarning>> = BTreeMap::new();
+    for warning in warnings {
+        by_bank.entry(warning.bank()).or_default().push(warning);
+    }
+    report.insert_result("warnings", &by_bank);
+    if !notify.is_empty() {
+        let summary = notification(&report, &notify_layout, previous);
+        let client = Client::new();
+        for url in &notify {
+            if let Err(e) = notify::send(&client, url, &summary).await {
+                report.warn(fill(Msg::NotifyFailed, &[url, &e]));
+            }
+        }
+    }
+    report
+}
+
+fn per_key_table(lines: &[KeyReport]) -> String {
+    let mut table = Table::new(&["key", "banks", "branches", "failed", "last banks", "last branches", "dropped"]);
+    for line in lines {
+        let before = |count: fn(&keycount::KeyCount) -> usize| line.previous.as_ref().map(count).map(|n| n.to_string());
+        table.push(vec![
+            line.search_key.to_string(),
+            line.current.banks.to_string(),
+            line.current.branches.to_string(),
+            line.current.failed.to_string(),
+            before(|count| count.banks).unwrap_or_default(),
+            before(|count| count.branches).unwrap_or_default(),
+            if line.dropped { "yes".to_owned() } else { String::new() },
+        ]);
+    }
+    table.to_string()
+}
+
+fn notification(report: &Report, layout: &Layout, previous: Option<Vec<Bank>>) -> Summary {
+    let status = match report.exit_code {
+        ExitCode::Success => "ok",
+        ExitCode::Partial => "partial",
+        _ => "failed",
+    };
+    let counts = ["banks", "completed", "files", "retried", "queued_failures"]
+        .iter()
+        .filter_map(|key| report.results.get(*key).map(|value| ((*key).to_owned(), value.to_string())))
+        .collect();
+    let changes = match (previous, report.exit_code) {
+        (Some(previous), ExitCode::Success | ExitCode::Partial) => load_json_dataset(layout)
+            .map(|current| diff::diff(&previous, &current).iter().map(|change| change_cells(change).join(" ")).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    Summary {
+        title: fill(Msg::CrawlNotification, &[&status]),
+        ok: report.exit_code == ExitCode::Success,
+        counts,
+        changes,
+        failures: report.errors.iter().chain(&report.warnings).cloned().collect(),
+    }
+}
+
+async fn crawl(
+    opt: CrawlOpt,
+    layout: Layout,
+    cancel: CancellationToken,
+    warnings: Arc<ParseWarnings>,
+    counter: Arc<KeyCounter>,
+) -> Result<Report, Error> {
+    let _lock = CrawlLock::acquire(&layout)?;
+    compiled::invalidate(&layout);
+    let mut summary = CrawlSummary::default();
+    let mut lines = Vec::new();
+    let client = ZnginClient::builder()
+        .source(source(&layout, opt.source.as_deref())?)
+        .jitter(Duration::from_millis(opt.jitter_ms))
+        .max_bandwidth(opt.max_bandwidth)
+        .max_requests(opt.max_requests)
+        .host_limits(opt.host_limits.clone())
+        .bank_concurrency(opt.bank_concurrency)
+        .branch_concurrency(opt.branch_concurrency)
+        .window(opt.window)
+        .markup(Markup::load(&layout)?)
+        .enrich(opt.enrich)
+        .build()?;
+    let queue = Arc::new(RetryQueue::load(layout.retry_queue_file())?);
+    if !queue.is_empty() {
+        match retry::drain(&queue, &client, &layout).await {
+            Ok(retried) => {
+                summary.retried = retried;
+                lines.push(fill(Msg::RetriedQueue, &[&retried, &queue.len()]));
+            }
+            Err(Error::RequestBudgetExhausted) => {
+                queue.save()?;
+                summary.queued_failures = queue.len();
+                summary.stopped = Some("request_budget_exhausted");
+                lines.push(t(Msg::BudgetExhaustedWhileRetrying).to_owned());
+                return Ok(finish(summary, lines));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let progress = Arc::new(ConsoleProgress::default());
+    // Branch files wait in .staged with the bank list until the crawl passes the anomaly checks.
+    let writer = Arc::new(BankWriter::new(
+        layout.clone(),
+        plan::staged(&layout),
+        opt.on_duplicate,
+        queue.clone(),
+        opt.write_concurrency,
+    ));
+    let observers: Vec<Arc<dyn ProgressObserver>> = vec![progress.clone(), queue.clone(), warnings, counter, writer.clone()];
+    let observer: Arc<dyn ProgressObserver> = Arc::new(observers);
+    // A resumed crawl carries on with the plan of the crawl it resumes, once that got past the bank list.
+    // Crawls that wrote no plan resume from the bank list and the banks marked done instead.
+    let plan = if opt.resume { Plan::load(&layout)? } else { None };
+    let resumed = plan.as_ref().is_some_and(Plan::is_listed);
+    let mut banks = match &plan {
+        Some(plan) if resumed => {
+            if opt.shard.is_some() && opt.shard != plan.shard {
+                let planned = plan.shard.map_or_else(|| "all banks".to_owned(), |shard| format!("shard {}", shard));
+                return Err(Error::PlanMismatch(format!("the crawl being resumed covers {}", planned)));
+            }
+            // In plan order, which put the banks most worth fetching first.
+            let pending = plan
+                .pending_banks()
+                .into_iter()
+                .enumerate()
+                .map(|(position, code)| (code, position))
+                .collect::<HashMap<&BankCode, usize>>();
+            progress.plan(plan);
+            let mut banks = plan::planned_banks(&layout)?
+                .into_values()
+                .filter(|bank| pending.contains_key(&bank.code))
+                .collect::<Vec<Bank>>();
+            banks.sort_by_key(|bank| pending[&bank.code]);
+            banks
+        }
+        None if opt.resume => {
+            let mut banks = plan::planned_banks(&layout)?
+                .into_values()
+                .filter(|bank| opt.shard.is_none_or(|shard| shard.contains(&bank.code)))
+                .collect::<Vec<Bank>>();
+            banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+            banks
+        }
+        _ => {
+            let mut plan = Plan::start(&layout, all_search_keys(), opt.shard)?;
+            let banks = match client.fetch_all_banks(observer.clone(), cancel.clone(), all_search_keys()).await {
+                Ok(banks) => banks,
+                Err(Error::RequestBudgetExhausted) => {
+                    queue.save()?;
+                    summary.queued_failures = queue.len();
+                    summary.stopped = Some("request_budget_exhausted");
+                    lines.push(t(Msg::BudgetExhaustedBeforeBankList).to_owned());
+                    return Ok(finish(summary, lines));
+                }
+                Err(e) => return Err(e),
+            };
+            if cancel.is_cancelled() {
+                queue.save()?;
+                summary.queued_failures = queue.len();
+                summary.stopped = Some("cancelled");
+                lines.push(t(Msg::CancelledBeforeBankList).to_owned());
+                return Ok(finish(summary, lines));
+            }
+            let (mut banks, conflicts) = dedup::dedup_banks(banks, opt.on_duplicate)?;
+            summary.conflicts.extend(conflicts);
+            if let Some(shard) = opt.shard {
+                banks.retain(|bank| shard.contains(&bank.code));
+            }
+            let previous = load_banks(&layout).ok();
+            if let Some(previous) = &previous {
+                let counted = previous.keys().filter(|code| opt.shard.is_none_or(|shard| shard.contains(code))).count();
+                anomaly::check(Counted::Banks, counted, banks.len(), opt.max_drop)?;
+            }
+            let scheduled = schedule::order(&layout, &mut banks, &previous.unwrap_or_default());
+            lines.push(fill(Msg::Scheduled, &[&scheduled.new, &scheduled.changed, &scheduled.unchanged]));
+            summary.scheduled = Some(scheduled);
+            if opt.enrich {
+                summary.enrich_failed = client.enrich_banks(&mut banks, cancel.clone()).await;
+                let enriched = banks.iter().filter(|bank| bank.address.is_some() || bank.website.is_some()).count();
+                lines.push(fill(Msg::Enriched, &[&enriched, &banks.len()]));
+                summary.enriched = Some(enriched);
+            }
+            plan::stage(&layout, &banks, opt.shard)?;
+            plan.listed(&layout, &banks, &queue.failed_search_keys()).await?;
+            progress.plan(&plan);
+            banks
+        }
+    };
+    let max_age = opt.freshness.map(|hours| Duration::from_secs(hours * 60 * 60));
+    if (opt.resume && !resumed) || max_age.is_some() {
+        banks.retain(|bank| !marker::is_fresh(&layout, bank, max_age));
+    }
+    let completed = client.iterate_banks(observer, &cancel, &mut banks).await?;
+    queue.save()?;
+    let (written, conflicts) = writer.finish().await?;
+    summary.conflicts.extend(conflicts);
+    // Against everything staged, which includes what the crawl being resumed got.
+    anomaly::check_branches(&plan::staged_branches(&layout)?, &layout, opt.max_drop)?;
+    // A bank list missing the banks of a search key whose request failed would drop them from banks.json,
+    // so it stays staged for the retry of that key to fill in.
+    plan::commit(&layout, queue.failed_search_keys().is_empty())?;
+    lines.push(written.to_string());
+    Manifest::build(layout.out())?.save(&layout.manifest_file())?;
+    match &opt.sign_key {
+        Some(sign_key) => signing::sign(&layout, sign_key)?,
+        // A signature of the previous manifest would only fail verification.
+        None => {
+            let _ = std::fs::remove_file(layout.signature_file());
+        }
+    }
+    summary.banks = banks.len();
+    summary.completed = completed;
+    summary.files = written.files;
+    summary.bytes = written.bytes;
+    summary.queued_failures = queue.len();
+    if completed < banks.len() {
+        let reason = if cancel.is_cancelled() { Msg::Cancelled } else { Msg::BudgetExhausted };
+        summary.stopped = Some(if cancel.is_cancelled() { "cancelled" } else { "request_budget_exhausted" });
+        lines.push(fill(Msg::StoppedAfter, &[&t(reason), &completed, &banks.len()]));
+        return Ok(finish(summary, lines));
+    }
+    lines.push(t(Msg::Done).to_owned());
+    Ok(finish(summary, lines))
+}
+
+pub fn conflict_warning(conflict: &Conflict) -> String {
+    let (kept, dropped) = (&conflict.kept, &conflict.dropped);
+    match &conflict.branch_code {
+        Some(branch_code) => fill(
+            Msg::DuplicateBranch,
+            &[&conflict.bank_code, branch_code, &kept.name, &kept.phonetic, &dropped.name, &dropped.phonetic],
+        ),
+        None => fill(
+            Msg::DuplicateBank,
+            &[&conflict.bank_code, &kept.name, &kept.phonetic, &dropped.name, &dropped.phonetic],
+        ),
+    }
+}
+
+fn finish(summary: CrawlSummary, lines: Vec<String>) -> Report {
+    let mut report = Report::new(&summary, lines.iter().map(|line| format!("{}\n", line)).collect());
+    for conflict in &summary.conflicts {
+        report.warn(conflict_warning(conflict));
+    }
+    for bank_code in &summary.enrich_failed {
+        report.warn(fill(Msg::EnrichFailed, &[&bank_code.0]));
+    }
+    if summary.queued_failures > 0 {
+        report.warn(fill(Msg::QueuedFailures, &[&summary.queued_failures]));
+    }
+    if summary.queued_failures > 0 || summary.stopped.is_some() {
+        report = report.partial();
+    }
+    report
+}
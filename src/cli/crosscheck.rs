@@ -0,0 +1,91 @@
+use reqwest::Client;
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::crosscheck::{self, Discrepancy, Kind};
+use zngn::layout::Layout;
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::table::Table;
+use crate::cli::{load, Report};
+
+#[derive(Debug, StructOpt)]
+pub struct CrossCheckOpt {
+    /// Base URL of the zengin-code data, the directory holding banks.json and branches/
+    #[structopt(long, default_value = crosscheck::DEFAULT_SOURCE)]
+    source: String,
+    /// Only compare the bank lists, without downloading a branch file per bank
+    #[structopt(long)]
+    banks_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CrossCheckSummary<'a> {
+    source: &'a str,
+    banks: usize,
+    discrepancies: &'a [Discrepancy],
+}
+
+fn cells(discrepancy: &Discrepancy) -> Vec<String> {
+    let kind = match discrepancy.kind {
+        Kind::OnlyOurs => "only ours",
+        Kind::OnlyTheirs => "only theirs",
+        Kind::Name => "name",
+        Kind::Reading => "reading",
+    };
+    let code = match &discrepancy.branch_code {
+        Some(branch_code) => format!("{}-{}", discrepancy.bank_code, branch_code),
+        None => discrepancy.bank_code.clone(),
+    };
+    let (ours, theirs) = (&discrepancy.ours, &discrepancy.theirs);
+    vec![kind.to_owned(), code, ours.clone().unwrap_or_default(), theirs.clone().unwrap_or_default()]
+}
+
+// Compares the saved dataset with the one zengin-code publishes, which is kept up to date by hand from
+// other sources; banks or branches only one side has, and names or readings that differ, point at
+// rows the scraper may have misread.
+pub async fn run(opt: CrossCheckOpt, layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let branches_of = if opt.banks_only {
+        Vec::new()
+    } else {
+        banks
+            .iter()
+            .filter(|bank| !bank.branches.is_empty())
+            .map(|bank| bank.code.0.as_str())
+            .collect::<Vec<&str>>()
+    };
+    let reference = match crosscheck::fetch(&Client::new(), &opt.source, &branches_of).await {
+        Ok(reference) => reference,
+        Err(e) => return Report::from_error(&e),
+    };
+    let discrepancies = crosscheck::compare(&banks, &reference);
+    let summary = CrossCheckSummary {
+        source: &opt.source,
+        banks: banks.len(),
+        discrepancies: &discrepancies,
+    };
+    if discrepancies.is_empty() {
+        return Report::new(&summary, format!("{}\n", fill(Msg::CrossCheckClean, &[&banks.len(), &opt.source])));
+    }
+    let mut table = Table::new(&["kind", "code", "ours", "theirs"]);
+    for discrepancy in &discrepancies {
+        table.push(cells(discrepancy));
+    }
+    let counted = |kind| discrepancies.iter().filter(|discrepancy| discrepancy.kind == kind).count();
+    let text = fill(
+        Msg::CrossCheckFound,
+        &[
+            &discrepancies.len(),
+            &opt.source,
+            &counted(Kind::OnlyOurs),
+            &counted(Kind::OnlyTheirs),
+            &counted(Kind::Name),
+            &counted(Kind::Reading),
+        ],
+    );
+    Report::new(&summary, format!("{}{}\n", table, text))
+}
+
@@ -0,0 +1,177 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::dataset::Dataset;
+use zngn::delta::{self, Delta, Op};
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::manifest::Manifest;
+use zngn::writer::save_branch_files;
+use zngn::{compiled, load_json_dataset, migrate, save_banks, save_index, to_hashmap, Bank, Error};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct DeltaOpt {
+    /// Older snapshot: an output directory or a JSON file of banks or a saved dataset
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+    /// Newer snapshot, in the same forms as OLD
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+    /// File the patch is written to
+    #[structopt(short = "o", long = "patch", parse(from_os_str))]
+    patch: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ApplyDeltaOpt {
+    /// Patch written by `zngn delta`
+    #[structopt(parse(from_os_str))]
+    patch: PathBuf,
+    /// Patch this JSON file of banks instead of the output directory
+    #[structopt(long, parse(from_os_str))]
+    to: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct DeltaSummary {
+    from: String,
+    to: String,
+    banks_added: usize,
+    banks_removed: usize,
+    banks_updated: usize,
+    branches_put: usize,
+    branches_removed: usize,
+}
+
+impl DeltaSummary {
+    fn new(delta: &Delta) -> Self {
+        let mut summary = Self {
+            from: delta.from.clone(),
+            to: delta.to.clone(),
+            ..Self::default()
+        };
+        for op in &delta.ops {
+            match op {
+                Op::AddBank { .. } => summary.banks_added += 1,
+                Op::RemoveBank { .. } => summary.banks_removed += 1,
+                Op::UpdateBank { .. } => summary.banks_updated += 1,
+                Op::PutBranch { .. } => summary.branches_put += 1,
+                Op::RemoveBranch { .. } => summary.branches_removed += 1,
+            }
+        }
+        summary
+    }
+
+    fn counts(&self) -> String {
+        fill(
+            Msg::DeltaCounts,
+            &[
+                &self.banks_added,
+                &self.banks_removed,
+                &self.banks_updated,
+                &self.branches_put,
+                &self.branches_removed,
+            ],
+        )
+    }
+}
+
+// A snapshot is either an output directory, read in the layout given by --layout, or a single file.
+fn read_snapshot(layout: &Layout, path: &Path) -> Result<Vec<Bank>, Error> {
+    if path.is_dir() {
+        load_json_dataset(&layout.relocated(path.to_path_buf()))
+    } else {
+        Dataset::load(path).map(|dataset| dataset.banks)
+    }
+}
+
+fn read_patch(path: &Path) -> Result<Delta, Error> {
+    let file = File::open(path).map_err(Error::file(path))?;
+    serde_json::from_reader(file).map_err(Error::invalid(path))
+}
+
+pub fn run(opt: DeltaOpt, layout: &Layout) -> Report {
+    let written = read_snapshot(layout, &opt.old).and_then(|old| {
+        let new = read_snapshot(layout, &opt.new)?;
+        let delta = delta::delta(&old, &new);
+        let json = serde_json::to_vec(&delta).map_err(Error::SerializeFailed)?;
+        fs::write(&opt.patch, json).map_err(Error::file(&opt.patch))?;
+        Ok(delta)
+    });
+    match written {
+        Ok(delta) => {
+            let summary = DeltaSummary::new(&delta);
+            let text = fill(Msg::DeltaWritten, &[&delta.ops.len(), &opt.patch.display(), &summary.counts()]);
+            Report::new(&summary, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+pub async fn apply(opt: ApplyDeltaOpt, layout: &Layout) -> Report {
+    let applied = match read_patch(&opt.patch) {
+        Ok(delta) => match &opt.to {
+            Some(path) => apply_to_file(&delta, path).map(|_| delta),
+            None => apply_to_dir(&delta, layout).await.map(|_| delta),
+        },
+        Err(e) => Err(e),
+    };
+    match applied {
+        Ok(delta) => {
+            let summary = DeltaSummary::new(&delta);
+            let target = opt.to.unwrap_or_else(|| layout.out().to_path_buf());
+            let text = fill(Msg::DeltaApplied, &[&delta.ops.len(), &target.display(), &summary.counts()]);
+            Report::new(&summary, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+// The file is rewritten as a map of banks keyed by code, the shape of banks.json.
+fn apply_to_file(delta: &Delta, path: &Path) -> Result<(), Error> {
+    let mut banks = migrate::read_file(path)?;
+    delta::apply(&mut banks, delta)?;
+    let json = serde_json::to_vec(&to_hashmap(&banks)).map_err(Error::SerializeFailed)?;
+    fs::write(path, json).map_err(Error::file(path))
+}
+
+// Only the branch files of banks the patch touches are rewritten. The manifest is brought up to date
+// when there is one, which leaves any signature of the old one stale, so that is removed.
+async fn apply_to_dir(delta: &Delta, layout: &Layout) -> Result<(), Error> {
+    let _lock = CrawlLock::acquire(layout)?;
+    let mut banks = load_json_dataset(layout)?;
+    for bank in &mut banks {
+        bank.aliases.clear();
+    }
+    let before = banks.clone();
+    let touched = delta::apply(&mut banks, delta)?;
+    compiled::invalidate(layout);
+    for bank in before.iter().filter(|bank| !banks.iter().any(|b| b.code == bank.code)) {
+        let _ = fs::remove_file(layout.branch_file(bank));
+    }
+    let listed = banks
+        .iter()
+        .map(|bank| Bank {
+            branches: Vec::new(),
+            ..bank.clone()
+        })
+        .collect::<Vec<Bank>>();
+    save_banks(&listed, layout)?;
+    save_index(&banks, layout)?;
+    let changed = banks
+        .iter()
+        .filter(|bank| touched.contains(&bank.code.0))
+        .cloned()
+        .collect::<Vec<Bank>>();
+    save_branch_files(&changed, layout, 16).await?;
+    if layout.manifest_file().exists() {
+        Manifest::build(layout.out())?.save(&layout.manifest_file())?;
+        let _ = fs::remove_file(layout.signature_file());
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::collate::{self, SortKey, SortOrder};
+use zngn::export::{self, Options};
+use zngn::filter::Filter;
+use zngn::kana::PhoneticForm;
+use zngn::layout::Layout;
+use zngn::naming::Naming;
+use zngn::zengin::Abbreviations;
+
+use crate::cli::{formats, load, ExitCode, Report, Table};
+
+#[derive(Debug, StructOpt)]
+pub struct ExportOpt {
+    /// File the dataset is written to
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    /// File format: json, csv with one row per branch, sqlite, xlsx with a sheet of banks and one of
+    /// branches, zengin with the codes and 15 character kana names transfer files take, or another
+    /// registered format
+    #[structopt(long, default_value = "json")]
+    format: String,
+    /// Add FTS5 full-text tables over names and phonetics to a sqlite export
+    #[structopt(long)]
+    fts: bool,
+    /// Key style of json exports: snake, camel (camelCase), or ja (Japanese field names)
+    #[structopt(long, default_value = "snake")]
+    naming: Naming,
+    /// Kana of phonetic fields: halfwidth katakana as published, fullwidth katakana, or hiragana
+    #[structopt(long, default_value = "halfwidth")]
+    phonetic_form: PhoneticForm,
+    /// Order banks and branches by code, name or phonetic (gojūon order)
+    #[structopt(long, default_value = "code")]
+    sort: SortKey,
+    /// Sort direction: asc or desc
+    #[structopt(long, default_value = "asc")]
+    order: SortOrder,
+    /// Only export banks matching a filter, e.g. "code=0* AND branch_count>100"
+    #[structopt(long = "where")]
+    filter: Option<Filter>,
+    /// Rules abbreviating kana names of zengin exports, in place of abbreviations.json in the
+    /// output directory or the built-in ones
+    #[structopt(long, parse(from_os_str))]
+    abbreviations: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportSummary {
+    path: PathBuf,
+    banks: usize,
+    branches: usize,
+}
+
+pub fn run(opt: ExportOpt, layout: &Layout) -> Report {
+    let formats = formats();
+    let exporter = match formats.find(&opt.format) {
+        Ok(exporter) => exporter,
+        Err(e) => return Report::failed(ExitCode::Validation, e),
+    };
+    let mut banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    if let Some(filter) = &opt.filter {
+        banks.retain(|bank| filter.matches(bank));
+    }
+    collate::sort_dataset(&mut banks, opt.sort, opt.order);
+    let abbreviations = match &opt.abbreviations {
+        Some(path) => Abbreviations::read(path),
+        None => Abbreviations::load(layout),
+    };
+    let abbreviations = match abbreviations {
+        Ok(abbreviations) => abbreviations,
+        Err(e) => return Report::from_error(&e),
+    };
+    let options = Options {
+        fts: opt.fts,
+        naming: opt.naming,
+        phonetic_form: opt.phonetic_form,
+        abbreviations,
+    };
+    if let Err(e) = export::export_to_file_with(exporter, &banks, &options, &opt.path) {
+        return Report::from_error(&e);
+    }
+    let summary = ExportSummary {
+        path: opt.path,
+        banks: banks.len(),
+        branches: banks.iter().map(|bank| bank.branches.len()).sum(),
+    };
+    let mut table = Table::new(&["path", "banks", "branches"]);
+    table.push(vec![
+        summary.path.to_string_lossy().into_owned(),
+        summary.banks.to_string(),
+        summary.branches.to_string(),
+    ]);
+    Report::new(&summary, table.to_string())
+}
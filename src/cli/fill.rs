@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::cancel::CancellationToken;
+use zngn::client::ZnginClient;
+use zngn::compiled;
+use zngn::dedup::Policy;
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::manifest::Manifest;
+use zngn::markup::Markup;
+use zngn::progress::ProgressObserver;
+use zngn::retry::RetryQueue;
+use zngn::throttle::{parse_bandwidth, HostLimits};
+use zngn::writer::BankWriter;
+use zngn::plan::{self, Plan};
+use zngn::{missing_branch_files, Bank, BankCode, Error};
+
+use crate::cli::crawl::{conflict_warning, source, ConsoleProgress};
+use crate::cli::i18n::{fill, t, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct FillOpt {
+    /// Base URL of the site, e.g. that of a local `zngn mock-server`; the base in source.json, or
+    /// https://zengin.ajtw.net, when omitted
+    #[structopt(long)]
+    source: Option<String>,
+    /// Only list the banks whose branch files are missing, without fetching anything
+    #[structopt(long)]
+    dry_run: bool,
+    /// Sleep a random duration up to this many milliseconds before each request
+    #[structopt(long, default_value = "0")]
+    jitter_ms: u64,
+    /// Cap the overall download rate, in bytes per second; no cap when omitted
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    max_bandwidth: Option<u64>,
+    /// Limits for one host instead of --jitter-ms and --max-bandwidth, repeatable
+    #[structopt(long = "host-limit", number_of_values = 1)]
+    host_limits: Vec<HostLimits>,
+    /// Branch list requests in flight at the same time; unlimited when omitted
+    #[structopt(long)]
+    branch_concurrency: Option<usize>,
+    /// Stop issuing new requests after this many have been sent
+    #[structopt(long)]
+    max_requests: Option<usize>,
+    /// Number of branch files written at the same time
+    #[structopt(long, default_value = "16")]
+    write_concurrency: usize,
+    /// What to do when a branch code is seen twice with different names: keep-first, keep-latest or fail
+    #[structopt(long, default_value = "keep-first")]
+    on_duplicate: Policy,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct FillSummary {
+    // Codes of the banks found without a usable branch file.
+    missing: Vec<String>,
+    filled: usize,
+    files: usize,
+    bytes: usize,
+    queued_failures: usize,
+}
+
+pub async fn run(opt: FillOpt, layout: &Layout) -> Report {
+    match fill_missing(opt, layout).await {
+        Ok(report) => report,
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+// The banks the plan of the last crawl has yet to save, in plan order, then those whose branch file
+// is missing or empty, which covers files lost after they were saved.
+fn missing(layout: &Layout) -> Result<Vec<Bank>, Error> {
+    let mut missing = match Plan::load(layout)? {
+        Some(plan) if plan.is_listed() => {
+            let banks = plan::planned_banks(layout)?;
+            plan.pending_banks().into_iter().filter_map(|code| banks.get(code).cloned()).collect()
+        }
+        _ => Vec::new(),
+    };
+    // A first crawl cut off before committing leaves no bank list but the staged one.
+    if missing.is_empty() || layout.banks_file().exists() {
+        let pending = missing.iter().map(|bank| bank.code.clone()).collect::<HashSet<BankCode>>();
+        missing.extend(missing_branch_files(layout)?.into_iter().filter(|bank| !pending.contains(&bank.code)));
+    }
+    Ok(missing)
+}
+
+// Fetches the branches of only the banks a crawl left without a branch file, leaving the bank list and
+// every other branch file as they are.
+async fn fill_missing(opt: FillOpt, layout: &Layout) -> Result<Report, Error> {
+    let _lock = CrawlLock::acquire(layout)?;
+    let mut banks = missing(layout)?;
+    let mut summary = FillSummary {
+        missing: banks.iter().map(|bank| bank.code.0.clone()).collect(),
+        ..FillSummary::default()
+    };
+    if banks.is_empty() {
+        return Ok(Report::new(&summary, format!("{}\n", t(Msg::NothingToFill))));
+    }
+    if opt.dry_run {
+        let text = fill(Msg::MissingBranchFiles, &[&banks.len(), &summary.missing.join(", ")]);
+        return Ok(Report::new(&summary, format!("{}\n", text)));
+    }
+    compiled::invalidate(layout);
+    let client = ZnginClient::builder()
+        .source(source(layout, opt.source.as_deref())?)
+        .jitter(Duration::from_millis(opt.jitter_ms))
+        .max_bandwidth(opt.max_bandwidth)
+        .max_requests(opt.max_requests)
+        .host_limits(opt.host_limits)
+        .branch_concurrency(opt.branch_concurrency)
+        .markup(Markup::load(layout)?)
+        .build()?;
+    // Requests that fail go to the retry queue the next crawl drains, as they would during a crawl.
+    let queue = Arc::new(RetryQueue::load(layout.retry_queue_file())?);
+    let progress = Arc::new(ConsoleProgress::default());
+    if let Some(plan) = Plan::load(layout)?.filter(Plan::is_listed) {
+        progress.plan(&plan);
+    }
+    let writer = Arc::new(BankWriter::new(layout.clone(), layout.clone(), opt.on_duplicate, queue.clone(), opt.write_concurrency));
+    let observers: Vec<Arc<dyn ProgressObserver>> = vec![progress, queue.clone(), writer.clone()];
+    let observer: Arc<dyn ProgressObserver> = Arc::new(observers);
+    let cancel = CancellationToken::new();
+    {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        });
+    }
+    let completed = client.iterate_banks(observer, &cancel, &mut banks).await?;
+    queue.save()?;
+    let (written, conflicts) = writer.finish().await?;
+    // The manifest no longer matches, so it is brought up to date and the stale signature dropped.
+    if layout.manifest_file().exists() {
+        Manifest::build(layout.out())?.save(&layout.manifest_file())?;
+        let _ = fs::remove_file(layout.signature_file());
+    }
+    summary.filled = completed;
+    summary.files = written.files;
+    summary.bytes = written.bytes;
+    summary.queued_failures = queue.len();
+    let text = fill(Msg::Filled, &[&completed, &banks.len(), &written]);
+    let mut report = Report::new(&summary, format!("{}\n", text));
+    for conflict in &conflicts {
+        report.warn(conflict_warning(conflict));
+    }
+    if summary.queued_failures > 0 {
+        report.warn(fill(Msg::QueuedFailures, &[&summary.queued_failures]));
+    }
+    if completed < banks.len() || summary.queued_failures > 0 {
+        report = report.partial();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn missing_test() {
+        use std::fs;
+        use zngn::layout::{Layout, DEFAULT_TEMPLATE};
+        use zngn::plan::{self, Item, Plan};
+        use zngn::{save_banks, Bank, BankCode, Branch};
+        use crate::cli::fill::missing;
+
+        let dir = std::env::temp_dir().join(format!("zngn-fill-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let bank = |code: &str| {
+            let mut bank = Bank::new(format!("銀行{}", code), "ｷﾞﾝｺｳ".to_owned(), code.to_owned(), String::new());
+            bank.branches = vec![Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned())];
+            bank
+        };
+        let banks = [bank("0001"), bank("0005"), bank("0009")];
+        save_banks(&banks, &layout).unwrap();
+        let mut plan = Plan::start(&layout, "あ".chars(), None).unwrap();
+        plan.listed(&layout, &banks, &Default::default()).await.unwrap();
+        for bank in &banks[..2] {
+            bank.save_as_file(&layout).await.unwrap();
+            plan::record(&layout, &Item::Branches { bank_code: bank.code.clone() }).await.unwrap();
+        }
+        // Pending in the plan, then logged as done but emptied since.
+        Bank { branches: Vec::new(), ..bank("0001") }.save_as_file(&layout).await.unwrap();
+        let codes = missing(&layout).unwrap().into_iter().map(|bank| bank.code).collect::<Vec<BankCode>>();
+        assert_eq!(codes, [BankCode("0009".to_owned()), BankCode("0001".to_owned())]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,458 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use structopt::clap::App;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Lang::En),
+            "ja" => Ok(Lang::Ja),
+            _ => Err(format!("unknown language: {}", s)),
+        }
+    }
+}
+
+impl Lang {
+    // A POSIX locale name such as ja_JP.UTF-8; anything that is not Japanese gets English.
+    fn from_locale(locale: &str) -> Self {
+        if locale.starts_with("ja") {
+            Lang::Ja
+        } else {
+            Lang::En
+        }
+    }
+
+    // LC_ALL overrides LC_MESSAGES, which overrides LANG.
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map(|locale| Lang::from_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+
+    // The help text is built before clap parses anything, so for it `--lang` is picked out of the raw
+    // arguments; everything after parsing goes by the parsed option.
+    pub fn from_args(args: &[OsString]) -> Option<Self> {
+        let mut args = args.iter().filter_map(|arg| arg.to_str());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+            let value = match arg.strip_prefix("--lang") {
+                Some("") => args.next(),
+                Some(rest) => rest.strip_prefix('='),
+                None => None,
+            };
+            if let Some(lang) = value.and_then(|value| value.parse().ok()) {
+                return Some(lang);
+            }
+        }
+        None
+    }
+}
+
+static JAPANESE: AtomicBool = AtomicBool::new(false);
+
+pub fn set(lang: Lang) {
+    JAPANESE.store(lang == Lang::Ja, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    if JAPANESE.load(Ordering::Relaxed) {
+        Lang::Ja
+    } else {
+        Lang::En
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Msg {
+    Warning,
+    Error,
+    UnexpectedFailure,
+    NetworkFailure,
+    ParseFailure,
+    ValidationFailure,
+    LockHeld,
+    CountAnomaly,
+    BankCountDropped,
+    BranchCountDropped,
+    LoadFailed,
+    RequestFailed,
+    KeyYieldDropped,
+    KeyCountsNotSaved,
+    RetriedQueue,
+    BudgetExhaustedWhileRetrying,
+    CancelledBeforeBankList,
+    BudgetExhaustedBeforeBankList,
+    Cancelled,
+    BudgetExhausted,
+    StoppedAfter,
+    Done,
+    QueuedFailures,
+    Enriched,
+    Scheduled,
+    EnrichFailed,
+    StrictMode,
+    SkippedBankRow,
+    SkippedBranchRow,
+    KeptBankRow,
+    KeptBranchRow,
+    DuplicateBank,
+    DuplicateBranch,
+    NoBank,
+    NoBranch,
+    IndexSearchFailed,
+    Indexed,
+    ApiKeysUnreadable,
+    Serving,
+    ServingSnapshot,
+    MoreResults,
+    SchemaValid,
+    SchemaViolations,
+    Migrated,
+    MigrateSkipped,
+    BackedUp,
+    Restored,
+    Promoted,
+    PromotedUnchecked,
+    RolledBack,
+    RestoreWouldReplace,
+    Merged,
+    MergedWithoutBranches,
+    ChecksumsMatch,
+    ChecksumsDiffer,
+    FileMissing,
+    FileChanged,
+    FileUnlisted,
+    SignatureValid,
+    KeysWritten,
+    Signed,
+    DeltaWritten,
+    DeltaApplied,
+    DeltaCounts,
+    SyncUpToDate,
+    SyncPatched,
+    SyncDownloaded,
+    SyncFailed,
+    CrawlNotification,
+    NotifyFailed,
+    NothingToFill,
+    NoPlan,
+    PlanShard,
+    MissingBranchFiles,
+    Filled,
+    CrossCheckClean,
+    CrossCheckFound,
+    TransferValid,
+    TransferInvalid,
+    BatchChecked,
+    ResultsWritten,
+    #[cfg(feature = "dev")]
+    MockServing,
+}
+
+fn text(lang: Lang, msg: Msg) -> &'static str {
+    match lang {
+        Lang::En => match msg {
+            Msg::Warning => "warning",
+            Msg::Error => "error",
+            Msg::UnexpectedFailure => "unexpected failure",
+            Msg::NetworkFailure => "network failure",
+            Msg::ParseFailure => "parse failure",
+            Msg::ValidationFailure => "validation failure",
+            Msg::LockHeld => "another crawl holds the lock on the output directory",
+            Msg::CountAnomaly => "suspicious crawl result",
+            Msg::BankCountDropped => "found {} banks, down from {}; the saved snapshot was kept (see --max-drop)",
+            Msg::BranchCountDropped => "found {} branches, down from {}; the saved branch files were kept (see --max-drop)",
+            Msg::LoadFailed => "failed to load the dataset: {}",
+            Msg::RequestFailed => "request for {} failed: {}",
+            Msg::KeyYieldDropped => "search key {} found {} banks and {} branches, down from {} and {}; check its requests",
+            Msg::KeyCountsNotSaved => "could not save the per key counts: {}",
+            Msg::RetriedQueue => "retried {} queued requests, {} still failing",
+            Msg::BudgetExhaustedWhileRetrying => "request budget exhausted while retrying queued requests",
+            Msg::CancelledBeforeBankList => "cancelled before the bank list was complete",
+            Msg::BudgetExhaustedBeforeBankList => "request budget exhausted before the bank list was complete",
+            Msg::Cancelled => "cancelled",
+            Msg::BudgetExhausted => "request budget exhausted",
+            Msg::StoppedAfter => "{} after {} of {} banks, rerun with --resume to continue",
+            Msg::Done => "DONE",
+            Msg::QueuedFailures => "{} failed requests queued for the next run",
+            Msg::Enriched => "found an address or website for {} of {} banks",
+            Msg::Scheduled => "fetching branches of {} new, then {} changed, then {} unchanged banks",
+            Msg::EnrichFailed => "could not read the page of bank {}",
+            Msg::StrictMode => "strict mode: {} malformed rows",
+            Msg::SkippedBankRow => "skipped bank row {} under {}: {}",
+            Msg::SkippedBranchRow => "skipped branch row {} for bank {} under {}: {}",
+            Msg::KeptBankRow => "kept bank row {} under {}: {}",
+            Msg::KeptBranchRow => "kept branch row {} for bank {} under {}: {}",
+            Msg::DuplicateBank => "duplicate bank {}: kept {} ({}), dropped {} ({})",
+            Msg::DuplicateBranch => "duplicate branch {}-{}: kept {} ({}), dropped {} ({})",
+            Msg::NoBank => "no bank with code {}",
+            Msg::NoBranch => "no branch with code {} in bank {}",
+            Msg::IndexSearchFailed => "failed to search the index, run `zngn index` first: {}",
+            Msg::Indexed => "indexed {} banks and branches",
+            Msg::ApiKeysUnreadable => "failed to read API keys from {}: {}",
+            Msg::Serving => "serving {} banks on http://{}",
+            Msg::ServingSnapshot => "also serving {} banks under /v/{}/",
+            Msg::MoreResults => "showing up to {} of {}, continue with --offset {}",
+            Msg::SchemaValid => "{} matches the schema ({} banks)",
+            Msg::SchemaViolations => "{} breaks the schema in {} places",
+            Msg::Migrated => "migrated {} banks and {} branches from {} files",
+            Msg::MigrateSkipped => "skipped {}: no banks in a known format",
+            Msg::BackedUp => "backed up {} files ({} bytes) to {}",
+            Msg::Restored => "restored {} files from {}",
+            Msg::Promoted => "{} now points at {}",
+            Msg::PromotedUnchecked => "promoted with --force, without checking the snapshot",
+            Msg::RolledBack => "{} points back at {}, in place of {}",
+            Msg::RestoreWouldReplace => "{} already holds a dataset, pass --force to replace it",
+            Msg::Merged => "merged {} snapshots: {} banks and {} branches in {}",
+            Msg::MergedWithoutBranches => "{} banks have no branches yet: {}",
+            Msg::ChecksumsMatch => "all {} files match manifest.json",
+            Msg::ChecksumsDiffer => "{} files don't match manifest.json",
+            Msg::FileMissing => "{} is missing",
+            Msg::FileChanged => "{} was changed",
+            Msg::FileUnlisted => "{} is not in manifest.json",
+            Msg::SignatureValid => "manifest.json is signed by the key {}",
+            Msg::KeysWritten => "wrote the private key to {} and the public key to {}",
+            Msg::Signed => "signed manifest.json, signature in {}",
+            Msg::DeltaWritten => "wrote {} changes to {} ({})",
+            Msg::DeltaApplied => "applied {} changes to {} ({})",
+            Msg::DeltaCounts => "banks: {} added, {} removed, {} updated; branches: {} added or changed, {} removed",
+            Msg::SyncUpToDate => "up to date with {} at version {}",
+            Msg::SyncPatched => "applied {} changes from {}, now at version {}",
+            Msg::SyncDownloaded => "downloaded {} banks from {}, now at version {}",
+            Msg::SyncFailed => "sync failed, keeping the local dataset: {}",
+            Msg::CrawlNotification => "zngn crawl: {}",
+            Msg::NotifyFailed => "could not notify {}: {}",
+            Msg::NothingToFill => "every bank in banks.json has its branch file",
+            Msg::NoPlan => "no crawl has written a plan in {}",
+            Msg::PlanShard => "the plan covers shard {}",
+            Msg::MissingBranchFiles => "{} banks have no branch file: {}",
+            Msg::Filled => "fetched the branches of {} of {} banks missing them; {}",
+            Msg::CrossCheckClean => "all {} banks agree with {}",
+            Msg::CrossCheckFound => "{} discrepancies with {}: {} only ours, {} only theirs, {} names, {} readings",
+            Msg::TransferValid => "the destination can be transferred to",
+            Msg::TransferInvalid => "the destination has {} problems",
+            Msg::ResultsWritten => "wrote {} records to {}",
+            Msg::BatchChecked => "checked {} rows: {} ok, {} with warnings, {} for review, {} with errors; {} had codes resolved from names; wrote {}",
+            #[cfg(feature = "dev")]
+            Msg::MockServing => "serving {} recorded pages on http://{}",
+        },
+        Lang::Ja => match msg {
+            Msg::Warning => "警告",
+            Msg::Error => "エラー",
+            Msg::UnexpectedFailure => "予期しないエラー",
+            Msg::NetworkFailure => "通信エラー",
+            Msg::ParseFailure => "解析エラー",
+            Msg::ValidationFailure => "入力エラー",
+            Msg::LockHeld => "別のクロールが出力ディレクトリをロックしています",
+            Msg::CountAnomaly => "クロール結果が不審です",
+            Msg::BankCountDropped => "銀行が {1} 件から {0} 件に減ったため、保存済みのスナップショットを残しました（--max-drop を参照）",
+            Msg::BranchCountDropped => "支店が {1} 件から {0} 件に減ったため、保存済みの支店ファイルを残しました（--max-drop を参照）",
+            Msg::LoadFailed => "データセットを読み込めませんでした: {}",
+            Msg::RequestFailed => "{} のリクエストに失敗しました: {}",
+            Msg::KeyYieldDropped => "検索キー {} の結果が銀行 {3} 件・支店 {4} 件から {1} 件・{2} 件に減りました。リクエストを確認してください",
+            Msg::KeyCountsNotSaved => "検索キーごとの件数を保存できませんでした: {}",
+            Msg::RetriedQueue => "保留中のリクエストを {} 件再試行しました（{} 件は失敗したままです）",
+            Msg::BudgetExhaustedWhileRetrying => "保留中のリクエストの再試行中にリクエスト上限に達しました",
+            Msg::CancelledBeforeBankList => "銀行一覧の取得が完了する前に中断しました",
+            Msg::BudgetExhaustedBeforeBankList => "銀行一覧の取得が完了する前にリクエスト上限に達しました",
+            Msg::Cancelled => "中断しました",
+            Msg::BudgetExhausted => "リクエスト上限に達しました",
+            Msg::StoppedAfter => "{}（{} / {} 銀行まで完了）。続きは --resume を付けて再実行してください",
+            Msg::Done => "完了",
+            Msg::QueuedFailures => "失敗したリクエスト {} 件を次回の実行に回しました",
+            Msg::Enriched => "{1} 銀行のうち {0} 銀行の住所またはウェブサイトが見つかりました",
+            Msg::Scheduled => "新しい銀行 {} 件、変更のあった銀行 {} 件、変更のない銀行 {} 件の順に支店を取得します",
+            Msg::EnrichFailed => "銀行 {} のページを読めませんでした",
+            Msg::StrictMode => "strict モード: 不正な行が {} 件あります",
+            Msg::SkippedBankRow => "銀行一覧の {} 行目をスキップしました（検索キー {}）: {}",
+            Msg::SkippedBranchRow => "銀行 {1} の支店一覧の {0} 行目をスキップしました（検索キー {}）: {}",
+            Msg::KeptBankRow => "銀行一覧の {} 行目を要確認として残しました（検索キー {}）: {}",
+            Msg::KeptBranchRow => "銀行 {1} の支店一覧の {0} 行目を要確認として残しました（検索キー {}）: {}",
+            Msg::DuplicateBank => "銀行コード {} が重複しています: {}（{}）を残し、{}（{}）を除外しました",
+            Msg::DuplicateBranch => "支店コード {}-{} が重複しています: {}（{}）を残し、{}（{}）を除外しました",
+            Msg::NoBank => "銀行コード {} の銀行はありません",
+            Msg::NoBranch => "支店コード {} の支店は銀行 {} にありません",
+            Msg::IndexSearchFailed => "索引を検索できませんでした。先に `zngn index` を実行してください: {}",
+            Msg::Indexed => "銀行と支店 {} 件を索引に登録しました",
+            Msg::ApiKeysUnreadable => "{} から API キーを読み込めませんでした: {}",
+            Msg::Serving => "銀行 {} 件を http://{} で配信しています",
+            Msg::ServingSnapshot => "銀行 {} 件を /v/{}/ でも配信しています",
+            Msg::MoreResults => "{1} 件中 {0} 件目まで表示しています。続きは --offset {} で表示できます",
+            Msg::SchemaValid => "{} はスキーマに適合しています（銀行 {} 件）",
+            Msg::SchemaViolations => "{} にスキーマに反する箇所が {} か所あります",
+            Msg::Migrated => "{2} 個のファイルから銀行 {0} 件、支店 {1} 件を移行しました",
+            Msg::MigrateSkipped => "{} をスキップしました: 既知の形式の銀行データがありません",
+            Msg::BackedUp => "{2} に {0} 個のファイル（{1} バイト）をバックアップしました",
+            Msg::Restored => "{1} から {0} 個のファイルを復元しました",
+            Msg::Promoted => "{} の参照先を {} にしました",
+            Msg::PromotedUnchecked => "--force のため、スナップショットを検査せずに切り替えました",
+            Msg::RolledBack => "{} の参照先を {2} から {1} に戻しました",
+            Msg::RestoreWouldReplace => "{} にはすでにデータセットがあります。置き換えるには --force を付けてください",
+            Msg::Merged => "{} 個のスナップショットをまとめました: {3} に {1} 銀行、{2} 支店",
+            Msg::MergedWithoutBranches => "{} 銀行の支店がまだありません: {}",
+            Msg::ChecksumsMatch => "{} 個のファイルすべてが manifest.json と一致しました",
+            Msg::ChecksumsDiffer => "{} 個のファイルが manifest.json と一致しません",
+            Msg::FileMissing => "{} がありません",
+            Msg::FileChanged => "{} が変更されています",
+            Msg::FileUnlisted => "{} は manifest.json に載っていません",
+            Msg::SignatureValid => "manifest.json は鍵 {} で署名されています",
+            Msg::KeysWritten => "秘密鍵を {} に、公開鍵を {} に書き出しました",
+            Msg::Signed => "manifest.json に署名しました（署名は {}）",
+            Msg::DeltaWritten => "{1} に {0} 件の変更を書き出しました（{2}）",
+            Msg::DeltaApplied => "{1} に {0} 件の変更を適用しました（{2}）",
+            Msg::DeltaCounts => "銀行: 追加 {} 件、削除 {} 件、更新 {} 件; 支店: 追加・変更 {} 件、削除 {} 件",
+            Msg::SyncUpToDate => "{} と同じバージョン {} です",
+            Msg::SyncPatched => "{1} から {0} 件の変更を適用しました。バージョンは {2} です",
+            Msg::SyncDownloaded => "{1} から銀行 {0} 件をダウンロードしました。バージョンは {2} です",
+            Msg::SyncFailed => "同期に失敗したため、手元のデータセットを残しました: {}",
+            Msg::CrawlNotification => "zngn クロール: {}",
+            Msg::NotifyFailed => "{} に通知できませんでした: {}",
+            Msg::NothingToFill => "banks.json のすべての銀行に支店ファイルがあります",
+            Msg::NoPlan => "{} にはクロールの計画がありません",
+            Msg::PlanShard => "計画の対象はシャード {} です",
+            Msg::MissingBranchFiles => "支店ファイルのない銀行が {} 件あります: {}",
+            Msg::Filled => "支店ファイルのない銀行 {1} 件のうち {0} 件の支店を取得しました。{2}",
+            Msg::CrossCheckClean => "銀行 {} 件すべてが {} と一致しました",
+            Msg::CrossCheckFound => "{1} との不一致が {0} 件あります: こちらのみ {2} 件、先方のみ {3} 件、名前 {4} 件、読み {5} 件",
+            Msg::TransferValid => "この振込先に振り込めます",
+            Msg::TransferInvalid => "振込先に {} 件の問題があります",
+            Msg::ResultsWritten => "{} 件を {} に書き出しました",
+            Msg::BatchChecked => "{} 行を検証しました（問題なし {} 行、警告 {} 行、要確認 {} 行、エラー {} 行、名称からコードを補完 {} 行）。結果を {} に書き出しました",
+            #[cfg(feature = "dev")]
+            Msg::MockServing => "記録したページ {} 件を http://{} で配信しています",
+        },
+    }
+}
+
+pub fn t(msg: Msg) -> &'static str {
+    text(lang(), msg)
+}
+
+// Fills the placeholders of a message in order. `{0}`, `{1}`, ... pick an argument by position instead,
+// for translations that need a different word order; a plain `{}` then continues after the highest one used.
+pub fn fill(msg: Msg, args: &[&dyn Display]) -> String {
+    render(t(msg), args)
+}
+
+fn render(template: &str, args: &[&dyn Display]) -> String {
+    let mut rendered = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        rendered.push_str(&rest[..start]);
+        let index = match rest[start + 1..end].parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => next,
+        };
+        next = next.max(index + 1);
+        if let Some(arg) = args.get(index) {
+            rendered.push_str(&arg.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+const ABOUT_JA: &str = "全銀協の銀行コード・支店コードを収集します";
+
+const HELP_JA: &str = "コマンド:
+    crawl        zengin.ajtw.net から全銀行と支店を取得します
+    fill         支店ファイルが欠けている銀行だけ支店を取得し直します
+    search       名前または読みに検索語を含む銀行・支店を探します
+    find-branch  指定した名前・読みの支店を持つ銀行を探します
+    lookup       コードで銀行または支店を表示します
+    list         銀行または支店の一覧を、列と条件を選んで表示します
+    branches     1 つの銀行の支店を名前・読み・コードで検索します
+    export       保存済みのデータを 1 つのファイルに書き出します
+    site         保存済みのデータから静的な HTML サイトを作ります
+    diff         古いスナップショットからの変更を表示します
+    diff-bank    2 つのスナップショット間で 1 銀行の支店の追加・廃止・名称変更・コード変更を表示します
+    cross-check  zengin-code のデータと突き合わせ、食い違いを一覧にします
+    migrate      以前のバージョンが書き出したデータを現在の形式で書き直します
+    delta        2 つのスナップショットの差分をパッチファイルに書き出します
+    apply-delta  delta で作ったパッチを出力ディレクトリまたはファイルに適用します
+    sync         別の zngn サーバーからデータセットを取得し、手元のコピーを最新に保ちます
+    backup       出力ディレクトリをチェックサム付きの tar.gz にまとめます
+    restore      backup で作ったアーカイブから出力ディレクトリを復元します
+    promote      検査に通ったスナップショットを、出力ディレクトリが指す配信用のデータにします
+    rollback     出力ディレクトリの参照先を、1 つ前に promote したスナップショットに戻します
+    merge        crawl --shard で分担して取得したスナップショットを 1 つにまとめます
+    verify       出力ディレクトリのファイルを manifest.json のチェックサムや署名と照合します
+    keygen       manifest.json の署名に使う鍵を作ります
+    sign         manifest.json に署名します
+    index        `search --indexed` 用の全文索引を作ります
+    compile      保存済みのデータを読み込みの速い形式に変換します
+    plan         直近のクロールの計画と、そのうち終わった作業を表示します
+    stats        保存済みのデータを集計します
+    schema       データセット形式の JSON Schema を出力し、ファイルを検証します
+    quality      読みの誤り、不正なコード、重複した名前などの疑わしいデータを一覧にします
+    validate-transfer
+                 振込先の銀行・支店コード、口座番号、受取人名を検証します
+    batch-validate
+                 CSV の各行の銀行・支店コードと名称を検証し、コードのない行は名称から補完して、結果を書き加えた CSV を出力します
+    bench        読み込み時間、検索速度などを計測します
+    serve        保存済みのデータを HTTP で配信します
+    mock-server  記録した zengin.ajtw.net のページを配信し、オフラインでクロールを試せるようにします（dev 機能）
+
+各オプションの説明は英語のままです。--lang en で英語の表示に戻せます。
+
+終了コード:
+    0    成功
+    1    予期しないエラー
+    2    一部のみ成功（クロールが途中で止まった、再試行待ちのリクエストがある など）
+    3    通信エラー
+    4    解析エラー
+    5    入力エラー（引数の誤り、存在しないコード など）
+    6    別のクロールが出力ディレクトリをロックしています
+    7    クロールで見つかった銀行・支店が保存済みのスナップショットより大幅に少ないため、保存済みのものを残しました";
+
+// Clap only takes help text as static strings, so Japanese covers the top level summary and
+// exit codes while per option help stays in English.
+pub fn localize<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    match lang() {
+        Lang::En => app,
+        Lang::Ja => app.about(ABOUT_JA).after_help(HELP_JA),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn lang_test() {
+        use std::ffi::OsString;
+        use crate::cli::i18n::{render, Lang};
+
+        assert_eq!(Lang::from_locale("ja_JP.UTF-8"), Lang::Ja);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<OsString>>();
+        assert_eq!(Lang::from_args(&args(&["zngn", "--lang", "ja", "stats"])), Some(Lang::Ja));
+        assert_eq!(Lang::from_args(&args(&["zngn", "stats", "--lang=en"])), Some(Lang::En));
+        assert_eq!(Lang::from_args(&args(&["zngn", "search", "--", "--lang=ja"])), None);
+
+        assert_eq!(render("{} of {} banks", &[&3, &5]), "3 of 5 banks");
+        assert_eq!(render("銀行 {1} の {0} 行目（{}）", &[&2, &"0001", &"a"]), "銀行 0001 の 2 行目（a）");
+    }
+}
@@ -0,0 +1,230 @@
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+use structopt::StructOpt;
+use zngn::category::Category;
+use zngn::collate::{self, SortKey, SortOrder};
+use zngn::filter::Filter;
+use zngn::layout::Layout;
+use zngn::page::paginate;
+use zngn::search;
+use zngn::{Bank, BankCode, Branch};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{load, ExitCode, PageOpt, Report, Table};
+
+#[derive(Debug, StructOpt)]
+pub enum ListOpt {
+    /// List banks, e.g. `list banks --columns code,name --category 地方銀行`
+    Banks {
+        /// Columns shown, in order: code, name, name_en, phonetic, category, branches, address, website
+        #[structopt(long, use_delimiter = true, default_value = "code,name,phonetic,branches")]
+        columns: Vec<BankColumn>,
+        /// Only banks of this kind, by Japanese name or slug: 都市銀行 (city), 信託銀行 (trust), 地方銀行
+        /// (regional), 第二地方銀行 (second_regional), 信用金庫 (shinkin), 信用組合 (credit_union), 労働金庫
+        /// (labour), 農業協同組合 (agricultural), 漁業協同組合 (fishery), ゆうちょ銀行 (yucho) or その他 (other)
+        #[structopt(long)]
+        category: Option<Category>,
+        /// Only banks matching a filter, e.g. "name~銀行 AND branch_count>100"
+        #[structopt(long = "where")]
+        filter: Option<Filter>,
+        /// Order by code, name or phonetic (gojūon order)
+        #[structopt(long, default_value = "phonetic")]
+        sort: SortKey,
+        /// Sort direction: asc or desc
+        #[structopt(long, default_value = "asc")]
+        order: SortOrder,
+        #[structopt(flatten)]
+        page: PageOpt,
+    },
+    /// List the branches of a bank
+    Branches {
+        bank_code: BankCode,
+        /// Columns shown, in order: bank_code, bank_name, code, name, phonetic
+        #[structopt(long, use_delimiter = true, default_value = "code,name,phonetic")]
+        columns: Vec<BranchColumn>,
+        /// Only branches whose name or reading contains this, or whose code starts with it
+        #[structopt(long)]
+        query: Option<String>,
+        /// Order by code, name or phonetic (gojūon order)
+        #[structopt(long, default_value = "phonetic")]
+        sort: SortKey,
+        /// Sort direction: asc or desc
+        #[structopt(long, default_value = "asc")]
+        order: SortOrder,
+        #[structopt(flatten)]
+        page: PageOpt,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BankColumn {
+    Code,
+    Name,
+    NameEn,
+    Phonetic,
+    Category,
+    Branches,
+    Address,
+    Website,
+}
+
+impl BankColumn {
+    fn header(self) -> &'static str {
+        match self {
+            BankColumn::Code => "code",
+            BankColumn::Name => "name",
+            BankColumn::NameEn => "name_en",
+            BankColumn::Phonetic => "phonetic",
+            BankColumn::Category => "category",
+            BankColumn::Branches => "branches",
+            BankColumn::Address => "address",
+            BankColumn::Website => "website",
+        }
+    }
+
+    fn value(self, bank: &Bank) -> Value {
+        match self {
+            BankColumn::Code => Value::from(bank.code.0.as_str()),
+            BankColumn::Name => Value::from(bank.name.as_str()),
+            BankColumn::NameEn => bank.name_en.as_deref().map_or(Value::Null, Value::from),
+            BankColumn::Phonetic => Value::from(bank.phonetic.as_str()),
+            BankColumn::Category => Value::from(Category::of(bank).name_ja()),
+            BankColumn::Branches => Value::from(bank.branches.len()),
+            BankColumn::Address => bank.address.as_deref().map_or(Value::Null, Value::from),
+            BankColumn::Website => bank.website.as_deref().map_or(Value::Null, Value::from),
+        }
+    }
+}
+
+impl FromStr for BankColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "code" => Ok(BankColumn::Code),
+            "name" => Ok(BankColumn::Name),
+            "name_en" => Ok(BankColumn::NameEn),
+            "phonetic" => Ok(BankColumn::Phonetic),
+            "category" => Ok(BankColumn::Category),
+            "branches" => Ok(BankColumn::Branches),
+            "address" => Ok(BankColumn::Address),
+            "website" => Ok(BankColumn::Website),
+            _ => Err(format!("unknown bank column: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BranchColumn {
+    BankCode,
+    BankName,
+    Code,
+    Name,
+    Phonetic,
+}
+
+impl BranchColumn {
+    fn header(self) -> &'static str {
+        match self {
+            BranchColumn::BankCode => "bank_code",
+            BranchColumn::BankName => "bank_name",
+            BranchColumn::Code => "code",
+            BranchColumn::Name => "name",
+            BranchColumn::Phonetic => "phonetic",
+        }
+    }
+
+    fn value(self, bank: &Bank, branch: &Branch) -> Value {
+        match self {
+            BranchColumn::BankCode => Value::from(bank.code.0.as_str()),
+            BranchColumn::BankName => Value::from(bank.name.as_str()),
+            BranchColumn::Code => Value::from(branch.code.as_str()),
+            BranchColumn::Name => Value::from(&*branch.name),
+            BranchColumn::Phonetic => Value::from(&*branch.phonetic),
+        }
+    }
+}
+
+impl FromStr for BranchColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bank_code" => Ok(BranchColumn::BankCode),
+            "bank_name" => Ok(BranchColumn::BankName),
+            "code" => Ok(BranchColumn::Code),
+            "name" => Ok(BranchColumn::Name),
+            "phonetic" => Ok(BranchColumn::Phonetic),
+            _ => Err(format!("unknown branch column: {}", s)),
+        }
+    }
+}
+
+// Rows as objects of just the chosen columns for --output json, and as a table of the same columns.
+fn column_report(headers: &[&str], rows: Vec<Vec<Value>>) -> Report {
+    let mut table = Table::new(headers);
+    let mut objects = Vec::with_capacity(rows.len());
+    for row in rows {
+        table.push(
+            row.iter()
+                .map(|value| match value {
+                    Value::String(text) => text.clone(),
+                    Value::Null => String::new(),
+                    value => value.to_string(),
+                })
+                .collect(),
+        );
+        let object = headers
+            .iter()
+            .map(|header| header.to_string())
+            .zip(row)
+            .collect::<Map<String, Value>>();
+        objects.push(Value::Object(object));
+    }
+    Report::new(&objects, table.to_string())
+}
+
+pub fn run(opt: ListOpt, layout: &Layout) -> Report {
+    let mut banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    match opt {
+        ListOpt::Banks { columns, category, filter, sort, order, page } => {
+            banks.retain(|bank| {
+                category.is_none_or(|category| Category::of(bank) == category)
+                    && filter.as_ref().is_none_or(|filter| filter.matches(bank))
+            });
+            collate::sort_banks(&mut banks, sort, order);
+            let banks = paginate(&banks, page.offset, page.limit);
+            let headers = columns.iter().map(|column| column.header()).collect::<Vec<&str>>();
+            let rows = banks
+                .items
+                .iter()
+                .map(|bank| columns.iter().map(|column| column.value(bank)).collect())
+                .collect();
+            column_report(&headers, rows).paged(&banks)
+        }
+        ListOpt::Branches { bank_code, columns, query, sort, order, page } => {
+            let bank = match banks.iter_mut().find(|bank| bank.code == bank_code) {
+                Some(bank) => bank,
+                None => return Report::failed(ExitCode::Validation, fill(Msg::NoBank, &[&bank_code])),
+            };
+            collate::sort_branches(&mut bank.branches, sort, order);
+            let bank = &*bank;
+            let branches = paginate(
+                search::search_branches(bank, query.as_deref().unwrap_or_default()),
+                page.offset,
+                page.limit,
+            );
+            let headers = columns.iter().map(|column| column.header()).collect::<Vec<&str>>();
+            let rows = branches
+                .items
+                .iter()
+                .map(|branch| columns.iter().map(|column| column.value(bank, branch)).collect())
+                .collect();
+            column_report(&headers, rows).paged(&branches)
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::shard::{self, Shard};
+use zngn::{load_banks, with_saved_branches, Bank, Error};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{sync, Report};
+
+#[derive(Debug, StructOpt)]
+pub struct MergeOpt {
+    /// Output directories of the partial crawls, e.g. those of `crawl --shard 1/3` to `--shard 3/3`
+    #[structopt(parse(from_os_str), required = true, min_values = 2)]
+    snapshots: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct MergeSummary {
+    snapshots: Vec<PathBuf>,
+    shards: Vec<Shard>,
+    banks: usize,
+    branches: usize,
+    // Banks listed without any branches, which a shard's crawl has yet to reach.
+    missing: Vec<String>,
+}
+
+// The banks of a snapshot as saved, without the aliases and English names loading adds.
+fn part(layout: &Layout) -> Result<(Option<Shard>, Vec<Bank>), Error> {
+    let banks = load_banks(layout)?
+        .into_values()
+        .map(|bank| with_saved_branches(layout, bank))
+        .collect::<Result<Vec<Bank>, Error>>()?;
+    Ok((shard::load(layout)?, banks))
+}
+
+async fn merge(opt: MergeOpt, layout: &Layout) -> Result<Report, Error> {
+    let _lock = CrawlLock::acquire(layout)?;
+    let parts = opt
+        .snapshots
+        .iter()
+        .map(|snapshot| part(&layout.relocated(snapshot.clone())))
+        .collect::<Result<Vec<(Option<Shard>, Vec<Bank>)>, Error>>()?;
+    let mut shards = parts.iter().filter_map(|(shard, _)| *shard).collect::<Vec<Shard>>();
+    shards.sort_by_key(|shard| shard.index);
+    let banks = shard::merge(parts)?;
+    sync::write(&banks, layout).await?;
+    let summary = MergeSummary {
+        snapshots: opt.snapshots,
+        shards,
+        banks: banks.len(),
+        branches: banks.iter().map(|bank| bank.branches.len()).sum(),
+        missing: banks
+            .iter()
+            .filter(|bank| bank.branches.is_empty())
+            .map(|bank| bank.code.0.clone())
+            .collect(),
+    };
+    let text = fill(
+        Msg::Merged,
+        &[&summary.snapshots.len(), &summary.banks, &summary.branches, &layout.out().display()],
+    );
+    let mut report = Report::new(&summary, format!("{}\n", text));
+    if !summary.missing.is_empty() {
+        report.warn(fill(Msg::MergedWithoutBranches, &[&summary.missing.len(), &summary.missing.join(", ")]));
+    }
+    Ok(report)
+}
+
+pub async fn run(opt: MergeOpt, layout: &Layout) -> Report {
+    match merge(opt, layout).await {
+        Ok(report) => report,
+        Err(e) => Report::from_error(&e),
+    }
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::migrate;
+use zngn::writer::save_branch_files;
+use zngn::{save_banks, save_index, Bank, Error};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct MigrateOpt {
+    /// Output directory written by an earlier version
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+    /// Directory the dataset is rewritten to, in the layout given by --layout
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+struct MigrateSummary {
+    banks: usize,
+    branches: usize,
+    files_read: usize,
+    files_written: usize,
+    skipped: Vec<PathBuf>,
+}
+
+pub async fn run(opt: MigrateOpt, layout: &Layout) -> Report {
+    match migrate(opt, layout).await {
+        Ok(report) => report,
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+async fn migrate(opt: MigrateOpt, layout: &Layout) -> Result<Report, Error> {
+    let (banks, found) = migrate::read(&opt.old)?;
+    let layout = layout.relocated(opt.new);
+    // The bank list holds no branches, as after the first step of a crawl.
+    let listed = banks
+        .iter()
+        .map(|bank| Bank {
+            branches: Vec::new(),
+            ..bank.clone()
+        })
+        .collect::<Vec<Bank>>();
+    save_banks(&listed, &layout)?;
+    save_index(&banks, &layout)?;
+    let crawled = banks.iter().filter(|bank| !bank.branches.is_empty()).cloned().collect::<Vec<Bank>>();
+    let written = save_branch_files(&crawled, &layout, 16).await?;
+    for file in [layout.aliases_file(), layout.english_names_file(), layout.parser_file(), layout.source_file(), layout.abbreviations_file()] {
+        let old = opt.old.join(file.file_name().unwrap_or_default());
+        if old.exists() {
+            fs::copy(&old, &file).map_err(Error::SaveBankFileFailed)?;
+        }
+    }
+    let summary = MigrateSummary {
+        banks: banks.len(),
+        branches: banks.iter().map(|bank| bank.branches.len()).sum(),
+        files_read: found.files,
+        files_written: written.files,
+        skipped: found.skipped,
+    };
+    let mut report = Report::new(
+        &summary,
+        format!("{}\n", fill(Msg::Migrated, &[&summary.banks, &summary.branches, &summary.files_read])),
+    );
+    for path in &summary.skipped {
+        report.warn(fill(Msg::MigrateSkipped, &[&path.display()]));
+    }
+    Ok(report)
+}
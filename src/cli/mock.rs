@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zngn::logging;
+use zngn::mock::{self, Recordings};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct MockServerOpt {
+    /// Address the mock site listens on; crawl it with --source http://<address>
+    #[structopt(long, default_value = "127.0.0.1:8081")]
+    bind: SocketAddr,
+    /// Directory of recorded pages named ginkou_<key>.html and shitenmeisai_<search param>_<key>.html,
+    /// with 英 spelled alphanumeric; without it a few bundled pages are served
+    #[structopt(long, parse(from_os_str))]
+    pages: Option<PathBuf>,
+}
+
+// Runs until interrupted; any search key or bank without a recorded page gets an empty list.
+pub async fn run(opt: MockServerOpt) -> Report {
+    let recordings = match &opt.pages {
+        Some(dir) => match Recordings::load(dir) {
+            Ok(recordings) => recordings,
+            Err(e) => return Report::from_error(&e),
+        },
+        None => Recordings::bundled(),
+    };
+    logging::info(&fill(Msg::MockServing, &[&recordings.len(), &opt.bind]));
+    match mock::serve(opt.bind, recordings).await {
+        Ok(()) => Report::new(&(), String::new()),
+        Err(e) => Report::from_error(&e),
+    }
+}
@@ -0,0 +1,411 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use structopt::StructOpt;
+use zngn::export::Registry;
+use zngn::layout::Layout;
+use zngn::logging;
+use zngn::page::Page;
+use zngn::{load_dataset, Bank, Error};
+
+pub mod backup;
+pub mod bench;
+pub mod crawl;
+pub mod crosscheck;
+pub mod delta;
+pub mod export;
+pub mod fill;
+pub mod i18n;
+pub mod list;
+pub mod merge;
+pub mod migrate;
+#[cfg(feature = "dev")]
+pub mod mock;
+pub mod promote;
+pub mod query;
+pub mod serve;
+pub mod site;
+pub mod sync;
+mod table;
+pub mod transfer;
+pub mod verify;
+
+pub use table::Table;
+
+use i18n::{fill, t, Msg};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    Table,
+    Json,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            _ => Err(format!("unknown output: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, StructOpt)]
+pub struct PageOpt {
+    /// Show at most this many results; ranked search shows 20 by default, other listings everything
+    #[structopt(long)]
+    pub limit: Option<usize>,
+    /// Skip this many results first
+    #[structopt(long, default_value = "0")]
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct FormatOpt {
+    /// Write the results in a registered format instead of a table: json, csv, xlsx or sqlite
+    #[structopt(long)]
+    pub format: Option<String>,
+    /// File the formatted results are written to; stdout when omitted, which sqlite doesn't support
+    #[structopt(long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+}
+
+// The formats `export` and `--format` accept. New formats are registered here.
+pub fn formats() -> Registry {
+    Registry::default()
+}
+
+// Process exit status by failure class, so wrappers can react without parsing stderr.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitCode {
+    Success = 0,
+    Failure = 1,
+    Partial = 2,
+    Network = 3,
+    Parse = 4,
+    Validation = 5,
+    LockHeld = 6,
+    Anomaly = 7,
+}
+
+pub const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    success
+    1    unexpected failure
+    2    partial success, e.g. the crawl stopped early or requests are queued for retry
+    3    network failure
+    4    parse failure
+    5    validation failure, e.g. bad arguments or unknown codes
+    6    another crawl holds the lock on the output directory
+    7    the crawl found far fewer banks or branches than the saved snapshot, which was left as is";
+
+impl From<&Error> for ExitCode {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::FetchBankError { .. }
+            | Error::FetchBranchError { .. }
+            | Error::FetchDetailError { .. }
+            | Error::RemoteFailed { .. } => {
+                ExitCode::Network
+            }
+            Error::ParseFailed => ExitCode::Parse,
+            Error::InvalidLayout(_)
+            | Error::InvalidYuchoNumber(_)
+            | Error::DuplicateCodes(_)
+            | Error::SchemaViolations(_)
+            | Error::ChecksumMismatch(_)
+            | Error::InvalidKey(_)
+            | Error::BadSignature
+            | Error::DeltaFailed(_)
+            | Error::BatchFailed(_)
+            | Error::PromoteFailed(_)
+            | Error::MergeFailed(_)
+            | Error::PlanMismatch(_) => ExitCode::Validation,
+            Error::LockHeld(_) => ExitCode::LockHeld,
+            Error::CountDropped { .. } => ExitCode::Anomaly,
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+impl ExitCode {
+    // Kind of the failures reported with this code that aren't an `Error`, such as rejected arguments.
+    fn kind(self) -> &'static str {
+        match self {
+            ExitCode::Success => "success",
+            ExitCode::Failure => "failure",
+            ExitCode::Partial => "partial",
+            ExitCode::Network => "network",
+            ExitCode::Parse => "parse",
+            ExitCode::Validation => "validation",
+            ExitCode::LockHeld => "lock_held",
+            ExitCode::Anomaly => "anomaly",
+        }
+    }
+
+    fn headline(self) -> Msg {
+        match self {
+            ExitCode::Network => Msg::NetworkFailure,
+            ExitCode::Parse => Msg::ParseFailure,
+            ExitCode::Validation => Msg::ValidationFailure,
+            ExitCode::LockHeld => Msg::LockHeld,
+            ExitCode::Anomaly => Msg::CountAnomaly,
+            _ => Msg::UnexpectedFailure,
+        }
+    }
+}
+
+// A failed load is itself the command's outcome, so the report is returned unboxed.
+#[allow(clippy::result_large_err)]
+pub fn load(layout: &Layout) -> Result<Vec<Bank>, Report> {
+    load_dataset(layout).map_err(|e| Report::load_failed(&e))
+}
+
+// What `--output json` prints for every command, as a single document on stdout:
+//
+//     {
+//       "status": "ok" | "partial" | "error",
+//       "exit_code": 0,
+//       "results": <command specific, null when the command failed>,
+//       "warnings": ["..."],
+//       "errors": ["..."],
+//       "error": {"kind": "lock_held", "message": "..."},
+//       "total": 1234
+//     }
+//
+// `error` is only present when the command failed. Its `kind` is the snake case name of the error, such
+// as "fetch_bank_error" or "plan_mismatch", or for failures that aren't one, such as rejected
+// arguments, the class of the exit code: "validation", "lock_held", "anomaly" and so on. `message`
+// describes it without the headline the entry in `errors` starts with.
+//
+// `total` is only present for paged listings and counts the results across all pages.
+//
+// Fields are only ever added to this structure, never renamed or removed.
+#[derive(Debug, Serialize)]
+struct Envelope<'a> {
+    status: &'static str,
+    exit_code: i32,
+    results: &'a Value,
+    warnings: &'a [String],
+    errors: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a Failure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct Failure {
+    kind: &'static str,
+    message: String,
+}
+
+// The outcome of a command, rendered either as human readable text or as an `Envelope`.
+#[derive(Debug)]
+pub struct Report {
+    results: Value,
+    text: String,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+    failure: Option<Failure>,
+    total: Option<usize>,
+    exit_code: ExitCode,
+    // Results written by `formatted`, printed instead of the text.
+    formatted: Option<Vec<u8>>,
+}
+
+impl Report {
+    pub fn new<T: Serialize>(results: &T, text: String) -> Self {
+        Self {
+            results: serde_json::to_value(results).unwrap_or(Value::Null),
+            text,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            failure: None,
+            total: None,
+            exit_code: ExitCode::Success,
+            formatted: None,
+        }
+    }
+
+    pub fn failed(exit_code: ExitCode, error: String) -> Self {
+        Self {
+            results: Value::Null,
+            text: String::new(),
+            warnings: Vec::new(),
+            failure: Some(Failure {
+                kind: exit_code.kind(),
+                message: error.clone(),
+            }),
+            errors: vec![error],
+            total: None,
+            exit_code,
+            formatted: None,
+        }
+    }
+
+    pub fn from_error(error: &Error) -> Self {
+        let exit_code = ExitCode::from(error);
+        Self::failed(exit_code, format!("{}: {}", t(exit_code.headline()), error)).caused_by(error)
+    }
+
+    // For commands that failed to load the dataset before doing anything else.
+    pub fn load_failed(error: &Error) -> Self {
+        Self::failed(ExitCode::from(error), fill(Msg::LoadFailed, &[error])).caused_by(error)
+    }
+
+    fn caused_by(mut self, error: &Error) -> Self {
+        self.failure = Some(Failure {
+            kind: error.kind(),
+            message: error.to_string(),
+        });
+        self
+    }
+
+    // Marks a report whose results are usable but incomplete.
+    pub fn partial(mut self) -> Self {
+        if self.exit_code == ExitCode::Success {
+            self.exit_code = ExitCode::Partial;
+        }
+        self
+    }
+
+    // Adds a field to object results; results of failed commands are left alone.
+    pub fn insert_result<T: Serialize>(&mut self, key: &str, value: &T) {
+        if let Value::Object(results) = &mut self.results {
+            results.insert(key.to_owned(), serde_json::to_value(value).unwrap_or(Value::Null));
+        }
+    }
+
+    // Records that the results are one page of a longer listing.
+    pub fn paged<T>(mut self, page: &Page<T>) -> Self {
+        self.total = Some(page.total);
+        if page.has_more() {
+            let shown = page.offset + page.items.len();
+            self.text.push_str(&format!("{}\n", fill(Msg::MoreResults, &[&shown, &page.total, &shown])));
+        }
+        self
+    }
+
+    // Writes the results in the format `opt` names, one record per result, to its file or else in place
+    // of the text. Reports without results, such as failed ones, are left as they are.
+    pub fn formatted(mut self, opt: &FormatOpt) -> Self {
+        let name = match &opt.format {
+            Some(name) => name,
+            None => return self,
+        };
+        let formats = formats();
+        let exporter = match formats.find(name) {
+            Ok(exporter) => exporter,
+            Err(e) => return Report::failed(ExitCode::Validation, e),
+        };
+        let records = match &self.results {
+            Value::Null => return self,
+            Value::Array(items) => items.clone(),
+            results => vec![results.clone()],
+        };
+        let written = match &opt.file {
+            Some(path) => exporter.records_to_file(&records, path).map(|()| {
+                self.text = format!("{}\n", fill(Msg::ResultsWritten, &[&records.len(), &path.display()]));
+            }),
+            None => {
+                let mut out = Vec::new();
+                exporter.write_records(&records, &mut out).map(|()| self.formatted = Some(out))
+            }
+        };
+        match written {
+            Ok(()) => self,
+            Err(e) => Report::from_error(&e),
+        }
+    }
+
+    // Writes the report to the log, for commands that keep running instead of printing it.
+    pub fn log(&self) {
+        for line in self.text.lines().filter(|line| !line.trim().is_empty()) {
+            logging::info(line);
+        }
+        for warning in &self.warnings {
+            logging::warn(warning);
+        }
+        for error in &self.errors {
+            logging::error(error);
+        }
+    }
+
+    pub fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    pub fn error(&mut self, error: String) {
+        self.errors.push(error);
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code as i32
+    }
+
+    fn envelope(&self) -> Envelope<'_> {
+        Envelope {
+            status: match self.exit_code {
+                ExitCode::Success => "ok",
+                ExitCode::Partial => "partial",
+                _ => "error",
+            },
+            exit_code: self.exit_code(),
+            results: &self.results,
+            warnings: &self.warnings,
+            errors: &self.errors,
+            error: self.failure.as_ref(),
+            total: self.total,
+        }
+    }
+
+    pub fn emit(&self, output: Output) {
+        match output {
+            Output::Json => println!("{}", serde_json::to_string_pretty(&self.envelope()).unwrap()),
+            Output::Table => {
+                match &self.formatted {
+                    Some(formatted) => {
+                        let mut stdout = std::io::stdout();
+                        let _ = stdout.write_all(formatted).and_then(|()| stdout.flush());
+                    }
+                    None => print!("{}", self.text),
+                }
+                for warning in &self.warnings {
+                    eprintln!("{}: {}", t(Msg::Warning), warning);
+                }
+                for error in &self.errors {
+                    eprintln!("{}: {}", t(Msg::Error), error);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn envelope_test() {
+        use std::path::PathBuf;
+        use serde_json::json;
+        use zngn::Error;
+        use crate::cli::{ExitCode, Report};
+
+        let report = Report::from_error(&Error::LockHeld(PathBuf::from("out/.lock")));
+        let envelope = serde_json::to_value(report.envelope()).unwrap();
+        assert_eq!(envelope["status"], "error");
+        assert_eq!(envelope["exit_code"], 6);
+        assert_eq!(envelope["error"], json!({"kind": "lock_held", "message": "out/.lock is held by another run"}));
+
+        let report = Report::failed(ExitCode::Validation, "unknown bank 9999".to_owned());
+        let envelope = serde_json::to_value(report.envelope()).unwrap();
+        assert_eq!(envelope["error"], json!({"kind": "validation", "message": "unknown bank 9999"}));
+
+        let envelope = serde_json::to_value(Report::new(&(), String::new()).envelope()).unwrap();
+        assert!(envelope.get("error").is_none());
+    }
+}
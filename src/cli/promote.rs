@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::promote::{self, Gates, Promotion};
+
+use crate::cli::i18n::{fill, t, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct PromoteOpt {
+    /// Output directory of the crawl to serve from now on
+    #[structopt(parse(from_os_str))]
+    snapshot: PathBuf,
+    /// Refuse a snapshot with more than this many percent fewer banks or branches than the one it
+    /// replaces; 100 turns the check off
+    #[structopt(long, default_value = "10")]
+    max_drop: f64,
+    /// Also require manifest.json to be signed with the private key of this public key
+    #[structopt(long, parse(from_os_str))]
+    public_key: Option<PathBuf>,
+    /// Promote without running any checks
+    #[structopt(long)]
+    force: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Promoted {
+    pointer: PathBuf,
+    snapshot: PathBuf,
+    previous: Option<PathBuf>,
+    promoted_at: u64,
+}
+
+// The output directory is the pointer, so every other command, `serve --watch` included, reads
+// whichever snapshot was promoted last.
+pub fn run(opt: PromoteOpt, layout: &Layout) -> Report {
+    let previous = match promote::pointed(layout.out()) {
+        Ok(previous) => previous,
+        Err(e) => return Report::from_error(&e),
+    };
+    if !opt.force {
+        let gates = Gates {
+            max_drop: opt.max_drop,
+            public_key: opt.public_key,
+        };
+        let current = previous.as_ref().map(|_| layout);
+        if let Err(e) = promote::check(&layout.relocated(opt.snapshot.clone()), current, &gates) {
+            return Report::from_error(&e);
+        }
+    }
+    let promotion = match promote::promote(layout.out(), &opt.snapshot) {
+        Ok(promotion) => promotion,
+        Err(e) => return Report::from_error(&e),
+    };
+    let promoted = Promoted {
+        pointer: layout.out().to_path_buf(),
+        snapshot: promotion.snapshot,
+        previous,
+        promoted_at: promotion.promoted_at,
+    };
+    let text = fill(Msg::Promoted, &[&promoted.pointer.display(), &promoted.snapshot.display()]);
+    let mut report = Report::new(&promoted, format!("{}\n", text));
+    if opt.force {
+        report.warn(t(Msg::PromotedUnchecked).to_owned());
+    }
+    report
+}
+
+#[derive(Debug, Serialize)]
+struct RolledBack {
+    pointer: PathBuf,
+    from: Promotion,
+    to: Promotion,
+}
+
+pub fn rollback(layout: &Layout) -> Report {
+    match promote::rollback(layout.out()) {
+        Ok((from, to)) => {
+            let rolled_back = RolledBack {
+                pointer: layout.out().to_path_buf(),
+                from,
+                to,
+            };
+            let text = fill(
+                Msg::RolledBack,
+                &[
+                    &rolled_back.pointer.display(),
+                    &rolled_back.to.snapshot.display(),
+                    &rolled_back.from.snapshot.display(),
+                ],
+            );
+            Report::new(&rolled_back, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
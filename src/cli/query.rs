@@ -0,0 +1,627 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use zngn::compiled;
+use zngn::dataset::Dataset;
+use zngn::diff::{self, Change};
+use zngn::filter::Filter;
+use zngn::fulltext::{self, FullTextIndex, IndexedHit};
+use zngn::intern::{self, Interned};
+use zngn::layout::Layout;
+use zngn::page::paginate;
+use zngn::plan::{Plan, Progress};
+use zngn::quality;
+use zngn::schema;
+use zngn::search::{self, Highlight, Hit, MatchField, SearchHit};
+use zngn::{load_bank, load_banks_validated, load_json_dataset, Bank, BankCode, Branch, Error};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{load, ExitCode, PageOpt, Report, Table};
+
+#[derive(Debug, Serialize)]
+struct HitRow<'a> {
+    kind: &'a str,
+    bank_code: &'a str,
+    bank_name: &'a str,
+    branch_code: Option<&'a str>,
+    branch_name: Option<&'a str>,
+    phonetic: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f32>,
+    // Why a substring search matched; see `SearchHit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<MatchField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matched: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlights: Option<Vec<Highlight>>,
+}
+
+impl<'a> From<SearchHit<'a>> for HitRow<'a> {
+    fn from(hit: SearchHit<'a>) -> Self {
+        Self {
+            field: Some(hit.field),
+            matched: Some(hit.text),
+            highlights: Some(hit.highlights),
+            ..Self::from(hit.hit)
+        }
+    }
+}
+
+impl<'a> From<Hit<'a>> for HitRow<'a> {
+    fn from(hit: Hit<'a>) -> Self {
+        match hit {
+            Hit::Bank(bank) => Self {
+                kind: "bank",
+                bank_code: &bank.code.0,
+                bank_name: &bank.name,
+                branch_code: None,
+                branch_name: None,
+                phonetic: &bank.phonetic,
+                score: None,
+                field: None,
+                matched: None,
+                highlights: None,
+            },
+            Hit::Branch(bank, branch) => Self {
+                kind: "branch",
+                bank_code: &bank.code.0,
+                bank_name: &bank.name,
+                branch_code: Some(&branch.code),
+                branch_name: Some(&branch.name),
+                phonetic: &branch.phonetic,
+                score: None,
+                field: None,
+                matched: None,
+                highlights: None,
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a IndexedHit> for HitRow<'a> {
+    fn from(hit: &'a IndexedHit) -> Self {
+        Self {
+            kind: &hit.kind,
+            bank_code: &hit.bank_code,
+            bank_name: &hit.bank_name,
+            branch_code: hit.branch_code.as_deref(),
+            branch_name: hit.branch_code.as_ref().map(|_| hit.name.as_str()),
+            phonetic: &hit.phonetic,
+            score: Some(hit.score),
+            field: None,
+            matched: None,
+            highlights: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BankRow<'a> {
+    code: &'a str,
+    name: &'a str,
+    phonetic: &'a str,
+    branches: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct BranchRow<'a> {
+    bank_code: &'a str,
+    bank_name: &'a str,
+    code: &'a str,
+    name: &'a str,
+    phonetic: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    banks: usize,
+    branches: usize,
+    banks_without_branches: usize,
+    // Sharing of repeated branch names and readings in memory.
+    interned: Interned,
+}
+
+fn hit_report(rows: &[HitRow]) -> Report {
+    let ranked = rows.iter().any(|row| row.score.is_some());
+    let mut columns = vec!["kind", "bank", "bank name", "branch", "branch name", "phonetic"];
+    if ranked {
+        columns.push("score");
+    }
+    let mut table = Table::new(&columns);
+    for row in rows {
+        let mut cells = vec![
+            row.kind.to_owned(),
+            row.bank_code.to_owned(),
+            row.bank_name.to_owned(),
+            row.branch_code.unwrap_or_default().to_owned(),
+            row.branch_name.unwrap_or_default().to_owned(),
+            row.phonetic.to_owned(),
+        ];
+        if ranked {
+            cells.push(row.score.map(|score| format!("{:.3}", score)).unwrap_or_default());
+        }
+        table.push(cells);
+    }
+    Report::new(&rows, table.to_string())
+}
+
+pub fn search(layout: &Layout, query: &str, filter: Option<&Filter>, page: PageOpt) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let banks = banks.iter().filter(|bank| filter.is_none_or(|filter| filter.matches(bank)));
+    let hits = paginate(search::search_hits(banks, query), page.offset, page.limit);
+    let rows = hits.items.iter().cloned().map(HitRow::from).collect::<Vec<HitRow>>();
+    hit_report(&rows).paged(&hits)
+}
+
+const DEFAULT_RANKED_LIMIT: usize = 20;
+
+// Ranked search against the index written by `zngn index`, without loading the dataset.
+pub fn search_indexed(layout: &Layout, query: &str, page: PageOpt) -> Report {
+    let limit = page.offset + page.limit.unwrap_or(DEFAULT_RANKED_LIMIT);
+    let hits = match FullTextIndex::open(&layout.fulltext_dir()).and_then(|index| index.search(query, limit)) {
+        Ok(hits) => hits,
+        Err(e) => {
+            return Report::failed(
+                ExitCode::from(&e),
+                fill(Msg::IndexSearchFailed, &[&e]),
+            )
+        }
+    };
+    let rows = hits.iter().skip(page.offset).map(HitRow::from).collect::<Vec<HitRow>>();
+    hit_report(&rows)
+}
+
+#[derive(Debug, Serialize)]
+struct IndexSummary {
+    documents: usize,
+}
+
+pub fn index(layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    match fulltext::build(&banks, &layout.fulltext_dir()) {
+        Ok(documents) => {
+            let summary = IndexSummary { documents };
+            Report::new(&summary, format!("{}\n", fill(Msg::Indexed, &[&documents])))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CompileSummary {
+    path: PathBuf,
+    banks: usize,
+    branches: usize,
+    bytes: u64,
+    // The memory-mapped archive, when built with the mmap feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<ArchivedFile>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchivedFile {
+    path: PathBuf,
+    bytes: u64,
+}
+
+#[cfg(feature = "mmap")]
+fn write_archive(banks: &[Bank], layout: &Layout) -> Result<Option<ArchivedFile>, zngn::Error> {
+    let bytes = zngn::archived::write(banks, layout)?;
+    Ok(Some(ArchivedFile {
+        path: layout.archived_file(),
+        bytes,
+    }))
+}
+
+#[cfg(not(feature = "mmap"))]
+fn write_archive(_banks: &[Bank], _layout: &Layout) -> Result<Option<ArchivedFile>, zngn::Error> {
+    Ok(None)
+}
+
+// Always reads the JSON snapshot, so a stale compiled file is never compiled again.
+pub fn compile(layout: &Layout) -> Report {
+    let banks = match load_json_dataset(layout) {
+        Ok(banks) => banks,
+        Err(e) => return Report::from_error(&e),
+    };
+    let written = compiled::compile(&banks, layout)
+        .and_then(|bytes| write_archive(&banks, layout).map(|archived| (bytes, archived)));
+    match written {
+        Ok((bytes, archived)) => {
+            let summary = CompileSummary {
+                path: layout.compiled_file(),
+                banks: banks.len(),
+                branches: banks.iter().map(|bank| bank.branches.len()).sum(),
+                bytes,
+                archived,
+            };
+            let mut table = Table::new(&["path", "banks", "branches", "bytes"]);
+            table.push(vec![
+                summary.path.to_string_lossy().into_owned(),
+                summary.banks.to_string(),
+                summary.branches.to_string(),
+                summary.bytes.to_string(),
+            ]);
+            if let Some(archived) = &summary.archived {
+                table.push(vec![
+                    archived.path.to_string_lossy().into_owned(),
+                    summary.banks.to_string(),
+                    summary.branches.to_string(),
+                    archived.bytes.to_string(),
+                ]);
+            }
+            Report::new(&summary, table.to_string())
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+// One bank or branch, as the single object in JSON but in the same table as lists of them.
+fn bank_report(row: BankRow) -> Report {
+    Report::new(&row, render_banks(std::slice::from_ref(&row)))
+}
+
+fn branch_report(row: BranchRow) -> Report {
+    Report::new(&row, render_branches(std::slice::from_ref(&row)))
+}
+
+pub fn lookup(layout: &Layout, bank_code: &str, branch_code: Option<&str>) -> Report {
+    #[cfg(feature = "mmap")]
+    {
+        let pattern = search::is_pattern(bank_code) || branch_code.is_some_and(search::is_pattern);
+        if let (false, Ok(Some(dataset))) = (pattern, zngn::archived::MappedDataset::open(layout)) {
+            return lookup_mapped(&dataset, bank_code, branch_code);
+        }
+    }
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    if search::is_pattern(bank_code) || branch_code.is_some_and(search::is_pattern) {
+        return lookup_pattern(&banks, bank_code, branch_code);
+    }
+    let bank = match search::lookup_bank(&banks, bank_code) {
+        Some(bank) => bank,
+        None => return Report::failed(ExitCode::Validation, fill(Msg::NoBank, &[&bank_code])),
+    };
+    match branch_code {
+        None => bank_report(BankRow {
+            code: &bank.code.0,
+            name: &bank.name,
+            phonetic: &bank.phonetic,
+            branches: bank.branches.len(),
+        }),
+        Some(branch_code) => match search::lookup_branch(bank, branch_code) {
+            Some(branch) => branch_report(BranchRow::new(bank, branch)),
+            None => Report::failed(
+                ExitCode::Validation,
+                fill(Msg::NoBranch, &[&branch_code, &bank_code]),
+            ),
+        },
+    }
+}
+
+// Same answers as `lookup`, read from the mapped archive without loading the dataset.
+#[cfg(feature = "mmap")]
+fn lookup_mapped(dataset: &zngn::archived::MappedDataset, bank_code: &str, branch_code: Option<&str>) -> Report {
+    let bank = match dataset.lookup_bank(bank_code) {
+        Some(bank) => bank,
+        None => return Report::failed(ExitCode::Validation, fill(Msg::NoBank, &[&bank_code])),
+    };
+    match branch_code {
+        None => bank_report(BankRow {
+            code: bank.code,
+            name: bank.name,
+            phonetic: bank.phonetic,
+            branches: bank.branches,
+        }),
+        Some(branch_code) => match dataset.lookup_branch(bank_code, branch_code) {
+            Some(branch) => branch_report(BranchRow {
+                bank_code: branch.bank.code,
+                bank_name: branch.bank.name,
+                code: branch.code,
+                name: branch.name,
+                phonetic: branch.phonetic,
+            }),
+            None => Report::failed(
+                ExitCode::Validation,
+                fill(Msg::NoBranch, &[&branch_code, &bank_code]),
+            ),
+        },
+    }
+}
+
+// Every bank, or every branch, whose codes match wildcard patterns such as 000* or 0?1.
+fn lookup_pattern(banks: &[Bank], bank_code: &str, branch_code: Option<&str>) -> Report {
+    let matched = search::lookup_banks(banks, bank_code);
+    match branch_code {
+        None => bank_rows(matched),
+        Some(branch_code) => branch_table(
+            matched
+                .into_iter()
+                .flat_map(|bank| {
+                    search::lookup_branches(bank, branch_code)
+                        .into_iter()
+                        .map(move |branch| BranchRow::new(bank, branch))
+                })
+                .collect(),
+        ),
+    }
+}
+
+pub fn stats(layout: &Layout) -> Report {
+    let mut banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let interned = intern::intern(&mut banks);
+    let stats = Stats {
+        banks: banks.len(),
+        branches: banks.iter().map(|bank| bank.branches.len()).sum(),
+        banks_without_branches: banks.iter().filter(|bank| bank.branches.is_empty()).count(),
+        interned,
+    };
+    let mut table = Table::new(&["metric", "value"]);
+    table.push(vec!["banks".to_owned(), stats.banks.to_string()]);
+    table.push(vec!["branches".to_owned(), stats.branches.to_string()]);
+    table.push(vec!["banks without branches".to_owned(), stats.banks_without_branches.to_string()]);
+    table.push(vec!["distinct branch names and readings".to_owned(), interned.distinct.to_string()]);
+    table.push(vec!["bytes saved by sharing them".to_owned(), interned.bytes_saved.to_string()]);
+    Report::new(&stats, table.to_string())
+}
+
+#[derive(Debug, Serialize)]
+struct PlanStatus {
+    created_at: u64,
+    shard: Option<String>,
+    bank_lists: Progress,
+    banks: Progress,
+    pending: Vec<String>,
+}
+
+pub fn plan(layout: &Layout) -> Report {
+    let plan = match Plan::load(layout) {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return Report::failed(ExitCode::Validation, fill(Msg::NoPlan, &[&layout.out().display()])),
+        Err(e) => return Report::from_error(&e),
+    };
+    let (bank_lists, banks) = plan.progress();
+    let status = PlanStatus {
+        created_at: plan.created_at,
+        shard: plan.shard.map(|shard| shard.to_string()),
+        bank_lists,
+        banks,
+        pending: plan.pending_banks().into_iter().map(|code| code.0.clone()).collect(),
+    };
+    let mut table = Table::new(&["work", "done", "planned"]);
+    table.push(vec!["bank list search keys".to_owned(), bank_lists.done.to_string(), bank_lists.total.to_string()]);
+    table.push(vec!["banks' branches".to_owned(), banks.done.to_string(), banks.total.to_string()]);
+    let mut text = table.to_string();
+    if let Some(shard) = &status.shard {
+        text.push_str(&format!("{}\n", fill(Msg::PlanShard, &[shard])));
+    }
+    Report::new(&status, text)
+}
+
+pub fn quality(layout: &Layout, examples: usize) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let issues = quality::inspect(&banks, examples);
+    let mut table = Table::new(&["check", "count", "examples"]);
+    for issue in &issues {
+        table.push(vec![
+            issue.check.describe().to_owned(),
+            issue.count.to_string(),
+            issue.examples.join("; "),
+        ]);
+    }
+    Report::new(&issues, table.to_string())
+}
+
+#[derive(Debug, Serialize)]
+struct SchemaCheck {
+    path: PathBuf,
+    banks: usize,
+}
+
+// Prints the schema, or with `check` validates a dataset file (banks.json when no path is given) against it.
+pub fn schema(layout: &Layout, check: Option<Option<PathBuf>>) -> Report {
+    let path = match check {
+        None => {
+            let schema = schema::schema();
+            let text = format!("{}\n", serde_json::to_string_pretty(&schema).unwrap());
+            return Report::new(&schema, text);
+        }
+        Some(path) => path,
+    };
+    let (path, loaded) = match path {
+        Some(path) => {
+            let loaded = schema::load_validated(&path);
+            (path, loaded)
+        }
+        None => (layout.banks_file(), load_banks_validated(layout)),
+    };
+    match loaded {
+        Ok(banks) => {
+            let text = format!("{}\n", fill(Msg::SchemaValid, &[&path.display(), &banks.len()]));
+            Report::new(&SchemaCheck { path, banks: banks.len() }, text)
+        }
+        Err(Error::SchemaViolations(violations)) => {
+            let mut report = Report::failed(
+                ExitCode::Validation,
+                fill(Msg::SchemaViolations, &[&path.display(), &violations.len()]),
+            );
+            for violation in &violations {
+                let pointer = if violation.pointer.is_empty() { "/" } else { &violation.pointer };
+                report.error(format!("{}: {}", pointer, violation.message));
+            }
+            report
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+fn bank_rows<'a>(banks: impl IntoIterator<Item = &'a Bank>) -> Report {
+    let rows = banks
+        .into_iter()
+        .map(|bank| BankRow {
+            code: &bank.code.0,
+            name: &bank.name,
+            phonetic: &bank.phonetic,
+            branches: bank.branches.len(),
+        })
+        .collect::<Vec<BankRow>>();
+    Report::new(&rows, render_banks(&rows))
+}
+
+fn render_banks(rows: &[BankRow]) -> String {
+    let mut table = Table::new(&["code", "name", "phonetic", "branches"]);
+    for row in rows {
+        table.push(vec![
+            row.code.to_owned(),
+            row.name.to_owned(),
+            row.phonetic.to_owned(),
+            row.branches.to_string(),
+        ]);
+    }
+    table.to_string()
+}
+
+impl<'a> BranchRow<'a> {
+    fn new(bank: &'a Bank, branch: &'a Branch) -> Self {
+        Self {
+            bank_code: &bank.code.0,
+            bank_name: &bank.name,
+            code: &branch.code,
+            name: &branch.name,
+            phonetic: &branch.phonetic,
+        }
+    }
+}
+
+fn branch_table(rows: Vec<BranchRow>) -> Report {
+    Report::new(&rows, render_branches(&rows))
+}
+
+fn render_branches(rows: &[BranchRow]) -> String {
+    let mut table = Table::new(&["bank", "bank name", "code", "name", "phonetic"]);
+    for row in rows {
+        table.push(vec![
+            row.bank_code.to_owned(),
+            row.bank_name.to_owned(),
+            row.code.to_owned(),
+            row.name.to_owned(),
+            row.phonetic.to_owned(),
+        ]);
+    }
+    table.to_string()
+}
+
+// The branches of one bank matching `query`, or all of them, reading only that bank's branch file.
+pub fn branches(layout: &Layout, bank_code: &BankCode, query: Option<&str>, page: PageOpt) -> Report {
+    let bank = match load_bank(layout, bank_code) {
+        Ok(Some(bank)) => bank,
+        Ok(None) => return Report::failed(ExitCode::Validation, fill(Msg::NoBank, &[&bank_code])),
+        Err(e) => return Report::load_failed(&e),
+    };
+    let branches = paginate(search::search_branches(&bank, query.unwrap_or_default()), page.offset, page.limit);
+    branch_table(branches.items.iter().map(|branch| BranchRow::new(&bank, branch)).collect()).paged(&branches)
+}
+
+// Every bank with a branch of the given name, e.g. to see who has a 梅田支店.
+pub fn find_branches(layout: &Layout, query: &str, exact: bool) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let rows = search::find_branches(&banks, query, exact)
+        .map(|(bank, branch)| BranchRow::new(bank, branch))
+        .collect();
+    branch_table(rows)
+}
+
+pub fn change_cells(change: &Change) -> Vec<String> {
+    let (kind, subject, detail) = match change {
+        Change::BankAdded { code, name } => ("bank added", code.clone(), name.clone()),
+        Change::BankRemoved { code, name } => ("bank removed", code.clone(), name.clone()),
+        Change::BankRenamed { code, from, to } => ("bank renamed", code.clone(), format!("{} -> {}", from, to)),
+        Change::BankMerged { code, name, into_code, into_name, branches } => (
+            "bank merged",
+            code.clone(),
+            format!("{} -> {} {} ({} branches moved)", name, into_code, into_name, branches),
+        ),
+        Change::BranchAdded { bank_code, code, name } => {
+            ("branch added", format!("{}-{}", bank_code, code), name.clone())
+        }
+        Change::BranchRemoved { bank_code, code, name } => {
+            ("branch removed", format!("{}-{}", bank_code, code), name.clone())
+        }
+        Change::BranchRenamed { bank_code, code, from, to } => (
+            "branch renamed",
+            format!("{}-{}", bank_code, code),
+            format!("{} -> {}", from, to),
+        ),
+        Change::BranchMoved { name, from_bank_code, from_code, to_bank_code, to_code } => (
+            "branch moved",
+            format!("{}-{}", from_bank_code, from_code),
+            format!("{} -> {}-{}", name, to_bank_code, to_code),
+        ),
+    };
+    vec![kind.to_owned(), subject, detail]
+}
+
+// Changes from the snapshot in `old` to the one in the output directory.
+pub fn diff(layout: &Layout, old: PathBuf) -> Report {
+    let previous = match load(&layout.relocated(old)) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let current = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let changes = diff::diff(&previous, &current);
+    change_report(&changes)
+}
+
+fn change_report(changes: &[Change]) -> Report {
+    let mut table = Table::new(&["change", "code", "detail"]);
+    for change in changes {
+        table.push(change_cells(change));
+    }
+    Report::new(&changes, table.to_string())
+}
+
+// A snapshot given on the command line: an output directory, or a dataset or bank list in a JSON file.
+#[allow(clippy::result_large_err)]
+fn load_snapshot(layout: &Layout, path: PathBuf) -> Result<Vec<Bank>, Report> {
+    if path.is_dir() {
+        return load(&layout.relocated(path));
+    }
+    Dataset::load(&path)
+        .map(|dataset| dataset.banks)
+        .map_err(|e| Report::load_failed(&e))
+}
+
+// Branch changes of one bank, for auditing a reorganization without the noise of the whole dataset.
+pub fn diff_bank(layout: &Layout, bank_code: &BankCode, old: PathBuf, new: PathBuf) -> Report {
+    let (old, new) = match (load_snapshot(layout, old), load_snapshot(layout, new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(report), _) | (_, Err(report)) => return report,
+    };
+    let find = |banks: &[Bank]| banks.iter().find(|bank| &bank.code == bank_code).cloned();
+    let (old, new) = (find(&old), find(&new));
+    if old.is_none() && new.is_none() {
+        return Report::failed(ExitCode::Validation, fill(Msg::NoBank, &[&bank_code]));
+    }
+    change_report(&diff::diff_bank(old.as_ref(), new.as_ref()))
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use hyper::Method;
+use structopt::StructOpt;
+use zngn::dataset::Dataset;
+use zngn::layout::Layout;
+use zngn::logging;
+use zngn::server::{self, Config, State};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{ExitCode, Report};
+
+#[derive(Debug, StructOpt)]
+pub struct ServeOpt {
+    /// Address the HTTP server listens on
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+    /// Serve Swagger UI for the OpenAPI document at /docs
+    #[structopt(long)]
+    swagger_ui: bool,
+    /// Origin allowed to call the API from a browser, repeatable; "*" allows any origin
+    #[structopt(long = "cors-origin", number_of_values = 1)]
+    cors_origins: Vec<String>,
+    /// Methods advertised to cross-origin callers
+    #[structopt(long = "cors-method", number_of_values = 1, default_value = "GET")]
+    cors_methods: Vec<Method>,
+    /// Let clients cache successful responses for this many seconds
+    #[structopt(long)]
+    cache_max_age: Option<u64>,
+    /// API key clients must present as a bearer token or X-Api-Key header, repeatable
+    #[structopt(long = "api-key", env = "ZNGN_API_KEYS", use_delimiter = true, hide_env_values = true)]
+    api_keys: Vec<String>,
+    /// File with one API key per line; blank lines and lines starting with # are ignored
+    #[structopt(long, parse(from_os_str))]
+    api_keys_file: Option<PathBuf>,
+    /// Check every this many seconds for a finished crawl and reload the dataset; POST /reload always works
+    #[structopt(long)]
+    watch: Option<u64>,
+    /// Number of earlier dataset versions kept after reloads for GET /changes?since=
+    #[structopt(long, default_value = "10")]
+    keep_versions: usize,
+    /// Fail GET /readyz once the dataset was crawled more than this many seconds ago
+    #[structopt(long)]
+    ready_max_age: Option<u64>,
+    /// On SIGTERM or Ctrl-C, wait this many seconds for requests under way before exiting
+    #[structopt(long, default_value = "30")]
+    drain_timeout: u64,
+    /// Also serve another dataset under /v/NAME/, given as NAME=PATH to an output directory or
+    /// banks file; repeatable
+    #[structopt(long = "snapshot", number_of_values = 1)]
+    snapshots: Vec<NamedSnapshot>,
+    /// Keep the results of this many recent searches for repeated queries; 0 turns caching off
+    #[structopt(long, default_value = "1024")]
+    search_cache: usize,
+    /// Search the dataset again for a cached query once its results are this many seconds old
+    #[structopt(long, default_value = "60")]
+    search_cache_ttl: u64,
+}
+
+#[derive(Debug)]
+struct NamedSnapshot {
+    name: String,
+    path: PathBuf,
+}
+
+impl FromStr for NamedSnapshot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, path)) if !name.is_empty() && !name.contains('/') && !path.is_empty() => Ok(NamedSnapshot {
+                name: name.to_owned(),
+                path: PathBuf::from(path),
+            }),
+            _ => Err(format!("expected NAME=PATH, got {}", s)),
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn read_api_keys(path: &Path) -> Result<Vec<String>, Report> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        Report::failed(
+            ExitCode::Validation,
+            fill(Msg::ApiKeysUnreadable, &[&path.display(), &e]),
+        )
+    })?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect())
+}
+
+pub async fn run(opt: ServeOpt, layout: &Layout) -> Report {
+    let dataset = match Dataset::open(layout) {
+        Ok(dataset) => dataset,
+        Err(e) => return Report::load_failed(&e),
+    };
+    logging::info(&fill(Msg::Serving, &[&dataset.len(), &opt.bind]));
+    let mut api_keys = opt.api_keys;
+    if let Some(path) = &opt.api_keys_file {
+        match read_api_keys(path) {
+            Ok(keys) => api_keys.extend(keys),
+            Err(report) => return report,
+        }
+    }
+    let mut allowed_methods = opt.cors_methods;
+    if !allowed_methods.contains(&Method::OPTIONS) {
+        allowed_methods.push(Method::OPTIONS);
+    }
+    let config = Config {
+        swagger_ui: opt.swagger_ui,
+        allowed_origins: opt.cors_origins,
+        allowed_methods,
+        cache_max_age: opt.cache_max_age,
+        api_keys,
+        watch_interval: opt.watch.map(Duration::from_secs),
+        keep_versions: opt.keep_versions,
+        ready_max_age: opt.ready_max_age.map(Duration::from_secs),
+        drain_timeout: Duration::from_secs(opt.drain_timeout),
+        search_cache_size: opt.search_cache,
+        search_cache_ttl: Duration::from_secs(opt.search_cache_ttl),
+    };
+    let mut state = State::new(dataset.banks, config).dated(dataset.generated_at).reloadable(layout.clone());
+    for snapshot in opt.snapshots {
+        let loaded = if snapshot.path.is_dir() {
+            Dataset::open(&layout.relocated(snapshot.path.clone()))
+        } else {
+            Dataset::load(&snapshot.path)
+        };
+        let dataset = match loaded {
+            Ok(dataset) => dataset,
+            Err(e) => return Report::load_failed(&e),
+        };
+        logging::info(&fill(Msg::ServingSnapshot, &[&dataset.len(), &snapshot.name]));
+        state = state.with_dataset(&snapshot.name, dataset);
+    }
+    match server::serve(opt.bind, state).await {
+        Ok(()) => Report::new(&(), String::new()),
+        Err(e) => Report::from_error(&e),
+    }
+}
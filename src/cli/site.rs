@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::site::{self, Options};
+
+use crate::cli::{load, Report, Table};
+
+#[derive(Debug, StructOpt)]
+pub struct SiteOpt {
+    /// Directory the site is written to
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+    /// Heading of the index page and suffix of every page title
+    #[structopt(long, default_value = "Zengin bank codes")]
+    title: String,
+    /// Add a search box running search.wasm in the browser; build it from wasm/ with
+    /// `cargo build -p zngn-search-wasm --release --target wasm32-unknown-unknown` and serve the site over HTTP
+    #[structopt(long, parse(from_os_str))]
+    search: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct SiteSummary {
+    dir: PathBuf,
+    pages: usize,
+    bytes: usize,
+}
+
+pub fn run(opt: SiteOpt, layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let options = Options {
+        title: opt.title,
+        search: opt.search,
+    };
+    let built = match site::build(&banks, &opt.dir, &options) {
+        Ok(built) => built,
+        Err(e) => return Report::from_error(&e),
+    };
+    let summary = SiteSummary {
+        dir: opt.dir,
+        pages: built.pages,
+        bytes: built.bytes,
+    };
+    let mut table = Table::new(&["dir", "pages", "bytes"]);
+    table.push(vec![
+        summary.dir.to_string_lossy().into_owned(),
+        summary.pages.to_string(),
+        summary.bytes.to_string(),
+    ]);
+    Report::new(&summary, table.to_string())
+}
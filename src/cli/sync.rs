@@ -0,0 +1,144 @@
+use std::fs;
+use std::time::Duration;
+
+use serde::Serialize;
+use structopt::StructOpt;
+use tokio::time::interval;
+use zngn::backup;
+use zngn::delta::fingerprint;
+use zngn::layout::Layout;
+use zngn::lock::CrawlLock;
+use zngn::logging;
+use zngn::manifest::Manifest;
+use zngn::sync::{self, Pulled, Remote};
+use zngn::writer::save_branch_files;
+use zngn::{load_json_dataset, save_banks, save_index, Bank, Error};
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::Report;
+
+#[derive(Debug, StructOpt)]
+pub struct SyncOpt {
+    /// URL of a zngn server, e.g. https://zngn.internal:8080
+    #[structopt(long)]
+    from: String,
+    /// Keep running and check for a new dataset every this many seconds
+    #[structopt(long)]
+    interval: Option<u64>,
+    /// API key the server requires
+    #[structopt(long, env = "ZNGN_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncSummary {
+    version: String,
+    banks: usize,
+    // Operations applied from a patch; None when the whole dataset was downloaded.
+    changes: Option<usize>,
+    updated: bool,
+}
+
+fn local(layout: &Layout) -> Result<Option<Vec<Bank>>, Error> {
+    if !layout.banks_file().exists() {
+        return Ok(None);
+    }
+    let mut banks = load_json_dataset(layout)?;
+    for bank in &mut banks {
+        bank.aliases.clear();
+    }
+    Ok(Some(banks))
+}
+
+// The new dataset is written next to the output directory and swapped in by renaming the directory
+// once complete, so readers find the old dataset or the new one, never a mix. Files the dataset
+// doesn't carry, such as the alias table, are kept.
+pub async fn write(banks: &[Bank], layout: &Layout) -> Result<(), Error> {
+    let staging = layout.relocated(backup::sibling(layout.out(), "syncing"));
+    let _ = fs::remove_dir_all(staging.out());
+    let staged = match stage(banks, layout, &staging).await {
+        Ok(()) => backup::swap_in(staging.out(), layout.out()),
+        Err(e) => Err(e),
+    };
+    if staged.is_err() {
+        let _ = fs::remove_dir_all(staging.out());
+    }
+    staged
+}
+
+async fn stage(banks: &[Bank], layout: &Layout, staging: &Layout) -> Result<(), Error> {
+    let listed = banks
+        .iter()
+        .map(|bank| Bank {
+            branches: Vec::new(),
+            ..bank.clone()
+        })
+        .collect::<Vec<Bank>>();
+    save_banks(&listed, staging)?;
+    save_index(banks, staging)?;
+    let crawled = banks.iter().filter(|bank| !bank.branches.is_empty()).cloned().collect::<Vec<Bank>>();
+    save_branch_files(&crawled, staging, 16).await?;
+    for (file, staged) in [
+        (layout.aliases_file(), staging.aliases_file()),
+        (layout.english_names_file(), staging.english_names_file()),
+        (layout.parser_file(), staging.parser_file()),
+        (layout.source_file(), staging.source_file()),
+        (layout.abbreviations_file(), staging.abbreviations_file()),
+    ] {
+        if file.exists() {
+            fs::copy(file, staged).map_err(Error::SaveBankFileFailed)?;
+        }
+    }
+    Manifest::build(staging.out())?.save(&staging.manifest_file())
+}
+
+async fn sync_once(remote: &Remote, layout: &Layout) -> Result<(SyncSummary, String), Error> {
+    let _lock = CrawlLock::acquire(layout)?;
+    let mut banks = local(layout)?;
+    let pulled = sync::pull(remote, &mut banks).await?;
+    let banks = banks.unwrap_or_default();
+    let summary = SyncSummary {
+        version: fingerprint(&banks),
+        banks: banks.len(),
+        changes: match &pulled {
+            Pulled::Patched(delta) => Some(delta.ops.len()),
+            _ => None,
+        },
+        updated: !matches!(pulled, Pulled::UpToDate),
+    };
+    if summary.updated {
+        write(&banks, layout).await?;
+    }
+    let text = match pulled {
+        Pulled::UpToDate => fill(Msg::SyncUpToDate, &[&remote.url(), &summary.version]),
+        Pulled::Patched(delta) => fill(Msg::SyncPatched, &[&delta.ops.len(), &remote.url(), &summary.version]),
+        Pulled::Downloaded => fill(Msg::SyncDownloaded, &[&summary.banks, &remote.url(), &summary.version]),
+    };
+    Ok((summary, text))
+}
+
+// Without --interval this syncs once. With it, it keeps polling and a failed round is only logged, as
+// the next one may well succeed.
+pub async fn run(opt: SyncOpt, layout: &Layout) -> Report {
+    let remote = match Remote::new(&opt.from, opt.api_key) {
+        Ok(remote) => remote,
+        Err(e) => return Report::from_error(&e),
+    };
+    let period = match opt.interval {
+        Some(seconds) => Duration::from_secs(seconds.max(1)),
+        None => {
+            return match sync_once(&remote, layout).await {
+                Ok((summary, text)) => Report::new(&summary, format!("{}\n", text)),
+                Err(e) => Report::from_error(&e),
+            }
+        }
+    };
+    let mut ticks = interval(period);
+    loop {
+        ticks.tick().await;
+        match sync_once(&remote, layout).await {
+            Ok((_, text)) => logging::info(&text),
+            Err(e) => logging::error(&fill(Msg::SyncFailed, &[&e])),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use unicode_width::UnicodeWidthStr;
+
+// A plain text table whose columns line up even with full-width (kanji, kana) cells.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths = self.headers.iter().map(|header| header.width()).collect::<Vec<usize>>();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        widths
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter, cells: &[String], widths: &[usize]) -> fmt::Result {
+    let line = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+        .collect::<Vec<String>>()
+        .join("  ");
+    writeln!(f, "{}", line.trim_end())
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths = self.widths();
+        write_row(f, &self.headers, &widths)?;
+        let rule = widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<String>>();
+        write_row(f, &rule, &widths)?;
+        for row in &self.rows {
+            write_row(f, row, &widths)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn east_asian_width_test() {
+        use crate::cli::table::Table;
+
+        let mut table = Table::new(&["code", "name"]);
+        table.push(vec!["0001".to_owned(), "みずほ銀行".to_owned()]);
+        table.push(vec!["0005".to_owned(), "ﾐﾂﾋﾞｼ".to_owned()]);
+        assert_eq!(
+            table.to_string(),
+            "code  name\n----  ----------\n0001  みずほ銀行\n0005  ﾐﾂﾋﾞｼ\n"
+        );
+    }
+}
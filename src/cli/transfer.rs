@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+use zngn::batch::{self, ColumnNames};
+use zngn::layout::Layout;
+use zngn::transfer::{self, AccountType, Destination};
+use zngn::Error;
+
+use crate::cli::i18n::{fill, t, Msg};
+use crate::cli::{load, ExitCode, Report, Table};
+
+#[derive(Debug, StructOpt)]
+pub struct ValidateTransferOpt {
+    /// Bank code, with or without its leading zeros
+    #[structopt(long)]
+    bank: String,
+    /// Branch code, with or without its leading zeros
+    #[structopt(long)]
+    branch: String,
+    /// 預金種目: ordinary, current, savings or other
+    #[structopt(long, default_value = "ordinary")]
+    account_type: AccountType,
+    /// Account number of up to 7 digits; hyphens and full-width digits are accepted
+    #[structopt(long)]
+    account: String,
+    /// Receiver name in kana, normalized to half-width katakana as a transfer record carries it
+    #[structopt(long)]
+    name: String,
+}
+
+// Checks a transfer destination before it goes into a transfer file: that the bank and branch
+// exist, and that the account number and receiver name fit a zengin record.
+pub fn run(opt: ValidateTransferOpt, layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let destination = Destination {
+        bank_code: &opt.bank,
+        branch_code: &opt.branch,
+        account_type: opt.account_type,
+        account: &opt.account,
+        name: &opt.name,
+    };
+    let checked = transfer::check(&banks, &destination);
+    if !checked.is_valid() {
+        let mut report = Report::failed(ExitCode::Validation, fill(Msg::TransferInvalid, &[&checked.problems.len()]));
+        for problem in &checked.problems {
+            report.error(format!("{}: {}", problem.field, problem.message));
+        }
+        return report;
+    }
+    let mut table = Table::new(&["bank", "branch", "type", "account", "name"]);
+    table.push(vec![
+        format!("{} {}", checked.bank_code, checked.bank_name.clone().unwrap_or_default()),
+        format!("{} {}", checked.branch_code, checked.branch_name.clone().unwrap_or_default()),
+        checked.account_type.code().to_string(),
+        checked.account.clone(),
+        checked.name.clone(),
+    ]);
+    Report::new(&checked, format!("{}{}\n", table, t(Msg::TransferValid)))
+}
+
+#[derive(Debug, StructOpt)]
+pub struct BatchValidateOpt {
+    /// CSV file with a header row; rows without codes have them resolved from the bank and branch names
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    /// Where to write the annotated copy; defaults to the input's name with .checked.csv
+    #[structopt(long, parse(from_os_str))]
+    to: Option<PathBuf>,
+    /// Header of the bank code column, when it isn't bank_code, bank, 金融機関コード, 銀行コード or 銀行番号
+    #[structopt(long)]
+    bank_column: Option<String>,
+    /// Header of the branch code column, when it isn't branch_code, branch, 支店コード or 支店番号
+    #[structopt(long)]
+    branch_column: Option<String>,
+    /// Header of the bank name column, when it isn't bank_name, 金融機関名 or 銀行名
+    #[structopt(long)]
+    bank_name_column: Option<String>,
+    /// Header of the branch name column, when it isn't branch_name or 支店名
+    #[structopt(long)]
+    branch_name_column: Option<String>,
+}
+
+// Checks a whole payout file at once. The annotated copy is written even when rows fail or are left
+// for review, since it is what tells which ones; the exit status then reports a validation failure.
+pub fn batch(opt: BatchValidateOpt, layout: &Layout) -> Report {
+    let banks = match load(layout) {
+        Ok(banks) => banks,
+        Err(report) => return report,
+    };
+    let output = opt.to.clone().unwrap_or_else(|| opt.input.with_extension("checked.csv"));
+    let names = ColumnNames {
+        bank_code: opt.bank_column,
+        branch_code: opt.branch_column,
+        bank_name: opt.bank_name_column,
+        branch_name: opt.branch_name_column,
+    };
+    let checked = File::open(&opt.input).map_err(Error::file(&opt.input)).and_then(|input| {
+        let file = File::create(&output).map_err(Error::ExportFailed)?;
+        batch::validate(&banks, input, BufWriter::new(file), &names)
+    });
+    let summary = match checked {
+        Ok(summary) => summary,
+        Err(e) => return Report::from_error(&e),
+    };
+    let text = fill(
+        Msg::BatchChecked,
+        &[
+            &summary.rows,
+            &summary.ok,
+            &summary.warnings,
+            &summary.review,
+            &summary.errors,
+            &summary.resolved,
+            &output.display(),
+        ],
+    );
+    let mut report = Report::new(&summary, format!("{}\n", text));
+    report.insert_result("output", &output);
+    if summary.errors > 0 || summary.review > 0 {
+        report.exit_code = ExitCode::Validation;
+    }
+    report
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use structopt::StructOpt;
+use zngn::layout::Layout;
+use zngn::manifest::{Manifest, Mismatch, Problem};
+use zngn::signing;
+
+use crate::cli::i18n::{fill, Msg};
+use crate::cli::{ExitCode, Report};
+
+#[derive(Debug, StructOpt)]
+pub struct VerifyOpt {
+    /// Check every file against the SHA-256 checksums in manifest.json
+    #[structopt(long)]
+    checksums: bool,
+    /// Check that manifest.json was signed with the private key of this public key
+    #[structopt(long, parse(from_os_str))]
+    public_key: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct Verified {
+    files: usize,
+    mismatches: Vec<Mismatch>,
+}
+
+fn describe(mismatch: &Mismatch) -> String {
+    let msg = match mismatch.problem {
+        Problem::Missing => Msg::FileMissing,
+        Problem::Changed => Msg::FileChanged,
+        Problem::Unlisted => Msg::FileUnlisted,
+    };
+    fill(msg, &[&mismatch.path])
+}
+
+// Files added since the manifest was written, such as a later `zngn compile`, are only warned about;
+// missing or changed files fail the check.
+fn checksums(layout: &Layout) -> Report {
+    let manifest = match Manifest::load(&layout.manifest_file()) {
+        Ok(manifest) => manifest,
+        Err(e) => return Report::from_error(&e),
+    };
+    let mismatches = match manifest.verify(layout.out()) {
+        Ok(mismatches) => mismatches,
+        Err(e) => return Report::from_error(&e),
+    };
+    let (unlisted, broken): (Vec<&Mismatch>, Vec<&Mismatch>) =
+        mismatches.iter().partition(|mismatch| mismatch.problem == Problem::Unlisted);
+    let mut report = if broken.is_empty() {
+        let verified = Verified {
+            files: manifest.files.len(),
+            mismatches: mismatches.clone(),
+        };
+        Report::new(&verified, format!("{}\n", fill(Msg::ChecksumsMatch, &[&manifest.files.len()])))
+    } else {
+        let mut report = Report::failed(ExitCode::Validation, fill(Msg::ChecksumsDiffer, &[&broken.len()]));
+        for mismatch in &broken {
+            report.error(describe(mismatch));
+        }
+        report
+    };
+    for mismatch in unlisted {
+        report.warn(describe(mismatch));
+    }
+    report
+}
+
+// Checksums are checked unless only the signature is asked for. The signature is checked first:
+// checksums from an untrusted manifest prove nothing.
+pub fn run(opt: VerifyOpt, layout: &Layout) -> Report {
+    if let Some(public_key) = &opt.public_key {
+        if let Err(e) = signing::verify(layout, public_key) {
+            return Report::from_error(&e);
+        }
+        if !opt.checksums {
+            return Report::new(&(), format!("{}\n", fill(Msg::SignatureValid, &[&public_key.display()])));
+        }
+    }
+    checksums(layout)
+}
+
+#[derive(Debug, Serialize)]
+struct Keys {
+    private_key: PathBuf,
+    public_key: PathBuf,
+}
+
+pub fn keygen(path: PathBuf) -> Report {
+    match signing::generate(&path) {
+        Ok(public_key) => {
+            let text = fill(Msg::KeysWritten, &[&path.display(), &public_key.display()]);
+            Report::new(&Keys { private_key: path, public_key }, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
+
+pub fn sign(layout: &Layout, private_key: &Path) -> Report {
+    match signing::sign(layout, private_key) {
+        Ok(()) => {
+            let signature = layout.signature_file();
+            let text = fill(Msg::Signed, &[&signature.display()]);
+            Report::new(&signature, format!("{}\n", text))
+        }
+        Err(e) => Report::from_error(&e),
+    }
+}
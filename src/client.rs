@@ -0,0 +1,965 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::str::Chars;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use encoding_rs::Encoding;
+use reqwest::header::{CONTENT_TYPE, COOKIE};
+use reqwest::{Client, Proxy, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::delay_for;
+
+use crate::cancel::CancellationToken;
+use crate::dataset::Dataset;
+use crate::dedup::{self, Policy};
+use crate::layout::Layout;
+use crate::markup::Markup;
+use crate::pool::parse_in_pool;
+use crate::progress::ProgressObserver;
+use crate::retry::FailedRequest;
+use crate::throttle::{HostLimits, Throttle, Window};
+use crate::parse::{parse_banks, parse_branches, parse_detail, parse_hidden_fields};
+use crate::session::{CookieJar, SessionSource};
+use crate::{all_search_keys, gather, joined, report_failure, Bank, BankCode, Branch, Error, Parsed};
+
+const DEFAULT_CONCURRENCY: usize = 8;
+
+// Pages the bank list and the branch lists are fetched from.
+pub const DEFAULT_BASE: &str = "https://zengin.ajtw.net";
+
+// Names of the form fields the site's pages take.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormFields {
+    // The search key of the bank list.
+    pub bank_search_key: String,
+    // The search key of a branch list, and the bank it lists, as the bank list's search parameter.
+    pub branch_search_key: String,
+    pub branch_search_param: String,
+}
+
+impl Default for FormFields {
+    fn default() -> Self {
+        Self {
+            bank_search_key: "gm".to_owned(),
+            branch_search_key: "sm".to_owned(),
+            branch_search_param: "pz".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormMethod {
+    Post,
+    Get,
+}
+
+impl FormMethod {
+    fn other(self) -> Self {
+        match self {
+            FormMethod::Post => FormMethod::Get,
+            FormMethod::Get => FormMethod::Post,
+        }
+    }
+}
+
+// The character encoding form values are sent in, by its WHATWG label, e.g. "shift_jis" or "euc-jp".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FormEncoding(&'static Encoding);
+
+impl TryFrom<String> for FormEncoding {
+    type Error = String;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        Encoding::for_label(label.as_bytes())
+            .map(FormEncoding)
+            .ok_or_else(|| format!("unknown encoding {:?}", label))
+    }
+}
+
+impl From<FormEncoding> for String {
+    fn from(encoding: FormEncoding) -> Self {
+        encoding.0.name().to_lowercase()
+    }
+}
+
+impl Default for FormEncoding {
+    fn default() -> Self {
+        FormEncoding(encoding_rs::UTF_8)
+    }
+}
+
+// A form as application/x-www-form-urlencoded, its values in `encoding`. Characters the encoding
+// lacks are sent as HTML character references, as browsers do.
+fn encode_form(form: &[(&str, String)], encoding: FormEncoding) -> String {
+    let escape = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => (*byte as char).to_string(),
+                b' ' => "+".to_owned(),
+                _ => format!("%{:02X}", byte),
+            })
+            .collect::<String>()
+    };
+    form.iter()
+        .map(|(name, value)| {
+            let (value, _, _) = encoding.0.encode(value);
+            format!("{}={}", escape(name.as_bytes()), escape(&value))
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+// Where the pages are and what their forms take. The defaults match the site as it is; a source.json
+// in the output directory overrides any of them, so the crawler can follow the site when it moves a
+// page or renames a field, e.g. {"banks_path": "ginkou2.php"}.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Source {
+    pub base: String,
+    // Relative to `base`, or full URLs.
+    pub banks_path: String,
+    pub branches_path: String,
+    pub fields: FormFields,
+    // How forms are sent: POST with the fields in the body, or GET with them in the query string.
+    pub method: FormMethod,
+    // Send the form the other way when the site refuses the method with 405 or 501, and keep to
+    // that way for the rest of the run.
+    pub fallback: bool,
+    pub encoding: FormEncoding,
+    // A page to fetch first for a session cookie and CSRF token, for when the site starts requiring one.
+    pub session: Option<SessionSource>,
+}
+
+impl Source {
+    // The site's pages under another base URL, e.g. a mirror or `zngn mock-server`.
+    pub fn at(base: &str) -> Self {
+        Self::default().with_base(base)
+    }
+
+    pub fn with_base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_owned();
+        self
+    }
+
+    pub fn load(layout: &Layout) -> Result<Self, Error> {
+        let path = layout.source_file();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(&path).map_err(Error::file(&path))?;
+        serde_json::from_reader(file).map_err(Error::invalid(&path))
+    }
+
+    fn url(&self, path: &str) -> String {
+        if Url::parse(path).is_ok() {
+            return path.to_owned();
+        }
+        format!("{}/{}", self.base.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+
+    pub fn banks_url(&self) -> String {
+        self.url(&self.banks_path)
+    }
+
+    pub fn branches_url(&self) -> String {
+        self.url(&self.branches_path)
+    }
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self {
+            base: DEFAULT_BASE.to_owned(),
+            banks_path: "ginkou.php".to_owned(),
+            branches_path: "shitenmeisai.php".to_owned(),
+            fields: FormFields::default(),
+            method: FormMethod::Post,
+            fallback: true,
+            encoding: FormEncoding::default(),
+            session: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ZnginClientBuilder {
+    concurrency: Option<usize>,
+    bank_concurrency: Option<usize>,
+    branch_concurrency: Option<usize>,
+    retries: usize,
+    jitter: Duration,
+    max_bandwidth: Option<u64>,
+    max_requests: Option<usize>,
+    host_limits: Vec<HostLimits>,
+    window: Option<Window>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    source: Source,
+    markup: Markup,
+    enrich: bool,
+}
+
+impl ZnginClientBuilder {
+    // Requests in flight at the same time; unlimited by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    // Bank list requests in flight at the same time, within `concurrency`; unlimited by default.
+    pub fn bank_concurrency(mut self, concurrency: Option<usize>) -> Self {
+        self.bank_concurrency = concurrency.map(|concurrency| concurrency.max(1));
+        self
+    }
+
+    // Branch list requests in flight at the same time, within `concurrency`; unlimited by default.
+    pub fn branch_concurrency(mut self, concurrency: Option<usize>) -> Self {
+        self.branch_concurrency = concurrency.map(|concurrency| concurrency.max(1));
+        self
+    }
+
+    // Times a request failing on the network is sent again before it counts as failed.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    // Sleep a random duration up to `jitter` before each request.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // Bytes per second.
+    pub fn max_bandwidth(mut self, max_bandwidth: Option<u64>) -> Self {
+        self.max_bandwidth = max_bandwidth;
+        self
+    }
+
+    // Requests sent in total before fetching fails with `Error::RequestBudgetExhausted`.
+    pub fn max_requests(mut self, max_requests: Option<usize>) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    // Limits for particular hosts instead of the ones above.
+    pub fn host_limits(mut self, host_limits: Vec<HostLimits>) -> Self {
+        self.host_limits = host_limits;
+        self
+    }
+
+    // Only send requests during this time of day, pausing outside it.
+    pub fn window(mut self, window: Option<Window>) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    // Send every request through this proxy URL.
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    pub fn source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn markup(mut self, markup: Markup) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    // Read links to each bank's own page and any extra columns of the bank list, for `enrich_banks`.
+    pub fn enrich(mut self, enrich: bool) -> Self {
+        self.enrich = enrich;
+        self
+    }
+
+    pub fn build(self) -> Result<ZnginClient, Error> {
+        let mut http = Client::builder();
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            http = http.proxy(Proxy::all(proxy).map_err(Error::ClientFailed)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            http = http.user_agent(user_agent);
+        }
+        let mut throttle = Throttle::new(self.jitter, self.max_bandwidth, self.max_requests)
+            .with_hosts(self.host_limits)
+            .with_window(self.window);
+        if let Some(concurrency) = self.concurrency {
+            throttle = throttle.with_concurrency(concurrency);
+        }
+        Ok(ZnginClient {
+            http: http.build().map_err(Error::ClientFailed)?,
+            throttle,
+            bank_slots: self.bank_concurrency.map(|concurrency| Arc::new(Semaphore::new(concurrency))),
+            branch_slots: self.branch_concurrency.map(|concurrency| Arc::new(Semaphore::new(concurrency))),
+            retries: self.retries,
+            method: Arc::new(Mutex::new(self.source.method)),
+            cookies: Arc::new(CookieJar::default()),
+            tokens: Arc::new(tokio::sync::Mutex::new(None)),
+            source: Arc::new(self.source),
+            markup: Arc::new(self.markup),
+            enrich: self.enrich,
+        })
+    }
+}
+
+// Form fields by name and value.
+type Fields = Vec<(String, String)>;
+
+// Fetches banks and branches from a source, paced by its throttle. Cloning is cheap and clones share
+// the throttle, so limits hold across every clone.
+#[derive(Debug, Clone)]
+pub struct ZnginClient {
+    http: Client,
+    throttle: Throttle,
+    // Caps on bank list and branch list requests in flight, apart from the throttle's per host.
+    bank_slots: Option<Arc<Semaphore>>,
+    branch_slots: Option<Arc<Semaphore>>,
+    retries: usize,
+    // The method forms are sent with, which a fallback switches for every clone.
+    method: Arc<Mutex<FormMethod>>,
+    cookies: Arc<CookieJar>,
+    // The hidden fields of the session's form page, once fetched.
+    tokens: Arc<tokio::sync::Mutex<Option<Fields>>>,
+    source: Arc<Source>,
+    markup: Arc<Markup>,
+    enrich: bool,
+}
+
+// Requests that failed for good during `fetch_dataset`.
+#[derive(Default)]
+struct Failures(Mutex<Vec<FailedRequest>>);
+
+impl ProgressObserver for Failures {
+    fn request_failed(&self, _search_key: char, error: &Error) {
+        if let Some(request) = FailedRequest::from_error(error) {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner).push(request);
+        }
+    }
+}
+
+fn network_failure(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::FetchBankError { .. } | Error::FetchBranchError { .. } | Error::FetchDetailError { .. }
+    )
+}
+
+// Waits for one of `slots`, when there are any; hold the permit until the request is done.
+async fn acquire(slots: &Option<Arc<Semaphore>>) -> Option<SemaphorePermit<'_>> {
+    match slots {
+        Some(slots) => Some(slots.acquire().await),
+        None => None,
+    }
+}
+
+impl ZnginClient {
+    pub fn builder() -> ZnginClientBuilder {
+        ZnginClientBuilder::default()
+    }
+
+    // One request, sent again after a growing pause while it fails and retries remain. An error status
+    // is a failure like a broken connection, since its page would parse as no results, except for the
+    // `handled` ones the caller answers itself.
+    async fn send(
+        &self,
+        url: &str,
+        request: impl Fn(&Client) -> RequestBuilder,
+        fail: impl Fn(reqwest::Error) -> Error,
+        handled: &[StatusCode],
+    ) -> Result<(StatusCode, String), Error> {
+        let mut attempt = 0;
+        loop {
+            let slot = self.throttle.wait(url).await?;
+            let sent = async {
+                let parsed = Url::parse(url).ok();
+                let mut request = request(&self.http);
+                if let Some(cookie) = parsed.as_ref().and_then(|url| self.cookies.header(url)) {
+                    request = request.header(COOKIE, cookie);
+                }
+                let response = request.send().await?;
+                if let Some(url) = &parsed {
+                    self.cookies.store(url, response.headers());
+                }
+                let status = response.status();
+                let response = if handled.contains(&status) { response } else { response.error_for_status()? };
+                Ok((status, response.text().await?))
+            }
+            .await;
+            drop(slot);
+            match sent.map_err(&fail) {
+                Ok((status, html)) => {
+                    self.throttle.consume(url, html.len());
+                    return Ok((status, html));
+                }
+                Err(e) if attempt < self.retries && network_failure(&e) => {
+                    delay_for(Duration::from_millis(500 << attempt.min(6))).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // The session's hidden fields, fetching its form page on first use or when `refresh` is set.
+    async fn tokens(&self, refresh: bool, fail: impl Fn(reqwest::Error) -> Error) -> Result<Fields, Error> {
+        let session = match &self.source.session {
+            Some(session) => session,
+            None => return Ok(Vec::new()),
+        };
+        let mut tokens = self.tokens.lock().await;
+        if let (Some(tokens), false) = (tokens.as_ref(), refresh) {
+            return Ok(tokens.clone());
+        }
+        let url = self.source.url(&session.form_page);
+        let (_, html) = self.send(&url, |http| http.get(&url), fail, &[]).await?;
+        let fetched = parse_hidden_fields(&html, &session.token_fields);
+        *tokens = Some(fetched.clone());
+        Ok(fetched)
+    }
+
+    // Sends a form with the session's tokens, fetching them again once if the site says the session
+    // is stale.
+    async fn submit(&self, url: &str, form: &[(&str, String)], fail: impl Fn(reqwest::Error) -> Error) -> Result<String, Error> {
+        let tokens = self.tokens(false, &fail).await?;
+        let refresh_on = self
+            .source
+            .session
+            .iter()
+            .flat_map(|session| &session.refresh_on)
+            .filter_map(|status| StatusCode::from_u16(*status).ok())
+            .collect::<Vec<StatusCode>>();
+        let (status, html) = self.submit_with(url, form, &tokens, &refresh_on, &fail).await?;
+        if !refresh_on.contains(&status) {
+            return Ok(html);
+        }
+        let tokens = self.tokens(true, &fail).await?;
+        Ok(self.submit_with(url, form, &tokens, &[], &fail).await?.1)
+    }
+
+    // Sends a form the way the source says, falling back to the other method if the site refuses it.
+    // A refusal without a fallback, or of both methods, fails the request.
+    async fn submit_with(
+        &self,
+        url: &str,
+        form: &[(&str, String)],
+        tokens: &[(String, String)],
+        handled: &[StatusCode],
+        fail: impl Fn(reqwest::Error) -> Error,
+    ) -> Result<(StatusCode, String), Error> {
+        let form = form
+            .iter()
+            .cloned()
+            .chain(tokens.iter().map(|(name, value)| (name.as_str(), value.clone())))
+            .collect::<Vec<(&str, String)>>();
+        let encoded = encode_form(&form, self.source.encoding);
+        let request = |method: FormMethod| {
+            let encoded = encoded.clone();
+            move |http: &Client| match method {
+                FormMethod::Post => http
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(encoded.clone()),
+                FormMethod::Get => {
+                    let separator = if url.contains('?') { '&' } else { '?' };
+                    http.get(&format!("{}{}{}", url, separator, encoded))
+                }
+            }
+        };
+        let method = *self.method.lock().unwrap_or_else(PoisonError::into_inner);
+        let refused = [StatusCode::METHOD_NOT_ALLOWED, StatusCode::NOT_IMPLEMENTED];
+        if !self.source.fallback {
+            return self.send(url, request(method), &fail, handled).await;
+        }
+        let (status, html) = self.send(url, request(method), &fail, &[handled, &refused].concat()).await?;
+        if !refused.contains(&status) {
+            return Ok((status, html));
+        }
+        let sent = self.send(url, request(method.other()), &fail, handled).await?;
+        *self.method.lock().unwrap_or_else(PoisonError::into_inner) = method.other();
+        Ok(sent)
+    }
+
+    pub async fn fetch_banks(&self, search_key: char) -> Result<Parsed<Bank>, Error> {
+        let url = &self.source.banks_url();
+        let fail = |source| Error::FetchBankError {
+            search_key,
+            url: url.clone(),
+            source,
+        };
+        let form = [(self.source.fields.bank_search_key.as_str(), search_key.to_string())];
+        let slot = acquire(&self.bank_slots).await;
+        let html = self.submit(url, &form, fail).await?;
+        drop(slot);
+        let (markup, enrich) = (self.markup.clone(), self.enrich);
+        let mut parsed = parse_in_pool(html, move |html| parse_banks(html, &markup, enrich)).await?;
+        // Links on the page are relative to it.
+        if let Ok(base) = Url::parse(url) {
+            for bank in &mut parsed.items {
+                bank.detail_url = bank.detail_url.take().and_then(|link| Some(base.join(&link).ok()?.to_string()));
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub async fn fetch_branches(&self, bank: &Bank, search_key: char) -> Result<Parsed<Branch>, Error> {
+        let url = &self.source.branches_url();
+        let fail = |source| Error::FetchBranchError {
+            search_key,
+            bank_code: bank.code.clone(),
+            url: url.clone(),
+            source,
+        };
+        let fields = &self.source.fields;
+        let form = [
+            (fields.branch_search_key.as_str(), search_key.to_string()),
+            (fields.branch_search_param.as_str(), bank.search_param.clone()),
+        ];
+        let slot = acquire(&self.branch_slots).await;
+        let html = self.submit(url, &form, fail).await?;
+        drop(slot);
+        let markup = self.markup.clone();
+        parse_in_pool(html, move |html| parse_branches(html, &markup)).await
+    }
+
+    // Fills in the address and website of a bank from its own page, keeping what the bank list had.
+    // Banks without a link to one are left as they are.
+    pub async fn enrich_bank(&self, bank: &mut Bank) -> Result<(), Error> {
+        let url = match &bank.detail_url {
+            Some(url) => url.clone(),
+            None => return Ok(()),
+        };
+        let fail = |source| Error::FetchDetailError {
+            bank_code: bank.code.clone(),
+            url: url.clone(),
+            source,
+        };
+        let (_, html) = self.send(&url, |http| http.get(&url), fail, &[]).await?;
+        let markup = self.markup.clone();
+        let detail = parse_in_pool(html, move |html| parse_detail(html, &markup)).await?;
+        bank.address = bank.address.take().or(detail.address);
+        bank.website = bank.website.take().or(detail.website);
+        Ok(())
+    }
+
+    // `enrich_bank` for each bank, a few at a time as the throttle allows. A failed page leaves its bank
+    // as it was; the codes of those banks are returned. Pages left out by cancellation or an exhausted
+    // request budget don't count as failed.
+    pub async fn enrich_banks(&self, banks: &mut [Bank], cancel: CancellationToken) -> Vec<BankCode> {
+        let future = futures::future::join_all(banks.iter().map(|bank| {
+            let client = self.clone();
+            let cancel = cancel.clone();
+            let mut bank = bank.clone();
+            tokio::spawn(async move {
+                let result = tokio::select! {
+                    result = client.enrich_bank(&mut bank) => result,
+                    _ = cancel.cancelled() => Err(Error::Cancelled),
+                };
+                result.map(|()| bank)
+            })
+        }));
+        let mut failed = Vec::new();
+        for (bank, result) in banks.iter_mut().zip(future.await) {
+            match result {
+                Ok(Ok(enriched)) => *bank = enriched,
+                Ok(Err(Error::Cancelled | Error::RequestBudgetExhausted)) => {}
+                Ok(Err(_)) | Err(_) => failed.push(bank.code.clone()),
+            }
+        }
+        failed
+    }
+
+    // On cancellation the banks fetched so far are returned; check `cancel` to tell a partial result apart.
+    pub async fn fetch_all_banks(&self, observer: Arc<dyn ProgressObserver>, cancel: CancellationToken, search_keys: Chars<'static>) -> Result<Vec<Bank>, Error> {
+        let search_keys = search_keys.collect::<Vec<char>>();
+        let future = futures::future::join_all(search_keys.iter().map(|&search_key| {
+            let client = self.clone();
+            let observer = observer.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                let result = tokio::select! {
+                    result = client.fetch_banks(search_key) => result,
+                    _ = cancel.cancelled() => Err(Error::Cancelled),
+                };
+                report_failure(observer.as_ref(), search_key, &result);
+                result.map(|parsed| parsed.report(observer.as_ref(), search_key, None))
+            })
+        }));
+        let results = future.await.into_iter().zip(search_keys);
+        gather(results.map(|(result, search_key)| joined(observer.as_ref(), search_key, None, result)).collect())
+    }
+
+    // On cancellation the branches fetched so far are kept; check `cancel` to tell a partial result apart.
+    pub async fn fetch_all_branches(&self, bank: &mut Bank, observer: Arc<dyn ProgressObserver>, cancel: CancellationToken, search_keys: Chars<'static>) -> Result<(), Error> {
+        let search_keys = search_keys.collect::<Vec<char>>();
+        let future = futures::future::join_all(search_keys.iter().map(|&search_key| {
+            let client = self.clone();
+            let observer = observer.clone();
+            let cancel = cancel.clone();
+            let bank = bank.clone();
+            tokio::spawn(async move {
+                let result = tokio::select! {
+                    result = client.fetch_branches(&bank, search_key) => result,
+                    _ = cancel.cancelled() => Err(Error::Cancelled),
+                };
+                report_failure(observer.as_ref(), search_key, &result);
+                result.map(|parsed| parsed.report(observer.as_ref(), search_key, Some(&bank.code)))
+            })
+        }));
+        let results = future.await.into_iter().zip(search_keys);
+        let code = Some(&bank.code);
+        bank.branches = gather(results.map(|(result, search_key)| joined(observer.as_ref(), search_key, code, result)).collect())?;
+        Ok(())
+    }
+
+    // Returns how many banks from the front of `banks` had all their branches fetched.
+    pub async fn iterate_banks(&self, observer: Arc<dyn ProgressObserver>, cancel: &CancellationToken, banks: &mut [Bank]) -> Result<usize, Error> {
+        let total = banks.len();
+        for (completed, bank) in banks.iter_mut().enumerate() {
+            observer.bank_started(bank, completed, total);
+            match self.fetch_all_branches(bank, observer.clone(), cancel.clone(), all_search_keys()).await {
+                Ok(()) if !cancel.is_cancelled() => observer.bank_finished(bank, completed, total),
+                Ok(()) | Err(Error::RequestBudgetExhausted) => {
+                    observer.crawl_finished(completed, total);
+                    return Ok(completed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        observer.crawl_finished(total, total);
+        Ok(total)
+    }
+
+    // The whole crawl in one call: the bank list, then the branches of every bank. Codes seen twice
+    // keep their first record. Fails with `Error::Incomplete` when any request failed for good, rather
+    // than returning a dataset with holes in it.
+    pub async fn fetch_dataset(&self) -> Result<Dataset, Error> {
+        let failures = Arc::new(Failures::default());
+        let cancel = CancellationToken::new();
+        let banks = self.fetch_all_banks(failures.clone(), cancel.clone(), all_search_keys()).await?;
+        let (mut banks, _) = dedup::dedup_banks(banks, Policy::KeepFirst)?;
+        self.iterate_banks(failures.clone(), &cancel, &mut banks).await?;
+        for bank in &mut banks {
+            dedup::dedup_branches(bank, Policy::KeepFirst)?;
+        }
+        let failed = std::mem::take(&mut *failures.0.lock().unwrap_or_else(PoisonError::into_inner));
+        if !failed.is_empty() {
+            return Err(Error::Incomplete(failed));
+        }
+        Ok(Dataset::new(banks).generated(&self.source.banks_url()))
+    }
+}
+
+// `ZnginClient::fetch_dataset` with a client suited to a one-off crawl of the public site: a few
+// requests at a time, each retried twice.
+pub async fn fetch_dataset() -> Result<Dataset, Error> {
+    ZnginClient::builder()
+        .concurrency(DEFAULT_CONCURRENCY)
+        .retries(2)
+        .timeout(Duration::from_secs(60))
+        .build()?
+        .fetch_dataset()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn builder_test() {
+        use std::time::Duration;
+        use crate::client::{Source, ZnginClient};
+        use crate::Error;
+
+        let client = ZnginClient::builder()
+            .concurrency(0)
+            .retries(2)
+            .timeout(Duration::from_secs(30))
+            .user_agent("zngn-test")
+            .proxy("http://127.0.0.1:3128")
+            .source(Source::at("https://mirror.example.com/"))
+            .build()
+            .unwrap();
+        assert_eq!(client.retries, 2);
+        assert_eq!(client.source.banks_url(), "https://mirror.example.com/ginkou.php");
+        assert!(matches!(ZnginClient::builder().proxy("not a url").build(), Err(Error::ClientFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn kind_concurrency_test() {
+        use std::collections::HashMap;
+        use std::convert::Infallible;
+        use std::net::TcpListener;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Response, Server};
+        use crate::client::{Source, ZnginClient};
+        use crate::Bank;
+
+        // A slow site recording the most requests in flight at once, by page.
+        let in_flight = Arc::new(Mutex::new(HashMap::<String, (usize, usize)>::new()));
+        let recorded = in_flight.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let in_flight = recorded.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let in_flight = in_flight.clone();
+                    let path = request.uri().path().to_owned();
+                    async move {
+                        {
+                            let mut in_flight = in_flight.lock().unwrap();
+                            let (now, peak) = in_flight.entry(path.clone()).or_default();
+                            *now += 1;
+                            *peak = (*peak).max(*now);
+                        }
+                        tokio::time::delay_for(Duration::from_millis(50)).await;
+                        in_flight.lock().unwrap().get_mut(&path).unwrap().0 -= 1;
+                        Ok::<_, Infallible>(Response::new(Body::empty()))
+                    }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+        let client = ZnginClient::builder()
+            .source(Source::at(&base))
+            .bank_concurrency(Some(1))
+            .branch_concurrency(Some(3))
+            .build()
+            .unwrap();
+        let bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let banks = futures::future::join_all("あかさた".chars().map(|key| client.fetch_banks(key)));
+        let branches = futures::future::join_all("あかさたなはまや".chars().map(|key| client.fetch_branches(&bank, key)));
+        futures::future::join(banks, branches).await;
+        let in_flight = in_flight.lock().unwrap();
+        assert_eq!(in_flight["/ginkou.php"].1, 1);
+        assert_eq!(in_flight["/shitenmeisai.php"].1, 3);
+    }
+
+    #[test]
+    fn source_test() {
+        use crate::client::Source;
+
+        let source = serde_json::from_str::<Source>(
+            r#"{"branches_path": "/v2/shiten.php", "fields": {"branch_search_param": "bank"}}"#,
+        )
+        .unwrap()
+        .with_base("http://127.0.0.1:8080/");
+        assert_eq!(source.banks_url(), "http://127.0.0.1:8080/ginkou.php");
+        assert_eq!(source.branches_url(), "http://127.0.0.1:8080/v2/shiten.php");
+        assert_eq!(
+            (source.fields.branch_search_key.as_str(), source.fields.branch_search_param.as_str()),
+            ("sm", "bank")
+        );
+        let source = serde_json::from_str::<Source>(r#"{"banks_path": "https://other.example.com/list"}"#).unwrap();
+        assert_eq!(source.banks_url(), "https://other.example.com/list");
+        assert!(serde_json::from_str::<Source>(r#"{"encoding": "klingon"}"#).is_err());
+    }
+
+    #[test]
+    fn encode_form_test() {
+        use crate::client::{encode_form, FormEncoding};
+
+        let encoding = |label: &str| serde_json::from_value::<FormEncoding>(label.into()).unwrap();
+        let form = [("gm", "あ".to_owned()), ("pz", "0x 1".to_owned())];
+        assert_eq!(encode_form(&form, FormEncoding::default()), "gm=%E3%81%82&pz=0x+1");
+        assert_eq!(encode_form(&form, encoding("shift_jis")), "gm=%82%A0&pz=0x+1");
+        assert_eq!(encode_form(&form, encoding("euc-jp")), "gm=%A4%A2&pz=0x+1");
+        assert_eq!(encode_form(&[("gm", "€".to_owned())], encoding("shift_jis")), "gm=%26%238364%3B");
+    }
+
+    #[tokio::test]
+    async fn fallback_test() {
+        use std::convert::Infallible;
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Method, Response, Server, StatusCode};
+        use crate::client::{FormMethod, Source, ZnginClient};
+        use crate::Error;
+
+        // A site that only takes GET, echoing the query string, and has a page that takes neither.
+        let posts = Arc::new(AtomicUsize::new(0));
+        let counted = posts.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ginkou.php", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let posts = counted.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let response = if request.uri().path() == "/closed.php" || request.method() == Method::POST {
+                        posts.fetch_add(usize::from(request.method() == Method::POST), Ordering::SeqCst);
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+                        response
+                    } else {
+                        Response::new(Body::from(request.uri().query().unwrap_or("").to_owned()))
+                    };
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+        let client = ZnginClient::builder().build().unwrap();
+        let fail = |_| Error::ParseFailed;
+        assert_eq!(client.submit(&url, &[("gm", "あ".to_owned())], fail).await.unwrap(), "gm=%E3%81%82");
+        assert_eq!(*client.method.lock().unwrap(), FormMethod::Get);
+        assert_eq!(client.submit(&url, &[("gm", "い".to_owned())], fail).await.unwrap(), "gm=%E3%81%84");
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+
+        let source = Source {
+            fallback: false,
+            ..Source::default()
+        };
+        let client = ZnginClient::builder().source(source).build().unwrap();
+        assert!(matches!(client.submit(&url, &[("gm", "あ".to_owned())], fail).await, Err(Error::ParseFailed)));
+
+        // Refused both ways, the request fails rather than reading as a page of no results.
+        let closed = url.replace("ginkou.php", "closed.php");
+        let client = ZnginClient::builder().build().unwrap();
+        assert!(matches!(client.submit(&closed, &[("gm", "あ".to_owned())], fail).await, Err(Error::ParseFailed)));
+        assert_eq!(*client.method.lock().unwrap(), FormMethod::Post);
+    }
+
+    #[tokio::test]
+    async fn session_test() {
+        use std::convert::Infallible;
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use hyper::header::{COOKIE, SET_COOKIE};
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Response, Server, StatusCode};
+        use crate::client::{Source, ZnginClient};
+        use crate::session::SessionSource;
+        use crate::Error;
+
+        // A site whose form page hands out a session cookie and a token that is only good from the
+        // second page on, so the first form is refused as stale.
+        let pages = Arc::new(AtomicUsize::new(0));
+        let served = pages.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let pages = served.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let pages = pages.clone();
+                    async move {
+                        if request.uri().path() == "/index.php" {
+                            let page = pages.fetch_add(1, Ordering::SeqCst) + 1;
+                            let html = format!(
+                                r#"<form><input type="hidden" name="csrf" value="t{}"><input type="hidden" name="x" value="y"></form>"#,
+                                page
+                            );
+                            let mut response = Response::new(Body::from(html));
+                            response.headers_mut().insert(SET_COOKIE, "sid=s1; path=/".parse().unwrap());
+                            return Ok::<_, Infallible>(response);
+                        }
+                        let cookie = request.headers().get(COOKIE).map(|cookie| cookie.to_str().unwrap().to_owned());
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        let body = String::from_utf8(body.to_vec()).unwrap();
+                        if cookie.as_deref() != Some("sid=s1") || !body.ends_with("csrf=t2") {
+                            let mut response = Response::new(Body::empty());
+                            *response.status_mut() = StatusCode::FORBIDDEN;
+                            return Ok(response);
+                        }
+                        Ok(Response::new(Body::from(body)))
+                    }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+        let source = Source {
+            session: Some(SessionSource {
+                form_page: "index.php".to_owned(),
+                token_fields: vec!["csrf".to_owned()],
+                ..SessionSource::default()
+            }),
+            ..Source::at(&base)
+        };
+        let client = ZnginClient::builder().source(source).build().unwrap();
+        let fail = |_| Error::ParseFailed;
+        let url = client.source.banks_url();
+        assert_eq!(client.submit(&url, &[("gm", "a".to_owned())], fail).await.unwrap(), "gm=a&csrf=t2");
+        assert_eq!(client.submit(&url, &[("gm", "b".to_owned())], fail).await.unwrap(), "gm=b&csrf=t2");
+        assert_eq!(pages.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn status_test() {
+        use std::convert::Infallible;
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Response, Server, StatusCode};
+        use crate::client::{Source, ZnginClient};
+        use crate::Error;
+
+        // A site that is down for its first two requests, answering with an error page.
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let requests = counted.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    let response = if requests.fetch_add(1, Ordering::SeqCst) < 2 {
+                        let mut response = Response::new(Body::from("<html><table></table></html>"));
+                        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                        response
+                    } else {
+                        Response::new(Body::from(include_str!("../data/pages/ginkou_alphanumeric.html")))
+                    };
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+        let client = ZnginClient::builder().source(Source::at(&base)).build().unwrap();
+        assert!(matches!(client.fetch_banks('英').await, Err(Error::FetchBankError { .. })));
+        let client = ZnginClient::builder().source(Source::at(&base)).retries(1).build().unwrap();
+        assert_eq!(client.fetch_banks('英').await.unwrap().items.len(), 3);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
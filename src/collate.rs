@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::{Bank, Branch};
+
+const GOJUON: &str = "ｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜｦﾝ";
+const SMALL: &str = "ｧｨｩｪｫｬｭｮｯ";
+const SMALL_AS: &str = "ｱｲｳｴｵﾔﾕﾖﾂ";
+const FULLWIDTH: &str = "アイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワヲン";
+const FULLWIDTH_SMALL: &str = "ァィゥェォャュョッ";
+const FULLWIDTH_VOICED: &str = "ガギグゲゴザジズゼゾダヂヅデドバビブベボ";
+const FULLWIDTH_VOICED_AS: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+const FULLWIDTH_SEMI_VOICED: &str = "パピプペポ";
+const FULLWIDTH_SEMI_VOICED_AS: &str = "ハヒフヘホ";
+
+// Secondary weights: plain < voiced (ﾞ) < semi-voiced (ﾟ), as in a Japanese dictionary.
+const PLAIN: u8 = 0;
+const VOICED: u8 = 1;
+const SEMI_VOICED: u8 = 2;
+
+fn position(table: &str, c: char) -> Option<usize> {
+    table.chars().position(|t| t == c)
+}
+
+fn nth(table: &str, n: usize) -> char {
+    table.chars().nth(n).unwrap()
+}
+
+fn hiragana_to_katakana(c: char) -> char {
+    match c {
+        'ぁ'..='ゖ' => std::char::from_u32(c as u32 + 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+// Folds any kana into its half-width base letter plus a voicing weight.
+fn fold(c: char) -> Option<(char, u8)> {
+    let c = hiragana_to_katakana(c);
+    if GOJUON.contains(c) {
+        return Some((c, PLAIN));
+    }
+    if let Some(i) = position(SMALL, c) {
+        return Some((nth(SMALL_AS, i), PLAIN));
+    }
+    if let Some(i) = position(FULLWIDTH, c) {
+        return Some((nth(GOJUON, i), PLAIN));
+    }
+    if let Some(i) = position(FULLWIDTH_SMALL, c) {
+        return Some((nth(SMALL_AS, i), PLAIN));
+    }
+    if let Some(i) = position(FULLWIDTH_VOICED, c) {
+        return fold(nth(FULLWIDTH_VOICED_AS, i)).map(|(base, _)| (base, VOICED));
+    }
+    if let Some(i) = position(FULLWIDTH_SEMI_VOICED, c) {
+        return fold(nth(FULLWIDTH_SEMI_VOICED_AS, i)).map(|(base, _)| (base, SEMI_VOICED));
+    }
+    if c == 'ヴ' {
+        return Some(('ｳ', VOICED));
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GojuonKey {
+    primary: Vec<u32>,
+    secondary: Vec<u8>,
+    raw: String,
+}
+
+// Sort key ordering readings the way a Japanese dictionary does (あいうえお順): voicing marks and
+// small kana only break ties, long vowel marks and punctuation are ignored, and digits or latin
+// letters come before any kana.
+pub fn gojuon_key(reading: &str) -> GojuonKey {
+    let mut primary = Vec::new();
+    let mut secondary = Vec::new();
+    for c in reading.chars() {
+        match c {
+            'ﾞ' => {
+                if let Some(last) = secondary.last_mut() {
+                    *last = VOICED;
+                }
+            }
+            'ﾟ' => {
+                if let Some(last) = secondary.last_mut() {
+                    *last = SEMI_VOICED;
+                }
+            }
+            _ => match fold(c) {
+                Some((base, weight)) => {
+                    primary.push(0x11_0000 + position(GOJUON, base).unwrap() as u32);
+                    secondary.push(weight);
+                }
+                None if c.is_alphanumeric() => {
+                    primary.push(c.to_ascii_uppercase() as u32);
+                    secondary.push(PLAIN);
+                }
+                None => {}
+            },
+        }
+    }
+    GojuonKey {
+        primary,
+        secondary,
+        raw: reading.to_owned(),
+    }
+}
+
+pub fn compare(a: &str, b: &str) -> Ordering {
+    gojuon_key(a).cmp(&gojuon_key(b))
+}
+
+// First kana of each gojūon row (行), in order.
+pub const GOJUON_ROWS: &str = "あかさたなはまやらわ";
+
+// The row (0 for あ行 to 9 for わ行) of a reading's first kana; None when it starts with a letter or digit.
+pub fn gojuon_row(reading: &str) -> Option<usize> {
+    let (base, _) = fold(reading.chars().next()?)?;
+    let position = position(GOJUON, base).unwrap();
+    // Rows have five kana each, except や行 with three and わ行 with ﾜｦﾝ.
+    Some(match position {
+        0..=34 => position / 5,
+        35..=37 => 7,
+        38..=42 => 8,
+        _ => 9,
+    })
+}
+
+// Whether two readings spell the same kana, whether in hiragana, full-width or half-width katakana.
+// Zengin data writes the long vowel mark as a hyphen, so the two are treated alike.
+pub fn same_reading(a: &str, b: &str) -> bool {
+    let key = |reading: &str| gojuon_key(&reading.replace(['ー', 'ｰ', '－'], "-"));
+    let (a, b) = (key(a), key(b));
+    a.primary == b.primary && a.secondary == b.secondary
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Code,
+    Name,
+    Phonetic,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "code" => Ok(SortKey::Code),
+            "name" => Ok(SortKey::Name),
+            "phonetic" => Ok(SortKey::Phonetic),
+            _ => Err(format!("unknown sort key: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(format!("unknown sort order: {}", s)),
+        }
+    }
+}
+
+fn sort_by<T>(items: &mut [T], key: SortKey, order: SortOrder, fields: impl Fn(&T) -> (&str, &str, &str)) {
+    match key {
+        SortKey::Code => items.sort_by(|a, b| fields(a).0.cmp(fields(b).0)),
+        SortKey::Name => items.sort_by(|a, b| fields(a).1.cmp(fields(b).1)),
+        SortKey::Phonetic => items.sort_by_cached_key(|item| gojuon_key(fields(item).2)),
+    }
+    if order == SortOrder::Desc {
+        items.reverse();
+    }
+}
+
+pub fn sort_banks(banks: &mut [Bank], key: SortKey, order: SortOrder) {
+    sort_by(banks, key, order, |bank| (&bank.code.0, &bank.name, &bank.phonetic));
+}
+
+pub fn sort_branches(branches: &mut [Branch], key: SortKey, order: SortOrder) {
+    sort_by(branches, key, order, |branch| (&branch.code, &branch.name, &branch.phonetic));
+}
+
+// Orders the banks and, within each bank, its branches by the same key.
+pub fn sort_dataset(banks: &mut [Bank], key: SortKey, order: SortOrder) {
+    sort_banks(banks, key, order);
+    for bank in banks.iter_mut() {
+        sort_branches(&mut bank.branches, key, order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn gojuon_order_test() {
+        use crate::collate::gojuon_key;
+
+        let mut readings = vec!["ﾗｸﾃﾝ", "ｷﾗﾎﾞｼ", "ｶﾞｸ", "ｶｸ", "ｱｵﾓﾘ", "ｶｲ", "ｷﾞﾌ", "ﾐｽﾞﾎ", "PAYPAY"];
+        readings.sort_by_key(|reading| gojuon_key(reading));
+        assert_eq!(readings, vec!["PAYPAY", "ｱｵﾓﾘ", "ｶｲ", "ｶｸ", "ｶﾞｸ", "ｷﾞﾌ", "ｷﾗﾎﾞｼ", "ﾐｽﾞﾎ", "ﾗｸﾃﾝ"]);
+    }
+
+    #[test]
+    fn fold_width_and_script_test() {
+        use crate::collate::compare;
+        use std::cmp::Ordering;
+
+        assert_eq!(compare("ｶｸ", "ガク"), Ordering::Less);
+        assert_eq!(compare("ガク", "ｷ"), Ordering::Less);
+        assert_eq!(compare("がく", "ｷ"), Ordering::Less);
+        assert_eq!(compare("ｼﾝｷﾝ", "ｼﾞｪｲｴｲ"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::{Bank, BankCode, Branch, Error};
+
+const MAGIC: [u8; 4] = *b"ZNGN";
+// Bumped whenever the layout below changes; files of another version are ignored.
+const VERSION: u32 = 2;
+
+// Every distinct string is stored once and referred to by its position in `strings`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Compiled {
+    strings: Vec<String>,
+    banks: Vec<CompiledBank>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CompiledBank {
+    name: u32,
+    phonetic: u32,
+    code: u32,
+    search_param: u32,
+    // code, name and phonetic of each branch.
+    branches: Vec<[u32; 3]>,
+    // detail_url, address and website, which only enriched crawls have.
+    details: [Option<u32>; 3],
+}
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    positions: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn add(&mut self, s: &str) -> u32 {
+        if let Some(&position) = self.positions.get(s) {
+            return position;
+        }
+        let position = self.strings.len() as u32;
+        self.strings.push(s.to_owned());
+        self.positions.insert(s.to_owned(), position);
+        position
+    }
+}
+
+// Writes the dataset in the compact format and returns the file size. Aliases are left out since
+// they come from the alias tables, which are applied again on load.
+pub fn compile(banks: &[Bank], layout: &Layout) -> Result<u64, Error> {
+    let mut table = StringTable::default();
+    let banks = banks
+        .iter()
+        .map(|bank| CompiledBank {
+            name: table.add(&bank.name),
+            phonetic: table.add(&bank.phonetic),
+            code: table.add(&bank.code.0),
+            search_param: table.add(&bank.search_param),
+            branches: bank
+                .branches
+                .iter()
+                .map(|branch| [table.add(&branch.code), table.add(&branch.name), table.add(&branch.phonetic)])
+                .collect(),
+            details: [&bank.detail_url, &bank.address, &bank.website].map(|detail| detail.as_deref().map(|s| table.add(s))),
+        })
+        .collect();
+    let compiled = Compiled {
+        strings: table.strings,
+        banks,
+    };
+    let path = layout.compiled_file();
+    let file = File::create(&path).map_err(Error::file(&path))?;
+    let mut writer = BufWriter::new(file);
+    bincode::serialize_into(&mut writer, &(MAGIC, VERSION)).map_err(Error::CompiledDatasetFailed)?;
+    bincode::serialize_into(&mut writer, &compiled).map_err(Error::CompiledDatasetFailed)?;
+    drop(writer);
+    fs::metadata(&path).map(|metadata| metadata.len()).map_err(Error::file(&path))
+}
+
+// The compiled dataset, or None when there is none or it is older than the JSON snapshot.
+pub fn load(layout: &Layout) -> Result<Option<Vec<Bank>>, Error> {
+    let path = layout.compiled_file();
+    let compiled_at = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return Ok(None),
+    };
+    let saved_at = fs::metadata(layout.banks_file()).and_then(|metadata| metadata.modified());
+    if saved_at.is_ok_and(|saved_at| saved_at > compiled_at) {
+        return Ok(None);
+    }
+    let file = File::open(&path).map_err(Error::file(&path))?;
+    let mut reader = BufReader::new(file);
+    let header: ([u8; 4], u32) = bincode::deserialize_from(&mut reader).map_err(Error::CompiledDatasetFailed)?;
+    if header != (MAGIC, VERSION) {
+        return Ok(None);
+    }
+    let compiled: Compiled = bincode::deserialize_from(&mut reader).map_err(Error::CompiledDatasetFailed)?;
+    let strings = compiled.strings.into_iter().map(Arc::<str>::from).collect::<Vec<Arc<str>>>();
+    let string = |position: u32| -> Result<&Arc<str>, Error> {
+        strings.get(position as usize).ok_or(Error::ParseFailed)
+    };
+    let mut banks = Vec::with_capacity(compiled.banks.len());
+    for bank in compiled.banks {
+        let mut branches = Vec::with_capacity(bank.branches.len());
+        for [code, name, phonetic] in bank.branches {
+            branches.push(Branch {
+                name: string(name)?.clone(),
+                phonetic: string(phonetic)?.clone(),
+                code: string(code)?.to_string(),
+            });
+        }
+        let [detail_url, address, website] = bank
+            .details
+            .map(|position| position.map(|position| string(position).map(|s| s.to_string())).transpose());
+        banks.push(Bank {
+            name: string(bank.name)?.to_string(),
+            phonetic: string(bank.phonetic)?.to_string(),
+            code: BankCode(string(bank.code)?.to_string()),
+            search_param: string(bank.search_param)?.to_string(),
+            branches,
+            aliases: Vec::new(),
+            name_en: None,
+            detail_url: detail_url?,
+            address: address?,
+            website: website?,
+        });
+    }
+    Ok(Some(banks))
+}
+
+// Called before a crawl writes anything, so a half updated snapshot never hides behind an old compiled file.
+pub fn invalidate(layout: &Layout) {
+    let _ = fs::remove_file(layout.compiled_file());
+    let _ = fs::remove_file(layout.archived_file());
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn compile_test() {
+        use std::path::PathBuf;
+        use std::sync::Arc;
+        use crate::compiled::{compile, invalidate, load};
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::{Bank, Branch};
+
+        let out = std::env::temp_dir().join(format!("zngn-compiled-{}", std::process::id()));
+        std::fs::create_dir_all(&out).unwrap();
+        let layout = Layout::new(PathBuf::from(&out), DEFAULT_TEMPLATE.to_owned()).unwrap();
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        neko.address = Some("東京都千代田区".to_owned());
+        let mut inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        inu.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let banks = vec![neko, inu];
+
+        assert!(load(&layout).unwrap().is_none());
+        compile(&banks, &layout).unwrap();
+        let loaded = load(&layout).unwrap().unwrap();
+        assert_eq!(loaded, banks);
+        assert!(Arc::ptr_eq(&loaded[0].branches[0].name, &loaded[1].branches[0].name));
+
+        invalidate(&layout);
+        assert!(load(&layout).unwrap().is_none());
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+}
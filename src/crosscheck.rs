@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use futures::stream::{iter as siter, StreamExt};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::collate::same_reading;
+use crate::{Bank, Error};
+
+// The published data of https://github.com/zengin-code/source-data: banks.json and branches/<code>.json.
+pub const DEFAULT_SOURCE: &str = "https://raw.githubusercontent.com/zengin-code/source-data/master/data";
+
+// zengin-code names banks without their legal form, e.g. みずほ for みずほ銀行.
+const LEGAL_FORMS: [&str; 9] = [
+    "銀行",
+    "信用金庫",
+    "信用組合",
+    "労働金庫",
+    "農業協同組合",
+    "漁業協同組合",
+    "信用農業協同組合連合会",
+    "信用漁業協同組合連合会",
+    "農林中央金庫",
+];
+
+// Branch files fetched at the same time.
+const CONCURRENCY: usize = 8;
+
+// A bank or branch as zengin-code lists it; the hiragana and romaji spellings aren't compared.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Entry {
+    pub code: String,
+    pub name: String,
+    pub kana: String,
+}
+
+// zengin-code's banks by code, along with the branches of the banks that were fetched.
+#[derive(Debug, Clone, Default)]
+pub struct Reference {
+    pub banks: BTreeMap<String, Entry>,
+    pub branches: BTreeMap<String, BTreeMap<String, Entry>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    // Only in our dataset.
+    OnlyOurs,
+    // Only in zengin-code.
+    OnlyTheirs,
+    Name,
+    Reading,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Discrepancy {
+    pub kind: Kind,
+    pub bank_code: String,
+    // None when the discrepancy is about the bank itself.
+    pub branch_code: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+// Full-width letters and digits become ASCII and spaces go, as the two sources differ in both.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '！'..='～' => std::char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+fn same_bank_name(ours: &str, theirs: &str) -> bool {
+    let (ours, theirs) = (normalize(ours), normalize(theirs));
+    ours == theirs || ours.strip_prefix(&theirs).is_some_and(|form| LEGAL_FORMS.contains(&form))
+}
+
+// Likewise, branches go without 支店.
+fn same_branch_name(ours: &str, theirs: &str) -> bool {
+    let (ours, theirs) = (normalize(ours), normalize(theirs));
+    ours == theirs || ours.strip_prefix(&theirs) == Some("支店")
+}
+
+struct Compared<'a> {
+    discrepancies: Vec<Discrepancy>,
+    bank_code: &'a str,
+    branch_code: Option<&'a str>,
+}
+
+impl Compared<'_> {
+    fn push(&mut self, kind: Kind, ours: Option<&str>, theirs: Option<&str>) {
+        self.discrepancies.push(Discrepancy {
+            kind,
+            bank_code: self.bank_code.to_owned(),
+            branch_code: self.branch_code.map(str::to_owned),
+            ours: ours.map(str::to_owned),
+            theirs: theirs.map(str::to_owned),
+        });
+    }
+}
+
+// Differences between our banks and zengin-code's, ordered by code. Branches are compared only for
+// banks both sides have branches of, so a bank list crawled without branches compares cleanly.
+pub fn compare(banks: &[Bank], reference: &Reference) -> Vec<Discrepancy> {
+    let ours = banks.iter().map(|bank| (bank.code.0.as_str(), bank)).collect::<BTreeMap<&str, &Bank>>();
+    let mut compared = Compared {
+        discrepancies: Vec::new(),
+        bank_code: "",
+        branch_code: None,
+    };
+    for (code, entry) in &reference.banks {
+        if !ours.contains_key(code.as_str()) {
+            compared.bank_code = code;
+            compared.branch_code = None;
+            compared.push(Kind::OnlyTheirs, None, Some(&entry.name));
+        }
+    }
+    for (code, bank) in ours {
+        compared.bank_code = code;
+        compared.branch_code = None;
+        let entry = match reference.banks.get(code) {
+            Some(entry) => entry,
+            None => {
+                compared.push(Kind::OnlyOurs, Some(&bank.name), None);
+                continue;
+            }
+        };
+        if !same_bank_name(&bank.name, &entry.name) {
+            compared.push(Kind::Name, Some(&bank.name), Some(&entry.name));
+        }
+        if !same_reading(&bank.phonetic, &entry.kana) {
+            compared.push(Kind::Reading, Some(&bank.phonetic), Some(&entry.kana));
+        }
+        let theirs = match reference.branches.get(code) {
+            Some(theirs) if !bank.branches.is_empty() => theirs,
+            _ => continue,
+        };
+        let mut branches = bank.branches.iter().map(|branch| (branch.code.as_str(), branch)).collect::<BTreeMap<_, _>>();
+        for (branch_code, entry) in theirs {
+            compared.branch_code = Some(branch_code);
+            match branches.remove(branch_code.as_str()) {
+                None => compared.push(Kind::OnlyTheirs, None, Some(&entry.name)),
+                Some(branch) => {
+                    if !same_branch_name(&branch.name, &entry.name) {
+                        compared.push(Kind::Name, Some(&branch.name), Some(&entry.name));
+                    }
+                    if !same_reading(&branch.phonetic, &entry.kana) {
+                        compared.push(Kind::Reading, Some(&branch.phonetic), Some(&entry.kana));
+                    }
+                }
+            }
+        }
+        for (branch_code, branch) in branches {
+            compared.branch_code = Some(branch_code);
+            compared.push(Kind::OnlyOurs, Some(&branch.name), None);
+        }
+    }
+    let mut discrepancies = compared.discrepancies;
+    discrepancies.sort_by(|a, b| (&a.bank_code, &a.branch_code).cmp(&(&b.bank_code, &b.branch_code)));
+    discrepancies
+}
+
+// None when the file isn't there, as for banks zengin-code lists no branches of.
+async fn get<T: for<'de> Deserialize<'de>>(client: &Client, url: String) -> Result<Option<T>, Error> {
+    let fail = |source| Error::RemoteFailed {
+        url: url.clone(),
+        source,
+    };
+    let response = client.get(&url).send().await.map_err(fail)?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let body = response.error_for_status().map_err(fail)?.bytes().await.map_err(fail)?;
+    serde_json::from_slice(&body).map(Some).map_err(Error::LoadBanksFileFailed)
+}
+
+// zengin-code's bank list, plus the branches of each bank in `branches_of`.
+pub async fn fetch(client: &Client, source: &str, branches_of: &[&str]) -> Result<Reference, Error> {
+    let source = source.trim_end_matches('/');
+    let url = format!("{}/banks.json", source);
+    let banks = get(client, url.clone())
+        .await?
+        .ok_or_else(|| Error::CrossCheckFailed(format!("no bank list at {}", url)))?;
+    let mut reference = Reference {
+        banks,
+        branches: BTreeMap::new(),
+    };
+    let fetched = siter(branches_of.iter().filter(|code| reference.banks.contains_key(**code)))
+        .map(|code| async move {
+            let branches = get(client, format!("{}/branches/{}.json", source, code)).await?;
+            Ok((code.to_string(), branches))
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<Result<(String, Option<BTreeMap<String, Entry>>), Error>>>()
+        .await;
+    for result in fetched {
+        if let (code, Some(branches)) = result? {
+            reference.branches.insert(code, branches);
+        }
+    }
+    Ok(reference)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn compare_test() {
+        use std::collections::BTreeMap;
+        use crate::crosscheck::{compare, Entry, Kind, Reference};
+        use crate::{Bank, Branch};
+
+        let entry = |code: &str, name: &str, kana: &str| Entry {
+            code: code.to_owned(),
+            name: name.to_owned(),
+            kana: kana.to_owned(),
+        };
+        let mut mizuho = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned());
+        mizuho.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        mizuho.append_branch(Branch::new("丸之内支店".to_owned(), "ﾏﾙﾉｳﾁ".to_owned(), "002".to_owned()));
+        mizuho.append_branch(Branch::new("丸の内中央支店".to_owned(), "ﾏﾙﾉｳﾁﾁﾕｳｵｳ".to_owned(), "004".to_owned()));
+        let ufj = Bank::new("三菱ＵＦＪ銀行".to_owned(), "ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ".to_owned(), "0005".to_owned(), "x5".to_owned());
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "x222".to_owned());
+
+        let mut reference = Reference::default();
+        for bank in [entry("0001", "みずほ", "ミズホ"), entry("0005", "三菱UFJ", "ミツビシユーエフジエイ"), entry("0009", "三井住友", "ミツイスミトモ"), entry("0222", "いぬ", "イヌ")] {
+            reference.banks.insert(bank.code.clone(), bank);
+        }
+        let mut branches = BTreeMap::new();
+        branches.insert("001".to_owned(), entry("001", "東京営業部", "トウキョウ"));
+        branches.insert("002".to_owned(), entry("002", "丸之内", "マルノウチ"));
+        branches.insert("003".to_owned(), entry("003", "丸の内中央", "マルノウチチュウオウ"));
+        reference.branches.insert("0001".to_owned(), branches);
+
+        let found = compare(&[mizuho, ufj, neko], &reference)
+            .into_iter()
+            .map(|d| (d.kind, format!("{}-{}", d.bank_code, d.branch_code.unwrap_or_default())))
+            .collect::<Vec<(Kind, String)>>();
+        let expected = [
+            (Kind::OnlyTheirs, "0001-003"),
+            (Kind::OnlyOurs, "0001-004"),
+            (Kind::OnlyTheirs, "0009-"),
+            (Kind::Name, "0222-"),
+            (Kind::Reading, "0222-"),
+        ];
+        assert_eq!(found, expected.iter().map(|(kind, code)| (*kind, code.to_string())).collect::<Vec<(Kind, String)>>());
+    }
+}
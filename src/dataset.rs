@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::export::{self, Format, Options};
+use crate::layout::Layout;
+use crate::manifest::Manifest;
+use crate::schema::Violation;
+use crate::search::{self, Hit, SearchHit};
+use crate::{load_dataset, migrate, prepare_parent_dir, Bank, BankCode, Branch, Error};
+
+// Bumped whenever the layout of a saved dataset changes in a way older readers can't follow.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Every bank with its branches, ordered by bank code, along with where and when it was crawled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dataset {
+    pub schema_version: u32,
+    // Unix time the banks were fetched, when known.
+    #[serde(default)]
+    pub generated_at: Option<u64>,
+    // The site the banks were fetched from, when known.
+    #[serde(default)]
+    pub source: Option<String>,
+    pub banks: Vec<Bank>,
+}
+
+impl Dataset {
+    pub fn new(mut banks: Vec<Bank>) -> Self {
+        banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+        Self {
+            schema_version: SCHEMA_VERSION,
+            generated_at: None,
+            source: None,
+            banks,
+        }
+    }
+
+    // Stamps the dataset as fetched from `source` just now.
+    pub fn generated(mut self, source: &str) -> Self {
+        self.generated_at = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs());
+        self.source = Some(source.to_owned());
+        self
+    }
+
+    // The dataset in an output directory, dated by its manifest when there is one.
+    pub fn open(layout: &Layout) -> Result<Self, Error> {
+        let mut dataset = Self::new(load_dataset(layout)?);
+        if layout.manifest_file().exists() {
+            dataset.generated_at = Some(Manifest::load(&layout.manifest_file())?.created_at);
+        }
+        Ok(dataset)
+    }
+
+    // Reads a file written by `save`. Bare bank lists, such as exports or banks.json, load too, just
+    // without metadata.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::file(path))?;
+        let document: Value = serde_json::from_reader(file).map_err(Error::invalid(path))?;
+        if document.get("schema_version").is_none() {
+            return migrate::read_file(path).map(Self::new);
+        }
+        let mut dataset: Self = serde_json::from_value(document).map_err(Error::invalid(path))?;
+        if dataset.schema_version > SCHEMA_VERSION {
+            return Err(Error::SchemaViolations(vec![Violation {
+                pointer: "/schema_version".to_owned(),
+                message: format!("schema version {} is newer than this zngn reads", dataset.schema_version),
+            }]));
+        }
+        dataset.banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+        Ok(dataset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        prepare_parent_dir(path);
+        let file = File::create(path).map_err(Error::file(path))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self).map_err(Error::SerializeFailed)?;
+        writer.flush().map_err(Error::file(path))
+    }
+
+    pub fn export<W: Write>(&self, format: Format, options: &Options, writer: W) -> Result<(), Error> {
+        export::write(&self.banks, format, options, writer)
+    }
+
+    pub fn export_to_file(&self, format: Format, options: &Options, path: &Path) -> Result<(), Error> {
+        export::export_to_file(&self.banks, format, options, path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.banks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.banks.is_empty()
+    }
+
+    pub fn bank(&self, code: &str) -> Option<&Bank> {
+        let code = code.parse::<BankCode>().ok()?;
+        self.banks
+            .binary_search_by(|bank| bank.code.0.cmp(&code.0))
+            .ok()
+            .map(|i| &self.banks[i])
+    }
+
+    pub fn branch(&self, bank_code: &str, branch_code: &str) -> Option<&Branch> {
+        self.bank(bank_code).and_then(|bank| search::lookup_branch(bank, branch_code))
+    }
+
+    // Banks whose code matches a glob such as `00*`.
+    pub fn lookup_banks(&self, pattern: &str) -> Vec<&Bank> {
+        search::lookup_banks(&self.banks, pattern)
+    }
+
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = Hit<'a>> + 'a {
+        search::search(&self.banks, query)
+    }
+
+    pub fn search_hits<'a>(&'a self, query: &'a str) -> impl Iterator<Item = SearchHit<'a>> + 'a {
+        search::search_hits(&self.banks, query)
+    }
+
+    pub fn find_branches<'a>(&'a self, query: &'a str, exact: bool) -> impl Iterator<Item = (&'a Bank, &'a Branch)> + 'a {
+        search::find_branches(&self.banks, query, exact)
+    }
+
+    pub fn to_hashmap(&self) -> HashMap<BankCode, Bank> {
+        crate::to_hashmap(&self.banks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn save_load_test() {
+        use std::fs;
+        use crate::dataset::{Dataset, SCHEMA_VERSION};
+        use crate::{to_hashmap, Bank, Branch};
+
+        let dir = std::env::temp_dir().join(format!("zngn-dataset-{}", std::process::id()));
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.branches.push(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let dataset = Dataset::new(vec![neko.clone(), inu.clone()]).generated("https://zengin.example.com");
+        assert_eq!(dataset.bank("0111"), Some(&inu));
+        assert_eq!(dataset.bank("111"), Some(&inu));
+        assert_eq!(dataset.branch("222", "1").map(|branch| &*branch.name), Some("本店"));
+        assert_eq!(dataset.branch("0222", "001").map(|branch| &*branch.name), Some("本店"));
+        assert_eq!(dataset.search("ﾎﾝﾃﾝ").count(), 1);
+
+        let path = dir.join("dataset.json");
+        dataset.save(&path).unwrap();
+        assert_eq!(Dataset::load(&path).unwrap(), dataset);
+
+        // A bare banks.json loads without metadata.
+        fs::write(&path, serde_json::to_string(&to_hashmap(&[neko, inu])).unwrap()).unwrap();
+        let bare = Dataset::load(&path).unwrap();
+        assert_eq!((bare.len(), bare.source), (2, None));
+
+        fs::write(&path, format!(r#"{{"schema_version": {}, "banks": []}}"#, SCHEMA_VERSION + 1)).unwrap();
+        assert!(Dataset::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{Bank, Branch, Error};
+
+// What to do when the same code shows up twice with different names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    KeepFirst,
+    KeepLatest,
+    Fail,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-first" => Ok(Policy::KeepFirst),
+            "keep-latest" => Ok(Policy::KeepLatest),
+            "fail" => Ok(Policy::Fail),
+            _ => Err(format!("unknown duplicate policy: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Variant {
+    pub name: String,
+    pub phonetic: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub bank_code: String,
+    // Set for branch conflicts, absent for bank conflicts.
+    pub branch_code: Option<String>,
+    pub kept: Variant,
+    pub dropped: Variant,
+}
+
+// Collapses items sharing a key. Exact repeats are dropped quietly; differing ones are returned
+// as (kept, dropped) pairs. The surviving item stays where the first occurrence was.
+fn dedup<T: Clone>(
+    items: Vec<T>,
+    key: impl Fn(&T) -> &str,
+    same: impl Fn(&T, &T) -> bool,
+    policy: Policy,
+) -> (Vec<T>, Vec<(T, T)>) {
+    let mut kept: Vec<T> = Vec::with_capacity(items.len());
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for item in items {
+        match positions.get(key(&item)) {
+            None => {
+                positions.insert(key(&item).to_owned(), kept.len());
+                kept.push(item);
+            }
+            Some(&i) if same(&kept[i], &item) => {}
+            Some(&i) => {
+                if policy == Policy::KeepLatest {
+                    let previous = std::mem::replace(&mut kept[i], item);
+                    conflicts.push((kept[i].clone(), previous));
+                } else {
+                    conflicts.push((kept[i].clone(), item));
+                }
+            }
+        }
+    }
+    (kept, conflicts)
+}
+
+fn fail_on(conflicts: &[Conflict], policy: Policy) -> Result<(), Error> {
+    if policy == Policy::Fail && !conflicts.is_empty() {
+        return Err(Error::DuplicateCodes(conflicts.to_vec()));
+    }
+    Ok(())
+}
+
+pub fn dedup_banks(banks: Vec<Bank>, policy: Policy) -> Result<(Vec<Bank>, Vec<Conflict>), Error> {
+    let same = |a: &Bank, b: &Bank| a.name == b.name && a.phonetic == b.phonetic;
+    let (banks, pairs) = dedup(banks, |bank| &bank.code.0, same, policy);
+    let conflicts = pairs
+        .into_iter()
+        .map(|(kept, dropped)| Conflict {
+            bank_code: kept.code.0,
+            branch_code: None,
+            kept: Variant { name: kept.name.to_string(), phonetic: kept.phonetic.to_string() },
+            dropped: Variant { name: dropped.name.to_string(), phonetic: dropped.phonetic.to_string() },
+        })
+        .collect::<Vec<Conflict>>();
+    fail_on(&conflicts, policy)?;
+    Ok((banks, conflicts))
+}
+
+pub fn dedup_branches(bank: &mut Bank, policy: Policy) -> Result<Vec<Conflict>, Error> {
+    let same = |a: &Branch, b: &Branch| a.name == b.name && a.phonetic == b.phonetic;
+    let branches = std::mem::take(&mut bank.branches);
+    let (branches, pairs) = dedup(branches, |branch| &branch.code, same, policy);
+    bank.branches = branches;
+    let conflicts = pairs
+        .into_iter()
+        .map(|(kept, dropped)| Conflict {
+            bank_code: bank.code.0.clone(),
+            branch_code: Some(kept.code),
+            kept: Variant { name: kept.name.to_string(), phonetic: kept.phonetic.to_string() },
+            dropped: Variant { name: dropped.name.to_string(), phonetic: dropped.phonetic.to_string() },
+        })
+        .collect::<Vec<Conflict>>();
+    fail_on(&conflicts, policy)?;
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn dedup_branches_test() {
+        use crate::dedup::{dedup_branches, Policy};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "123".to_owned()));
+        bank.append_branch(Branch::new("ねこ町支店".to_owned(), "ﾈｺﾏﾁ".to_owned(), "456".to_owned()));
+        bank.append_branch(Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "123".to_owned()));
+        bank.append_branch(Branch::new("三毛支店".to_owned(), "ﾐｹ".to_owned(), "123".to_owned()));
+
+        let mut first = bank.clone();
+        let conflicts = dedup_branches(&mut first, Policy::KeepFirst).unwrap();
+        assert_eq!(first.branches.len(), 2);
+        assert_eq!(&*first.branches[0].name, "みけ支店");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].dropped.name, "三毛支店");
+
+        let mut latest = bank.clone();
+        dedup_branches(&mut latest, Policy::KeepLatest).unwrap();
+        assert_eq!(&*latest.branches[0].name, "三毛支店");
+
+        assert!(dedup_branches(&mut bank, Policy::Fail).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Bank, Branch, Error};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    // A bank that wasn't there, with all its branches.
+    AddBank {
+        bank: Bank,
+    },
+    RemoveBank {
+        code: String,
+    },
+    // New name, reading and search parameter of a bank.
+    UpdateBank {
+        code: String,
+        name: String,
+        phonetic: String,
+        search_param: String,
+    },
+    // A branch added or changed.
+    PutBranch {
+        bank_code: String,
+        branch: Branch,
+    },
+    RemoveBranch {
+        bank_code: String,
+        code: String,
+    },
+}
+
+// What turns one snapshot into another. `from` and `to` are the fingerprints of both, so a patch is
+// never applied to a dataset it wasn't made for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delta {
+    pub from: String,
+    pub to: String,
+    pub ops: Vec<Op>,
+}
+
+// SHA-256 over the banks and branches in code order. Aliases are left out since they come from the
+// alias tables rather than the snapshot.
+pub fn fingerprint(banks: &[Bank]) -> String {
+    let mut sorted = banks.iter().collect::<Vec<&Bank>>();
+    sorted.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    let mut hasher = Sha256::new();
+    for bank in sorted {
+        let mut branches = bank.branches.iter().collect::<Vec<&Branch>>();
+        branches.sort_by(|a, b| a.code.cmp(&b.code));
+        let record = (&bank.code.0, &bank.name, &bank.phonetic, &bank.search_param, branches);
+        // Hashing can't fail, and these plain strings always serialize.
+        let _ = serde_json::to_writer(&mut hasher, &record);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn by_code(banks: &[Bank]) -> BTreeMap<&str, &Bank> {
+    banks.iter().map(|bank| (bank.code.0.as_str(), bank)).collect()
+}
+
+pub fn delta(old: &[Bank], new: &[Bank]) -> Delta {
+    let (old_banks, new_banks) = (by_code(old), by_code(new));
+    let mut ops = Vec::new();
+    for code in old_banks.keys().filter(|code| !new_banks.contains_key(*code)) {
+        ops.push(Op::RemoveBank {
+            code: (*code).to_owned(),
+        });
+    }
+    for (code, bank) in &new_banks {
+        let previous = match old_banks.get(code) {
+            Some(previous) => previous,
+            None => {
+                let mut bank = (*bank).clone();
+                bank.aliases.clear();
+                ops.push(Op::AddBank { bank });
+                continue;
+            }
+        };
+        if (&previous.name, &previous.phonetic, &previous.search_param) != (&bank.name, &bank.phonetic, &bank.search_param) {
+            ops.push(Op::UpdateBank {
+                code: (*code).to_owned(),
+                name: bank.name.clone(),
+                phonetic: bank.phonetic.clone(),
+                search_param: bank.search_param.clone(),
+            });
+        }
+        let old_branches = previous
+            .branches
+            .iter()
+            .map(|branch| (branch.code.as_str(), branch))
+            .collect::<HashMap<&str, &Branch>>();
+        for branch in &bank.branches {
+            if old_branches.get(branch.code.as_str()) != Some(&branch) {
+                ops.push(Op::PutBranch {
+                    bank_code: (*code).to_owned(),
+                    branch: branch.clone(),
+                });
+            }
+        }
+        for branch in &previous.branches {
+            if !bank.branches.iter().any(|b| b.code == branch.code) {
+                ops.push(Op::RemoveBranch {
+                    bank_code: (*code).to_owned(),
+                    code: branch.code.clone(),
+                });
+            }
+        }
+    }
+    Delta {
+        from: fingerprint(old),
+        to: fingerprint(new),
+        ops,
+    }
+}
+
+fn bank_mut<'a>(banks: &'a mut [Bank], code: &str) -> Result<&'a mut Bank, Error> {
+    banks
+        .iter_mut()
+        .find(|bank| bank.code.0 == code)
+        .ok_or_else(|| Error::DeltaFailed(format!("no bank {}", code)))
+}
+
+// Applies the patch in place and returns the codes of the banks it touched.
+pub fn apply(banks: &mut Vec<Bank>, delta: &Delta) -> Result<Vec<String>, Error> {
+    let found = fingerprint(banks);
+    if found != delta.from {
+        return Err(Error::DeltaFailed(format!("made for snapshot {}, not {}", delta.from, found)));
+    }
+    let mut touched = Vec::new();
+    for op in &delta.ops {
+        match op {
+            Op::AddBank { bank } => {
+                touched.push(bank.code.0.clone());
+                banks.push(bank.clone());
+            }
+            Op::RemoveBank { code } => {
+                touched.push(code.clone());
+                banks.retain(|bank| &bank.code.0 != code);
+            }
+            Op::UpdateBank { code, name, phonetic, search_param } => {
+                let bank = bank_mut(banks, code)?;
+                bank.name = name.clone();
+                bank.phonetic = phonetic.clone();
+                bank.search_param = search_param.clone();
+                touched.push(code.clone());
+            }
+            Op::PutBranch { bank_code, branch } => {
+                let bank = bank_mut(banks, bank_code)?;
+                match bank.branches.iter_mut().find(|b| b.code == branch.code) {
+                    Some(existing) => *existing = branch.clone(),
+                    None => bank.branches.push(branch.clone()),
+                }
+                touched.push(bank_code.clone());
+            }
+            Op::RemoveBranch { bank_code, code } => {
+                bank_mut(banks, bank_code)?.branches.retain(|b| &b.code != code);
+                touched.push(bank_code.clone());
+            }
+        }
+    }
+    banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    let found = fingerprint(banks);
+    if found != delta.to {
+        return Err(Error::DeltaFailed(format!("produced snapshot {}, expected {}", found, delta.to)));
+    }
+    touched.sort();
+    touched.dedup();
+    Ok(touched)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn apply_test() {
+        use crate::delta::{apply, delta, fingerprint, Op};
+        use crate::{Bank, Branch, Error};
+
+        let branch = |name: &str, code: &str| Branch::new(name.to_owned(), "ｶﾅ".to_owned(), code.to_owned());
+        let mut a = Bank::new("あ銀行".to_owned(), "ｱ".to_owned(), "0100".to_owned(), "x".to_owned());
+        a.append_branch(branch("本店", "001"));
+        a.append_branch(branch("駅前支店", "002"));
+        let b = Bank::new("い銀行".to_owned(), "ｲ".to_owned(), "0200".to_owned(), "x".to_owned());
+        let old = vec![a.clone(), b];
+
+        let mut renamed = a;
+        renamed.name = "あ信用金庫".to_owned();
+        renamed.branches.remove(1);
+        renamed.append_branch(branch("港支店", "003"));
+        let c = Bank::new("う銀行".to_owned(), "ｳ".to_owned(), "0300".to_owned(), "x".to_owned());
+        let new = vec![c, renamed];
+
+        let patch = delta(&old, &new);
+        assert_eq!(patch.ops.len(), 5);
+        assert!(matches!(&patch.ops[0], Op::RemoveBank { code } if code == "0200"));
+        let mut patched = old.clone();
+        assert_eq!(apply(&mut patched, &patch).unwrap(), vec!["0100", "0200", "0300"]);
+        assert_eq!(fingerprint(&patched), fingerprint(&new));
+        assert!(matches!(apply(&mut patched, &patch), Err(Error::DeltaFailed(_))));
+        assert_eq!(delta(&new, &new).ops, Vec::new());
+    }
+}
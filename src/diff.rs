@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{Bank, Branch};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    BankAdded {
+        code: String,
+        name: String,
+    },
+    BankRemoved {
+        code: String,
+        name: String,
+    },
+    BankRenamed {
+        code: String,
+        from: String,
+        to: String,
+    },
+    // A removed bank whose branches mostly reappeared under another bank.
+    BankMerged {
+        code: String,
+        name: String,
+        into_code: String,
+        into_name: String,
+        branches: usize,
+    },
+    BranchAdded {
+        bank_code: String,
+        code: String,
+        name: String,
+    },
+    BranchRemoved {
+        bank_code: String,
+        code: String,
+        name: String,
+    },
+    BranchRenamed {
+        bank_code: String,
+        code: String,
+        from: String,
+        to: String,
+    },
+    // Same branch name and reading found under another bank or code.
+    BranchMoved {
+        name: String,
+        from_bank_code: String,
+        from_code: String,
+        to_bank_code: String,
+        to_code: String,
+    },
+}
+
+#[derive(Debug)]
+struct Located<'a> {
+    bank: &'a Bank,
+    branch: &'a Branch,
+}
+
+fn by_code(banks: &[Bank]) -> HashMap<&str, &Bank> {
+    banks.iter().map(|bank| (bank.code.0.as_str(), bank)).collect()
+}
+
+fn same_branch(a: &Branch, b: &Branch) -> bool {
+    a.name == b.name && a.phonetic == b.phonetic
+}
+
+// Compares two snapshots, reporting renames, moves and mergers instead of the remove/add
+// pairs they would otherwise show up as.
+pub fn diff(old: &[Bank], new: &[Bank]) -> Vec<Change> {
+    let old_banks = by_code(old);
+    let new_banks = by_code(new);
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    for bank in old {
+        match new_banks.get(bank.code.0.as_str()) {
+            None => removed.extend(bank.branches.iter().map(|branch| Located { bank, branch })),
+            Some(current) => {
+                if current.name != bank.name {
+                    changes.push(Change::BankRenamed {
+                        code: bank.code.0.clone(),
+                        from: bank.name.clone(),
+                        to: current.name.clone(),
+                    });
+                }
+                for branch in &bank.branches {
+                    match current.branches.iter().find(|b| b.code == branch.code) {
+                        None => removed.push(Located { bank, branch }),
+                        Some(b) if b.name != branch.name => changes.push(Change::BranchRenamed {
+                            bank_code: bank.code.0.clone(),
+                            code: branch.code.clone(),
+                            from: branch.name.to_string(),
+                            to: b.name.to_string(),
+                        }),
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+    }
+    for bank in new {
+        let previous = old_banks.get(bank.code.0.as_str());
+        for branch in &bank.branches {
+            if !previous.is_some_and(|p| p.branches.iter().any(|b| b.code == branch.code)) {
+                added.push(Located { bank, branch });
+            }
+        }
+    }
+
+    let mut moves = Vec::new();
+    removed.retain(|gone| match added.iter().position(|new| same_branch(gone.branch, new.branch)) {
+        Some(i) => {
+            moves.push((gone.bank, gone.branch, added.remove(i)));
+            false
+        }
+        None => true,
+    });
+
+    // Tally where the branches of each vanished bank went, to tell a merger from a closure.
+    let mut merged_into: HashMap<&str, &Bank> = HashMap::new();
+    for bank in old.iter().filter(|bank| !new_banks.contains_key(bank.code.0.as_str())) {
+        let mut destinations: HashMap<&str, (usize, &Bank)> = HashMap::new();
+        for (from, _, to) in &moves {
+            if from.code == bank.code {
+                destinations.entry(to.bank.code.0.as_str()).or_insert((0, to.bank)).0 += 1;
+            }
+        }
+        match destinations.values().max_by_key(|(count, _)| *count) {
+            Some(&(count, into)) if count * 2 >= bank.branches.len() => {
+                merged_into.insert(bank.code.0.as_str(), into);
+                changes.push(Change::BankMerged {
+                    code: bank.code.0.clone(),
+                    name: bank.name.clone(),
+                    into_code: into.code.0.clone(),
+                    into_name: into.name.clone(),
+                    branches: count,
+                });
+            }
+            _ => changes.push(Change::BankRemoved {
+                code: bank.code.0.clone(),
+                name: bank.name.clone(),
+            }),
+        }
+    }
+    for bank in new.iter().filter(|bank| !old_banks.contains_key(bank.code.0.as_str())) {
+        changes.push(Change::BankAdded {
+            code: bank.code.0.clone(),
+            name: bank.name.clone(),
+        });
+    }
+
+    for (from, branch, to) in moves {
+        // Branches that followed their bank into a merger are covered by `BankMerged`.
+        if merged_into.get(from.code.0.as_str()).is_some_and(|into| into.code == to.bank.code) {
+            continue;
+        }
+        changes.push(Change::BranchMoved {
+            name: branch.name.to_string(),
+            from_bank_code: from.code.0.clone(),
+            from_code: branch.code.clone(),
+            to_bank_code: to.bank.code.0.clone(),
+            to_code: to.branch.code.clone(),
+        });
+    }
+    for gone in removed {
+        changes.push(Change::BranchRemoved {
+            bank_code: gone.bank.code.0.clone(),
+            code: gone.branch.code.clone(),
+            name: gone.branch.name.to_string(),
+        });
+    }
+    for new in added {
+        changes.push(Change::BranchAdded {
+            bank_code: new.bank.code.0.clone(),
+            code: new.branch.code.clone(),
+            name: new.branch.name.to_string(),
+        });
+    }
+    changes
+}
+
+// `diff` narrowed to one bank, given as it was in each snapshot, or None where it was missing. Branches
+// that changed code within the bank show up as `BranchMoved` from and to the same bank.
+pub fn diff_bank(old: Option<&Bank>, new: Option<&Bank>) -> Vec<Change> {
+    diff(old.map_or(&[], std::slice::from_ref), new.map_or(&[], std::slice::from_ref))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn merger_and_rename_test() {
+        use crate::diff::{diff, Change};
+        use crate::{Bank, Branch};
+
+        let branch = |name: &str, code: &str| Branch::new(name.to_owned(), "ｶﾅ".to_owned(), code.to_owned());
+        let mut a = Bank::new("あ銀行".to_owned(), "ｱ".to_owned(), "0100".to_owned(), "x".to_owned());
+        a.append_branch(branch("本店", "001"));
+        a.append_branch(branch("駅前支店", "002"));
+        let mut b = Bank::new("い銀行".to_owned(), "ｲ".to_owned(), "0200".to_owned(), "x".to_owned());
+        b.append_branch(branch("本店", "001"));
+        b.append_branch(branch("港支店", "005"));
+        let old = vec![a, b.clone()];
+
+        let mut merged = b;
+        merged.name = "あいホールディングス銀行".to_owned();
+        merged.branches[1].name = "みなと支店".into();
+        merged.append_branch(branch("駅前支店", "102"));
+        let new = vec![merged];
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::BankRenamed {
+                    code: "0200".to_owned(),
+                    from: "い銀行".to_owned(),
+                    to: "あいホールディングス銀行".to_owned(),
+                },
+                Change::BranchRenamed {
+                    bank_code: "0200".to_owned(),
+                    code: "005".to_owned(),
+                    from: "港支店".to_owned(),
+                    to: "みなと支店".to_owned(),
+                },
+                Change::BankMerged {
+                    code: "0100".to_owned(),
+                    name: "あ銀行".to_owned(),
+                    into_code: "0200".to_owned(),
+                    into_name: "あいホールディングス銀行".to_owned(),
+                    branches: 1,
+                },
+                Change::BranchRemoved {
+                    bank_code: "0100".to_owned(),
+                    code: "001".to_owned(),
+                    name: "本店".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_bank_test() {
+        use crate::diff::{diff_bank, Change};
+        use crate::{Bank, Branch};
+
+        let mut old = Bank::new("あ銀行".to_owned(), "ｱ".to_owned(), "0100".to_owned(), "x".to_owned());
+        old.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        old.append_branch(Branch::new("駅前支店".to_owned(), "ｴｷﾏｴ".to_owned(), "002".to_owned()));
+        old.append_branch(Branch::new("港支店".to_owned(), "ﾐﾅﾄ".to_owned(), "003".to_owned()));
+        let mut new = Bank::new("あ銀行".to_owned(), "ｱ".to_owned(), "0100".to_owned(), "x".to_owned());
+        new.append_branch(Branch::new("本店営業部".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        new.append_branch(Branch::new("駅前支店".to_owned(), "ｴｷﾏｴ".to_owned(), "102".to_owned()));
+        new.append_branch(Branch::new("山支店".to_owned(), "ﾔﾏ".to_owned(), "004".to_owned()));
+
+        assert_eq!(
+            diff_bank(Some(&old), Some(&new)),
+            vec![
+                Change::BranchRenamed {
+                    bank_code: "0100".to_owned(),
+                    code: "001".to_owned(),
+                    from: "本店".to_owned(),
+                    to: "本店営業部".to_owned(),
+                },
+                Change::BranchMoved {
+                    name: "駅前支店".to_owned(),
+                    from_bank_code: "0100".to_owned(),
+                    from_code: "002".to_owned(),
+                    to_bank_code: "0100".to_owned(),
+                    to_code: "102".to_owned(),
+                },
+                Change::BranchRemoved {
+                    bank_code: "0100".to_owned(),
+                    code: "003".to_owned(),
+                    name: "港支店".to_owned(),
+                },
+                Change::BranchAdded {
+                    bank_code: "0100".to_owned(),
+                    code: "004".to_owned(),
+                    name: "山支店".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(diff_bank(None, Some(&new)).len(), 4);
+        assert!(diff_bank(None, None).is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use crate::layout::Layout;
+use crate::{Bank, BankCode, Error};
+
+// English names of major banks, keyed by bank code.
+const BUNDLED: &str = include_str!("../data/english_names.json");
+
+#[derive(Debug, Clone, Default)]
+pub struct EnglishNames(HashMap<BankCode, String>);
+
+impl EnglishNames {
+    pub fn bundled() -> Self {
+        Self(serde_json::from_str(BUNDLED).unwrap_or_default())
+    }
+
+    // The bundled names, overridden and extended by `english_names.json` in the output directory.
+    pub fn load(layout: &Layout) -> Result<Self, Error> {
+        let mut names = Self::bundled();
+        let path = layout.english_names_file();
+        if path.exists() {
+            let file = File::open(&path).map_err(Error::file(&path))?;
+            let user: HashMap<BankCode, String> = serde_json::from_reader(file).map_err(Error::invalid(&path))?;
+            names.0.extend(user);
+        }
+        Ok(names)
+    }
+
+    pub fn apply(&self, banks: &mut [Bank]) {
+        for bank in banks {
+            if let Some(name) = self.0.get(&bank.code) {
+                bank.name_en = Some(name.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn english_names_test() {
+        use std::fs;
+        use crate::english::EnglishNames;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::Bank;
+
+        let dir = std::env::temp_dir().join(format!("zngn-english-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        fs::write(layout.english_names_file(), r#"{"0005": "MUFG", "9999": "Test Bank"}"#).unwrap();
+
+        let mut banks = vec![
+            Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned()),
+            Bank::new("三菱ＵＦＪ銀行".to_owned(), "ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ".to_owned(), "0005".to_owned(), "x5".to_owned()),
+            Bank::new("テスト銀行".to_owned(), "ﾃｽﾄ".to_owned(), "9999".to_owned(), "x9".to_owned()),
+        ];
+        EnglishNames::load(&layout).unwrap().apply(&mut banks);
+        let names = banks.iter().map(|bank| bank.name_en.as_deref()).collect::<Vec<Option<&str>>>();
+        assert_eq!(names, vec![Some("Mizuho Bank"), Some("MUFG"), Some("Test Bank")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
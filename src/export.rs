@@ -0,0 +1,440 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::kana::{self, PhoneticForm};
+use crate::naming::{self, Naming};
+use crate::zengin::{Abbreviations, ZenginExporter};
+use crate::{prepare_parent_dir, sqlite, xlsx, Bank, Error};
+
+// The built-in formats, each an `Exporter` in the default `Registry`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+    Sqlite,
+    Xlsx,
+    Zengin,
+}
+
+impl Format {
+    pub fn exporter(self) -> &'static dyn Exporter {
+        match self {
+            Format::Json => &JsonExporter,
+            Format::Csv => &CsvExporter,
+            Format::Sqlite => &SqliteExporter,
+            Format::Xlsx => &XlsxExporter,
+            Format::Zengin => &ZenginExporter,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "sqlite" => Ok(Format::Sqlite),
+            "xlsx" => Ok(Format::Xlsx),
+            "zengin" => Ok(Format::Zengin),
+            _ => Err(format!("unknown export format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // Add FTS5 full-text tables to sqlite exports.
+    pub fts: bool,
+    // Key style of json exports.
+    pub naming: Naming,
+    // Kana phonetic fields are written in.
+    pub phonetic_form: PhoneticForm,
+    // Abbreviations of kana names cut to fixed-width fields.
+    pub abbreviations: Abbreviations,
+}
+
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    bank_code: &'a str,
+    bank_name: &'a str,
+    bank_name_en: &'a str,
+    bank_phonetic: &'a str,
+    bank_aliases: &'a str,
+    branch_code: &'a str,
+    branch_name: &'a str,
+    branch_phonetic: &'a str,
+}
+
+// One row per branch; banks without branches still get a row with the branch columns left empty.
+fn write_csv(banks: &[Bank], writer: &mut dyn Write) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for bank in banks {
+        // Former names share one cell, separated by "|".
+        let aliases = bank
+            .aliases
+            .iter()
+            .map(|alias| alias.name.as_str())
+            .collect::<Vec<&str>>()
+            .join("|");
+        let row = |code, name, phonetic| CsvRow {
+            bank_code: &bank.code.0,
+            bank_name: &bank.name,
+            bank_name_en: bank.name_en.as_deref().unwrap_or_default(),
+            bank_phonetic: &bank.phonetic,
+            bank_aliases: &aliases,
+            branch_code: code,
+            branch_name: name,
+            branch_phonetic: phonetic,
+        };
+        if bank.branches.is_empty() {
+            writer.serialize(row("", "", ""))?;
+        }
+        for branch in &bank.branches {
+            writer.serialize(row(&branch.code, &branch.name, &branch.phonetic))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// The banks as exported: with phonetics converted unless they are kept half-width, as stored.
+fn prepared<'a>(banks: &'a [Bank], options: &Options) -> Cow<'a, [Bank]> {
+    if options.phonetic_form == PhoneticForm::HalfWidth {
+        return Cow::Borrowed(banks);
+    }
+    let mut banks = banks.to_vec();
+    kana::convert_banks(&mut banks, options.phonetic_form);
+    Cow::Owned(banks)
+}
+
+fn to_file_only(name: &str) -> Error {
+    Error::ExportFailed(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} exports can only be written to a file", name),
+    ))
+}
+
+// A file format data can be written in. The dataset goes through `write_banks`; results of other
+// commands, such as search hits, a diff or stats, through `write_records`, one record each. Formats
+// that can't be streamed, such as sqlite, write to a file only and override the `_to_file` methods.
+pub trait Exporter: Send + Sync {
+    // The name it is chosen by, as in `--format`.
+    fn name(&self) -> &'static str;
+
+    // Writes the banks, their phonetics already converted as the options ask.
+    fn write_banks(&self, banks: &[Bank], options: &Options, writer: &mut dyn Write) -> Result<(), Error>;
+
+    fn write_records(&self, records: &[Value], writer: &mut dyn Write) -> Result<(), Error>;
+
+    fn banks_to_file(&self, banks: &[Bank], options: &Options, path: &Path) -> Result<(), Error> {
+        prepare_parent_dir(path);
+        let file = File::create(path).map_err(Error::ExportFailed)?;
+        self.write_banks(banks, options, &mut BufWriter::new(file))
+    }
+
+    fn records_to_file(&self, records: &[Value], path: &Path) -> Result<(), Error> {
+        prepare_parent_dir(path);
+        let file = File::create(path).map_err(Error::ExportFailed)?;
+        self.write_records(records, &mut BufWriter::new(file))
+    }
+}
+
+// Formats by name. The default registry has the built-in ones; applications embedding the crate
+// register their own, which replace a built-in one of the same name.
+pub struct Registry {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Registry {
+    pub fn empty() -> Self {
+        Self { exporters: Vec::new() }
+    }
+
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters.retain(|registered| registered.name() != exporter.name());
+        self.exporters.push(exporter);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.exporters.iter().find(|exporter| exporter.name() == name).map(|exporter| &**exporter)
+    }
+
+    // The exporter for `name`, or an error listing the registered ones.
+    pub fn find(&self, name: &str) -> Result<&dyn Exporter, String> {
+        self.get(name)
+            .ok_or_else(|| format!("unknown format {}; expected one of {}", name, self.names().join(", ")))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.exporters.iter().map(|exporter| exporter.name()).collect()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(JsonExporter));
+        registry.register(Box::new(CsvExporter));
+        registry.register(Box::new(SqliteExporter));
+        registry.register(Box::new(XlsxExporter));
+        registry.register(Box::new(ZenginExporter));
+        registry
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Registry").field("exporters", &self.names()).finish()
+    }
+}
+
+// Writes a record's nested objects as columns of their own, named by path such as `interned.distinct`.
+// Lists of plain values share one cell separated by "|", as former names do in csv exports; anything
+// else nested is written as JSON.
+fn flatten(prefix: &str, value: &Value, cells: &mut Vec<(String, String)>) {
+    let cell = match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&name, value, cells);
+            }
+            return;
+        }
+        Value::Array(items) if items.iter().all(|item| !item.is_object() && !item.is_array()) => {
+            items.iter().map(scalar).collect::<Vec<String>>().join("|")
+        }
+        Value::Array(_) => value.to_string(),
+        _ => scalar(value),
+    };
+    let name = if prefix.is_empty() { "value".to_owned() } else { prefix.to_owned() };
+    cells.push((name, cell));
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+// Records as rows under one header, the columns in the order they first appear. Records lacking a
+// column, as differently shaped records do, have it empty.
+pub fn tabulate(records: &[Value]) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut headers: Vec<String> = Vec::new();
+    let flattened = records
+        .iter()
+        .map(|record| {
+            let mut cells = Vec::new();
+            flatten("", record, &mut cells);
+            for (name, _) in &cells {
+                if !headers.contains(name) {
+                    headers.push(name.clone());
+                }
+            }
+            cells
+        })
+        .collect::<Vec<Vec<(String, String)>>>();
+    let rows = flattened
+        .into_iter()
+        .map(|cells| {
+            headers
+                .iter()
+                .map(|header| {
+                    cells.iter().find(|(name, _)| name == header).map(|(_, cell)| cell.clone()).unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect();
+    (headers, rows)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn write_banks(&self, banks: &[Bank], options: &Options, writer: &mut dyn Write) -> Result<(), Error> {
+        if options.naming == Naming::Snake {
+            serde_json::to_writer_pretty(&mut *writer, banks).map_err(|e| Error::ExportFailed(e.into()))?;
+        } else {
+            let document = serde_json::to_value(banks).map_err(|e| Error::ExportFailed(e.into()))?;
+            let document = naming::rename_keys(document, options.naming);
+            serde_json::to_writer_pretty(&mut *writer, &document).map_err(|e| Error::ExportFailed(e.into()))?;
+        }
+        writer.flush().map_err(Error::ExportFailed)
+    }
+
+    fn write_records(&self, records: &[Value], writer: &mut dyn Write) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut *writer, records).map_err(|e| Error::ExportFailed(e.into()))?;
+        writer.flush().map_err(Error::ExportFailed)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write_banks(&self, banks: &[Bank], _: &Options, writer: &mut dyn Write) -> Result<(), Error> {
+        write_csv(banks, writer).map_err(|e| Error::ExportFailed(e.into()))
+    }
+
+    fn write_records(&self, records: &[Value], writer: &mut dyn Write) -> Result<(), Error> {
+        let (headers, rows) = tabulate(records);
+        let mut writer = csv::Writer::from_writer(writer);
+        let write = |writer: &mut csv::Writer<&mut dyn Write>| -> Result<(), csv::Error> {
+            writer.write_record(&headers)?;
+            for row in &rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+            Ok(())
+        };
+        write(&mut writer).map_err(|e| Error::ExportFailed(e.into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct XlsxExporter;
+
+impl Exporter for XlsxExporter {
+    fn name(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn write_banks(&self, banks: &[Bank], _: &Options, writer: &mut dyn Write) -> Result<(), Error> {
+        xlsx::write(banks, writer)
+    }
+
+    fn write_records(&self, records: &[Value], writer: &mut dyn Write) -> Result<(), Error> {
+        let (headers, rows) = tabulate(records);
+        xlsx::write_rows(&headers, &rows, writer)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SqliteExporter;
+
+impl Exporter for SqliteExporter {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn write_banks(&self, _: &[Bank], _: &Options, _: &mut dyn Write) -> Result<(), Error> {
+        Err(to_file_only(self.name()))
+    }
+
+    fn write_records(&self, _: &[Value], _: &mut dyn Write) -> Result<(), Error> {
+        Err(to_file_only(self.name()))
+    }
+
+    fn banks_to_file(&self, banks: &[Bank], options: &Options, path: &Path) -> Result<(), Error> {
+        sqlite::export(banks, path, options.fts)
+    }
+
+    fn records_to_file(&self, records: &[Value], path: &Path) -> Result<(), Error> {
+        let (headers, rows) = tabulate(records);
+        sqlite::export_rows(&headers, &rows, path)
+    }
+}
+
+pub fn write<W: Write>(banks: &[Bank], format: Format, options: &Options, writer: W) -> Result<(), Error> {
+    write_with(format.exporter(), banks, options, writer)
+}
+
+pub fn export_to_file(banks: &[Bank], format: Format, options: &Options, path: &Path) -> Result<(), Error> {
+    export_to_file_with(format.exporter(), banks, options, path)
+}
+
+pub fn write_with<W: Write>(exporter: &dyn Exporter, banks: &[Bank], options: &Options, mut writer: W) -> Result<(), Error> {
+    exporter.write_banks(&prepared(banks, options), options, &mut writer)
+}
+
+pub fn export_to_file_with(exporter: &dyn Exporter, banks: &[Bank], options: &Options, path: &Path) -> Result<(), Error> {
+    exporter.banks_to_file(&prepared(banks, options), options, path)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn csv_export_test() {
+        use crate::export::{write, Format, Options};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x001".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        bank.name_en = Some("Mizuho Bank".to_owned());
+        let empty = Bank::new("空銀行".to_owned(), "ｶﾗ".to_owned(), "9999".to_owned(), "0x999".to_owned());
+
+        let mut out = Vec::new();
+        write(&[bank, empty], Format::Csv, &Options::default(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "bank_code,bank_name,bank_name_en,bank_phonetic,bank_aliases,branch_code,branch_name,branch_phonetic\n\
+             0001,みずほ銀行,Mizuho Bank,ﾐｽﾞﾎ,,001,東京営業部,ﾄｳｷﾖｳ\n\
+             9999,空銀行,,ｶﾗ,,,,\n"
+        );
+    }
+
+    #[test]
+    fn registry_test() {
+        use std::io::Write;
+        use serde_json::{json, Value};
+        use crate::export::{Exporter, Format, Options, Registry};
+        use crate::{Bank, Error};
+
+        struct Lines;
+
+        impl Exporter for Lines {
+            fn name(&self) -> &'static str {
+                "csv"
+            }
+
+            fn write_banks(&self, banks: &[Bank], _: &Options, writer: &mut dyn Write) -> Result<(), Error> {
+                banks.iter().try_for_each(|bank| writeln!(writer, "{}", bank.code.0).map_err(Error::ExportFailed))
+            }
+
+            fn write_records(&self, records: &[Value], writer: &mut dyn Write) -> Result<(), Error> {
+                writeln!(writer, "{}", records.len()).map_err(Error::ExportFailed)
+            }
+        }
+
+        let records = [
+            json!({"change": "bank_added", "code": "0001", "interned": {"distinct": 3}, "aliases": ["a", "b"]}),
+            json!({"change": "bank_removed", "code": "0002", "extra": null}),
+        ];
+        let mut registry = Registry::default();
+        let mut out = Vec::new();
+        registry.find("csv").unwrap().write_records(&records, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "aliases,change,code,interned.distinct,extra\n\
+             a|b,bank_added,0001,3,\n\
+             ,bank_removed,0002,,\n"
+        );
+        assert!(Format::Sqlite.exporter().write_records(&records, &mut Vec::new()).is_err());
+        assert!(registry.find("yaml").err().unwrap().contains("json, csv, sqlite, xlsx"));
+
+        // A registered format replaces the built-in one of its name.
+        registry.register(Box::new(Lines));
+        let mut out = Vec::new();
+        registry.find("csv").unwrap().write_records(&records, &mut out).unwrap();
+        assert_eq!(out, b"2\n");
+        assert_eq!(registry.names(), ["json", "sqlite", "xlsx", "zengin", "csv"]);
+    }
+}
@@ -0,0 +1,311 @@
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use crate::search::code_matches;
+use crate::Bank;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Code,
+    Name,
+    Phonetic,
+    // Any former name of the bank.
+    Alias,
+    // Any branch name or reading.
+    Branch,
+    BranchCount,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "code" => Ok(Field::Code),
+            "name" => Ok(Field::Name),
+            "phonetic" => Ok(Field::Phonetic),
+            "alias" => Ok(Field::Alias),
+            "branch" => Ok(Field::Branch),
+            "branch_count" => Ok(Field::BranchCount),
+            _ => Err(format!("unknown field: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Contains,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn ordering(self) -> bool {
+        matches!(self, Op::Lt | Op::Le | Op::Gt | Op::Ge)
+    }
+
+    fn compare<T: PartialOrd>(self, a: T, b: T) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Contains => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Compare(Field, Op, String),
+}
+
+// Conditions on banks, e.g. `name~みずほ AND branch_count>100`.
+//
+// Comparisons are `field op value` with no spaces inside; quote values containing spaces or parentheses.
+// Fields are code, name, phonetic, alias, branch (any branch name or reading) and branch_count.
+// Operators are = and != for every field, ~ (contains) for text fields, and < <= > >= for code and
+// branch_count. An = on code takes the * and ? wildcards of `lookup`. Comparisons combine with
+// AND, OR, NOT and parentheses; AND binds tighter than OR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Expr);
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut tokens = tokens.into_iter().peekable();
+        let expr = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(Filter(expr)),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, bank: &Bank) -> bool {
+        eval(&self.0, bank)
+    }
+}
+
+fn word(chars: &mut Peekable<Chars>, stop: impl Fn(char) -> bool) -> String {
+    let mut word = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '(' || c == ')' || stop(c) {
+            break;
+        }
+        word.push(c);
+        chars.next();
+    }
+    word
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let name = word(&mut chars, |c| "=!~<>".contains(c));
+                match name.to_ascii_uppercase().as_str() {
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => tokens.push(comparison(&name, &mut chars)?),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn comparison(name: &str, chars: &mut Peekable<Chars>) -> Result<Token, String> {
+    let field = name.parse::<Field>()?;
+    let mut op = String::new();
+    while let Some(&c) = chars.peek() {
+        if !"=!~<>".contains(c) {
+            break;
+        }
+        op.push(c);
+        chars.next();
+    }
+    let op = match op.as_str() {
+        "=" => Op::Eq,
+        "!=" => Op::Ne,
+        "~" => Op::Contains,
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ">" => Op::Gt,
+        ">=" => Op::Ge,
+        "" => return Err(format!("missing operator after {}", name)),
+        _ => return Err(format!("unknown operator: {}", op)),
+    };
+    let value = if chars.peek() == Some(&'"') {
+        chars.next();
+        chars.by_ref().take_while(|&c| c != '"').collect::<String>()
+    } else {
+        word(chars, |_| false)
+    };
+    if value.is_empty() {
+        return Err(format!("missing value after {}", name));
+    }
+    match field {
+        Field::BranchCount if op == Op::Contains => Err("branch_count can't be used with ~".to_owned()),
+        Field::BranchCount if value.parse::<usize>().is_err() => {
+            Err(format!("branch_count {} is not a number", value))
+        }
+        Field::Name | Field::Phonetic | Field::Alias | Field::Branch if op.ordering() => {
+            Err(format!("{} can only be compared with =, != or ~", name))
+        }
+        _ => Ok(Token::Compare(field, op, value)),
+    }
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut expr = parse_and(tokens)?;
+    while tokens.peek() == Some(&Token::Or) {
+        tokens.next();
+        expr = Expr::Or(Box::new(expr), Box::new(parse_and(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut expr = parse_not(tokens)?;
+    while tokens.peek() == Some(&Token::And) {
+        tokens.next();
+        expr = Expr::And(Box::new(expr), Box::new(parse_not(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Expr, String> {
+    match tokens.next() {
+        Some(Token::Not) => Ok(Expr::Not(Box::new(parse_not(tokens)?))),
+        Some(Token::Open) => {
+            let expr = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err("missing )".to_owned()),
+            }
+        }
+        Some(Token::Compare(field, op, value)) => Ok(Expr::Compare(field, op, value)),
+        Some(token) => Err(format!("unexpected {:?}", token)),
+        None => Err("unexpected end of query".to_owned()),
+    }
+}
+
+fn text_matches(text: &str, op: Op, value: &str) -> bool {
+    match op {
+        Op::Contains => text.contains(value),
+        _ => op.compare(text, value),
+    }
+}
+
+// Whether any of `texts` satisfies the comparison; != holds when none of them equals the value.
+fn any_matches<'a>(mut texts: impl Iterator<Item = &'a str>, op: Op, value: &str) -> bool {
+    match op {
+        Op::Ne => texts.all(|text| text != value),
+        _ => texts.any(|text| text_matches(text, op, value)),
+    }
+}
+
+fn eval(expr: &Expr, bank: &Bank) -> bool {
+    match expr {
+        Expr::And(a, b) => eval(a, bank) && eval(b, bank),
+        Expr::Or(a, b) => eval(a, bank) || eval(b, bank),
+        Expr::Not(a) => !eval(a, bank),
+        Expr::Compare(field, op, value) => match field {
+            Field::Code => match op {
+                Op::Eq => code_matches(&bank.code.0, value),
+                Op::Ne => !code_matches(&bank.code.0, value),
+                _ => text_matches(&bank.code.0, *op, value),
+            },
+            Field::Name => text_matches(&bank.name, *op, value),
+            Field::Phonetic => text_matches(&bank.phonetic, *op, value),
+            Field::Alias => any_matches(
+                bank.aliases
+                    .iter()
+                    .flat_map(|alias| std::iter::once(alias.name.as_str()).chain(alias.phonetic.as_deref())),
+                *op,
+                value,
+            ),
+            Field::Branch => any_matches(
+                bank.branches
+                    .iter()
+                    .flat_map(|branch| vec![&*branch.name, &*branch.phonetic]),
+                *op,
+                value,
+            ),
+            Field::BranchCount => op.compare(bank.branches.len(), value.parse().unwrap_or_default()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn filter_test() {
+        use crate::filter::Filter;
+        use crate::{Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("梅田支店".to_owned(), "ｳﾒﾀﾞ".to_owned(), "101".to_owned()));
+        neko.append_branch(Branch::new("本店営業部".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "102".to_owned()));
+        let inu = Bank::new("いぬ信用金庫".to_owned(), "ｲﾇ".to_owned(), "1111".to_owned(), "0x111".to_owned());
+
+        let matching = |query: &str| {
+            let filter = query.parse::<Filter>().unwrap();
+            [&neko, &inu]
+                .iter()
+                .filter(|bank| filter.matches(bank))
+                .map(|bank| bank.code.0.as_str())
+                .collect::<Vec<&str>>()
+        };
+        assert_eq!(matching("name~銀行 AND branch_count>1"), vec!["0222"]);
+        assert_eq!(matching("code=1* OR branch=梅田支店"), vec!["0222", "1111"]);
+        assert_eq!(matching("NOT (name~銀行 OR branch_count>=1)"), vec!["1111"]);
+        assert_eq!(matching(r#"branch!="本店営業部" and code<2000"#), vec!["1111"]);
+        assert_eq!(matching("phonetic=ｲﾇ"), vec!["1111"]);
+
+        assert!("category=都市銀行".parse::<Filter>().is_err());
+        assert!("branch_count~1".parse::<Filter>().is_err());
+        assert!("name>a".parse::<Filter>().is_err());
+        assert!("name~a AND".parse::<Filter>().is_err());
+        assert!("(name~a".parse::<Filter>().is_err());
+    }
+}
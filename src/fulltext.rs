@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TantivyDocument, TextFieldIndexing, TextOptions, Value,
+    STORED, STRING,
+};
+use tantivy::tokenizer::{LowerCaser, NgramTokenizer, TextAnalyzer};
+use tantivy::{doc, Index, IndexReader, Term};
+
+use crate::{Bank, Error};
+
+// Japanese names have no word boundaries, so names and readings are indexed as 1- and 2-grams.
+const TOKENIZER: &str = "ngram";
+const WRITER_MEMORY: usize = 50_000_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Fields {
+    kind: Field,
+    bank_code: Field,
+    bank_name: Field,
+    branch_code: Field,
+    name: Field,
+    phonetic: Field,
+}
+
+fn schema() -> (Schema, Fields) {
+    let text = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqs),
+        )
+        .set_stored();
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        kind: builder.add_text_field("kind", STRING | STORED),
+        bank_code: builder.add_text_field("bank_code", STRING | STORED),
+        bank_name: builder.add_text_field("bank_name", STORED),
+        branch_code: builder.add_text_field("branch_code", STRING | STORED),
+        name: builder.add_text_field("name", text.clone()),
+        phonetic: builder.add_text_field("phonetic", text),
+    };
+    (builder.build(), fields)
+}
+
+fn register_tokenizer(index: &Index) -> Result<(), Error> {
+    let ngram = NgramTokenizer::new(1, 2, false).map_err(Error::FullTextIndexFailed)?;
+    index
+        .tokenizers()
+        .register(TOKENIZER, TextAnalyzer::builder(ngram).filter(LowerCaser).build());
+    Ok(())
+}
+
+// Rebuilds the index in `dir` from scratch and returns how many documents it holds.
+pub fn build(banks: &[Bank], dir: &Path) -> Result<usize, Error> {
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| Error::FullTextIndexFailed(e.into()))?;
+    }
+    fs::create_dir_all(dir).map_err(|e| Error::FullTextIndexFailed(e.into()))?;
+    let (schema, f) = schema();
+    let index = Index::create_in_dir(dir, schema).map_err(Error::FullTextIndexFailed)?;
+    register_tokenizer(&index)?;
+    let mut writer = index.writer(WRITER_MEMORY).map_err(Error::FullTextIndexFailed)?;
+    let mut count = 0;
+    for bank in banks {
+        let mut document = doc!(
+            f.kind => "bank",
+            f.bank_code => bank.code.0.as_str(),
+            f.bank_name => bank.name.as_str(),
+            f.name => bank.name.as_str(),
+            f.phonetic => bank.phonetic.as_str(),
+        );
+        // Former names are extra values after the current ones, so they match but are never displayed.
+        for alias in &bank.aliases {
+            document.add_text(f.name, &alias.name);
+            if let Some(phonetic) = &alias.phonetic {
+                document.add_text(f.phonetic, phonetic);
+            }
+        }
+        writer.add_document(document).map_err(Error::FullTextIndexFailed)?;
+        count += 1;
+        for branch in &bank.branches {
+            writer
+                .add_document(doc!(
+                    f.kind => "branch",
+                    f.bank_code => bank.code.0.as_str(),
+                    f.bank_name => bank.name.as_str(),
+                    f.branch_code => branch.code.as_str(),
+                    f.name => &*branch.name,
+                    f.phonetic => &*branch.phonetic,
+                ))
+                .map_err(Error::FullTextIndexFailed)?;
+            count += 1;
+        }
+    }
+    writer.commit().map_err(Error::FullTextIndexFailed)?;
+    Ok(count)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedHit {
+    pub score: f32,
+    pub kind: String,
+    pub bank_code: String,
+    pub bank_name: String,
+    pub branch_code: Option<String>,
+    pub name: String,
+    pub phonetic: String,
+}
+
+pub struct FullTextIndex {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+}
+
+impl FullTextIndex {
+    pub fn open(dir: &Path) -> Result<Self, Error> {
+        let index = Index::open_in_dir(dir).map_err(Error::FullTextIndexFailed)?;
+        register_tokenizer(&index)?;
+        let reader = index.reader().map_err(Error::FullTextIndexFailed)?;
+        let (_, fields) = schema();
+        Ok(Self { index, reader, fields })
+    }
+
+    // Every n-gram of the query has to appear in either the name or the reading; hits are ranked by BM25.
+    fn query(&self, text: &str) -> Result<Option<BooleanQuery>, Error> {
+        let mut analyzer = self
+            .index
+            .tokenizer_for_field(self.fields.name)
+            .map_err(Error::FullTextIndexFailed)?;
+        let mut grams = BTreeSet::new();
+        let mut stream = analyzer.token_stream(text);
+        while stream.advance() {
+            grams.insert(stream.token().text.clone());
+        }
+        if grams.is_empty() {
+            return Ok(None);
+        }
+        let clauses = grams
+            .iter()
+            .map(|gram| {
+                let either = [self.fields.name, self.fields.phonetic]
+                    .iter()
+                    .map(|&field| {
+                        let term = Term::from_field_text(field, gram);
+                        let query: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+                        (Occur::Should, query)
+                    })
+                    .collect::<Vec<_>>();
+                let query: Box<dyn Query> = Box::new(BooleanQuery::new(either));
+                (Occur::Must, query)
+            })
+            .collect::<Vec<_>>();
+        Ok(Some(BooleanQuery::new(clauses)))
+    }
+
+    pub fn search(&self, text: &str, limit: usize) -> Result<Vec<IndexedHit>, Error> {
+        let query = match self.query(text)? {
+            Some(query) => query,
+            None => return Ok(Vec::new()),
+        };
+        let searcher = self.reader.searcher();
+        let top = searcher
+            .search(&query, &TopDocs::with_limit(limit))
+            .map_err(Error::FullTextIndexFailed)?;
+        let f = self.fields;
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address).map_err(Error::FullTextIndexFailed)?;
+            let get = |field| doc.get_first(field).and_then(|v| v.as_str()).map(str::to_owned);
+            hits.push(IndexedHit {
+                score,
+                kind: get(f.kind).unwrap_or_default(),
+                bank_code: get(f.bank_code).unwrap_or_default(),
+                bank_name: get(f.bank_name).unwrap_or_default(),
+                branch_code: get(f.branch_code),
+                name: get(f.name).unwrap_or_default(),
+                phonetic: get(f.phonetic).unwrap_or_default(),
+            });
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn indexed_search_test() {
+        use crate::fulltext::{build, FullTextIndex};
+        use crate::{Bank, Branch};
+
+        let dir = std::env::temp_dir().join(format!("zngn-fulltext-{}", std::process::id()));
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x001".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        bank.append_branch(Branch::new("京都中央支店".to_owned(), "ｷﾖｳﾄﾁﾕｳｵｳ".to_owned(), "521".to_owned()));
+        assert_eq!(build(&[bank], &dir).unwrap(), 3);
+
+        let index = FullTextIndex::open(&dir).unwrap();
+        let hits = index.search("東京", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].branch_code.as_deref(), Some("001"));
+        assert_eq!(index.search("ﾐｽﾞﾎ", 10).unwrap()[0].kind, "bank");
+        assert!(index.search("大阪", 10).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
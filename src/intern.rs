@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::Bank;
+
+// What sharing saved on a dataset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Interned {
+    // Branch names and readings in the dataset.
+    pub strings: usize,
+    // Distinct ones among them, each of which is now stored once.
+    pub distinct: usize,
+    // Bytes the repeated copies would take without sharing.
+    pub bytes_saved: usize,
+}
+
+// Points every repeated branch name and reading at a single allocation. Deserializing gives each
+// branch its own copies, which adds up for a server keeping every branch resident.
+pub fn intern(banks: &mut [Bank]) -> Interned {
+    let mut pool: HashSet<Arc<str>> = HashSet::new();
+    let mut interned = Interned::default();
+    for branch in banks.iter_mut().flat_map(|bank| bank.branches.iter_mut()) {
+        for text in [&mut branch.name, &mut branch.phonetic] {
+            interned.strings += 1;
+            match pool.get(&**text) {
+                Some(shared) => {
+                    interned.bytes_saved += text.len();
+                    *text = shared.clone();
+                }
+                None => {
+                    pool.insert(text.clone());
+                }
+            }
+        }
+    }
+    interned.distinct = pool.len();
+    interned
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn intern_test() {
+        use std::sync::Arc;
+        use crate::intern::{intern, Interned};
+        use crate::{Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let mut inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        inu.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let mut banks = vec![neko, inu];
+        assert!(!Arc::ptr_eq(&banks[0].branches[0].name, &banks[1].branches[0].name));
+
+        let interned = intern(&mut banks);
+        assert!(Arc::ptr_eq(&banks[0].branches[0].name, &banks[1].branches[0].name));
+        assert!(Arc::ptr_eq(&banks[0].branches[0].phonetic, &banks[1].branches[0].phonetic));
+        let saved = "本店".len() + "ﾎﾝﾃﾝ".len();
+        assert_eq!(interned, Interned { strings: 4, distinct: 2, bytes_saved: saved });
+    }
+}
@@ -0,0 +1,142 @@
+use std::str::FromStr;
+
+use crate::Bank;
+
+// Half-width katakana block (U+FF61 to U+FF9F) and the full-width character each one stands for.
+const HALFWIDTH: &str = "｡｢｣､･ｦｧｨｩｪｫｬｭｮｯｰｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜﾝﾞﾟ";
+const FULLWIDTH: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+// Full-width katakana that take a voicing mark, and those that also take a semi-voicing one.
+const VOICEABLE: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+const SEMI_VOICEABLE: &str = "ハヒフヘホ";
+
+// Which kana phonetic fields are written in. The bank's site returns half-width katakana, which is
+// what the dataset keeps; the others are produced on export.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PhoneticForm {
+    #[default]
+    HalfWidth,
+    FullWidth,
+    Hiragana,
+}
+
+impl FromStr for PhoneticForm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halfwidth" => Ok(PhoneticForm::HalfWidth),
+            "fullwidth" => Ok(PhoneticForm::FullWidth),
+            "hiragana" => Ok(PhoneticForm::Hiragana),
+            _ => Err(format!("unknown phonetic form: {}", s)),
+        }
+    }
+}
+
+fn offset(c: char, by: i32) -> char {
+    std::char::from_u32((c as i32 + by) as u32).unwrap_or(c)
+}
+
+fn to_fullwidth(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len() * 3);
+    for c in text.chars() {
+        let c = match c {
+            'ぁ'..='ゖ' => offset(c, 0x60),
+            ' ' => '\u{3000}',
+            '!'..='~' => offset(c, 0xFEE0),
+            _ => match HALFWIDTH.chars().position(|h| h == c) {
+                Some(i) => FULLWIDTH.chars().nth(i).unwrap(),
+                None => c,
+            },
+        };
+        // Half-width voicing marks are separate characters; fold them into the kana before them.
+        let last = converted.chars().last();
+        match (last, c) {
+            (Some('ウ'), '゛') => {
+                converted.pop();
+                converted.push('ヴ');
+            }
+            (Some(kana), '゛') if VOICEABLE.contains(kana) => {
+                converted.pop();
+                converted.push(offset(kana, 1));
+            }
+            (Some(kana), '゜') if SEMI_VOICEABLE.contains(kana) => {
+                converted.pop();
+                converted.push(offset(kana, 2));
+            }
+            _ => converted.push(c),
+        }
+    }
+    converted
+}
+
+fn to_halfwidth(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len());
+    for c in to_fullwidth(text).chars() {
+        let (base, mark) = match c {
+            'ヴ' => ('ウ', Some('ﾞ')),
+            _ if VOICEABLE.contains(offset(c, -1)) && !VOICEABLE.contains(c) => (offset(c, -1), Some('ﾞ')),
+            _ if SEMI_VOICEABLE.contains(offset(c, -2)) && !VOICEABLE.contains(c) => (offset(c, -2), Some('ﾟ')),
+            _ => (c, None),
+        };
+        match FULLWIDTH.chars().position(|f| f == base) {
+            Some(i) => converted.push(HALFWIDTH.chars().nth(i).unwrap()),
+            None => match base {
+                '\u{3000}' => converted.push(' '),
+                '！'..='～' => converted.push(offset(base, -0xFEE0)),
+                _ => converted.push(base),
+            },
+        }
+        converted.extend(mark);
+    }
+    converted
+}
+
+// Katakana, whichever width, and ASCII in full-width; voicing marks are folded into the kana.
+fn to_hiragana(text: &str) -> String {
+    to_fullwidth(text)
+        .chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => offset(c, -0x60),
+            _ => c,
+        })
+        .collect()
+}
+
+// Rewrites a reading in `form`, whether it was written in hiragana or katakana of either width.
+pub fn convert(text: &str, form: PhoneticForm) -> String {
+    match form {
+        PhoneticForm::HalfWidth => to_halfwidth(text),
+        PhoneticForm::FullWidth => to_fullwidth(text),
+        PhoneticForm::Hiragana => to_hiragana(text),
+    }
+}
+
+// Converts the phonetic fields of the banks, their former names and their branches.
+pub fn convert_banks(banks: &mut [Bank], form: PhoneticForm) {
+    for bank in banks {
+        bank.phonetic = convert(&bank.phonetic, form);
+        for alias in &mut bank.aliases {
+            if let Some(phonetic) = &alias.phonetic {
+                alias.phonetic = Some(convert(phonetic, form));
+            }
+        }
+        for branch in &mut bank.branches {
+            branch.phonetic = convert(&branch.phonetic, form).into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn convert_test() {
+        use crate::kana::{convert, PhoneticForm};
+
+        let reading = "ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ(ﾊﾟｽ) ｳﾞｧｰ";
+        assert_eq!(convert(reading, PhoneticForm::FullWidth), "ミツビシユ－エフジエイ（パス）　ヴァー");
+        assert_eq!(convert(reading, PhoneticForm::Hiragana), "みつびしゆ－えふじえい（ぱす）　ゔぁー");
+        assert_eq!(convert(reading, PhoneticForm::HalfWidth), reading);
+        assert_eq!(convert("みつびしゆ－えふじえい（ぱす）　ゔぁー", PhoneticForm::HalfWidth), reading);
+        assert_eq!(convert("ガッコウ", PhoneticForm::HalfWidth), "ｶﾞｯｺｳ");
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::sync::{Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::progress::ProgressObserver;
+use crate::{all_search_keys, BankCode, Error};
+
+// What the requests made under one search key yielded. Branches are summed over every bank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyCount {
+    pub banks: usize,
+    pub branches: usize,
+    // Requests under the key that failed for good.
+    #[serde(default)]
+    pub failed: usize,
+}
+
+pub type KeyCounts = BTreeMap<char, KeyCount>;
+
+// Register as a `ProgressObserver` to count results per search key during a crawl.
+#[derive(Debug, Default)]
+pub struct KeyCounter(Mutex<KeyCounts>);
+
+impl KeyCounter {
+    pub fn counts(&self) -> KeyCounts {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl ProgressObserver for KeyCounter {
+    fn key_fetched(&self, search_key: char, bank_code: Option<&BankCode>, count: usize) {
+        let mut counts = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let counted = counts.entry(search_key).or_default();
+        match bank_code {
+            Some(_) => counted.branches += count,
+            None => counted.banks += count,
+        }
+    }
+
+    fn request_failed(&self, search_key: char, _error: &Error) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).entry(search_key).or_default().failed += 1;
+    }
+}
+
+// The counts of the last full crawl, if one has been recorded.
+pub fn load(layout: &Layout) -> Result<Option<KeyCounts>, Error> {
+    let path = layout.key_counts_file();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(&path).map_err(Error::file(&path))?;
+    serde_json::from_reader(file).map(Some).map_err(Error::invalid(&path))
+}
+
+pub fn save(counts: &KeyCounts, layout: &Layout) -> Result<(), Error> {
+    let json = serde_json::to_vec_pretty(counts).map_err(Error::SerializeFailed)?;
+    fs::write(layout.key_counts_file(), json).map_err(Error::file(layout.key_counts_file()))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyReport {
+    pub search_key: char,
+    pub current: KeyCount,
+    pub previous: Option<KeyCount>,
+    // The key yielded nothing where the last run found banks or branches under it, which usually means
+    // its request failed without an error, e.g. the site answered with an empty page.
+    pub dropped: bool,
+}
+
+// One line per search key, in crawl order, comparing this run with the previous one.
+pub fn report(current: &KeyCounts, previous: Option<&KeyCounts>) -> Vec<KeyReport> {
+    all_search_keys()
+        .map(|search_key| {
+            let counted = current.get(&search_key).copied().unwrap_or_default();
+            let before = previous.map(|previous| previous.get(&search_key).copied().unwrap_or_default());
+            let dropped = before.is_some_and(|before| {
+                (before.banks > 0 && counted.banks == 0) || (before.branches > 0 && counted.branches == 0)
+            });
+            KeyReport {
+                search_key,
+                current: counted,
+                previous: before,
+                dropped,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn report_test() {
+        use crate::keycount::{report, KeyCount, KeyCounter, KeyCounts};
+        use crate::progress::ProgressObserver;
+        use crate::BankCode;
+
+        let counter = KeyCounter::default();
+        counter.key_fetched('あ', None, 12);
+        counter.key_fetched('あ', Some(&BankCode("0001".to_owned())), 3);
+        counter.key_fetched('あ', Some(&BankCode("0005".to_owned())), 4);
+        counter.key_fetched('い', None, 0);
+        let current = counter.counts();
+        assert_eq!(current[&'あ'], KeyCount { banks: 12, branches: 7, failed: 0 });
+
+        let mut previous = KeyCounts::new();
+        previous.insert('あ', KeyCount { banks: 11, branches: 7, failed: 0 });
+        previous.insert('い', KeyCount { banks: 9, branches: 40, failed: 0 });
+        let lines = report(&current, Some(&previous));
+        assert_eq!(lines.len(), crate::all_search_keys().count());
+        assert!(!lines[0].dropped);
+        assert!(lines[1].dropped);
+        assert_eq!(lines[1].previous.map(|count| count.banks), Some(9));
+        assert!(report(&current, None).iter().all(|line| !line.dropped));
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+
+use crate::romaji::slugify;
+use crate::{Bank, Error};
+
+pub const DEFAULT_TEMPLATE: &str = "{out}/{bank_code}.json";
+
+const BANKS_FILE: &str = "banks.json";
+const INDEX_FILE: &str = "index.json";
+const ALIASES_FILE: &str = "aliases.json";
+const ENGLISH_NAMES_FILE: &str = "english_names.json";
+const PARSER_FILE: &str = "parser.json";
+const SOURCE_FILE: &str = "source.json";
+const ABBREVIATIONS_FILE: &str = "abbreviations.json";
+const SHARD_FILE: &str = "shard.json";
+const DONE_DIR: &str = ".done";
+const RETRY_QUEUE_FILE: &str = "retry_queue.json";
+// Crawl bookkeeping rather than data, so hidden and left out of the manifest.
+const KEY_COUNTS_FILE: &str = ".key_counts.json";
+const PLAN_FILE: &str = ".plan.json";
+const PLAN_LOG_FILE: &str = ".plan.log";
+const STAGED_DIR: &str = ".staged";
+pub const LOCK_FILE: &str = ".lock";
+const FULLTEXT_DIR: &str = "fulltext";
+const COMPILED_FILE: &str = "dataset.bin";
+const ARCHIVED_FILE: &str = "dataset.rkyv";
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "manifest.json.sig";
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    out: PathBuf,
+    template: String,
+}
+
+impl Layout {
+    pub fn new(out: PathBuf, template: String) -> Result<Self, Error> {
+        // Every bank must end up in its own file.
+        if !template.contains("{bank_code}") {
+            return Err(Error::InvalidLayout(template));
+        }
+        Ok(Self { out, template })
+    }
+
+    // The same layout rooted at another output directory, e.g. an older snapshot.
+    pub fn relocated(&self, out: PathBuf) -> Self {
+        Self {
+            out,
+            template: self.template.clone(),
+        }
+    }
+
+    pub fn out(&self) -> &Path {
+        &self.out
+    }
+
+    pub fn banks_file(&self) -> PathBuf {
+        self.out.join(BANKS_FILE)
+    }
+
+    pub fn index_file(&self) -> PathBuf {
+        self.out.join(INDEX_FILE)
+    }
+
+    pub fn aliases_file(&self) -> PathBuf {
+        self.out.join(ALIASES_FILE)
+    }
+
+    pub fn english_names_file(&self) -> PathBuf {
+        self.out.join(ENGLISH_NAMES_FILE)
+    }
+
+    pub fn parser_file(&self) -> PathBuf {
+        self.out.join(PARSER_FILE)
+    }
+
+    pub fn source_file(&self) -> PathBuf {
+        self.out.join(SOURCE_FILE)
+    }
+
+    pub fn abbreviations_file(&self) -> PathBuf {
+        self.out.join(ABBREVIATIONS_FILE)
+    }
+
+    pub fn shard_file(&self) -> PathBuf {
+        self.out.join(SHARD_FILE)
+    }
+
+    pub fn retry_queue_file(&self) -> PathBuf {
+        self.out.join(RETRY_QUEUE_FILE)
+    }
+
+    pub fn key_counts_file(&self) -> PathBuf {
+        self.out.join(KEY_COUNTS_FILE)
+    }
+
+    pub fn plan_file(&self) -> PathBuf {
+        self.out.join(PLAN_FILE)
+    }
+
+    pub fn plan_log_file(&self) -> PathBuf {
+        self.out.join(PLAN_LOG_FILE)
+    }
+
+    pub fn staged_dir(&self) -> PathBuf {
+        self.out.join(STAGED_DIR)
+    }
+
+    pub fn lock_file(&self) -> PathBuf {
+        self.out.join(LOCK_FILE)
+    }
+
+    pub fn fulltext_dir(&self) -> PathBuf {
+        self.out.join(FULLTEXT_DIR)
+    }
+
+    pub fn compiled_file(&self) -> PathBuf {
+        self.out.join(COMPILED_FILE)
+    }
+
+    pub fn archived_file(&self) -> PathBuf {
+        self.out.join(ARCHIVED_FILE)
+    }
+
+    pub fn manifest_file(&self) -> PathBuf {
+        self.out.join(MANIFEST_FILE)
+    }
+
+    pub fn signature_file(&self) -> PathBuf {
+        self.out.join(SIGNATURE_FILE)
+    }
+
+    pub fn done_marker(&self, bank: &Bank) -> PathBuf {
+        self.out.join(DONE_DIR).join(&bank.code.0)
+    }
+
+    pub fn branch_file(&self, bank: &Bank) -> PathBuf {
+        let code = &bank.code.0;
+        let head = code.chars().next().map(String::from).unwrap_or_default();
+        let rendered = self
+            .template
+            .replace("{out}", &self.out.to_string_lossy())
+            .replace("{bank_code_head}", &head)
+            .replace("{bank_code}", code)
+            .replace("{bank_slug}", &slugify(&bank.phonetic));
+        PathBuf::from(rendered)
+    }
+
+    // Branch file path as recorded in the index, relative to the output directory when possible.
+    pub fn index_entry(&self, bank: &Bank) -> String {
+        let path = self.branch_file(bank);
+        path.strip_prefix(&self.out)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn branch_file_test() {
+        use std::path::PathBuf;
+        use crate::Bank;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+
+        let bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x001".to_owned());
+
+        let layout = Layout::new(PathBuf::from("dest"), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        assert_eq!(layout.branch_file(&bank), PathBuf::from("dest/0001.json"));
+
+        let layout = Layout::new(PathBuf::from("dest"), "{out}/branches/{bank_code_head}/{bank_code}.json".to_owned()).unwrap();
+        assert_eq!(layout.branch_file(&bank), PathBuf::from("dest/branches/0/0001.json"));
+
+        let layout = Layout::new(PathBuf::from("dest"), "{out}/{bank_code}_{bank_slug}.json".to_owned()).unwrap();
+        assert_eq!(layout.branch_file(&bank), PathBuf::from("dest/0001_mizuho.json"));
+        assert_eq!(layout.index_entry(&bank), "0001_mizuho.json");
+
+        assert!(Layout::new(PathBuf::from("dest"), "{out}/branches.json".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,787 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{PathBuf, Path};
+use std::str::{Chars, FromStr};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinError;
+use tokio::io::AsyncWriteExt;
+
+pub mod aliases;
+pub mod anomaly;
+pub mod backup;
+pub mod batch;
+pub mod client;
+#[cfg(feature = "mmap")]
+pub mod archived;
+pub mod cancel;
+pub mod category;
+pub mod charset;
+pub mod collate;
+pub mod compiled;
+pub mod crosscheck;
+pub mod dataset;
+pub mod dedup;
+pub mod delta;
+pub mod diff;
+pub mod english;
+pub mod export;
+pub mod filter;
+pub mod fulltext;
+pub mod intern;
+pub mod kana;
+pub mod keycount;
+pub mod layout;
+pub mod lock;
+pub mod logging;
+pub mod manifest;
+pub mod markup;
+pub mod marker;
+pub mod matcher;
+pub mod migrate;
+#[cfg(feature = "dev")]
+pub mod mock;
+pub mod naming;
+pub mod notify;
+pub mod page;
+mod parse;
+pub mod plan;
+pub mod pool;
+pub mod progress;
+pub mod promote;
+pub mod quality;
+pub mod retry;
+pub mod schedule;
+pub mod schema;
+mod romaji;
+pub mod search;
+pub mod server;
+pub mod session;
+pub mod shard;
+pub mod shared;
+pub mod signing;
+pub mod site;
+pub mod sqlite;
+pub mod sync;
+pub mod throttle;
+pub mod transfer;
+pub mod writer;
+pub mod xlsx;
+pub mod yucho;
+pub mod zengin;
+
+use layout::Layout;
+use progress::ProgressObserver;
+
+fn prepare_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    FetchBankError {
+        search_key: char,
+        url: String,
+        source: reqwest::Error,
+    },
+    FetchBranchError {
+        search_key: char,
+        bank_code: BankCode,
+        url: String,
+        source: reqwest::Error,
+    },
+    FetchDetailError {
+        bank_code: BankCode,
+        url: String,
+        source: reqwest::Error,
+    },
+    // The task fetching under `search_key`, the branches of `bank_code` when set, panicked or was aborted.
+    TaskFailed {
+        search_key: char,
+        bank_code: Option<BankCode>,
+        reason: String,
+    },
+    OpenBanksFileFailed(std::io::Error),
+    LoadBanksFileFailed(serde_json::Error),
+    SaveBankFileFailed(std::io::Error),
+    // Reading or writing any file other than the bank list and the branch files.
+    FileFailed {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // A file other than the bank list and the branch files that doesn't hold what it should.
+    InvalidFile {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    SerializeFailed(serde_json::Error),
+    InvalidLayout(String),
+    InvalidYuchoNumber(String),
+    ParseFailed,
+    RequestBudgetExhausted,
+    Cancelled,
+    LockHeld(PathBuf),
+    ExportFailed(std::io::Error),
+    FullTextIndexFailed(tantivy::TantivyError),
+    SqliteExportFailed(rusqlite::Error),
+    XlsxExportFailed(rust_xlsxwriter::XlsxError),
+    CompiledDatasetFailed(bincode::Error),
+    #[cfg(feature = "mmap")]
+    ArchivedDatasetFailed(String),
+    ServeFailed(hyper::Error),
+    DuplicateCodes(Vec<dedup::Conflict>),
+    SchemaViolations(Vec<schema::Violation>),
+    BackupFailed(std::io::Error),
+    ChecksumMismatch(Vec<manifest::Mismatch>),
+    InvalidKey(PathBuf),
+    BadSignature,
+    ClientFailed(reqwest::Error),
+    Incomplete(Vec<retry::FailedRequest>),
+    DeltaFailed(String),
+    RemoteFailed {
+        url: String,
+        source: reqwest::Error,
+    },
+    SyncFailed(String),
+    CrossCheckFailed(String),
+    BatchFailed(String),
+    CountDropped {
+        counted: anomaly::Counted,
+        previous: usize,
+        current: usize,
+    },
+    PromoteFailed(String),
+    MergeFailed(String),
+    PlanMismatch(String),
+}
+
+impl Error {
+    // For `map_err` on reading or writing `path`.
+    pub fn file(path: impl AsRef<Path>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.as_ref().to_owned();
+        move |source| Error::FileFailed { path, source }
+    }
+
+    // For `map_err` on parsing what was read from `path`.
+    pub fn invalid(path: impl AsRef<Path>) -> impl FnOnce(serde_json::Error) -> Self {
+        let path = path.as_ref().to_owned();
+        move |source| Error::InvalidFile { path, source }
+    }
+
+    // The variant's name in snake case, which scripts can match on; it never changes for a variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::FetchBankError { .. } => "fetch_bank_error",
+            Error::FetchBranchError { .. } => "fetch_branch_error",
+            Error::FetchDetailError { .. } => "fetch_detail_error",
+            Error::TaskFailed { .. } => "task_failed",
+            Error::OpenBanksFileFailed(_) => "open_banks_file_failed",
+            Error::LoadBanksFileFailed(_) => "load_banks_file_failed",
+            Error::SaveBankFileFailed(_) => "save_bank_file_failed",
+            Error::FileFailed { .. } => "file_failed",
+            Error::InvalidFile { .. } => "invalid_file",
+            Error::SerializeFailed(_) => "serialize_failed",
+            Error::InvalidLayout(_) => "invalid_layout",
+            Error::InvalidYuchoNumber(_) => "invalid_yucho_number",
+            Error::ParseFailed => "parse_failed",
+            Error::RequestBudgetExhausted => "request_budget_exhausted",
+            Error::Cancelled => "cancelled",
+            Error::LockHeld(_) => "lock_held",
+            Error::ExportFailed(_) => "export_failed",
+            Error::FullTextIndexFailed(_) => "full_text_index_failed",
+            Error::SqliteExportFailed(_) => "sqlite_export_failed",
+            Error::XlsxExportFailed(_) => "xlsx_export_failed",
+            Error::CompiledDatasetFailed(_) => "compiled_dataset_failed",
+            #[cfg(feature = "mmap")]
+            Error::ArchivedDatasetFailed(_) => "archived_dataset_failed",
+            Error::ServeFailed(_) => "serve_failed",
+            Error::DuplicateCodes(_) => "duplicate_codes",
+            Error::SchemaViolations(_) => "schema_violations",
+            Error::BackupFailed(_) => "backup_failed",
+            Error::ChecksumMismatch(_) => "checksum_mismatch",
+            Error::InvalidKey(_) => "invalid_key",
+            Error::BadSignature => "bad_signature",
+            Error::ClientFailed(_) => "client_failed",
+            Error::Incomplete(_) => "incomplete",
+            Error::DeltaFailed(_) => "delta_failed",
+            Error::RemoteFailed { .. } => "remote_failed",
+            Error::SyncFailed(_) => "sync_failed",
+            Error::CrossCheckFailed(_) => "cross_check_failed",
+            Error::BatchFailed(_) => "batch_failed",
+            Error::CountDropped { .. } => "count_dropped",
+            Error::PromoteFailed(_) => "promote_failed",
+            Error::MergeFailed(_) => "merge_failed",
+            Error::PlanMismatch(_) => "plan_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::FetchBankError { search_key, url, source } => {
+                write!(f, "fetching the banks under {} from {} failed: {}", search_key, url, source)
+            }
+            Error::FetchBranchError { search_key, bank_code, url, source } => write!(
+                f,
+                "fetching the branches of bank {} under {} from {} failed: {}",
+                bank_code, search_key, url, source
+            ),
+            Error::FetchDetailError { bank_code, url, source } => {
+                write!(f, "fetching the page of bank {} at {} failed: {}", bank_code, url, source)
+            }
+            Error::TaskFailed { search_key, bank_code: Some(bank_code), reason } => {
+                write!(f, "fetching the branches of bank {} under {} stopped: {}", bank_code, search_key, reason)
+            }
+            Error::TaskFailed { search_key, bank_code: None, reason } => {
+                write!(f, "fetching the banks under {} stopped: {}", search_key, reason)
+            }
+            Error::OpenBanksFileFailed(e) => write!(f, "reading the bank list or a branch file failed: {}", e),
+            Error::LoadBanksFileFailed(e) => write!(f, "the bank list or a branch file is not valid: {}", e),
+            Error::SaveBankFileFailed(e) => write!(f, "writing the bank list or a branch file failed: {}", e),
+            Error::FileFailed { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidFile { path, source } => write!(f, "{} is not valid: {}", path.display(), source),
+            Error::SerializeFailed(e) => write!(f, "writing JSON failed: {}", e),
+            Error::InvalidLayout(template) => write!(f, "the file name template {:?} has no {{bank_code}}", template),
+            Error::InvalidYuchoNumber(reason) => write!(f, "not a Yucho account: {}", reason),
+            Error::ParseFailed => f.write_str("a page or file could not be parsed"),
+            Error::RequestBudgetExhausted => f.write_str("the request budget ran out"),
+            Error::Cancelled => f.write_str("cancelled"),
+            Error::LockHeld(path) => write!(f, "{} is held by another run", path.display()),
+            Error::ExportFailed(e) => write!(f, "export failed: {}", e),
+            Error::FullTextIndexFailed(e) => write!(f, "the full text index failed: {}", e),
+            Error::SqliteExportFailed(e) => write!(f, "the SQLite export failed: {}", e),
+            Error::XlsxExportFailed(e) => write!(f, "the Excel export failed: {}", e),
+            Error::CompiledDatasetFailed(e) => write!(f, "the compiled dataset failed: {}", e),
+            #[cfg(feature = "mmap")]
+            Error::ArchivedDatasetFailed(reason) => write!(f, "the archived dataset failed: {}", reason),
+            Error::ServeFailed(e) => write!(f, "the server failed: {}", e),
+            Error::DuplicateCodes(conflicts) => {
+                let codes = conflicts
+                    .iter()
+                    .map(|conflict| match &conflict.branch_code {
+                        Some(branch_code) => format!("{}-{}", conflict.bank_code, branch_code),
+                        None => conflict.bank_code.clone(),
+                    })
+                    .collect::<Vec<String>>();
+                write!(f, "listed twice with different names: {}", codes.join(", "))
+            }
+            Error::SchemaViolations(violations) => {
+                let violations = violations
+                    .iter()
+                    .map(|violation| format!("{}: {}", violation.pointer, violation.message))
+                    .collect::<Vec<String>>();
+                write!(f, "does not match the schema: {}", violations.join("; "))
+            }
+            Error::BackupFailed(e) => write!(f, "backup failed: {}", e),
+            Error::ChecksumMismatch(mismatches) => {
+                let paths = mismatches.iter().map(|mismatch| mismatch.path.as_str()).collect::<Vec<&str>>();
+                write!(f, "files don't match the manifest: {}", paths.join(", "))
+            }
+            Error::InvalidKey(path) => write!(f, "{} is not a key made by `zngn keygen`", path.display()),
+            Error::BadSignature => f.write_str("the signature doesn't match the manifest"),
+            Error::ClientFailed(e) => write!(f, "the HTTP client could not be set up: {}", e),
+            Error::Incomplete(failed) => write!(f, "{} requests failed", failed.len()),
+            Error::DeltaFailed(reason)
+            | Error::SyncFailed(reason)
+            | Error::CrossCheckFailed(reason)
+            | Error::BatchFailed(reason)
+            | Error::PromoteFailed(reason)
+            | Error::MergeFailed(reason)
+            | Error::PlanMismatch(reason) => f.write_str(reason),
+            Error::RemoteFailed { url, source } => write!(f, "{}: {}", url, source),
+            Error::CountDropped { counted, previous, current } => {
+                let counted = match counted {
+                    anomaly::Counted::Banks => "banks",
+                    anomaly::Counted::Branches => "branches",
+                };
+                write!(f, "found {} {}, down from {}", current, counted, previous)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct Bank {
+    pub name: String,
+    pub phonetic: String,
+    pub code: BankCode,
+    pub search_param: String,
+    pub branches: Vec<Branch>,
+    // Former names, filled in from the alias table when the dataset is loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<aliases::Alias>,
+    // English name of major banks, filled in from the English name table when the dataset is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_en: Option<String>,
+    // The bank's own page on the site, its head office address and its website, when `crawl --enrich`
+    // found them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+}
+
+impl Bank {
+    pub fn new(name: String, phonetic: String, code: String, search_param: String) -> Self {
+        Self {
+            name,
+            phonetic,
+            code: BankCode(code),
+            search_param,
+            branches: Vec::new(),
+            aliases: Vec::new(),
+            name_en: None,
+            detail_url: None,
+            address: None,
+            website: None,
+        }
+    }
+
+    fn to_hashmap(&self) -> HashMap<BankCode, Self> {
+        let mut data = HashMap::new();
+        data.insert(self.code.clone(), self.clone());
+        data
+    }
+
+    fn filepath(&self, layout: &Layout) -> PathBuf {
+        layout.branch_file(self)
+    }
+
+    pub fn append_branch(&mut self, branch: Branch) {
+        self.branches.push(branch)
+    }
+
+    pub async fn save_as_file(&self, layout: &Layout) -> Result<usize, Error>{
+        let filepath = self.filepath(layout);
+        if let Some(parent) = filepath.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(Error::SaveBankFileFailed)?;
+        }
+        let hashmap = self.to_hashmap();
+        let data = serde_json::to_string(&hashmap).map_err(Error::SerializeFailed)?;
+        let mut file = tokio::fs::File::create(&filepath).await.map_err(Error::SaveBankFileFailed)?;
+        file.write_all(data.as_bytes()).await.map_err(Error::SaveBankFileFailed)?;
+        // tokio finishes a write in the background unless flushed, and the file is read back right after.
+        file.flush().await.map_err(Error::SaveBankFileFailed)?;
+        Ok(data.len())
+    }
+}
+
+fn report_failure<T>(observer: &dyn ProgressObserver, search_key: char, result: &Result<T, Error>) {
+    match result {
+        Ok(_) | Err(Error::Cancelled) => {}
+        Err(e) => observer.request_failed(search_key, e),
+    }
+}
+
+// The result of the task fetching under `search_key`. A task that panicked or was aborted is a failed
+// request like any other, handed to the observer so it is reported and queued.
+fn joined<T>(
+    observer: &dyn ProgressObserver,
+    search_key: char,
+    bank_code: Option<&BankCode>,
+    result: Result<Result<T, Error>, JoinError>,
+) -> Result<T, Error> {
+    result.unwrap_or_else(|e| {
+        let error = Error::TaskFailed {
+            search_key,
+            bank_code: bank_code.cloned(),
+            reason: e.to_string(),
+        };
+        observer.request_failed(search_key, &error);
+        Err(error)
+    })
+}
+
+// Flattens per search key task results, skipping the ones cut short by cancellation.
+// Failed requests were already handed to the observer and don't abort the crawl.
+fn gather<T>(results: Vec<Result<Vec<T>, Error>>) -> Result<Vec<T>, Error> {
+    let mut gathered = Vec::new();
+    for result in results {
+        match result {
+            Ok(items) => gathered.extend(items),
+            Err(Error::Cancelled)
+            | Err(Error::FetchBankError { .. })
+            | Err(Error::FetchBranchError { .. })
+            | Err(Error::TaskFailed { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(gathered)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    // The row was skipped: missing cells, no code, no search parameter.
+    MalformedRow,
+    // Kept, but the name or reading holds characters zengin data doesn't normally use.
+    OddCharacters,
+    // Kept, but the name looks cut off.
+    TruncatedName,
+    // Kept, but the code doesn't look like a zengin code.
+    SuspiciousRow,
+}
+
+// Something off about one row of a result table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseWarning {
+    // 1-based position among the data rows of the page.
+    pub row: usize,
+    pub kind: WarningKind,
+    // Code of the bank or branch on the row, when it has one.
+    pub code: Option<String>,
+    pub reason: String,
+}
+
+// Rows parsed from one page, along with the ones that were skipped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parsed<T> {
+    pub items: Vec<T>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl<T> Parsed<T> {
+    fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn skip(&mut self, row: usize, reason: String) {
+        self.warnings.push(ParseWarning {
+            row,
+            kind: WarningKind::MalformedRow,
+            code: None,
+            reason,
+        });
+    }
+
+    // Records anything unusual about a row that is kept.
+    fn inspect(&mut self, row: usize, code: &str, code_len: usize, name: &str, phonetic: &str) {
+        let mut warn = |kind, reason: String| {
+            self.warnings.push(ParseWarning {
+                row,
+                kind,
+                code: Some(code.to_owned()),
+                reason,
+            })
+        };
+        if code.chars().count() != code_len || !code.chars().all(|c| c.is_ascii_digit()) {
+            warn(WarningKind::SuspiciousRow, format!("code {:?} is not {} digits", code, code_len));
+        }
+        if let Some(c) = phonetic.chars().find(|&c| !is_zengin_phonetic(c)) {
+            warn(WarningKind::OddCharacters, format!("reading {:?} contains {:?}", phonetic, c));
+        }
+        if let Some(c) = name.chars().find(|&c| c.is_control() || c == '\u{fffd}') {
+            warn(WarningKind::OddCharacters, format!("name {:?} contains {:?}", name, c));
+        }
+        if name.ends_with('…') || name.ends_with("...") {
+            warn(WarningKind::TruncatedName, format!("name {:?} looks truncated", name));
+        }
+    }
+
+    // Hands the warnings to the observer and keeps the parsed items.
+    fn report(self, observer: &dyn ProgressObserver, search_key: char, bank_code: Option<&BankCode>) -> Vec<T> {
+        observer.key_fetched(search_key, bank_code, self.items.len());
+        for warning in &self.warnings {
+            observer.parse_warning(search_key, bank_code, warning);
+        }
+        self.items
+    }
+}
+
+// Characters allowed in zengin readings: half-width katakana, digits, upper case letters and a little punctuation.
+fn is_zengin_phonetic(c: char) -> bool {
+    matches!(c, 'ｦ'..='ﾟ' | '0'..='9' | 'A'..='Z' | ' ' | '(' | ')' | '-' | '.' | '/' | ',' | '\\')
+}
+
+// Names and readings repeat across banks (本店営業部, 駅前支店, ...), so they are shared
+// rather than owned; see `intern`.
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct Branch {
+    pub name: Arc<str>,
+    pub phonetic: Arc<str>,
+    pub code: String,
+}
+
+impl Branch {
+    pub fn new(name: String, phonetic: String, code: String) -> Self {
+        Self {
+            name: name.into(),
+            phonetic: phonetic.into(),
+            code,
+        }
+    }
+}
+
+// One key per gojūon initial, then 英 for names starting with a Latin letter or a digit, which the
+// site lists under its own 英数 entry instead of any kana row.
+const SEARCH_KEYS: &str = "あいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわ英";
+
+pub fn all_search_keys() -> Chars<'static> {
+    SEARCH_KEYS.chars()
+}
+
+pub fn save_banks(banks: &[Bank], layout: &Layout) -> Result<(), Error> {
+    let dest_path = layout.banks_file();
+    prepare_parent_dir(&dest_path);
+    let mut file = File::create(dest_path).map_err(Error::SaveBankFileFailed)?;
+    let data = to_hashmap(banks);
+    let json = serde_json::to_string(&data).map_err(Error::SerializeFailed)?;
+    file.write_all(json.as_bytes()).map_err(Error::SaveBankFileFailed)
+}
+
+pub fn save_index(banks: &[Bank], layout: &Layout) -> Result<(), Error> {
+    let dest_path = layout.index_file();
+    prepare_parent_dir(&dest_path);
+    let mut file = File::create(dest_path).map_err(Error::SaveBankFileFailed)?;
+    let data = banks
+        .iter()
+        .map(|bank| (bank.code.clone(), layout.index_entry(bank)))
+        .collect::<HashMap<BankCode, String>>();
+    let json = serde_json::to_string(&data).map_err(Error::SerializeFailed)?;
+    file.write_all(json.as_bytes()).map_err(Error::SaveBankFileFailed)
+}
+
+pub fn load_banks(layout: &Layout) -> Result<HashMap<BankCode, Bank>, Error> {
+    let dest_path = layout.banks_file();
+    let file = File::open(dest_path).map_err(Error::OpenBanksFileFailed)?;
+    serde_json::from_reader(&file).map_err(Error::LoadBanksFileFailed)
+}
+
+// Like `load_banks`, but checks the file against the dataset schema first and reports where it breaks it.
+pub fn load_banks_validated(layout: &Layout) -> Result<HashMap<BankCode, Bank>, Error> {
+    schema::load_validated(&layout.banks_file())
+}
+
+// Loads the dataset from the file written by `zngn compile` when it is up to date, otherwise from JSON.
+pub fn load_dataset(layout: &Layout) -> Result<Vec<Bank>, Error> {
+    match compiled::load(layout)? {
+        Some(mut banks) => {
+            aliases::Aliases::load(layout)?.apply(&mut banks);
+            english::EnglishNames::load(layout)?.apply(&mut banks);
+            Ok(banks)
+        }
+        None => load_json_dataset(layout),
+    }
+}
+
+// Loads the bank list together with every branch file saved so far, ordered by bank code.
+pub fn load_json_dataset(layout: &Layout) -> Result<Vec<Bank>, Error> {
+    let mut banks = load_banks(layout)?
+        .into_values()
+        .map(|bank| with_saved_branches(layout, bank))
+        .collect::<Result<Vec<Bank>, Error>>()?;
+    banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    aliases::Aliases::load(layout)?.apply(&mut banks);
+    english::EnglishNames::load(layout)?.apply(&mut banks);
+    intern::intern(&mut banks);
+    Ok(banks)
+}
+
+// Reads back the bank saved by `Bank::save_as_file`, falling back to `bank` itself when the file has no entry for it.
+pub fn load_branch_file(layout: &Layout, bank: &Bank) -> Result<Bank, Error> {
+    let file = File::open(layout.branch_file(bank)).map_err(Error::OpenBanksFileFailed)?;
+    let mut data: HashMap<BankCode, Bank> = serde_json::from_reader(&file).map_err(Error::LoadBanksFileFailed)?;
+    Ok(data.remove(&bank.code).unwrap_or_else(|| bank.clone()))
+}
+
+// `bank` with the branches saved for it, or as it is when no branch file has been saved yet. A
+// branch file that is there but can't be read is an error rather than a bank without branches.
+pub fn with_saved_branches(layout: &Layout, bank: Bank) -> Result<Bank, Error> {
+    match load_branch_file(layout, &bank) {
+        Err(Error::OpenBanksFileFailed(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(bank),
+        loaded => loaded,
+    }
+}
+
+// One bank and its branches, reading only that bank's branch file; None when no bank has the code.
+pub fn load_bank(layout: &Layout, code: &BankCode) -> Result<Option<Bank>, Error> {
+    match load_banks(layout)?.remove(code) {
+        Some(bank) => with_saved_branches(layout, bank).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Banks in the bank list whose branch file is missing, unreadable or has no branches, ordered by bank code.
+pub fn missing_branch_files(layout: &Layout) -> Result<Vec<Bank>, Error> {
+    let mut missing = load_banks(layout)?
+        .into_values()
+        .filter(|bank| load_branch_file(layout, bank).map_or(true, |loaded| loaded.branches.is_empty()))
+        .collect::<Vec<Bank>>();
+    missing.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    Ok(missing)
+}
+
+
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Clone)]
+pub struct BankCode(pub String);
+
+// Codes are written zero-padded, but are often typed or stored as numbers: "1" is bank 0001.
+fn pad_code(code: &str, width: usize, kind: &str) -> Result<String, String> {
+    let code = code.trim();
+    if code.is_empty() || code.len() > width || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("{} code must be 1 to {} digits: {:?}", kind, width, code));
+    }
+    Ok(format!("{:0>width$}", code, width = width))
+}
+
+impl BankCode {
+    pub const WIDTH: usize = 4;
+}
+
+impl FromStr for BankCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        pad_code(s, Self::WIDTH, "bank").map(BankCode)
+    }
+}
+
+impl fmt::Display for BankCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Branch codes are plain strings on `Branch`; this is how they are parsed from user input.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct BranchCode(pub String);
+
+impl BranchCode {
+    pub const WIDTH: usize = 3;
+}
+
+impl FromStr for BranchCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        pad_code(s, Self::WIDTH, "branch").map(BranchCode)
+    }
+}
+
+impl fmt::Display for BranchCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub fn to_hashmap(banks: &[Bank]) -> HashMap<BankCode, Bank> {
+    let mut data = HashMap::new();
+    for bank in banks.iter() {
+        data.extend(bank.to_hashmap());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn code_padding_test() {
+        use crate::{BankCode, BranchCode};
+
+        assert_eq!("1".parse::<BankCode>(), Ok(BankCode("0001".to_owned())));
+        assert_eq!(" 0005 ".parse::<BankCode>().unwrap().to_string(), "0005");
+        assert_eq!("12".parse::<BranchCode>(), Ok(BranchCode("012".to_owned())));
+        assert!("00001".parse::<BankCode>().is_err());
+        assert!("1a".parse::<BranchCode>().is_err());
+        assert!("".parse::<BankCode>().is_err());
+    }
+
+    #[test]
+    fn to_hashmap_test() {
+        use crate::{Bank, Branch, to_hashmap};
+
+        let mut bank1 = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let branch1_1 = Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "0123".to_owned());
+        let branch1_2 = Branch::new("とら支店".to_owned(), "ﾄﾗ".to_owned(), "0789".to_owned());
+        bank1.append_branch(branch1_1);
+        bank1.append_branch(branch1_2);
+
+        let mut bank2 = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let branch2_1 = Branch::new("しば支店".to_owned(), "ｼﾊﾞ".to_owned(), "0345".to_owned());
+        let branch2_2 = Branch::new("かい支店".to_owned(), "ｶｲ".to_owned(), "0456".to_owned());
+        bank2.append_branch(branch2_1);
+        bank2.append_branch(branch2_2);
+
+        let banks = vec![bank1.clone(), bank2.clone()];
+
+        let result = to_hashmap(&banks);
+        assert_eq!(result[&bank1.code], bank1);
+        assert_eq!(result[&bank2.code], bank2);
+    }
+
+    #[tokio::test]
+    async fn joined_test() {
+        use crate::progress::{ChannelObserver, CrawlEvent};
+        use crate::{gather, joined, BankCode, Error};
+
+        let (observer, mut receiver) = ChannelObserver::new();
+        let code = BankCode("0001".to_owned());
+        let panicked = tokio::spawn(async { panic!("parser bug") }).await;
+        let result: Result<Vec<u32>, Error> = joined(&observer, 'あ', Some(&code), panicked);
+        assert!(matches!(&result, Err(Error::TaskFailed { search_key: 'あ', bank_code: Some(_), .. })));
+        let fetched = joined(&observer, 'い', Some(&code), tokio::spawn(async { Ok(vec![1, 2]) }).await);
+        // The failed task is reported, so it is queued, and the crawl goes on without it.
+        assert!(matches!(receiver.try_recv(), Ok(CrawlEvent::RequestFailed { search_key: 'あ', .. })));
+        assert_eq!(gather(vec![result, fetched]).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn missing_branch_files_test() {
+        use std::fs;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::{missing_branch_files, save_banks, Bank, Branch};
+
+        let dir = std::env::temp_dir().join(format!("zngn-missing-{}", std::process::id()));
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let tori = Bank::new("とり銀行".to_owned(), "ﾄﾘ".to_owned(), "0333".to_owned(), "0x333".to_owned());
+        save_banks(&[neko.clone(), inu.clone(), tori.clone()], &layout).unwrap();
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        fs::write(layout.branch_file(&neko), serde_json::to_string(&neko.to_hashmap()).unwrap()).unwrap();
+        fs::write(layout.branch_file(&tori), serde_json::to_string(&tori.to_hashmap()).unwrap()).unwrap();
+
+        let missing = missing_branch_files(&layout).unwrap();
+        assert_eq!(missing.iter().map(|bank| bank.code.0.as_str()).collect::<Vec<&str>>(), ["0111", "0333"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn with_saved_branches_test() {
+        use std::fs;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::{load_bank, load_json_dataset, save_banks, with_saved_branches, Bank, Branch, Error};
+
+        let dir = std::env::temp_dir().join(format!("zngn-saved-branches-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        save_banks(&[neko.clone(), inu.clone()], &layout).unwrap();
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        fs::write(layout.branch_file(&neko), serde_json::to_string(&neko.to_hashmap()).unwrap()).unwrap();
+
+        // Not crawled yet: the bank as listed.
+        assert_eq!(with_saved_branches(&layout, inu.clone()).unwrap().branches.len(), 0);
+        assert_eq!(with_saved_branches(&layout, neko.clone()).unwrap().branches.len(), 1);
+        assert_eq!(load_json_dataset(&layout).unwrap().len(), 2);
+
+        // A broken branch file fails loading instead of publishing the bank without branches.
+        fs::write(layout.branch_file(&neko), "{").unwrap();
+        assert!(matches!(with_saved_branches(&layout, neko.clone()), Err(Error::LoadBanksFileFailed(_))));
+        assert!(load_json_dataset(&layout).is_err());
+        assert!(load_bank(&layout, &neko.code).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::layout::Layout;
+use crate::logging;
+use crate::Error;
+
+// How long a lock file without a PID counts as held, since its owner may not have written it yet.
+const UNWRITTEN_GRACE: Duration = Duration::from_secs(60);
+
+// Held for the duration of a crawl so two runs never write the same output directory.
+// The lock file is removed when this is dropped.
+#[derive(Debug)]
+pub struct CrawlLock {
+    path: PathBuf,
+}
+
+impl CrawlLock {
+    // Takes the lock, taking it over when the process that wrote it is no longer running.
+    pub fn acquire(layout: &Layout) -> Result<Self, Error> {
+        let path = layout.lock_file();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::file(parent))?;
+        }
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if !take_over(&path) {
+                        return Err(Error::LockHeld(path));
+                    }
+                }
+                Err(e) => return Err(Error::FileFailed { path, source: e }),
+            }
+        }
+    }
+}
+
+impl Drop for CrawlLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Whether a running process holds the lock on `layout`.
+pub fn is_held(layout: &Layout) -> bool {
+    held(&layout.lock_file())
+}
+
+fn held(path: &Path) -> bool {
+    match fs::read_to_string(path) {
+        Ok(content) => match content.trim().parse::<u32>() {
+            Ok(pid) => running(pid),
+            Err(_) => match fs::metadata(path).and_then(|m| m.modified()).map(|modified| modified.elapsed()) {
+                Ok(Ok(age)) => age < UNWRITTEN_GRACE,
+                _ => true,
+            },
+        },
+        Err(e) => e.kind() != ErrorKind::NotFound,
+    }
+}
+
+// Removes the lock at `path` when its owner is gone and returns whether it did. The file is moved
+// aside before it is removed, so of two runs taking over the same lock only one removes it, and a
+// lock taken in the meantime is put back.
+fn take_over(path: &Path) -> bool {
+    if held(path) {
+        return false;
+    }
+    let aside = path.with_extension(format!("stale-{}", std::process::id()));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        // Someone else moved it first; creating it again tells who won.
+        Err(e) => return e.kind() == ErrorKind::NotFound,
+    }
+    if held(&aside) {
+        let _ = fs::rename(&aside, path);
+        return false;
+    }
+    let owner = fs::read_to_string(&aside).unwrap_or_default();
+    let _ = fs::remove_file(&aside);
+    logging::warn(&format!(
+        "took over {} left by process {}, which is no longer running",
+        path.display(),
+        owner.trim()
+    ));
+    true
+}
+
+#[cfg(target_os = "linux")]
+fn running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn running(pid: u32) -> bool {
+    use std::process::{Command, Stdio};
+
+    // Signal 0 only checks the process exists; a process of another user answers "not permitted".
+    match Command::new("kill").arg("-0").arg(pid.to_string()).stdout(Stdio::null()).output() {
+        Ok(output) => output.status.success() || !String::from_utf8_lossy(&output.stderr).contains("No such process"),
+        Err(_) => true,
+    }
+}
+
+#[cfg(windows)]
+fn running(pid: u32) -> bool {
+    use std::process::{Command, Stdio};
+
+    let filter = format!("PID eq {}", pid);
+    match Command::new("tasklist").args(&["/FI", &filter, "/NH"]).stderr(Stdio::null()).output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).split_whitespace().any(|word| word == pid.to_string()),
+        Err(_) => true,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn lock_test() {
+        use std::fs;
+        use std::process::{Command, Stdio};
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::lock::{is_held, CrawlLock};
+        use crate::Error;
+
+        let dir = std::env::temp_dir().join(format!("zngn-lock-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+
+        let lock = CrawlLock::acquire(&layout).unwrap();
+        assert!(is_held(&layout));
+        assert!(matches!(CrawlLock::acquire(&layout), Err(Error::LockHeld(_))));
+        drop(lock);
+        assert!(!layout.lock_file().exists());
+        assert!(!is_held(&layout));
+
+        // Left by a process that has since exited.
+        let mut child = Command::new(std::env::current_exe().unwrap()).arg("--list").stdout(Stdio::null()).spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        fs::write(layout.lock_file(), pid.to_string()).unwrap();
+        assert!(!is_held(&layout));
+        let lock = CrawlLock::acquire(&layout).unwrap();
+        assert_eq!(fs::read_to_string(layout.lock_file()).unwrap(), std::process::id().to_string());
+        drop(lock);
+
+        // Just created by a run that has not written its PID yet.
+        fs::write(layout.lock_file(), "").unwrap();
+        assert!(matches!(CrawlLock::acquire(&layout), Err(Error::LockHeld(_))));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
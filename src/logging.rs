@@ -0,0 +1,302 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    // One JSON object per line: {"time": "...", "level": "info", "message": "..."}.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+// Start a new log file every hour or day (UTC), besides any size limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Hourly,
+    Daily,
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" => Ok(Period::Hourly),
+            "daily" => Ok(Period::Daily),
+            _ => Err(format!("unknown rotation: {}", s)),
+        }
+    }
+}
+
+impl Period {
+    fn seconds(self) -> u64 {
+        match self {
+            Period::Hourly => 60 * 60,
+            Period::Daily => 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub format: Format,
+    // Log to this file instead of stderr.
+    pub file: Option<PathBuf>,
+    // Rotate once the file grows past this many bytes.
+    pub max_size: Option<u64>,
+    pub rotate: Option<Period>,
+    // Rotated files kept as <file>.1 (newest) to <file>.<keep>.
+    pub keep: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            format: Format::Text,
+            file: None,
+            max_size: None,
+            rotate: None,
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Sink {
+    file: File,
+    size: u64,
+    // The rotation period the file was opened in.
+    period: u64,
+}
+
+#[derive(Debug)]
+struct Logger {
+    config: Config,
+    sink: Option<Sink>,
+}
+
+static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+// RFC 3339 in UTC, e.g. 2024-05-01T09:30:00Z.
+fn timestamp(secs: u64) -> String {
+    let (days, rest) = (secs / 86400, secs % 86400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's days_from_civil inverse.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl Logger {
+    fn period(&self, secs: u64) -> u64 {
+        self.config.rotate.map(|period| secs / period.seconds()).unwrap_or_default()
+    }
+
+    fn open(&self, path: &Path, secs: u64) -> io::Result<Sink> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let opened = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+        Ok(Sink {
+            size: metadata.len(),
+            // A file left by an earlier run belongs to the period it was last written in.
+            period: self.period(opened.map(|opened| opened.as_secs()).unwrap_or(secs)),
+            file,
+        })
+    }
+
+    fn rotate(&mut self, path: &Path, secs: u64) -> io::Result<()> {
+        self.sink = None;
+        let _ = fs::remove_file(rotated(path, self.config.keep));
+        for n in (1..self.config.keep).rev() {
+            let _ = fs::rename(rotated(path, n), rotated(path, n + 1));
+        }
+        if self.config.keep > 0 {
+            fs::rename(path, rotated(path, 1))?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        self.sink = Some(self.open(path, secs)?);
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        let path = match self.config.file.clone() {
+            Some(path) => path,
+            None => return writeln!(io::stderr(), "{}", line),
+        };
+        let secs = now();
+        let sink = match self.sink.take() {
+            Some(sink) => sink,
+            None => self.open(&path, secs)?,
+        };
+        let full = self.config.max_size.is_some_and(|max| sink.size > 0 && sink.size + line.len() as u64 >= max);
+        let sink = self.sink.insert(sink);
+        if full || sink.period != self.period(secs) {
+            self.rotate(&path, secs)?;
+        }
+        let sink = match self.sink.as_mut() {
+            Some(sink) => sink,
+            None => return writeln!(io::stderr(), "{}", line),
+        };
+        writeln!(sink.file, "{}", line)?;
+        sink.size += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+// Sets where and how later log lines are written; the file is opened here so a bad path fails early.
+pub fn init(config: Config) -> Result<(), Error> {
+    let mut logger = Logger { config, sink: None };
+    if let Some(path) = logger.config.file.clone() {
+        logger.sink = Some(logger.open(&path, now()).map_err(Error::file(&path))?);
+    }
+    let _ = LOGGER.set(Mutex::new(logger));
+    Ok(())
+}
+
+fn line(format: Format, level: Level, secs: u64, message: &str) -> String {
+    match format {
+        // Plain text keeps the lines exactly as messages were always printed.
+        Format::Text => message.to_owned(),
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Line<'a> {
+                time: String,
+                level: Level,
+                message: &'a str,
+            }
+            let line = Line {
+                time: timestamp(secs),
+                level,
+                message,
+            };
+            serde_json::to_string(&line).unwrap_or_default()
+        }
+    }
+}
+
+// Logs to stderr as plain text until `init` says otherwise. A log that can't be written is dropped
+// rather than failing the work being logged.
+pub fn log(level: Level, message: &str) {
+    match LOGGER.get() {
+        Some(logger) => {
+            let mut logger = logger.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let line = line(logger.config.format, level, now(), message);
+            let _ = logger.write(&line);
+        }
+        None => eprintln!("{}", line(Format::Text, level, now(), message)),
+    }
+}
+
+// Makes sure lines logged so far are on disk, for a process about to exit.
+pub fn flush() {
+    if let Some(logger) = LOGGER.get() {
+        let mut logger = logger.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(sink) = logger.sink.as_mut() {
+            let _ = sink.file.flush().and_then(|()| sink.file.sync_data());
+        }
+    }
+    let _ = io::stderr().flush();
+}
+
+pub fn info(message: &str) {
+    log(Level::Info, message);
+}
+
+pub fn warn(message: &str) {
+    log(Level::Warn, message);
+}
+
+pub fn error(message: &str) {
+    log(Level::Error, message);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn rotate_test() {
+        use std::fs;
+        use crate::logging::{line, timestamp, Config, Format, Level, Logger};
+
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(1709251199), "2024-02-29T23:59:59Z");
+        assert_eq!(
+            line(Format::Json, Level::Warn, 0, "slow"),
+            r#"{"time":"1970-01-01T00:00:00Z","level":"warn","message":"slow"}"#
+        );
+
+        let dir = std::env::temp_dir().join(format!("zngn-logging-{}", std::process::id()));
+        let path = dir.join("zngn.log");
+        let mut logger = Logger {
+            config: Config {
+                file: Some(path.clone()),
+                max_size: Some(10),
+                keep: 2,
+                ..Config::default()
+            },
+            sink: None,
+        };
+        for message in ["first", "second", "third", "fourth"] {
+            logger.write(message).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(dir.join("zngn.log.1")).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(dir.join("zngn.log.2")).unwrap(), "second\n");
+        assert!(!dir.join("zngn.log.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,301 +1,291 @@
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::prelude::*;
-use std::path::{PathBuf, Path};
-use std::str::Chars;
-
-use futures::stream::{StreamExt, iter as siter};
-use reqwest::Client;
-use select::{
-    document::Document,
-    node::Node,
-    predicate::{Class, Name, Predicate, Text},
-};
-use serde::{Deserialize, Serialize};
-
-const BRANCHES_DIR: &'static str = "dest";
-
-fn prepare_dest_dir() {
-    let _ = fs::create_dir_all(BRANCHES_DIR);
-}
-
-#[derive(Debug)]
-enum Error {
-    FetchBankError(reqwest::Error),
-    FechBranchError(reqwest::Error),
-    LoadBanksFileFailed(serde_json::Error),
-    SaveBankFileFailed(std::io::Error),
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
-struct Bank {
-    name: String,
-    phonetic: String,
-    code: BankCode,
-    search_param: String,
-    branches: Vec<Branch>,
-}
-
-impl Bank {
-    fn new(name: String, phonetic: String, code: String, search_param: String) -> Self {
-        Self {
-            name,
-            phonetic,
-            code: BankCode(code),
-            search_param,
-            branches: Vec::new(),
-        }
-    }
-
-    fn to_hashmap(&self) -> HashMap<BankCode, Self> {
-        let mut data = HashMap::new();
-        data.insert(self.code.clone(), self.clone());
-        data
-    }
-
-    fn filepath(&self) -> PathBuf {
-        let mut path = PathBuf::new();
-        path.push(BRANCHES_DIR);
-        path.push(format!("{}.json", &self.code.0));
-        path
-    }
-
-    fn append_branch(&mut self, branch: Branch) {
-        self.branches.push(branch)
-    }
-
-    async fn save_as_file(&self) -> Result<(), Error>{
-        let filepath = self.filepath();
-        let hashmap = self.to_hashmap();
-        let mut file = File::create(&filepath).map_err(Error::SaveBankFileFailed)?;
-        let data = serde_json::to_string(&hashmap).unwrap();
-        let mut stream = siter(data.as_bytes().chunks(100));
-        while let Some(content) = stream.next().await {
-            file.write(content);
-        }
-        Ok(())
-    }
-
-    async fn fetch_branches(&self, client: Client, search_key: char) -> Result<Vec<Branch>, Error> {
-        let html = client
-            .post("https://zengin.ajtw.net/shitenmeisai.php")
-            .form(&[("sm", search_key.to_string()), ("pz", self.search_param.clone())])
-            .send()
-            .await
-            .map_err(Error::FechBranchError)?
-            .text()
-            .await
-            .unwrap();
-        Ok(parse_branches(html))
-    }
-
-    async fn fetch_all_branches(&mut self, client: Client, search_keys: Chars<'static>) -> Result<Self, Error>{
-        let future = futures::future::join_all(
-            search_keys
-                .clone()
-                .map(|search_key| {
-                    let client = client.clone();
-                    let bank = self.clone();
-                    tokio::spawn( async move {
-                        bank.fetch_branches(client, search_key).await
-                    })
-                })
-        );
-        self.branches = future
-            .await
-            .into_iter()
-            .filter(|task_result| task_result.is_ok())
-            .map(|task_result| task_result.unwrap().unwrap())
-            .flatten()
-            .collect::<Vec<Branch>>();
-        Ok(self.clone())
-    }
-}
-
-fn filter_blank(node: &Node) -> bool {
-    let text = node.find(Text).next();
-    if text.is_none() {
-        return false;
-    }
-    text.unwrap().text() != "該当するデータはありません"
-}
-
-fn parse_branches(html: String) -> Vec<Branch> {
-    let document = Document::from(html.as_str());
-    document
-        .find(Name("tbody").descendant(Name("tr")))
-        .filter(filter_blank)
-        .map(|node| {
-            let mut datarows = node.children();
-            let name = datarows.next().unwrap().text();
-            let phonetic = datarows.next().unwrap().text();
-            let code = datarows.next().unwrap().text();
-            Branch {
-                name,
-                phonetic,
-                code
-            }
-        })
-        .collect::<Vec<Branch>>()
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
-struct Branch {
-    name: String,
-    phonetic: String,
-    code: String,
-}
-
-impl Branch {
-    fn new(name: String, phonetic: String, code: String) -> Self {
-        Self {
-            name,
-            phonetic,
-            code,
-        }
-    }
-}
-
-async fn fetch_banks(client: Client, search_key: char) -> Result<Vec<Bank>, Error> {
-    let html = client
-        .post("https://zengin.ajtw.net/ginkou.php")
-        .form(&[("gm", &search_key.to_string())])
-        .send()
-        .await
-        .map_err(Error::FetchBankError)?
-        .text()
-        .await
-        .unwrap();
-    Ok(parse_banks(html))
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process;
+
+use structopt::StructOpt;
+use zngn::filter::Filter;
+use zngn::layout::{self, Layout};
+use zngn::logging;
+use zngn::BankCode;
+
+mod cli;
+
+use cli::bench::BenchOpt;
+use cli::crawl::CrawlOpt;
+use cli::crosscheck::CrossCheckOpt;
+use cli::delta::{ApplyDeltaOpt, DeltaOpt};
+use cli::export::ExportOpt;
+use cli::fill::FillOpt;
+use cli::i18n::{self, Lang};
+use cli::list::ListOpt;
+use cli::merge::MergeOpt;
+use cli::migrate::MigrateOpt;
+#[cfg(feature = "dev")]
+use cli::mock::MockServerOpt;
+use cli::promote::PromoteOpt;
+use cli::serve::ServeOpt;
+use cli::site::SiteOpt;
+use cli::sync::SyncOpt;
+use cli::transfer::{BatchValidateOpt, ValidateTransferOpt};
+use cli::verify::VerifyOpt;
+use cli::{FormatOpt, Output, PageOpt};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zngn", about = "Scrape zengin bank and branch codes", after_help = cli::EXIT_CODES_HELP)]
+struct Opt {
+    /// Directory all output files are written under
+    #[structopt(long, parse(from_os_str), default_value = "dest", global = true)]
+    out: PathBuf,
+    /// Path template for per-bank branch files; placeholders: {out}, {bank_code}, {bank_code_head}, {bank_slug}
+    #[structopt(long, default_value = layout::DEFAULT_TEMPLATE, global = true)]
+    layout: String,
+    /// How results are printed: table, or json for a stable machine readable document
+    #[structopt(long, default_value = "table", global = true)]
+    output: Output,
+    /// Language for messages: en or ja; defaults to the locale from LC_ALL, LC_MESSAGES or LANG
+    #[structopt(long, global = true)]
+    lang: Option<Lang>,
+    /// Write progress and server logs to this file instead of stderr
+    #[structopt(long, parse(from_os_str), global = true)]
+    log_file: Option<PathBuf>,
+    /// Log line format: text, or json with one object per line for log collectors
+    #[structopt(long, default_value = "text", global = true)]
+    log_format: logging::Format,
+    /// Rotate the log file once it reaches this many bytes
+    #[structopt(long, global = true)]
+    log_max_size: Option<u64>,
+    /// Rotate the log file every hour or day (UTC): hourly or daily
+    #[structopt(long, global = true)]
+    log_rotate: Option<logging::Period>,
+    /// Rotated log files kept, as <file>.1 to <file>.N
+    #[structopt(long, default_value = "5", global = true)]
+    log_keep: usize,
+    #[structopt(subcommand)]
+    command: Command,
 }
 
-fn parse_banks(html: String) -> Vec<Bank> {
-    let document = Document::from(html.as_str());
-    document
-        .find(Class("j0").descendant(Name("tbody").descendant(Name("tr"))))
-        .filter(filter_blank)
-        .map(|node| {
-            let mut datarows = node.children();
-            let name = datarows.next().unwrap().text();
-            let phonetic = datarows.next().unwrap().text();
-            let code = datarows.next().unwrap().text();
-            let search_param = datarows
-                .next()
-                .unwrap()
-                .find(Name("button"))
-                .next()
-                .unwrap()
-                .attr("value")
-                .unwrap()
-                .to_owned();
-            Bank::new(
-                name,
-                phonetic,
-                code,
-                search_param,
-            )
-        })
-        .collect::<Vec<Bank>>()
-}
-
-fn all_search_keys() -> Chars<'static> {
-    "あいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわ".chars()
-}
-
-async fn fetch_all_banks(client: Client, search_keys: Chars<'static>) -> Vec<Bank> {
-    let future = futures::future::join_all(
-        search_keys
-            .clone()
-            .map(|search_key| {
-                let client = client.clone();
-                tokio::spawn(async move {
-                    fetch_banks(client, search_key).await
-                })
-            })
-    );
-    future
-        .await
-        .into_iter()
-        .filter(|task_result| task_result.is_ok())  // FIXME: handle JoinError
-        .map(|task_result| task_result.unwrap().unwrap())  // FIXME: save failed requests
-        .flatten()
-        .collect::<Vec<Bank>>()
-}
-
-const BANKS_JSON: &'static str = "dest/banks.json";
-
-fn save_banks(banks: &Vec<Bank>) {
-    let dest_path = Path::new(BANKS_JSON);
-    let mut file = File::create(dest_path).unwrap();
-    let data = to_hashmap(&banks);
-    let _ = file.write_all(serde_json::to_string(&data).unwrap().as_bytes());
-}
-
-fn load_banks() -> Result<HashMap<BankCode, Bank>, Error> {
-    let dest_path = Path::new(BANKS_JSON);
-    let file = File::open(dest_path).unwrap();
-    serde_json::from_reader(&file).map_err(Error::LoadBanksFileFailed)
-}
-
-
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Clone)]
-struct BankCode(String);
-
-fn to_hashmap(banks: &Vec<Bank>) -> HashMap<BankCode, Bank> {
-    let mut data = HashMap::new();
-    for bank in banks.iter() {
-        data.extend(bank.to_hashmap());
-    }
-    data
-}
-
-async fn iterate_banks(client: &Client, banks: &mut Vec<Bank>) -> Result<(), Error>{
-    for bank in banks.iter_mut() {
-        let client = client.clone();
-        let search_keys = all_search_keys();
-        let bank = bank.fetch_all_branches(client, search_keys).await?;
-        bank.save_as_file().await?;
-    }
-    Ok(())
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Fetch every bank and its branches from zengin.ajtw.net
+    Crawl(CrawlOpt),
+    /// Fetch the branches of only the banks the last crawl's plan has yet to save, or without a plan
+    /// those in banks.json whose branch file is missing or empty, e.g. after a crawl that failed partway
+    Fill(FillOpt),
+    /// Find banks and branches whose name or reading contains the query
+    Search {
+        query: String,
+        /// Rank hits using the full-text index built by `zngn index`
+        #[structopt(long)]
+        indexed: bool,
+        #[structopt(flatten)]
+        page: PageOpt,
+        /// Only search banks matching a filter, e.g. "name~銀行 AND branch_count>100"
+        #[structopt(long = "where")]
+        filter: Option<Filter>,
+        #[structopt(flatten)]
+        format: FormatOpt,
+    },
+    /// Find the banks that have a branch with this name or reading, e.g. 本店営業部
+    FindBranch {
+        name: String,
+        /// Match the whole branch name or reading instead of any part of it
+        #[structopt(long)]
+        exact: bool,
+    },
+    /// Show a bank, or one of its branches, by code, leading zeros optional; * and ? in either code list every match
+    Lookup {
+        bank_code: String,
+        branch_code: Option<String>,
+    },
+    /// List banks or the branches of a bank, with a choice of columns and filters
+    List(ListOpt),
+    /// Search the branches of one bank by name, reading in any kana, or code, e.g. `branches 5 --query うめだ`;
+    /// only that bank's branch file is read
+    Branches {
+        bank_code: BankCode,
+        /// Part of a branch name or reading, or a code prefix; every branch when omitted
+        #[structopt(long)]
+        query: Option<String>,
+        #[structopt(flatten)]
+        page: PageOpt,
+    },
+    /// Write the saved dataset to a single file
+    Export(ExportOpt),
+    /// Render the saved dataset as a static HTML site: an index of banks by gojūon row and a page of
+    /// branches per bank
+    Site(SiteOpt),
+    /// Show what changed since an older snapshot, e.g. a copy of the output directory from last month
+    Diff {
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(flatten)]
+        format: FormatOpt,
+    },
+    /// Show the branches one bank added, removed, renamed or renumbered between two snapshots, each an
+    /// output directory or a JSON file such as an export
+    DiffBank {
+        bank_code: BankCode,
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+        #[structopt(flatten)]
+        format: FormatOpt,
+    },
+    /// Compare the saved dataset with the zengin-code data and list banks, branches, names and readings
+    /// that differ, a check that the scraper still reads the site correctly
+    CrossCheck(CrossCheckOpt),
+    /// Rewrite a dataset saved by an earlier version in the current format, so it needn't be crawled again
+    Migrate(MigrateOpt),
+    /// Write the changes between two snapshots as a compact patch of added, removed and updated records
+    Delta(DeltaOpt),
+    /// Apply a patch from `zngn delta` to the output directory, or to a JSON file of banks with --to
+    ApplyDelta(ApplyDeltaOpt),
+    /// Keep the output directory in sync with a remote `zngn serve`, by patches where possible, instead of crawling
+    Sync(SyncOpt),
+    /// Pack the output directory into a .tar.gz with a manifest of checksums
+    Backup {
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+    },
+    /// Replace the output directory with the contents of a backup, after checking its checksums
+    Restore {
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+        /// Replace a dataset already in the output directory
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Make the output directory, a link, point at a crawled snapshot once it passes verification and
+    /// anomaly checks against the snapshot it pointed at, e.g. `zngn --out releases/latest promote
+    /// releases/2024-06-01`
+    Promote(PromoteOpt),
+    /// Point the output directory back at the snapshot promoted before the current one
+    Rollback,
+    /// Combine snapshots crawled in parts with `crawl --shard` into the output directory
+    Merge(MergeOpt),
+    /// Check the output directory against the manifest.json a crawl writes, and its signature
+    Verify(VerifyOpt),
+    /// Create an ed25519 key pair for signing manifest.json; the public key is written next to it as <path>.pub
+    Keygen {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Sign manifest.json, so consumers can check where a snapshot comes from with `verify --public-key`
+    Sign {
+        /// Private key made by `zngn keygen`
+        #[structopt(long, parse(from_os_str), env = "ZNGN_SIGN_KEY")]
+        key: PathBuf,
+    },
+    /// Build the full-text index used by `search --indexed`
+    Index,
+    /// Convert the saved dataset into a compact file that later commands load much faster (built with
+    /// the mmap feature, also an archive that `lookup` reads in place)
+    Compile,
+    /// Show the plan of the last crawl and how much of it is done, which `crawl --resume` and `fill`
+    /// carry on with
+    Plan,
+    /// Summarize the saved dataset
+    Stats {
+        #[structopt(flatten)]
+        format: FormatOpt,
+    },
+    /// Print the JSON Schema of the dataset files
+    Schema {
+        /// Validate a dataset file against the schema instead, banks.json when no path is given
+        #[structopt(long)]
+        check: Option<Option<PathBuf>>,
+    },
+    /// List suspicious records: empty or non-kana readings, malformed codes, banks without branches and duplicate names
+    Quality {
+        /// Records shown per check
+        #[structopt(long, default_value = "5")]
+        examples: usize,
+    },
+    /// Check a transfer destination: that the bank and branch exist, the account number is well formed
+    /// and the receiver name only has characters a zengin transfer record allows
+    ValidateTransfer(ValidateTransferOpt),
+    /// Check the bank and branch codes, and names if present, on every row of a CSV file such as a payout
+    /// file or vendor master, resolving codes from names where missing, and write a copy annotated with
+    /// the resolved codes and names or what is wrong
+    BatchValidate(BatchValidateOpt),
+    /// Time dataset loading, lookups and searches against the saved dataset
+    Bench(BenchOpt),
+    /// Serve the saved dataset over HTTP
+    Serve(ServeOpt),
+    /// Serve recorded pages of zengin.ajtw.net, so `crawl --source` can run end to end without the real site
+    #[cfg(feature = "dev")]
+    MockServer(MockServerOpt),
 }
 
 #[tokio::main]
 async fn main() {
-    let client = Client::new();
-    let search_keys = all_search_keys();
-    let banks = fetch_all_banks(client.clone(), search_keys).await;
-    save_banks(&banks);
-    let data = load_banks().unwrap();
-    let mut bank = data.get(&BankCode("2740".to_owned())).unwrap().clone();
-    let search_keys = "う".chars();
-    let bank = bank.fetch_all_branches(client.clone(), search_keys).await;
-    println!("{:?}", &bank);
-    let _ = bank.unwrap().save_as_file().await;
-    println!("DONE");
+    let args = env::args_os().collect::<Vec<OsString>>();
+    // Only for the help text, which is built before the arguments are parsed.
+    i18n::set(Lang::from_args(&args).unwrap_or_else(Lang::detect));
+    let opt = Opt::from_clap(&i18n::localize(Opt::clap()).get_matches_from(args));
+    i18n::set(opt.lang.unwrap_or_else(Lang::detect));
+    let logged = logging::init(logging::Config {
+        format: opt.log_format,
+        file: opt.log_file,
+        max_size: opt.log_max_size,
+        rotate: opt.log_rotate,
+        keep: opt.log_keep,
+    });
+    let (out, layout) = (opt.out, opt.layout);
+    let report = match logged.and_then(|()| Layout::new(out, layout)) {
+        Err(e) => cli::Report::from_error(&e),
+        Ok(layout) => match opt.command {
+            Command::Crawl(crawl) => cli::crawl::run(crawl, layout).await,
+            Command::Fill(fill) => cli::fill::run(fill, &layout).await,
+            Command::Search { query, indexed: false, filter, page, format } => {
+                cli::query::search(&layout, &query, filter.as_ref(), page).formatted(&format)
+            }
+            Command::Search { query, indexed: true, page, format, .. } => {
+                cli::query::search_indexed(&layout, &query, page).formatted(&format)
+            }
+            Command::FindBranch { name, exact } => cli::query::find_branches(&layout, &name, exact),
+            Command::Diff { old, format } => cli::query::diff(&layout, old).formatted(&format),
+            Command::DiffBank { bank_code, old, new, format } => {
+                cli::query::diff_bank(&layout, &bank_code, old, new).formatted(&format)
+            }
+            Command::CrossCheck(cross_check) => cli::crosscheck::run(cross_check, &layout).await,
+            Command::Migrate(migrate) => cli::migrate::run(migrate, &layout).await,
+            Command::Delta(delta) => cli::delta::run(delta, &layout),
+            Command::ApplyDelta(apply) => cli::delta::apply(apply, &layout).await,
+            Command::Sync(sync) => cli::sync::run(sync, &layout).await,
+            Command::Backup { archive } => cli::backup::backup(&layout, archive),
+            Command::Restore { archive, force } => cli::backup::restore(&layout, archive, force),
+            Command::Promote(promote) => cli::promote::run(promote, &layout),
+            Command::Rollback => cli::promote::rollback(&layout),
+            Command::Merge(merge) => cli::merge::run(merge, &layout).await,
+            Command::Verify(verify) => cli::verify::run(verify, &layout),
+            Command::Keygen { path } => cli::verify::keygen(path),
+            Command::Sign { key } => cli::verify::sign(&layout, &key),
+            Command::Index => cli::query::index(&layout),
+            Command::Compile => cli::query::compile(&layout),
+            Command::Lookup { bank_code, branch_code } => {
+                cli::query::lookup(&layout, &bank_code, branch_code.as_deref())
+            }
+            Command::List(list) => cli::list::run(list, &layout),
+            Command::Branches { bank_code, query, page } => {
+                cli::query::branches(&layout, &bank_code, query.as_deref(), page)
+            }
+            Command::Export(export) => cli::export::run(export, &layout),
+            Command::Site(site) => cli::site::run(site, &layout),
+            Command::Plan => cli::query::plan(&layout),
+            Command::Stats { format } => cli::query::stats(&layout).formatted(&format),
+            Command::Schema { check } => cli::query::schema(&layout, check),
+            Command::Quality { examples } => cli::query::quality(&layout, examples),
+            Command::ValidateTransfer(transfer) => cli::transfer::run(transfer, &layout),
+            Command::BatchValidate(batch) => cli::transfer::batch(batch, &layout),
+            Command::Bench(bench) => cli::bench::run(bench, &layout),
+            Command::Serve(serve) => cli::serve::run(serve, &layout).await,
+            #[cfg(feature = "dev")]
+            Command::MockServer(mock) => cli::mock::run(mock).await,
+        },
+    };
+    report.emit(opt.output);
+    process::exit(report.exit_code());
 }
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn to_hashmap_test() {
-        use crate::{Bank, Branch, to_hashmap};
-
-        let mut bank1 = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
-        let branch1_1 = Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "0123".to_owned());
-        let branch1_2 = Branch::new("とら支店".to_owned(), "ﾄﾗ".to_owned(), "0789".to_owned());
-        bank1.append_branch(branch1_1);
-        bank1.append_branch(branch1_2);
-
-        let mut bank2 = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
-        let branch2_1 = Branch::new("しば支店".to_owned(), "ｼﾊﾞ".to_owned(), "0345".to_owned());
-        let branch2_2 = Branch::new("かい支店".to_owned(), "ｶｲ".to_owned(), "0456".to_owned());
-        bank2.append_branch(branch2_1);
-        bank2.append_branch(branch2_2);
-
-        let banks = vec![bank1.clone(), bank2.clone()];
-
-        let result = to_hashmap(&banks);
-        assert_eq!(result[&bank1.code], bank1);
-        assert_eq!(result[&bank2.code], bank2);
-    }
-}
\ No newline at end of file
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::layout::{MANIFEST_FILE, SIGNATURE_FILE};
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+// Checksums of every file of an output directory, keyed by path relative to it with / separators.
+// Crawl bookkeeping (dot files such as the lock and the done markers) and the manifest itself, along
+// with its signature, are left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    // Unix time the manifest was made.
+    pub created_at: u64,
+    pub files: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    Missing,
+    Changed,
+    // Present but not in the manifest.
+    Unlisted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub path: String,
+    pub problem: Problem,
+}
+
+fn hash(path: &Path) -> Result<Entry, Error> {
+    let mut file = File::open(path).map_err(Error::file(path))?;
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut file, &mut hasher).map_err(Error::file(path))?;
+    Ok(Entry {
+        sha256: format!("{:x}", hasher.finalize()),
+        bytes,
+    })
+}
+
+fn walk(dir: &Path, prefix: &str, files: &mut BTreeMap<String, Entry>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir).map_err(Error::file(dir))? {
+        let path = entry.map_err(Error::file(dir))?.path();
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if name.starts_with('.') || (prefix.is_empty() && (name == MANIFEST_FILE || name == SIGNATURE_FILE)) {
+            continue;
+        }
+        let relative = format!("{}{}", prefix, name);
+        if path.is_dir() {
+            walk(&path, &format!("{}/", relative), files)?;
+        } else {
+            files.insert(relative, hash(&path)?);
+        }
+    }
+    Ok(())
+}
+
+impl Manifest {
+    pub fn build(dir: &Path) -> Result<Self, Error> {
+        let mut files = BTreeMap::new();
+        walk(dir, "", &mut files)?;
+        Ok(Self {
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            files,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::file(path))?;
+        serde_json::from_reader(file).map_err(Error::invalid(path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_vec_pretty(self).map_err(Error::SerializeFailed)?;
+        fs::write(path, json).map_err(Error::file(path))
+    }
+
+    // How `dir` differs from the manifest; empty when every file is intact.
+    pub fn verify(&self, dir: &Path) -> Result<Vec<Mismatch>, Error> {
+        let current = Self::build(dir)?;
+        let mut mismatches = Vec::new();
+        for (path, entry) in &self.files {
+            let problem = match current.files.get(path) {
+                None => Problem::Missing,
+                Some(found) if found != entry => Problem::Changed,
+                Some(_) => continue,
+            };
+            mismatches.push(Mismatch {
+                path: path.clone(),
+                problem,
+            });
+        }
+        for path in current.files.keys().filter(|path| !self.files.contains_key(*path)) {
+            mismatches.push(Mismatch {
+                path: path.clone(),
+                problem: Problem::Unlisted,
+            });
+        }
+        Ok(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn verify_test() {
+        use std::fs;
+        use crate::manifest::{Manifest, Mismatch, Problem};
+
+        let dir = std::env::temp_dir().join(format!("zngn-manifest-{}", std::process::id()));
+        fs::create_dir_all(dir.join("0").join(".done")).unwrap();
+        fs::write(dir.join("banks.json"), "{}").unwrap();
+        fs::write(dir.join("0").join("0001.json"), "{}").unwrap();
+        fs::write(dir.join("0").join(".done").join("0001"), "1").unwrap();
+        fs::write(dir.join(".lock"), "1").unwrap();
+
+        let manifest = Manifest::build(&dir).unwrap();
+        assert_eq!(manifest.files.keys().collect::<Vec<_>>(), vec!["0/0001.json", "banks.json"]);
+        assert_eq!(manifest.files["banks.json"].bytes, 2);
+        assert_eq!(
+            manifest.files["banks.json"].sha256,
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+        manifest.save(&dir.join("manifest.json")).unwrap();
+        assert_eq!(Manifest::load(&dir.join("manifest.json")).unwrap(), manifest);
+        assert_eq!(manifest.verify(&dir).unwrap(), Vec::new());
+
+        fs::write(dir.join("banks.json"), "{ }").unwrap();
+        fs::remove_file(dir.join("0").join("0001.json")).unwrap();
+        fs::write(dir.join("extra.json"), "{}").unwrap();
+        let mismatch = |path: &str, problem| Mismatch {
+            path: path.to_owned(),
+            problem,
+        };
+        assert_eq!(
+            manifest.verify(&dir).unwrap(),
+            vec![
+                mismatch("0/0001.json", Problem::Missing),
+                mismatch("banks.json", Problem::Changed),
+                mismatch("extra.json", Problem::Unlisted),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
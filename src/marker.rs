@@ -0,0 +1,44 @@
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::layout::Layout;
+use crate::{Bank, Error};
+
+pub async fn mark_done(layout: &Layout, bank: &Bank) -> Result<(), Error> {
+    let path = layout.done_marker(bank);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(Error::file(parent))?;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    tokio::fs::write(&path, now.as_secs().to_string())
+        .await
+        .map_err(Error::file(&path))
+}
+
+// Forgets that the bank was completed, when its branches were saved with some missing.
+pub async fn clear_done(layout: &Layout, bank: &Bank) -> Result<(), Error> {
+    let path = layout.done_marker(bank);
+    match tokio::fs::remove_file(&path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::FileFailed { path, source: e }),
+        _ => Ok(()),
+    }
+}
+
+// When the bank's branches were last saved; None when they never were.
+pub fn done_at(layout: &Layout, bank: &Bank) -> Option<SystemTime> {
+    let content = fs::read_to_string(layout.done_marker(bank)).ok()?;
+    let secs = content.trim().parse::<u64>().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Whether the bank was completed at all (`max_age` is None) or within `max_age`.
+pub fn is_fresh(layout: &Layout, bank: &Bank, max_age: Option<Duration>) -> bool {
+    match (done_at(layout, bank), max_age) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(done_at), Some(max_age)) => {
+            let age = SystemTime::now().duration_since(done_at).unwrap_or_default();
+            age <= max_age
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::convert::TryFrom;
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::Error;
+
+// A CSS selector as the parsers take it, e.g. `.j0 tbody tr` or `table.k1 > tbody > tr:not(.head)`,
+// kept with its source so it is written back as it was read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Selector {
+    source: String,
+    parsed: scraper::Selector,
+}
+
+impl TryFrom<String> for Selector {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let parsed = scraper::Selector::parse(&source).map_err(|e| format!("unsupported selector {:?}: {}", source, e))?;
+        Ok(Self { source, parsed })
+    }
+}
+
+impl From<Selector> for String {
+    fn from(selector: Selector) -> Self {
+        selector.source
+    }
+}
+
+impl PartialEq for Selector {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Selector {
+    // Only for the defaults below, which are known to parse.
+    fn new(source: &str) -> Self {
+        Self::try_from(source.to_owned()).unwrap_or_else(|_| Self {
+            source: source.to_owned(),
+            parsed: scraper::Selector::parse("*").expect("universal selector"),
+        })
+    }
+
+    pub(crate) fn as_scraper(&self) -> &scraper::Selector {
+        &self.parsed
+    }
+}
+
+// Which cell of a bank list row holds what, counting from 0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BankColumns {
+    pub name: usize,
+    pub phonetic: usize,
+    pub code: usize,
+    pub search_param: usize,
+    // Read by `crawl --enrich` only; the site has no such columns at the moment.
+    pub address: Option<usize>,
+    pub website: Option<usize>,
+}
+
+impl Default for BankColumns {
+    fn default() -> Self {
+        Self {
+            name: 0,
+            phonetic: 1,
+            code: 2,
+            search_param: 3,
+            address: None,
+            website: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BranchColumns {
+    pub name: usize,
+    pub phonetic: usize,
+    pub code: usize,
+}
+
+impl Default for BranchColumns {
+    fn default() -> Self {
+        Self {
+            name: 0,
+            phonetic: 1,
+            code: 2,
+        }
+    }
+}
+
+// Where the parsers find rows and cells on the site's pages. The defaults match the site as it is;
+// a parser.json in the output directory overrides any of them, so a small markup change can be
+// worked around without a new release, e.g. {"bank_rows": ".k1 tbody tr"}.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Markup {
+    pub bank_rows: Selector,
+    pub branch_rows: Selector,
+    pub bank_columns: BankColumns,
+    pub branch_columns: BranchColumns,
+    // The element in the search parameter cell of a bank row, and its attribute holding the parameter.
+    pub search_param: Selector,
+    pub search_param_attribute: String,
+    // For `crawl --enrich`: a link in a bank row to the bank's own page, and where that page has the
+    // head office address and the link to the bank's website.
+    pub bank_link: Selector,
+    pub detail_address: Selector,
+    pub detail_website: Selector,
+}
+
+impl Default for Markup {
+    fn default() -> Self {
+        Self {
+            bank_rows: Selector::new(".j0 tbody tr"),
+            branch_rows: Selector::new("tbody tr"),
+            bank_columns: BankColumns::default(),
+            branch_columns: BranchColumns::default(),
+            search_param: Selector::new("button"),
+            search_param_attribute: "value".to_owned(),
+            bank_link: Selector::new("a[href]"),
+            detail_address: Selector::new("address"),
+            detail_website: Selector::new("a.website[href]"),
+        }
+    }
+}
+
+impl Markup {
+    pub fn load(layout: &Layout) -> Result<Self, Error> {
+        let path = layout.parser_file();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = File::open(&path).map_err(Error::file(&path))?;
+        serde_json::from_reader(file).map_err(Error::invalid(&path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn selector_test() {
+        use scraper::Html;
+        use crate::markup::{Markup, Selector};
+
+        let html = r#"<div class="j0 wide"><table id="list"><tbody><tr><td>a</td></tr></tbody></table></div>
+            <table><tbody><tr class="head"><td>b</td></tr></tbody></table>"#;
+        let document = Html::parse_document(html);
+        let texts = |selector: &str| {
+            let selector = serde_json::from_value::<Selector>(selector.into()).unwrap();
+            document.select(selector.as_scraper()).map(|element| element.text().collect()).collect::<Vec<String>>()
+        };
+        assert_eq!(texts(".j0 tbody tr"), vec!["a"]);
+        assert_eq!(texts("tbody tr"), vec!["a", "b"]);
+        assert_eq!(texts("div.j0 table#list td"), vec!["a"]);
+        assert_eq!(texts("tbody > tr:not(.head)"), vec!["a"]);
+        assert_eq!(texts(".k1 tr"), Vec::<String>::new());
+        assert!(serde_json::from_str::<Selector>(r#""tbody >""#).is_err());
+
+        let markup = serde_json::from_str::<Markup>(r#"{"bank_rows": "table.k1 tr", "bank_columns": {"code": 0}}"#).unwrap();
+        assert_eq!(String::from(markup.bank_rows), "table.k1 tr");
+        assert_eq!((markup.bank_columns.code, markup.bank_columns.name), (0, 0));
+        assert_eq!(markup.branch_rows, Markup::default().branch_rows);
+    }
+}
@@ -0,0 +1,8 @@
+// How a search query matches a record. Kept free of the rest of the crate and of any dependency, so the
+// search module of generated sites (wasm/) compiles this very file and matches exactly like `zngn search`.
+
+// Numeric queries also match codes by prefix, so a picker can narrow down as digits are typed.
+pub fn matches(code: &str, name: &str, phonetic: &str, query: &str) -> bool {
+    let numeric = !query.is_empty() && query.chars().all(|c| c.is_ascii_digit());
+    name.contains(query) || phonetic.contains(query) || (numeric && code.starts_with(query))
+}
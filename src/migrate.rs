@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::naming::snake_keys;
+use crate::schema::Violation;
+use crate::{Bank, Error};
+
+// Files of an output directory that don't hold banks.
+const NOT_BANKS: &[&str] = &["index.json", "aliases.json", "retry_queue.json"];
+
+#[derive(Debug, Default, Serialize)]
+pub struct Found {
+    pub files: usize,
+    // JSON files that held no banks in any known shape.
+    pub skipped: Vec<PathBuf>,
+}
+
+fn json_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir).map_err(Error::file(dir))? {
+        let path = entry.map_err(Error::file(dir))?.path();
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            json_files(&path, files)?;
+        } else if name.ends_with(".json") && !NOT_BANKS.contains(&name.as_str()) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// The banks in a document: a map of banks keyed by code (banks.json and branch files of every
+// version so far), a single bank, or an array of banks (`export --format json`, in any naming).
+fn banks_in(document: Value) -> Option<Vec<Bank>> {
+    let document = snake_keys(document);
+    let banks = match document {
+        Value::Array(banks) => banks,
+        Value::Object(ref object) if object.contains_key("code") => vec![document],
+        Value::Object(object) => object.into_iter().map(|(_, bank)| bank).collect(),
+        _ => return None,
+    };
+    banks.into_iter().map(|bank| serde_json::from_value(bank).ok()).collect()
+}
+
+// Collects every bank under `old`, whatever layout and version wrote it. Where a bank turns up more
+// than once the copy with the most branches wins, so branch files beat the bare bank list.
+pub fn read(old: &Path) -> Result<(Vec<Bank>, Found), Error> {
+    let mut files = Vec::new();
+    json_files(old, &mut files)?;
+    files.sort();
+    let mut found = Found::default();
+    let mut banks: BTreeMap<String, Bank> = BTreeMap::new();
+    for path in files {
+        let file = File::open(&path).map_err(Error::file(&path))?;
+        let read = serde_json::from_reader(file).ok().and_then(banks_in);
+        match read {
+            Some(read) if !read.is_empty() => {
+                found.files += 1;
+                for mut bank in read {
+                    // Exports carry the aliases applied on load, which come from the alias tables.
+                    bank.aliases.clear();
+                    match banks.get(&bank.code.0) {
+                        Some(known) if known.branches.len() >= bank.branches.len() => {}
+                        _ => {
+                            banks.insert(bank.code.0.clone(), bank);
+                        }
+                    }
+                }
+            }
+            _ => found.skipped.push(path),
+        }
+    }
+    Ok((banks.into_values().collect(), found))
+}
+
+// The banks in a single JSON file in any of the shapes `read` accepts.
+pub fn read_file(path: &Path) -> Result<Vec<Bank>, Error> {
+    let file = File::open(path).map_err(Error::file(path))?;
+    let document = serde_json::from_reader(file).map_err(Error::invalid(path))?;
+    let mut banks = banks_in(document).ok_or_else(|| {
+        Error::SchemaViolations(vec![Violation {
+            pointer: String::new(),
+            message: "no banks in a known format".to_owned(),
+        }])
+    })?;
+    banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    Ok(banks)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn read_test() {
+        use serde_json::json;
+        use crate::migrate::read;
+        use crate::naming::{rename_keys, Naming};
+        use crate::{to_hashmap, Bank, Branch};
+
+        let old = std::env::temp_dir().join(format!("zngn-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(old.join("02")).unwrap();
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let list = to_hashmap(&[neko.clone(), inu.clone()]);
+        std::fs::write(old.join("banks.json"), serde_json::to_string(&list).unwrap()).unwrap();
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        std::fs::write(old.join("02").join("0222.json"), serde_json::to_string(&neko.to_hashmap()).unwrap()).unwrap();
+        let exported = rename_keys(serde_json::to_value(vec![&inu]).unwrap(), Naming::Camel);
+        std::fs::write(old.join("export.json"), exported.to_string()).unwrap();
+        std::fs::write(old.join("index.json"), json!({"0222": "02/0222.json"}).to_string()).unwrap();
+        std::fs::write(old.join("notes.json"), "[1, 2]").unwrap();
+
+        let (banks, found) = read(&old).unwrap();
+        assert_eq!(banks, vec![inu, neko]);
+        assert_eq!(found.files, 3);
+        assert_eq!(found.skipped, vec![old.join("notes.json")]);
+        std::fs::remove_dir_all(&old).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+
+use crate::client::Source;
+use crate::Error;
+
+// What the site answers for a search key nothing is listed under: the page with an empty table.
+const EMPTY_BANKS: &str = "<html><body><form><table class=\"j0\"><tbody></tbody></table></form></body></html>";
+const EMPTY_BRANCHES: &str = "<html><body><table><tbody></tbody></table></body></html>";
+
+// Search keys appear in file names as themselves, except 英, which is spelled out.
+fn search_key(name: &str) -> Option<char> {
+    match name {
+        "alphanumeric" => Some('英'),
+        _ => {
+            let mut chars = name.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+    }
+}
+
+// Pages recorded from zengin.ajtw.net: bank lists by search key, and branch lists by the bank's
+// search parameter and search key.
+#[derive(Debug, Clone, Default)]
+pub struct Recordings {
+    banks: HashMap<char, String>,
+    branches: HashMap<(String, char), String>,
+}
+
+impl Recordings {
+    // The pages the parser tests use: three banks under 英, and branches of the first of them.
+    pub fn bundled() -> Self {
+        let mut recordings = Self::default();
+        recordings
+            .banks
+            .insert('英', include_str!("../data/pages/ginkou_alphanumeric.html").to_owned());
+        recordings.branches.insert(
+            ("0033".to_owned(), '英'),
+            include_str!("../data/pages/shitenmeisai_alphanumeric.html").to_owned(),
+        );
+        recordings
+    }
+
+    // Pages saved in a directory as ginkou_<key>.html and shitenmeisai_<search param>_<key>.html,
+    // e.g. ginkou_あ.html or shitenmeisai_0033_alphanumeric.html. Other files are ignored.
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        let mut recordings = Self::default();
+        for entry in fs::read_dir(dir).map_err(Error::file(dir))? {
+            let path = entry.map_err(Error::file(dir))?.path();
+            let stem = match (path.file_stem().and_then(|stem| stem.to_str()), path.extension()) {
+                (Some(stem), Some(extension)) if extension == "html" => stem.to_owned(),
+                _ => continue,
+            };
+            if let Some(key) = stem.strip_prefix("ginkou_").and_then(search_key) {
+                recordings.banks.insert(key, fs::read_to_string(&path).map_err(Error::file(&path))?);
+            } else if let Some((param, key)) = stem
+                .strip_prefix("shitenmeisai_")
+                .and_then(|rest| rest.rsplit_once('_'))
+                .and_then(|(param, key)| Some((param.to_owned(), search_key(key)?)))
+            {
+                let page = fs::read_to_string(&path).map_err(Error::file(&path))?;
+                recordings.branches.insert((param, key), page);
+            }
+        }
+        Ok(recordings)
+    }
+
+    pub fn len(&self) -> usize {
+        self.banks.len() + self.branches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The site's answer to a form posted to `path`, as the client sends it.
+    pub fn respond(&self, path: &str, form: &HashMap<String, String>) -> Option<&str> {
+        let key = |field: &str| form.get(field).and_then(|value| value.chars().next());
+        let (site, path) = (Source::default(), path.trim_start_matches('/'));
+        if path == site.banks_path {
+            let key = key(&site.fields.bank_search_key)?;
+            Some(self.banks.get(&key).map_or(EMPTY_BANKS, String::as_str))
+        } else if path == site.branches_path {
+            let (param, key) = (form.get(&site.fields.branch_search_param)?, key(&site.fields.branch_search_key)?);
+            Some(self.branches.get(&(param.clone(), key)).map_or(EMPTY_BRANCHES, String::as_str))
+        } else {
+            None
+        }
+    }
+}
+
+async fn handle(recordings: &Recordings, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path().to_owned();
+    let form = if request.method() == Method::POST {
+        let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+        serde_urlencoded::from_bytes::<HashMap<String, String>>(&body).unwrap_or_default()
+    } else {
+        serde_urlencoded::from_str(request.uri().query().unwrap_or("")).unwrap_or_default()
+    };
+    match recordings.respond(&path, &form) {
+        Some(page) => {
+            let mut response = Response::new(Body::from(page.to_owned()));
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("text/html; charset=UTF-8"));
+            response
+        }
+        None => {
+            let mut response = Response::new(Body::from("not found"));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    }
+}
+
+// Serves the recorded pages at the site's paths, so a crawl pointed at `addr` runs end to end offline.
+pub async fn serve(addr: SocketAddr, recordings: Recordings) -> Result<(), Error> {
+    let server = Server::try_bind(&addr).map_err(Error::ServeFailed)?;
+    serve_on(server, recordings).await
+}
+
+async fn serve_on(server: hyper::server::Builder<hyper::server::conn::AddrIncoming>, recordings: Recordings) -> Result<(), Error> {
+    let recordings = Arc::new(recordings);
+    let make_service = make_service_fn(move |_| {
+        let recordings = recordings.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let recordings = recordings.clone();
+                async move { Ok::<_, Infallible>(handle(&recordings, request).await) }
+            }))
+        }
+    });
+    server.serve(make_service).await.map_err(Error::ServeFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn crawl_against_mock_test() {
+        use std::net::TcpListener;
+        use hyper::Server;
+        use crate::client::{Source, ZnginClient};
+        use crate::mock::{serve_on, Recordings};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_on(Server::from_tcp(listener).unwrap(), Recordings::bundled()));
+
+        let client = ZnginClient::builder().source(Source::at(&base)).build().unwrap();
+        let banks = client.fetch_banks('英').await.unwrap().items;
+        assert_eq!(banks.len(), 3);
+        let branches = client.fetch_branches(&banks[0], '英').await.unwrap().items;
+        assert_eq!(branches.iter().map(|branch| branch.code.as_str()).collect::<Vec<&str>>(), vec!["101", "102", "811"]);
+        assert!(client.fetch_banks('あ').await.unwrap().items.is_empty());
+    }
+}
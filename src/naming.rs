@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+// Key style of exported JSON documents. Field names are snake_case English everywhere else.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Naming {
+    #[default]
+    Snake,
+    Camel,
+    Japanese,
+}
+
+impl FromStr for Naming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snake" => Ok(Naming::Snake),
+            "camel" => Ok(Naming::Camel),
+            "ja" => Ok(Naming::Japanese),
+            _ => Err(format!("unknown naming: {}", s)),
+        }
+    }
+}
+
+fn camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+const JAPANESE: &[(&str, &str)] = &[
+    ("name", "名称"),
+    ("name_en", "英語名称"),
+    ("phonetic", "フリガナ"),
+    ("code", "コード"),
+    ("search_param", "検索キー"),
+    ("branches", "支店"),
+    ("aliases", "旧名称"),
+    ("until", "使用終了日"),
+    ("bank_code", "金融機関コード"),
+    ("bank_name", "金融機関名"),
+    ("branch_code", "支店コード"),
+    ("branch_name", "支店名"),
+];
+
+fn japanese(key: &str) -> String {
+    match JAPANESE.iter().find(|(snake, _)| *snake == key) {
+        Some((_, ja)) => (*ja).to_owned(),
+        None => key.to_owned(),
+    }
+}
+
+// Back to snake_case from any of the styles, e.g. for reading exports in again.
+fn snake_case(key: &str) -> String {
+    if let Some((snake, _)) = JAPANESE.iter().find(|(_, ja)| *ja == key) {
+        return (*snake).to_owned();
+    }
+    let mut snake = String::with_capacity(key.len() + 2);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn rename(key: &str, naming: Naming) -> String {
+    match naming {
+        Naming::Snake => key.to_owned(),
+        Naming::Camel => camel_case(key),
+        Naming::Japanese => japanese(key),
+    }
+}
+
+// Renames the keys of every object in `value`, however deeply nested.
+pub fn rename_keys(value: Value, naming: Naming) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (rename(&key, naming), rename_keys(value, naming)))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| rename_keys(item, naming)).collect()),
+        other => other,
+    }
+}
+
+// Undoes `rename_keys` whatever naming the document was written with.
+pub fn snake_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (snake_case(&key), snake_keys(value)))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(snake_keys).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn rename_keys_test() {
+        use serde_json::json;
+        use crate::naming::{rename_keys, snake_keys, Naming};
+
+        let value = json!([{"search_param": "x", "branches": [{"name": "本店"}]}]);
+        assert_eq!(
+            rename_keys(value.clone(), Naming::Camel),
+            json!([{"searchParam": "x", "branches": [{"name": "本店"}]}])
+        );
+        assert_eq!(
+            rename_keys(value.clone(), Naming::Japanese),
+            json!([{"検索キー": "x", "支店": [{"名称": "本店"}]}])
+        );
+        for naming in [Naming::Snake, Naming::Camel, Naming::Japanese] {
+            assert_eq!(snake_keys(rename_keys(value.clone(), naming)), value);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::Error;
+
+// Changes and failures listed in a chat message; the rest are only counted.
+const SHOWN: usize = 10;
+
+// Where a notification goes, told apart by the webhook URL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Slack,
+    Discord,
+    // Any other URL receives the `Summary` itself as JSON.
+    Webhook,
+}
+
+impl Target {
+    pub fn detect(url: &str) -> Self {
+        let host = url.split("://").nth(1).unwrap_or(url).split('/').next().unwrap_or_default();
+        match host {
+            "hooks.slack.com" => Target::Slack,
+            "discord.com" | "discordapp.com" | "ptb.discord.com" | "canary.discord.com" => Target::Discord,
+            _ => Target::Webhook,
+        }
+    }
+}
+
+// The outcome of a run, as reported to a team channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub title: String,
+    pub ok: bool,
+    // Label and value pairs such as ("banks", "1234").
+    pub counts: Vec<(String, String)>,
+    // What changed since the previous snapshot, one line each.
+    pub changes: Vec<String>,
+    pub failures: Vec<String>,
+}
+
+fn listed(lines: &[String], bullet: &str) -> String {
+    let mut text = lines
+        .iter()
+        .take(SHOWN)
+        .map(|line| format!("{}{}", bullet, line))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if lines.len() > SHOWN {
+        text.push_str(&format!("\n… and {} more", lines.len() - SHOWN));
+    }
+    text
+}
+
+impl Summary {
+    pub fn payload(&self, target: Target) -> Value {
+        match target {
+            Target::Slack => {
+                let counts = self
+                    .counts
+                    .iter()
+                    .map(|(label, value)| json!({"type": "mrkdwn", "text": format!("*{}*\n{}", label, value)}))
+                    .collect::<Vec<Value>>();
+                let mut blocks = vec![
+                    json!({"type": "header", "text": {"type": "plain_text", "text": self.title}}),
+                    json!({"type": "section", "fields": counts}),
+                ];
+                for (heading, lines) in [("Changes", &self.changes), ("Failures", &self.failures)] {
+                    if !lines.is_empty() {
+                        let text = format!("*{}*\n{}", heading, listed(lines, "• "));
+                        blocks.push(json!({"type": "section", "text": {"type": "mrkdwn", "text": text}}));
+                    }
+                }
+                json!({"text": self.title, "blocks": blocks})
+            }
+            Target::Discord => {
+                let mut fields = self
+                    .counts
+                    .iter()
+                    .map(|(label, value)| json!({"name": label, "value": value, "inline": true}))
+                    .collect::<Vec<Value>>();
+                for (heading, lines) in [("Changes", &self.changes), ("Failures", &self.failures)] {
+                    if !lines.is_empty() {
+                        fields.push(json!({"name": heading, "value": listed(lines, "- ")}));
+                    }
+                }
+                let color = if self.ok { 0x2eb67d } else { 0xe01e5a };
+                json!({"embeds": [{"title": self.title, "color": color, "fields": fields}]})
+            }
+            Target::Webhook => serde_json::to_value(self).unwrap_or(Value::Null),
+        }
+    }
+}
+
+pub async fn send(client: &Client, url: &str, summary: &Summary) -> Result<(), Error> {
+    let body = serde_json::to_vec(&summary.payload(Target::detect(url))).map_err(Error::SerializeFailed)?;
+    let fail = |source| Error::RemoteFailed {
+        url: url.to_owned(),
+        source,
+    };
+    client
+        .post(url)
+        .header("content-type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fail)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn payload_test() {
+        use crate::notify::{Summary, Target};
+
+        assert_eq!(Target::detect("https://hooks.slack.com/services/T0/B0/x"), Target::Slack);
+        assert_eq!(Target::detect("https://discord.com/api/webhooks/1/x"), Target::Discord);
+        assert_eq!(Target::detect("https://example.com/hooks/zngn"), Target::Webhook);
+
+        let summary = Summary {
+            title: "zngn crawl: done".to_owned(),
+            ok: true,
+            counts: vec![("banks".to_owned(), "2".to_owned())],
+            changes: (0..12).map(|i| format!("branch added 0001-{:03}", i)).collect(),
+            failures: Vec::new(),
+        };
+        let slack = summary.payload(Target::Slack);
+        assert_eq!(slack["blocks"][1]["fields"][0]["text"], "*banks*\n2");
+        assert_eq!(slack["blocks"].as_array().unwrap().len(), 3);
+        assert!(slack["blocks"][2]["text"]["text"].as_str().unwrap().ends_with("… and 2 more"));
+        let discord = summary.payload(Target::Discord);
+        assert_eq!(discord["embeds"][0]["fields"][1]["name"], "Changes");
+        assert_eq!(summary.payload(Target::Webhook)["counts"][0][1], "2");
+    }
+}
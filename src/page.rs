@@ -0,0 +1,50 @@
+// One window of a listing, along with how many items the whole listing has.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+    // Whether items past the end of this page were left out.
+    pub fn has_more(&self) -> bool {
+        self.offset + self.items.len() < self.total
+    }
+}
+
+// Keeps the items inside the window and only counts the rest, so a listing of every branch
+// never has to be collected in full. No limit means everything from `offset` on.
+pub fn paginate<T>(items: impl IntoIterator<Item = T>, offset: usize, limit: Option<usize>) -> Page<T> {
+    let mut total = 0;
+    let mut kept = Vec::new();
+    for item in items {
+        if total >= offset && limit.is_none_or(|limit| kept.len() < limit) {
+            kept.push(item);
+        }
+        total += 1;
+    }
+    Page {
+        total,
+        offset,
+        items: kept,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn paginate_test() {
+        use crate::page::paginate;
+
+        let page = paginate(1..=10, 3, Some(4));
+        assert_eq!((page.total, page.items.clone()), (10, vec![4, 5, 6, 7]));
+        assert!(page.has_more());
+
+        let page = paginate(1..=10, 8, None);
+        assert_eq!(page.items, vec![9, 10]);
+        assert!(!page.has_more());
+
+        assert_eq!(paginate(1..=3, 5, Some(2)).items, Vec::<i32>::new());
+    }
+}
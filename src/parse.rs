@@ -0,0 +1,329 @@
+use scraper::{ElementRef, Html};
+
+use crate::markup::{Markup, Selector};
+use crate::{Bank, Branch, Parsed};
+
+// What the site shows in place of rows when nothing is listed under a search key.
+const NO_DATA: &str = "該当するデータはありません";
+
+// The element cells of a table row, indexed as the site lays them out.
+struct Row<'a> {
+    cells: Vec<ElementRef<'a>>,
+}
+
+impl<'a> Row<'a> {
+    // The row, or the reason it can't be used. `columns` are the indexes read, the code's first.
+    fn new(element: ElementRef<'a>, columns: &[usize]) -> Result<Self, String> {
+        let cells = element.child_elements().collect::<Vec<ElementRef>>();
+        let expected = columns.iter().max().map_or(0, |last| last + 1);
+        if cells.len() < expected {
+            return Err(format!("expected {} cells, found {}", expected, cells.len()));
+        }
+        let row = Self { cells };
+        if row.text(columns[0]).trim().is_empty() {
+            return Err("empty code".to_owned());
+        }
+        Ok(row)
+    }
+
+    fn text(&self, column: usize) -> String {
+        self.cells[column].text().collect()
+    }
+
+    fn cell(&self, column: usize) -> ElementRef<'a> {
+        self.cells[column]
+    }
+
+    // Text of an optional column, None when the row is too short for it or the cell is blank.
+    fn optional_text(&self, column: Option<usize>) -> Option<String> {
+        let text = self.cells.get(column?)?.text().collect::<String>();
+        Some(text.trim().to_owned()).filter(|text| !text.is_empty())
+    }
+
+    fn attr(&self, selector: &Selector, attribute: &str) -> Option<String> {
+        self.cells
+            .iter()
+            .find_map(|cell| cell.select(selector.as_scraper()).next()?.value().attr(attribute))
+            .map(str::to_owned)
+    }
+}
+
+// How one kind of result table is read: where its rows are, which cells a row needs, and how a row
+// becomes an item.
+trait RowExtractor {
+    type Item;
+    // Digits in a well formed code.
+    const CODE_LENGTH: usize;
+
+    fn rows<'m>(&self, markup: &'m Markup) -> &'m Selector;
+
+    // Indexes of the cells read, the code's first.
+    fn columns(&self, markup: &Markup) -> Vec<usize>;
+
+    fn extract(&self, row: &Row, markup: &Markup) -> Result<Self::Item, String>;
+
+    // Code, name and reading, for the checks every kept row goes through.
+    fn fields(item: &Self::Item) -> (&str, &str, &str);
+}
+
+// Links and extra columns are only read when enriching, so a plain crawl saves what it always did.
+struct BankRows {
+    enrich: bool,
+}
+
+impl RowExtractor for BankRows {
+    type Item = Bank;
+    const CODE_LENGTH: usize = 4;
+
+    fn rows<'m>(&self, markup: &'m Markup) -> &'m Selector {
+        &markup.bank_rows
+    }
+
+    fn columns(&self, markup: &Markup) -> Vec<usize> {
+        let columns = &markup.bank_columns;
+        vec![columns.code, columns.name, columns.phonetic, columns.search_param]
+    }
+
+    fn extract(&self, row: &Row, markup: &Markup) -> Result<Bank, String> {
+        let columns = &markup.bank_columns;
+        let search_param = row
+            .cell(columns.search_param)
+            .select(markup.search_param.as_scraper())
+            .next()
+            .and_then(|element| element.value().attr(&markup.search_param_attribute))
+            .ok_or_else(|| "missing search parameter".to_owned())?;
+        let mut bank = Bank::new(
+            row.text(columns.name),
+            row.text(columns.phonetic),
+            row.text(columns.code),
+            search_param.to_owned(),
+        );
+        if self.enrich {
+            bank.detail_url = row.attr(&markup.bank_link, "href");
+            bank.address = row.optional_text(columns.address);
+            bank.website = row.optional_text(columns.website);
+        }
+        Ok(bank)
+    }
+
+    fn fields(bank: &Bank) -> (&str, &str, &str) {
+        (&bank.code.0, &bank.name, &bank.phonetic)
+    }
+}
+
+struct BranchRows;
+
+impl RowExtractor for BranchRows {
+    type Item = Branch;
+    const CODE_LENGTH: usize = 3;
+
+    fn rows<'m>(&self, markup: &'m Markup) -> &'m Selector {
+        &markup.branch_rows
+    }
+
+    fn columns(&self, markup: &Markup) -> Vec<usize> {
+        let columns = &markup.branch_columns;
+        vec![columns.code, columns.name, columns.phonetic]
+    }
+
+    fn extract(&self, row: &Row, markup: &Markup) -> Result<Branch, String> {
+        let columns = &markup.branch_columns;
+        Ok(Branch::new(row.text(columns.name), row.text(columns.phonetic), row.text(columns.code)))
+    }
+
+    fn fields(branch: &Branch) -> (&str, &str, &str) {
+        (&branch.code, &branch.name, &branch.phonetic)
+    }
+}
+
+// A row holding only the no data notice, or no text at all, isn't a row of data.
+fn is_data(row: &ElementRef) -> bool {
+    row.text().next().is_some_and(|text| text != NO_DATA)
+}
+
+// Rows are numbered from 1 among the data rows, as parse warnings report them.
+fn parse_rows<E: RowExtractor>(html: &str, markup: &Markup, extractor: E) -> Parsed<E::Item> {
+    let document = Html::parse_document(html);
+    let mut parsed = Parsed::new();
+    let columns = extractor.columns(markup);
+    let rows = document.select(extractor.rows(markup).as_scraper()).filter(is_data);
+    for (row, element) in rows.enumerate() {
+        match Row::new(element, &columns).and_then(|cells| extractor.extract(&cells, markup)) {
+            Ok(item) => {
+                let (code, name, phonetic) = E::fields(&item);
+                parsed.inspect(row + 1, code, E::CODE_LENGTH, name, phonetic);
+                parsed.items.push(item);
+            }
+            Err(reason) => parsed.skip(row + 1, reason),
+        }
+    }
+    parsed
+}
+
+pub(crate) fn parse_banks(html: String, markup: &Markup, enrich: bool) -> Parsed<Bank> {
+    parse_rows(&html, markup, BankRows { enrich })
+}
+
+pub(crate) fn parse_branches(html: String, markup: &Markup) -> Parsed<Branch> {
+    parse_rows(&html, markup, BranchRows)
+}
+
+// What a bank's own page adds to the bank list.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Detail {
+    pub address: Option<String>,
+    pub website: Option<String>,
+}
+
+pub(crate) fn parse_detail(html: String, markup: &Markup) -> Detail {
+    let document = Html::parse_document(&html);
+    let first = |selector: &Selector| document.select(selector.as_scraper()).next();
+    let address = first(&markup.detail_address)
+        .map(|element| element.text().map(str::trim).filter(|text| !text.is_empty()).collect::<Vec<&str>>().join(" "))
+        .filter(|address| !address.is_empty());
+    let website = first(&markup.detail_website)
+        .and_then(|element| element.value().attr("href"))
+        .map(str::to_owned);
+    Detail { address, website }
+}
+
+// Name and value of the page's hidden form fields, of only the named ones unless `names` is empty.
+pub(crate) fn parse_hidden_fields(html: &str, names: &[String]) -> Vec<(String, String)> {
+    let document = Html::parse_document(html);
+    let hidden = scraper::Selector::parse("input[type=hidden][name]").expect("hidden input selector");
+    document
+        .select(&hidden)
+        .filter_map(|input| {
+            let name = input.value().attr("name")?;
+            let value = input.value().attr("value").unwrap_or("");
+            Some((name.to_owned(), value.to_owned()))
+        })
+        .filter(|(name, _)| names.is_empty() || names.contains(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_banks_skips_malformed_rows_test() {
+        use crate::markup::Markup;
+        use crate::parse::parse_banks;
+        use crate::{ParseWarning, WarningKind};
+
+        let html = r#"<table class="j0"><tbody>
+            <tr><td>ねこ銀行</td><td>ﾈｺ</td><td>0222</td><td><button value="0x222">支店</button></td></tr>
+            <tr><td>いぬ銀行</td><td>ｲﾇ</td></tr>
+            <tr><td>とり銀行</td><td>ﾄﾘ</td><td> </td><td><button value="0x333">支店</button></td></tr>
+            <tr><td>うし銀行</td><td>ｳｼ</td><td>0444</td><td></td></tr>
+            <tr><td>うま銀行...</td><td>ｳﾏぎんこう</td><td>555</td><td><button value="0x555">支店</button></td></tr>
+        </tbody></table>"#;
+        let parsed = parse_banks(html.to_owned(), &Markup::default(), false);
+        assert_eq!(parsed.items.len(), 2);
+        assert_eq!(parsed.items[0].search_param, "0x222");
+        let kinds = parsed
+            .warnings
+            .iter()
+            .map(|warning| (warning.row, warning.kind))
+            .collect::<Vec<(usize, WarningKind)>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (2, WarningKind::MalformedRow),
+                (3, WarningKind::MalformedRow),
+                (4, WarningKind::MalformedRow),
+                (5, WarningKind::SuspiciousRow),
+                (5, WarningKind::OddCharacters),
+                (5, WarningKind::TruncatedName),
+            ]
+        );
+        assert_eq!(
+            parsed.warnings[0],
+            ParseWarning {
+                row: 2,
+                kind: WarningKind::MalformedRow,
+                code: None,
+                reason: "expected 4 cells, found 2".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn alphanumeric_initials_test() {
+        use crate::markup::Markup;
+        use crate::all_search_keys;
+        use crate::parse::{parse_banks, parse_branches};
+
+        assert!(all_search_keys().any(|key| key == '英'));
+
+        let banks = parse_banks(include_str!("../data/pages/ginkou_alphanumeric.html").to_owned(), &Markup::default(), false);
+        assert_eq!(banks.warnings, vec![]);
+        let names = banks.items.iter().map(|bank| bank.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["ＰａｙＰａｙ銀行", "ＳＢＩ新生銀行", "ＧＭＯあおぞらネット銀行"]);
+        assert_eq!(banks.items[1].phonetic, "SBIｼﾝｾｲ");
+
+        let branches = parse_branches(include_str!("../data/pages/shitenmeisai_alphanumeric.html").to_owned(), &Markup::default());
+        assert_eq!(branches.warnings, vec![]);
+        let codes = branches.items.iter().map(|branch| branch.code.as_str()).collect::<Vec<&str>>();
+        assert_eq!(codes, vec!["101", "102", "811"]);
+        assert_eq!(&*branches.items[2].phonetic, "ATM1");
+    }
+
+    #[test]
+    fn enrich_test() {
+        use crate::markup::Markup;
+        use crate::parse::{parse_banks, parse_detail, Detail};
+
+        let html = r#"<table class="j0"><tbody>
+            <tr><td><a href="bank.php?c=0222">ねこ銀行</a></td><td>ﾈｺ</td><td>0222</td><td><button value="0x222">支店</button></td><td> 東京都 </td></tr>
+            <tr><td>いぬ銀行</td><td>ｲﾇ</td><td>0111</td><td><button value="0x111">支店</button></td></tr>
+        </tbody></table>"#;
+        let mut markup = Markup::default();
+        markup.bank_columns.address = Some(4);
+        let banks = parse_banks(html.to_owned(), &markup, true).items;
+        assert_eq!(banks[0].detail_url.as_deref(), Some("bank.php?c=0222"));
+        assert_eq!(banks[0].address.as_deref(), Some("東京都"));
+        assert_eq!((banks[1].detail_url.as_ref(), banks[1].address.as_ref()), (None, None));
+        assert_eq!(parse_banks(html.to_owned(), &markup, false).items[0].detail_url, None);
+
+        let page = r#"<h1>ねこ銀行</h1><address>東京都千代田区<br>
+            丸の内 1-1</address><p><a class="website" href="https://neko.example.com/">ウェブサイト</a></p>"#;
+        let detail = parse_detail(page.to_owned(), &markup);
+        assert_eq!(
+            detail,
+            Detail {
+                address: Some("東京都千代田区 丸の内 1-1".to_owned()),
+                website: Some("https://neko.example.com/".to_owned()),
+            }
+        );
+        assert_eq!(parse_detail("<p>準備中</p>".to_owned(), &markup), Detail::default());
+    }
+
+    // The pages in data/pages parse exactly as recorded in data/pages/golden, which was written by the
+    // parser before this one, so a change in parsing shows up as a diff there.
+    #[test]
+    fn golden_test() {
+        use serde_json::Value;
+        use crate::markup::Markup;
+        use crate::parse::{parse_banks, parse_branches};
+
+        let golden = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+        let banks = |html: &str| serde_json::to_value(parse_banks(html.to_owned(), &Markup::default(), false)).unwrap();
+        let branches = |html: &str| serde_json::to_value(parse_branches(html.to_owned(), &Markup::default())).unwrap();
+        assert_eq!(
+            banks(include_str!("../data/pages/ginkou_alphanumeric.html")),
+            golden(include_str!("../data/pages/golden/ginkou_alphanumeric.json"))
+        );
+        assert_eq!(
+            banks(include_str!("../data/pages/ginkou_malformed.html")),
+            golden(include_str!("../data/pages/golden/ginkou_malformed.json"))
+        );
+        assert_eq!(
+            branches(include_str!("../data/pages/shitenmeisai_alphanumeric.html")),
+            golden(include_str!("../data/pages/golden/shitenmeisai_alphanumeric.json"))
+        );
+        assert_eq!(
+            branches(include_str!("../data/pages/shitenmeisai_empty.html")),
+            golden(include_str!("../data/pages/golden/shitenmeisai_empty.json"))
+        );
+    }
+}
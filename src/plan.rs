@@ -0,0 +1,353 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::layout::Layout;
+use crate::shard::{self, Shard};
+use crate::{load_banks, load_branch_file, save_banks, save_index, Bank, BankCode, Error};
+
+// One piece of work of a crawl. A bank's branches are fetched under every search key but saved
+// together, so they are done together too.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Item {
+    // The bank list under one search key.
+    Banks { search_key: char },
+    // Every branch of one bank.
+    Branches { bank_code: BankCode },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Done {
+    #[serde(flatten)]
+    item: Item,
+    done_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+// Done and planned counts of one kind of item.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+// Everything a crawl set out to fetch, written before it fetches anything, and what of it is done.
+// The items are in .plan.json and finished ones are appended to .plan.log as they are saved, so a
+// crawl cut off at any point resumes, fills in or reports progress from the same record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub created_at: u64,
+    #[serde(default)]
+    pub shard: Option<Shard>,
+    pub items: Vec<Item>,
+    // When each item was done, from the log.
+    #[serde(skip)]
+    pub done: HashMap<Item, u64>,
+}
+
+impl Plan {
+    // Starts a plan with the bank list under each search key, replacing any earlier one.
+    pub fn start(layout: &Layout, search_keys: impl Iterator<Item = char>, shard: Option<Shard>) -> Result<Self, Error> {
+        let plan = Self {
+            created_at: now(),
+            shard,
+            items: search_keys.map(|search_key| Item::Banks { search_key }).collect(),
+            done: HashMap::new(),
+        };
+        plan.save(layout)?;
+        match fs::remove_file(layout.plan_log_file()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::FileFailed { path: layout.plan_log_file(), source: e }),
+            _ => Ok(plan),
+        }
+    }
+
+    // The plan of the last crawl into the output directory, if it wrote one.
+    pub fn load(layout: &Layout) -> Result<Option<Self>, Error> {
+        let file = match File::open(layout.plan_file()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::FileFailed { path: layout.plan_file(), source: e }),
+        };
+        let mut plan: Self = serde_json::from_reader(file).map_err(Error::invalid(layout.plan_file()))?;
+        let log = match File::open(layout.plan_log_file()) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Some(plan)),
+            Err(e) => return Err(Error::FileFailed { path: layout.plan_log_file(), source: e }),
+        };
+        for line in BufReader::new(log).lines() {
+            let line = line.map_err(Error::file(layout.plan_log_file()))?;
+            // A line cut short by a crash is an item not known to be done.
+            if let Ok(done) = serde_json::from_str::<Done>(&line) {
+                plan.done.insert(done.item, done.done_at);
+            }
+        }
+        Ok(Some(plan))
+    }
+
+    fn save(&self, layout: &Layout) -> Result<(), Error> {
+        let json = serde_json::to_vec(self).map_err(Error::SerializeFailed)?;
+        let staging = layout.plan_file().with_extension("json.tmp");
+        fs::write(&staging, json).map_err(Error::file(&staging))?;
+        fs::rename(&staging, layout.plan_file()).map_err(Error::file(layout.plan_file()))
+    }
+
+    // Adds the branches of `banks` once the bank list is saved, which finishes the bank list items
+    // except those of the search keys in `failed`, whose requests are queued.
+    pub async fn listed(&mut self, layout: &Layout, banks: &[Bank], failed: &HashSet<char>) -> Result<(), Error> {
+        self.items.retain(|item| matches!(item, Item::Banks { .. }));
+        self.items.extend(banks.iter().map(|bank| Item::Branches { bank_code: bank.code.clone() }));
+        self.save(layout)?;
+        let lists = self
+            .items
+            .iter()
+            .filter(|item| matches!(item, Item::Banks { search_key } if !failed.contains(search_key)))
+            .cloned()
+            .collect::<Vec<Item>>();
+        for item in lists {
+            record(layout, &item).await?;
+            self.done.insert(item, now());
+        }
+        Ok(())
+    }
+
+    pub fn is_done(&self, item: &Item) -> bool {
+        self.done.contains_key(item)
+    }
+
+    // Whether the bank list was saved, so the banks whose branches are planned are known. Search keys
+    // whose bank list failed are left to the retry queue and don't hold the plan up.
+    pub fn is_listed(&self) -> bool {
+        self.items.iter().any(|item| matches!(item, Item::Branches { .. }))
+    }
+
+    // Banks whose branches are planned but not done, in plan order.
+    pub fn pending_banks(&self) -> Vec<&BankCode> {
+        self.items
+            .iter()
+            .filter(|item| !self.is_done(item))
+            .filter_map(|item| match item {
+                Item::Branches { bank_code } => Some(bank_code),
+                Item::Banks { .. } => None,
+            })
+            .collect()
+    }
+
+    // Progress of the bank list and of the banks' branches.
+    pub fn progress(&self) -> (Progress, Progress) {
+        let mut lists = Progress::default();
+        let mut banks = Progress::default();
+        for item in &self.items {
+            let progress = match item {
+                Item::Banks { .. } => &mut lists,
+                Item::Branches { .. } => &mut banks,
+            };
+            progress.total += 1;
+            if self.is_done(item) {
+                progress.done += 1;
+            }
+        }
+        (lists, banks)
+    }
+}
+
+// What a crawl fetches is kept in .staged, the bank list as well as each bank's branch file and done
+// marker, until the branches found pass the anomaly checks, so a crawl that fails them leaves the
+// output directory as it was.
+pub fn staged(layout: &Layout) -> Layout {
+    layout.relocated(layout.staged_dir())
+}
+
+// Stages the bank list, its index and the shard it covers, dropping anything an earlier crawl staged
+// for another plan.
+pub fn stage(layout: &Layout, banks: &[Bank], shard: Option<Shard>) -> Result<(), Error> {
+    let staged = staged(layout);
+    match fs::remove_dir_all(staged.out()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(Error::FileFailed { path: staged.out().to_path_buf(), source: e }),
+        _ => {}
+    }
+    fs::create_dir_all(staged.out()).map_err(Error::file(staged.out()))?;
+    save_banks(banks, &staged)?;
+    save_index(banks, &staged)?;
+    shard::save(&staged, shard)
+}
+
+// Where the bank list the plan was made from is: staged until it is committed.
+pub fn list_layout(layout: &Layout) -> Layout {
+    let staged = staged(layout);
+    if staged.banks_file().exists() {
+        staged
+    } else {
+        layout.clone()
+    }
+}
+
+// The bank list the plan was made from.
+pub fn planned_banks(layout: &Layout) -> Result<HashMap<BankCode, Bank>, Error> {
+    load_banks(&list_layout(layout))
+}
+
+// Where the branch file of `bank` is kept: staged while a crawl that fetched it is not committed.
+pub fn branch_layout(layout: &Layout, bank: &Bank) -> Layout {
+    let staged = staged(layout);
+    if staged.branch_file(bank).exists() {
+        staged
+    } else {
+        layout.clone()
+    }
+}
+
+// The banks whose branches are staged, as staged, to check against the saved ones before committing.
+pub fn staged_branches(layout: &Layout) -> Result<Vec<Bank>, Error> {
+    let staged = staged(layout);
+    planned_banks(layout)?
+        .into_values()
+        .filter(|bank| staged.branch_file(bank).exists())
+        .map(|bank| load_branch_file(&staged, &bank))
+        .collect()
+}
+
+// Moves everything staged into the output directory. Returns whether anything was. Without
+// `with_bank_list`, the bank list, its index and shard stay staged, for a bank list that is missing
+// the banks of search keys whose requests failed, until a retry fills them in.
+pub fn commit(layout: &Layout, with_bank_list: bool) -> Result<bool, Error> {
+    let staged = staged(layout);
+    if !staged.out().exists() {
+        return Ok(false);
+    }
+    let listed = with_bank_list && staged.banks_file().exists();
+    if listed {
+        shard::save(layout, shard::load(&staged)?)?;
+    }
+    let mut skip = vec![staged.banks_file(), staged.shard_file()];
+    if !with_bank_list {
+        skip.push(staged.index_file());
+    }
+    move_into(staged.out(), layout.out(), &skip)?;
+    if !with_bank_list {
+        return Ok(true);
+    }
+    // The bank list goes last, so a staged one is there until everything else is in place.
+    if listed {
+        fs::rename(staged.banks_file(), layout.banks_file()).map_err(Error::file(layout.banks_file()))?;
+    }
+    let _ = fs::remove_dir_all(staged.out());
+    Ok(true)
+}
+
+// Moves the files under `from` to the same place under `to`, except those in `skip`.
+fn move_into(from: &Path, to: &Path, skip: &[PathBuf]) -> Result<(), Error> {
+    for entry in fs::read_dir(from).map_err(Error::file(from))? {
+        let source = entry.map_err(Error::file(from))?.path();
+        if skip.contains(&source) {
+            continue;
+        }
+        let target = to.join(source.file_name().unwrap_or_default());
+        if source.is_dir() {
+            fs::create_dir_all(&target).map_err(Error::file(&target))?;
+            move_into(&source, &target, skip)?;
+        } else {
+            fs::rename(&source, &target).map_err(Error::file(&target))?;
+        }
+    }
+    Ok(())
+}
+
+// Records `item` as done in the plan of the output directory, if it has one.
+pub async fn record(layout: &Layout, item: &Item) -> Result<(), Error> {
+    if !layout.plan_file().exists() {
+        return Ok(());
+    }
+    let mut line = serde_json::to_string(&Done { item: item.clone(), done_at: now() }).map_err(Error::SerializeFailed)?;
+    line.push('\n');
+    let mut log = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(layout.plan_log_file())
+        .await
+        .map_err(Error::file(layout.plan_log_file()))?;
+    // One write per line, so lines from banks saved at the same time don't interleave, flushed as
+    // tokio finishes writes in the background otherwise.
+    log.write_all(line.as_bytes()).await.map_err(Error::file(layout.plan_log_file()))?;
+    log.flush().await.map_err(Error::file(layout.plan_log_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn plan_test() {
+        use std::fs::{self, OpenOptions};
+        use std::io::Write;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::plan::{commit, planned_banks, record, stage, staged, staged_branches, Item, Plan, Progress};
+        use crate::shard::{self, Shard};
+        use crate::{Bank, BankCode, Error};
+
+        let dir = std::env::temp_dir().join(format!("zngn-plan-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let bank = |code: &str| Bank::new(format!("銀行{}", code), "ｷﾞﾝｺｳ".to_owned(), code.to_owned(), String::new());
+        let branches = |code: &str| Item::Branches { bank_code: BankCode(code.to_owned()) };
+
+        assert_eq!(Plan::load(&layout).unwrap(), None);
+        let shard = Some(Shard { index: 1, count: 2 });
+        let mut plan = Plan::start(&layout, "あい".chars(), shard).unwrap();
+        assert!(!Plan::load(&layout).unwrap().unwrap().is_listed());
+        plan.listed(&layout, &[bank("0001"), bank("0005"), bank("0009")], &['い'].into()).await.unwrap();
+        record(&layout, &branches("0005")).await.unwrap();
+        // A crash in the middle of a line leaves it unreadable, and the item pending.
+        let mut log = OpenOptions::new().append(true).open(layout.plan_log_file()).unwrap();
+        log.write_all(br#"{"kind":"branches","bank_co"#).unwrap();
+
+        let loaded = Plan::load(&layout).unwrap().unwrap();
+        assert!(loaded.is_listed());
+        assert_eq!(loaded.shard, shard);
+        assert_eq!(loaded.pending_banks(), [&BankCode("0001".to_owned()), &BankCode("0009".to_owned())]);
+        // The bank list under い failed, so it is not done.
+        assert!(loaded.is_done(&Item::Banks { search_key: 'あ' }) && !loaded.is_done(&Item::Banks { search_key: 'い' }));
+        assert_eq!(loaded.progress(), (Progress { done: 1, total: 2 }, Progress { done: 1, total: 3 }));
+
+        // A new plan starts from nothing done.
+        Plan::start(&layout, "あ".chars(), None).unwrap();
+        let restarted = Plan::load(&layout).unwrap().unwrap();
+        assert!(restarted.done.is_empty() && !restarted.is_listed());
+
+        // A staged bank list is what the plan goes by, but only replaces banks.json once committed,
+        // along with the branch files staged for it.
+        stage(&layout, &[bank("0001"), bank("0005")], shard).unwrap();
+        assert_eq!(planned_banks(&layout).unwrap().len(), 2);
+        assert!(!layout.banks_file().exists());
+        bank("0005").save_as_file(&staged(&layout)).await.unwrap();
+        assert_eq!(staged_branches(&layout).unwrap().len(), 1);
+        // Held back, the bank list stays staged while the branch files are moved.
+        assert!(commit(&layout, false).unwrap());
+        assert!(!layout.banks_file().exists() && layout.branch_file(&bank("0005")).exists());
+        assert!(staged_branches(&layout).unwrap().is_empty());
+        assert!(commit(&layout, true).unwrap());
+        assert_eq!(planned_banks(&layout).unwrap().len(), 2);
+        assert!(layout.banks_file().exists() && layout.index_file().exists() && !layout.staged_dir().exists());
+        assert!(layout.branch_file(&bank("0005")).exists());
+        // Staging again drops what was staged before.
+        stage(&layout, &[bank("0001")], None).unwrap();
+        bank("0001").save_as_file(&staged(&layout)).await.unwrap();
+        stage(&layout, &[bank("0001")], shard).unwrap();
+        assert!(staged_branches(&layout).unwrap().is_empty());
+        assert!(commit(&layout, true).unwrap());
+        assert_eq!(shard::load(&layout).unwrap(), shard);
+        assert!(!commit(&layout, true).unwrap());
+
+        // A broken plan is reported as that file, not as the bank list.
+        fs::write(layout.plan_file(), "{").unwrap();
+        assert!(matches!(Plan::load(&layout), Err(Error::InvalidFile { path, .. }) if path == layout.plan_file()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
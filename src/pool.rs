@@ -0,0 +1,26 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use futures::channel::oneshot;
+use rayon::{ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::Error;
+
+pub fn configure(num_threads: usize) -> Result<(), ThreadPoolBuildError> {
+    ThreadPoolBuilder::new().num_threads(num_threads).build_global()
+}
+
+// Run a CPU-bound parser on the rayon pool so it never blocks the runtime driving the requests.
+pub async fn parse_in_pool<T, F>(html: String, parse: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce(String) -> T + Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    rayon::spawn(move || {
+        let _ = sender.send(catch_unwind(AssertUnwindSafe(|| parse(html))));
+    });
+    match receiver.await {
+        Ok(Ok(parsed)) => Ok(parsed),
+        _ => Err(Error::ParseFailed),
+    }
+}
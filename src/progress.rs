@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::{Bank, BankCode, Error, ParseWarning};
+
+// Hooks for following a crawl as it runs. Every method defaults to doing nothing,
+// so implementors only override the events they care about.
+pub trait ProgressObserver: Send + Sync {
+    fn bank_started(&self, _bank: &Bank, _position: usize, _total: usize) {}
+
+    fn bank_finished(&self, _bank: &Bank, _position: usize, _total: usize) {}
+
+    fn request_failed(&self, _search_key: char, _error: &Error) {}
+
+    // A request under `search_key` succeeded with `count` rows: banks, or the branches of `bank_code`.
+    fn key_fetched(&self, _search_key: char, _bank_code: Option<&BankCode>, _count: usize) {}
+
+    fn parse_warning(&self, _search_key: char, _bank_code: Option<&BankCode>, _warning: &ParseWarning) {}
+
+    fn crawl_finished(&self, _completed: usize, _total: usize) {}
+}
+
+pub struct NoProgress;
+
+impl ProgressObserver for NoProgress {}
+
+// Fans every event out to each observer in turn.
+impl ProgressObserver for Vec<Arc<dyn ProgressObserver>> {
+    fn bank_started(&self, bank: &Bank, position: usize, total: usize) {
+        for observer in self {
+            observer.bank_started(bank, position, total);
+        }
+    }
+
+    fn bank_finished(&self, bank: &Bank, position: usize, total: usize) {
+        for observer in self {
+            observer.bank_finished(bank, position, total);
+        }
+    }
+
+    fn request_failed(&self, search_key: char, error: &Error) {
+        for observer in self {
+            observer.request_failed(search_key, error);
+        }
+    }
+
+    fn key_fetched(&self, search_key: char, bank_code: Option<&BankCode>, count: usize) {
+        for observer in self {
+            observer.key_fetched(search_key, bank_code, count);
+        }
+    }
+
+    fn parse_warning(&self, search_key: char, bank_code: Option<&BankCode>, warning: &ParseWarning) {
+        for observer in self {
+            observer.parse_warning(search_key, bank_code, warning);
+        }
+    }
+
+    fn crawl_finished(&self, completed: usize, total: usize) {
+        for observer in self {
+            observer.crawl_finished(completed, total);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrawlEvent {
+    BankStarted { code: BankCode, position: usize, total: usize },
+    BankFinished { code: BankCode, branches: usize, position: usize, total: usize },
+    RequestFailed { search_key: char, error: String },
+    KeyFetched { search_key: char, bank_code: Option<BankCode>, count: usize },
+    ParseWarning { search_key: char, bank_code: Option<BankCode>, warning: ParseWarning },
+    CrawlFinished { completed: usize, total: usize },
+}
+
+// Forwards every progress callback as a `CrawlEvent` to the receiver handed out by `new`.
+// Events sent after the receiver is dropped are discarded.
+pub struct ChannelObserver {
+    sender: UnboundedSender<CrawlEvent>,
+}
+
+impl ChannelObserver {
+    pub fn new() -> (Self, UnboundedReceiver<CrawlEvent>) {
+        let (sender, receiver) = unbounded_channel();
+        (Self { sender }, receiver)
+    }
+
+    fn send(&self, event: CrawlEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl ProgressObserver for ChannelObserver {
+    fn bank_started(&self, bank: &Bank, position: usize, total: usize) {
+        self.send(CrawlEvent::BankStarted {
+            code: bank.code.clone(),
+            position,
+            total,
+        });
+    }
+
+    fn bank_finished(&self, bank: &Bank, position: usize, total: usize) {
+        self.send(CrawlEvent::BankFinished {
+            code: bank.code.clone(),
+            branches: bank.branches.len(),
+            position,
+            total,
+        });
+    }
+
+    fn request_failed(&self, search_key: char, error: &Error) {
+        self.send(CrawlEvent::RequestFailed {
+            search_key,
+            error: error.to_string(),
+        });
+    }
+
+    fn key_fetched(&self, search_key: char, bank_code: Option<&BankCode>, count: usize) {
+        self.send(CrawlEvent::KeyFetched {
+            search_key,
+            bank_code: bank_code.cloned(),
+            count,
+        });
+    }
+
+    fn parse_warning(&self, search_key: char, bank_code: Option<&BankCode>, warning: &ParseWarning) {
+        self.send(CrawlEvent::ParseWarning {
+            search_key,
+            bank_code: bank_code.cloned(),
+            warning: warning.clone(),
+        });
+    }
+
+    fn crawl_finished(&self, completed: usize, total: usize) {
+        self.send(CrawlEvent::CrawlFinished { completed, total });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn channel_observer_test() {
+        use crate::progress::{ChannelObserver, CrawlEvent, ProgressObserver};
+        use crate::Bank;
+
+        let (observer, mut receiver) = ChannelObserver::new();
+        let bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        observer.bank_started(&bank, 0, 1);
+        observer.crawl_finished(1, 1);
+        drop(observer);
+
+        assert_eq!(
+            receiver.recv().await,
+            Some(CrawlEvent::BankStarted { code: bank.code.clone(), position: 0, total: 1 })
+        );
+        assert_eq!(receiver.recv().await, Some(CrawlEvent::CrawlFinished { completed: 1, total: 1 }));
+        assert_eq!(receiver.recv().await, None);
+    }
+}
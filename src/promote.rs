@@ -0,0 +1,236 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anomaly::{self, Counted};
+use crate::backup;
+use crate::dataset::Dataset;
+use crate::layout::Layout;
+use crate::lock;
+use crate::manifest::{Manifest, Mismatch, Problem};
+use crate::signing;
+use crate::Error;
+
+// What a snapshot has to pass before it is served: no crawl still writing it, a valid signature when
+// a public key is given, checksums matching its manifest, and no large drop in banks or branches
+// against the snapshot it replaces.
+#[derive(Debug, Clone)]
+pub struct Gates {
+    pub max_drop: f64,
+    pub public_key: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Promotion {
+    pub snapshot: PathBuf,
+    pub promoted_at: u64,
+}
+
+// Promotions are recorded next to the pointer, as <pointer>.history.json, oldest first.
+fn history_file(pointer: &Path) -> PathBuf {
+    let name = pointer.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    pointer.with_file_name(format!("{}.history.json", name))
+}
+
+pub fn history(pointer: &Path) -> Result<Vec<Promotion>, Error> {
+    let path = history_file(pointer);
+    match fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json).map_err(Error::invalid(&path)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::FileFailed { path, source: e }),
+    }
+}
+
+fn save_history(pointer: &Path, history: &[Promotion]) -> Result<(), Error> {
+    let json = serde_json::to_vec_pretty(history).map_err(Error::SerializeFailed)?;
+    fs::write(history_file(pointer), json).map_err(Error::file(history_file(pointer)))
+}
+
+// The snapshot the pointer leads to, or None before the first promotion. A real directory in its
+// place is refused, so promoting never replaces a crawl's output.
+pub fn pointed(pointer: &Path) -> Result<Option<PathBuf>, Error> {
+    match fs::symlink_metadata(pointer) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            let target = fs::read_link(pointer).map_err(Error::file(pointer))?;
+            Ok(Some(pointer.parent().map_or(target.clone(), |parent| parent.join(target))))
+        }
+        Ok(_) => Err(Error::PromoteFailed(format!(
+            "{} is not a pointer made by promote",
+            pointer.display()
+        ))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::FileFailed { path: pointer.to_path_buf(), source: e }),
+    }
+}
+
+// Runs the gates on `candidate`, comparing it with `current`, and returns its dataset.
+pub fn check(candidate: &Layout, current: Option<&Layout>, gates: &Gates) -> Result<Dataset, Error> {
+    if lock::is_held(candidate) {
+        return Err(Error::LockHeld(candidate.lock_file()));
+    }
+    if let Some(public_key) = &gates.public_key {
+        signing::verify(candidate, public_key)?;
+    }
+    if candidate.manifest_file().exists() {
+        let broken = Manifest::load(&candidate.manifest_file())?
+            .verify(candidate.out())?
+            .into_iter()
+            .filter(|mismatch| mismatch.problem != Problem::Unlisted)
+            .collect::<Vec<Mismatch>>();
+        if !broken.is_empty() {
+            return Err(Error::ChecksumMismatch(broken));
+        }
+    }
+    let dataset = Dataset::open(candidate)?;
+    if dataset.is_empty() {
+        return Err(Error::PromoteFailed(format!("{} has no banks", candidate.out().display())));
+    }
+    if let Some(current) = current {
+        let previous = Dataset::open(current)?;
+        anomaly::check(Counted::Banks, previous.len(), dataset.len(), gates.max_drop)?;
+        anomaly::check_branches(&dataset.banks, current, gates.max_drop)?;
+    }
+    Ok(dataset)
+}
+
+// Points the pointer at `snapshot`, an absolute path, relative to the pointer's directory when it is in there. The new
+// link is made next to the pointer and renamed over it, so readers see either snapshot, never none.
+fn point(pointer: &Path, snapshot: &Path) -> Result<(), Error> {
+    let failed = |e: io::Error| Error::PromoteFailed(format!("{}: {}", pointer.display(), e));
+    let parent = match pointer.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => parent.canonicalize().map_err(failed)?,
+        None => std::env::current_dir().map_err(failed)?,
+    };
+    let target = snapshot.strip_prefix(&parent).unwrap_or(snapshot);
+    let staging = backup::sibling(pointer, "promoting");
+    let _ = fs::remove_file(&staging);
+    link(target, &staging).map_err(failed)?;
+    replace(&staging, pointer).map_err(failed)
+}
+
+#[cfg(unix)]
+fn link(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(unix)]
+fn replace(staging: &Path, pointer: &Path) -> io::Result<()> {
+    fs::rename(staging, pointer)
+}
+
+#[cfg(windows)]
+fn link(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, path)
+}
+
+// Windows won't rename over a link, so there is a moment without one.
+#[cfg(windows)]
+fn replace(staging: &Path, pointer: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(pointer).is_ok() {
+        fs::remove_dir(pointer)?;
+    }
+    fs::rename(staging, pointer)
+}
+
+// Makes `snapshot` the one the pointer leads to and records it in the history. Gates are the
+// caller's to run first.
+pub fn promote(pointer: &Path, snapshot: &Path) -> Result<Promotion, Error> {
+    pointed(pointer)?;
+    let snapshot = snapshot
+        .canonicalize()
+        .map_err(|e| Error::PromoteFailed(format!("{}: {}", snapshot.display(), e)))?;
+    point(pointer, &snapshot)?;
+    let promotion = Promotion {
+        snapshot,
+        promoted_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    };
+    let mut history = history(pointer)?;
+    if history.last().map(|last| &last.snapshot) != Some(&promotion.snapshot) {
+        history.push(promotion.clone());
+    }
+    save_history(pointer, &history)?;
+    Ok(promotion)
+}
+
+// Points the pointer back at the snapshot promoted before the current one, which is dropped from the
+// history, and returns the two: (rolled back from, rolled back to).
+pub fn rollback(pointer: &Path) -> Result<(Promotion, Promotion), Error> {
+    pointed(pointer)?;
+    let mut history = history(pointer)?;
+    let from = match history.pop() {
+        Some(from) if !history.is_empty() => from,
+        _ => {
+            return Err(Error::PromoteFailed(format!(
+                "no earlier promotion of {} to roll back to",
+                pointer.display()
+            )))
+        }
+    };
+    let to = history[history.len() - 1].clone();
+    if !to.snapshot.is_dir() {
+        return Err(Error::PromoteFailed(format!("{} no longer exists", to.snapshot.display())));
+    }
+    point(pointer, &to.snapshot)?;
+    save_history(pointer, &history)?;
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    #[test]
+    fn promote_test() {
+        use std::fs;
+        use std::path::PathBuf;
+        use crate::dataset::Dataset;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::promote::{check, history, pointed, promote, rollback, Gates};
+        use crate::{Bank, Error};
+
+        let root = std::env::temp_dir().join(format!("zngn-promote-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let snapshot = |name: &str, banks: usize| {
+            let layout = Layout::new(root.join(name), DEFAULT_TEMPLATE.to_owned()).unwrap();
+            let banks = (0..banks)
+                .map(|i| Bank::new(format!("銀行{}", i), "ｷﾞﾝｺｳ".to_owned(), format!("{:04}", i), format!("0x{}", i)))
+                .collect::<Vec<Bank>>();
+            crate::save_banks(&banks, &layout).unwrap();
+            layout
+        };
+        let june = snapshot("2024-06-01", 10);
+        let july = snapshot("2024-07-01", 10);
+        let broken = snapshot("2024-08-01", 3);
+        let pointer = root.join("latest");
+        let latest = june.relocated(pointer.clone());
+        let gates = Gates {
+            max_drop: 10.0,
+            public_key: None,
+        };
+
+        assert_eq!(pointed(&pointer).unwrap(), None);
+        check(&june, None, &gates).unwrap();
+        promote(&pointer, june.out()).unwrap();
+        assert_eq!(Dataset::open(&latest).unwrap().len(), 10);
+        check(&july, Some(&latest), &gates).unwrap();
+        promote(&pointer, july.out()).unwrap();
+        match check(&broken, Some(&latest), &gates) {
+            Err(Error::CountDropped { previous: 10, current: 3, .. }) => {}
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(fs::read_link(&pointer).unwrap(), PathBuf::from("2024-07-01"));
+
+        let (from, to) = rollback(&pointer).unwrap();
+        assert_eq!(
+            (from.snapshot, to.snapshot),
+            (july.out().canonicalize().unwrap(), june.out().canonicalize().unwrap())
+        );
+        assert_eq!(fs::read_link(&pointer).unwrap(), PathBuf::from("2024-06-01"));
+        assert_eq!(history(&pointer).unwrap().len(), 1);
+        assert!(rollback(&pointer).is_err());
+        assert!(pointed(june.out()).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
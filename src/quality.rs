@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::Bank;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    EmptyPhonetic,
+    NonKanaPhonetic,
+    MalformedBankCode,
+    MalformedBranchCode,
+    NoBranches,
+    DuplicateBankName,
+    DuplicateBranchName,
+}
+
+impl Check {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Check::EmptyPhonetic => "empty phonetic",
+            Check::NonKanaPhonetic => "non-kana characters in phonetic",
+            Check::MalformedBankCode => "bank code not 4 digits",
+            Check::MalformedBranchCode => "branch code not 3 digits",
+            Check::NoBranches => "bank without branches",
+            Check::DuplicateBankName => "bank name used by several banks",
+            Check::DuplicateBranchName => "branch name used twice in a bank",
+        }
+    }
+}
+
+// Every record that failed one check, and the first few of them by way of example.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub check: Check,
+    pub count: usize,
+    pub examples: Vec<String>,
+}
+
+// Readings are half-width katakana as printed on transfer forms, which also allow digits, capital
+// letters, spaces and a few symbols.
+fn is_phonetic_char(c: char) -> bool {
+    matches!(c, '\u{ff61}'..='\u{ff9f}' | '0'..='9' | 'A'..='Z' | ' ' | '(' | ')' | '.' | '-' | '/' | ',')
+}
+
+fn is_code(code: &str, digits: usize) -> bool {
+    code.len() == digits && code.chars().all(|c| c.is_ascii_digit())
+}
+
+struct Collector {
+    examples: usize,
+    issues: HashMap<Check, Issue>,
+}
+
+impl Collector {
+    fn add(&mut self, check: Check, example: impl FnOnce() -> String) {
+        let issue = self.issues.entry(check).or_insert_with(|| Issue {
+            check,
+            count: 0,
+            examples: Vec::new(),
+        });
+        issue.count += 1;
+        if issue.examples.len() < self.examples {
+            issue.examples.push(example());
+        }
+    }
+
+    fn phonetic(&mut self, record: &str, phonetic: &str) {
+        if phonetic.trim().is_empty() {
+            self.add(Check::EmptyPhonetic, || record.to_owned());
+        } else if !phonetic.chars().all(is_phonetic_char) {
+            self.add(Check::NonKanaPhonetic, || format!("{} {}", record, phonetic));
+        }
+    }
+}
+
+// Runs every check over the dataset. Only checks something failed are returned, in a fixed order.
+pub fn inspect(banks: &[Bank], examples: usize) -> Vec<Issue> {
+    let mut collector = Collector {
+        examples,
+        issues: HashMap::new(),
+    };
+    let mut bank_names: HashMap<&str, Vec<&str>> = HashMap::new();
+    for bank in banks {
+        let record = format!("{} {}", bank.code.0, bank.name);
+        collector.phonetic(&record, &bank.phonetic);
+        if !is_code(&bank.code.0, 4) {
+            collector.add(Check::MalformedBankCode, || record.clone());
+        }
+        if bank.branches.is_empty() {
+            collector.add(Check::NoBranches, || record.clone());
+        }
+        bank_names.entry(&bank.name).or_default().push(&bank.code.0);
+
+        let mut branch_names: HashMap<&str, Vec<&str>> = HashMap::new();
+        for branch in &bank.branches {
+            let record = format!("{}-{} {}", bank.code.0, branch.code, branch.name);
+            collector.phonetic(&record, &branch.phonetic);
+            if !is_code(&branch.code, 3) {
+                collector.add(Check::MalformedBranchCode, || record.clone());
+            }
+            branch_names.entry(&branch.name).or_default().push(&branch.code);
+        }
+        let mut duplicates = branch_names.into_iter().filter(|(_, codes)| codes.len() > 1).collect::<Vec<_>>();
+        duplicates.sort();
+        for (name, codes) in duplicates {
+            collector.add(Check::DuplicateBranchName, || format!("{} {} ({})", bank.code.0, name, codes.join(", ")));
+        }
+    }
+    let mut duplicates = bank_names.into_iter().filter(|(_, codes)| codes.len() > 1).collect::<Vec<_>>();
+    duplicates.sort();
+    for (name, codes) in duplicates {
+        collector.add(Check::DuplicateBankName, || format!("{} ({})", name, codes.join(", ")));
+    }
+    let mut issues = collector.issues.into_values().collect::<Vec<Issue>>();
+    issues.sort_by_key(|issue| issue.check);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn inspect_test() {
+        use crate::quality::{inspect, Check};
+        use crate::{Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        neko.append_branch(Branch::new("本店".to_owned(), "ほんてん".to_owned(), "01".to_owned()));
+        neko.append_branch(Branch::new("駅前".to_owned(), "".to_owned(), "003".to_owned()));
+        let inu = Bank::new("ねこ銀行".to_owned(), "ﾈｺ(ｶ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+
+        let issues = inspect(&[neko, inu], 1);
+        let found = issues.iter().map(|issue| (issue.check, issue.count)).collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (Check::EmptyPhonetic, 1),
+                (Check::NonKanaPhonetic, 1),
+                (Check::MalformedBranchCode, 1),
+                (Check::NoBranches, 1),
+                (Check::DuplicateBankName, 1),
+                (Check::DuplicateBranchName, 1),
+            ]
+        );
+        assert_eq!(issues[0].examples, vec!["0222-003 駅前"]);
+        assert_eq!(issues[1].examples, vec!["0222-01 本店 ほんてん"]);
+        assert_eq!(issues[4].examples, vec!["ねこ銀行 (0222, 0111)"]);
+        assert_eq!(issues[5].examples, vec!["0222 本店 (001, 01)"]);
+    }
+}
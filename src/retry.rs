@@ -0,0 +1,326 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::logging;
+use crate::marker::mark_done;
+use crate::plan::{self, Item};
+use crate::progress::ProgressObserver;
+use crate::client::ZnginClient;
+use crate::{load_banks, save_banks, with_saved_branches, Bank, BankCode, Error};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailedRequest {
+    Banks { search_key: char },
+    Branches { bank_code: BankCode, search_key: char },
+}
+
+impl FailedRequest {
+    pub fn from_error(error: &Error) -> Option<Self> {
+        match error {
+            Error::FetchBankError { search_key, .. } => Some(FailedRequest::Banks { search_key: *search_key }),
+            Error::FetchBranchError { search_key, bank_code, .. } => Some(FailedRequest::Branches {
+                bank_code: bank_code.clone(),
+                search_key: *search_key,
+            }),
+            Error::TaskFailed { search_key, bank_code: None, .. } => Some(FailedRequest::Banks { search_key: *search_key }),
+            Error::TaskFailed { search_key, bank_code: Some(bank_code), .. } => Some(FailedRequest::Branches {
+                bank_code: bank_code.clone(),
+                search_key: *search_key,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Failed requests persisted between runs. Register it as a `ProgressObserver` to collect
+// failures during a crawl and `drain` it at the start of the next one. Every change is written
+// through to the file, so a crawl that dies still leaves its failures for the next one.
+#[derive(Debug)]
+pub struct RetryQueue {
+    path: PathBuf,
+    pending: Mutex<Vec<FailedRequest>>,
+}
+
+impl RetryQueue {
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let pending = if path.exists() {
+            let file = File::open(&path).map_err(Error::file(&path))?;
+            serde_json::from_reader(&file).map_err(Error::invalid(&path))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path,
+            pending: Mutex::new(pending),
+        })
+    }
+
+    // A panic elsewhere while the queue was locked leaves it as it was, which is still worth saving.
+    fn pending(&self) -> MutexGuard<'_, Vec<FailedRequest>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&self, request: FailedRequest) -> Result<(), Error> {
+        let mut pending = self.pending();
+        if pending.contains(&request) {
+            return Ok(());
+        }
+        pending.push(request);
+        self.write(&pending)
+    }
+
+    fn remove(&self, request: &FailedRequest) -> Result<(), Error> {
+        let mut pending = self.pending();
+        let queued = pending.len();
+        pending.retain(|pending| pending != request);
+        if pending.len() == queued {
+            return Ok(());
+        }
+        self.write(&pending)
+    }
+
+    // Banks with a branch request still queued, whose saved branches are missing some.
+    pub fn incomplete_banks(&self) -> HashSet<BankCode> {
+        self.pending()
+            .iter()
+            .filter_map(|request| match request {
+                FailedRequest::Branches { bank_code, .. } => Some(bank_code.clone()),
+                FailedRequest::Banks { .. } => None,
+            })
+            .collect()
+    }
+
+    // Search keys with a bank list request still queued, whose banks the saved list may be missing.
+    pub fn failed_search_keys(&self) -> HashSet<char> {
+        self.pending()
+            .iter()
+            .filter_map(|request| match request {
+                FailedRequest::Banks { search_key } => Some(*search_key),
+                FailedRequest::Branches { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.write(&self.pending())
+    }
+
+    fn write(&self, pending: &[FailedRequest]) -> Result<(), Error> {
+        if pending.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path).map_err(Error::file(&self.path))?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(Error::file(parent))?;
+        }
+        let data = serde_json::to_string(pending).map_err(Error::SerializeFailed)?;
+        let staging = self.path.with_extension("json.tmp");
+        fs::write(&staging, data).map_err(Error::file(&staging))?;
+        fs::rename(&staging, &self.path).map_err(Error::file(&self.path))
+    }
+}
+
+impl ProgressObserver for RetryQueue {
+    fn request_failed(&self, _search_key: char, error: &Error) {
+        if let Some(request) = FailedRequest::from_error(error) {
+            if let Err(e) = self.push(request) {
+                logging::warn(&format!("could not save the retry queue: {}", e));
+            }
+        }
+    }
+
+    // A request that failed before and went through this time is no longer owed.
+    fn key_fetched(&self, search_key: char, bank_code: Option<&BankCode>, _count: usize) {
+        let request = match bank_code {
+            Some(bank_code) => FailedRequest::Branches { bank_code: bank_code.clone(), search_key },
+            None => FailedRequest::Banks { search_key },
+        };
+        if let Err(e) = self.remove(&request) {
+            logging::warn(&format!("could not save the retry queue: {}", e));
+        }
+    }
+}
+
+enum Retried {
+    Banks,
+    // The bank whose branch file the request added to.
+    Branches(Box<Bank>),
+    // The bank is in no bank list yet, so the request waits for one that has it.
+    Unlisted,
+}
+
+async fn retry(request: &FailedRequest, client: &ZnginClient, layout: &Layout) -> Result<Retried, Error> {
+    match request {
+        FailedRequest::Banks { search_key } => {
+            let fetched = client.fetch_banks(*search_key).await?.items;
+            // Into the bank list a crawl held back for this key, if there is one.
+            let target = plan::list_layout(layout);
+            let mut banks = load_banks(&target).unwrap_or_default();
+            for bank in fetched {
+                banks.entry(bank.code.clone()).or_insert(bank);
+            }
+            save_banks(&banks.into_values().collect::<Vec<Bank>>(), &target)?;
+            Ok(Retried::Banks)
+        }
+        FailedRequest::Branches { bank_code, search_key } => {
+            // A crawl cut off before committing has the bank only in the staged list.
+            let banks = plan::planned_banks(layout)?;
+            let bank = match banks.get(bank_code) {
+                Some(bank) => bank,
+                None => return Ok(Retried::Unlisted),
+            };
+            // Merged into the staged file, if a crawl that was cut off left one, as committing it replaces the saved one.
+            let target = plan::branch_layout(layout, bank);
+            let mut saved = with_saved_branches(&target, bank.clone())?;
+            let fetched = client.fetch_branches(bank, *search_key).await?.items;
+            for branch in fetched {
+                if !saved.branches.iter().any(|saved| saved.code == branch.code) {
+                    saved.append_branch(branch);
+                }
+            }
+            saved.save_as_file(&target).await?;
+            Ok(Retried::Branches(Box::new(saved)))
+        }
+    }
+}
+
+// Replays queued requests, merging their results into the saved files. Requests that fail
+// again stay queued; a bank is marked done once none of its requests are left. Returns how many
+// succeeded.
+pub async fn drain(queue: &RetryQueue, client: &ZnginClient, layout: &Layout) -> Result<usize, Error> {
+    let mut retried = 0;
+    let pending = queue.pending().clone();
+    for request in pending {
+        let retried_as = match retry(&request, client, layout).await {
+            Ok(Retried::Unlisted) => continue,
+            Ok(retried_as) => retried_as,
+            Err(Error::RequestBudgetExhausted) => return Err(Error::RequestBudgetExhausted),
+            Err(_) => continue,
+        };
+        queue.remove(&request)?;
+        retried += 1;
+        match retried_as {
+            Retried::Banks => {
+                if let FailedRequest::Banks { search_key } = request {
+                    plan::record(layout, &Item::Banks { search_key }).await?;
+                }
+            }
+            Retried::Branches(bank) if !queue.incomplete_banks().contains(&bank.code) => {
+                mark_done(&plan::branch_layout(layout, &bank), &bank).await?;
+                plan::record(layout, &Item::Branches { bank_code: bank.code.clone() }).await?;
+            }
+            Retried::Branches(_) | Retried::Unlisted => {}
+        }
+    }
+    Ok(retried)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn failed_request_format_test() {
+        use crate::retry::FailedRequest;
+        use crate::BankCode;
+
+        let request = FailedRequest::Branches {
+            bank_code: BankCode("0001".to_owned()),
+            search_key: 'あ',
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"kind":"branches","bank_code":"0001","search_key":"あ"}"#);
+        assert_eq!(serde_json::from_str::<FailedRequest>(&json).unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn drain_test() {
+        use std::collections::HashMap;
+        use std::convert::Infallible;
+        use std::fs;
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Response, Server};
+        use crate::client::{Source, ZnginClient};
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::retry::{drain, FailedRequest, RetryQueue};
+        use crate::plan;
+        use crate::{load_branch_file, marker, Bank, BankCode};
+
+        // Branches of any bank under 英; requests under い break off while `down` is set.
+        let down = Arc::new(AtomicBool::new(true));
+        let flag = down.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| {
+            let down = flag.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let down = down.clone();
+                    async move {
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        let form = serde_urlencoded::from_bytes::<HashMap<String, String>>(&body).unwrap();
+                        if form.values().any(|value| value == "い") && down.load(Ordering::SeqCst) {
+                            return Err("down");
+                        }
+                        let page = if form.values().any(|value| value == "英") {
+                            include_str!("../data/pages/shitenmeisai_alphanumeric.html")
+                        } else {
+                            include_str!("../data/pages/shitenmeisai_empty.html")
+                        };
+                        Ok(Response::new(Body::from(page)))
+                    }
+                }))
+            }
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+        let dir = std::env::temp_dir().join(format!("zngn-drain-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let bank = Bank::new("ＰａｙＰａｙ銀行".to_owned(), "ﾍﾟｲﾍﾟｲ".to_owned(), "0033".to_owned(), "0033".to_owned());
+        // Only in the bank list of a crawl that was cut off before committing it.
+        plan::stage(&layout, std::slice::from_ref(&bank), None).unwrap();
+        let client = ZnginClient::builder().source(Source::at(&base)).build().unwrap();
+        let branches = |search_key| FailedRequest::Branches { bank_code: BankCode("0033".to_owned()), search_key };
+
+        // Every failure is on disk as soon as it is queued.
+        let queue = RetryQueue::load(layout.retry_queue_file()).unwrap();
+        queue.push(branches('英')).unwrap();
+        queue.push(branches('い')).unwrap();
+        queue.push(branches('い')).unwrap();
+        assert_eq!(RetryQueue::load(layout.retry_queue_file()).unwrap().len(), 2);
+        // A bank in no bank list stays queued.
+        let unlisted = FailedRequest::Branches { bank_code: BankCode("9999".to_owned()), search_key: '英' };
+        queue.push(unlisted.clone()).unwrap();
+
+        assert_eq!(drain(&queue, &client, &layout).await.unwrap(), 1);
+        assert_eq!(RetryQueue::load(layout.retry_queue_file()).unwrap().len(), 2);
+        assert_eq!(load_branch_file(&layout, &bank).unwrap().branches.len(), 3);
+        // Still missing the branches under い, so not done.
+        assert!(queue.incomplete_banks().contains(&bank.code));
+        assert!(!marker::is_fresh(&layout, &bank, None));
+
+        down.store(false, Ordering::SeqCst);
+        assert_eq!(drain(&queue, &client, &layout).await.unwrap(), 1);
+        assert_eq!(queue.pending().clone(), [unlisted]);
+        assert!(marker::is_fresh(&layout, &bank, None));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,140 @@
+fn syllable(kana: char, mark: Option<char>) -> Option<&'static str> {
+    let romaji = match (kana, mark) {
+        ('ｱ', None) | ('ｧ', None) => "a",
+        ('ｲ', None) | ('ｨ', None) => "i",
+        ('ｳ', None) | ('ｩ', None) => "u",
+        ('ｴ', None) | ('ｪ', None) => "e",
+        ('ｵ', None) | ('ｫ', None) => "o",
+        ('ｶ', None) => "ka",
+        ('ｷ', None) => "ki",
+        ('ｸ', None) => "ku",
+        ('ｹ', None) => "ke",
+        ('ｺ', None) => "ko",
+        ('ｻ', None) => "sa",
+        ('ｼ', None) => "shi",
+        ('ｽ', None) => "su",
+        ('ｾ', None) => "se",
+        ('ｿ', None) => "so",
+        ('ﾀ', None) => "ta",
+        ('ﾁ', None) => "chi",
+        ('ﾂ', None) => "tsu",
+        ('ﾃ', None) => "te",
+        ('ﾄ', None) => "to",
+        ('ﾅ', None) => "na",
+        ('ﾆ', None) => "ni",
+        ('ﾇ', None) => "nu",
+        ('ﾈ', None) => "ne",
+        ('ﾉ', None) => "no",
+        ('ﾊ', None) => "ha",
+        ('ﾋ', None) => "hi",
+        ('ﾌ', None) => "fu",
+        ('ﾍ', None) => "he",
+        ('ﾎ', None) => "ho",
+        ('ﾏ', None) => "ma",
+        ('ﾐ', None) => "mi",
+        ('ﾑ', None) => "mu",
+        ('ﾒ', None) => "me",
+        ('ﾓ', None) => "mo",
+        ('ﾔ', None) | ('ｬ', None) => "ya",
+        ('ﾕ', None) | ('ｭ', None) => "yu",
+        ('ﾖ', None) | ('ｮ', None) => "yo",
+        ('ﾗ', None) => "ra",
+        ('ﾘ', None) => "ri",
+        ('ﾙ', None) => "ru",
+        ('ﾚ', None) => "re",
+        ('ﾛ', None) => "ro",
+        ('ﾜ', None) => "wa",
+        ('ｦ', None) => "o",
+        ('ﾝ', None) => "n",
+        ('ｳ', Some('ﾞ')) => "vu",
+        ('ｶ', Some('ﾞ')) => "ga",
+        ('ｷ', Some('ﾞ')) => "gi",
+        ('ｸ', Some('ﾞ')) => "gu",
+        ('ｹ', Some('ﾞ')) => "ge",
+        ('ｺ', Some('ﾞ')) => "go",
+        ('ｻ', Some('ﾞ')) => "za",
+        ('ｼ', Some('ﾞ')) => "ji",
+        ('ｽ', Some('ﾞ')) => "zu",
+        ('ｾ', Some('ﾞ')) => "ze",
+        ('ｿ', Some('ﾞ')) => "zo",
+        ('ﾀ', Some('ﾞ')) => "da",
+        ('ﾁ', Some('ﾞ')) => "ji",
+        ('ﾂ', Some('ﾞ')) => "zu",
+        ('ﾃ', Some('ﾞ')) => "de",
+        ('ﾄ', Some('ﾞ')) => "do",
+        ('ﾊ', Some('ﾞ')) => "ba",
+        ('ﾋ', Some('ﾞ')) => "bi",
+        ('ﾌ', Some('ﾞ')) => "bu",
+        ('ﾍ', Some('ﾞ')) => "be",
+        ('ﾎ', Some('ﾞ')) => "bo",
+        ('ﾊ', Some('ﾟ')) => "pa",
+        ('ﾋ', Some('ﾟ')) => "pi",
+        ('ﾌ', Some('ﾟ')) => "pu",
+        ('ﾍ', Some('ﾟ')) => "pe",
+        ('ﾎ', Some('ﾟ')) => "po",
+        _ => return None,
+    };
+    Some(romaji)
+}
+
+fn is_small_y(kana: char) -> bool {
+    kana == 'ｬ' || kana == 'ｭ' || kana == 'ｮ'
+}
+
+// Romanize a half-width katakana reading (as published by zengin) into a lowercase,
+// hyphen separated ascii slug suitable for filenames.
+pub fn slugify(phonetic: &str) -> String {
+    let mut slug = String::new();
+    let mut geminate = false;
+    let mut chars = phonetic.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mark = match chars.peek() {
+            Some(&m) if m == 'ﾞ' || m == 'ﾟ' => chars.next(),
+            _ => None,
+        };
+        if c == 'ｯ' {
+            geminate = true;
+            continue;
+        }
+        if c == 'ｰ' {
+            continue;
+        }
+        match syllable(c, mark) {
+            Some(romaji) => {
+                if is_small_y(c) && slug.ends_with('i') {
+                    slug.pop();
+                    if slug.ends_with("sh") || slug.ends_with("ch") || slug.ends_with('j') {
+                        slug.push_str(&romaji[1..]);
+                        continue;
+                    }
+                }
+                if geminate {
+                    let doubled = if romaji.starts_with("ch") { 't' } else { romaji.chars().next().unwrap() };
+                    slug.push(doubled);
+                    geminate = false;
+                }
+                slug.push_str(romaji);
+            }
+            None if c.is_ascii_alphanumeric() => slug.push(c.to_ascii_lowercase()),
+            None => {
+                if !slug.is_empty() && !slug.ends_with('-') {
+                    slug.push('-');
+                }
+            }
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn slugify_test() {
+        use crate::romaji::slugify;
+
+        assert_eq!(slugify("ﾐｽﾞﾎ"), "mizuho");
+        assert_eq!(slugify("ﾗｸﾃﾝ"), "rakuten");
+        assert_eq!(slugify("ｼﾞｬﾊﾟﾝ ﾈｯﾄ"), "japan-netto");
+        assert_eq!(slugify("ｷｮｳﾄ(ｶ)"), "kyouto-ka");
+    }
+}
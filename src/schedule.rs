@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::layout::Layout;
+use crate::marker;
+use crate::{Bank, BankCode};
+
+// How much fetching a bank's branches adds to the saved snapshot, most first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    // Not in the previous bank list, or never saved with its branches.
+    New,
+    // Listed under another name, reading or branch page than before.
+    Changed,
+    // Listed as before; fetching it only refreshes the branches already saved.
+    Unchanged,
+}
+
+fn priority(bank: &Bank, before: Option<&Bank>, done_at: Option<SystemTime>) -> Priority {
+    match before {
+        Some(_) if done_at.is_none() => Priority::New,
+        Some(before) if before.name != bank.name || before.phonetic != bank.phonetic || before.search_param != bank.search_param => {
+            Priority::Changed
+        }
+        Some(_) => Priority::Unchanged,
+        None => Priority::New,
+    }
+}
+
+// Number of banks of each priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Scheduled {
+    pub new: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+}
+
+// Orders `banks` for an incremental crawl against the bank list saved by the one before: new banks
+// first, then changed ones, then unchanged ones, so a run cut short has saved what is most worth
+// having. Within each, banks saved longest ago go first, then by code.
+pub fn order(layout: &Layout, banks: &mut [Bank], previous: &HashMap<BankCode, Bank>) -> Scheduled {
+    let mut scheduled = Scheduled::default();
+    banks.sort_by_cached_key(|bank| {
+        let done_at = marker::done_at(layout, bank);
+        let priority = priority(bank, previous.get(&bank.code), done_at);
+        match priority {
+            Priority::New => scheduled.new += 1,
+            Priority::Changed => scheduled.changed += 1,
+            Priority::Unchanged => scheduled.unchanged += 1,
+        }
+        (priority, done_at, bank.code.0.clone())
+    });
+    scheduled
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn schedule_test() {
+        use std::collections::HashMap;
+        use std::fs;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::schedule::{order, Scheduled};
+        use crate::{Bank, BankCode};
+
+        let dir = std::env::temp_dir().join(format!("zngn-schedule-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let bank = |code: &str, name: &str| Bank::new(name.to_owned(), "ｷﾞﾝｺｳ".to_owned(), code.to_owned(), format!("0x{}", code));
+        let done = |code: &str, at: u64| {
+            let marker = layout.done_marker(&bank(code, ""));
+            fs::create_dir_all(marker.parent().unwrap()).unwrap();
+            fs::write(marker, at.to_string()).unwrap();
+        };
+        let codes = |banks: &[Bank]| banks.iter().map(|bank| bank.code.0.clone()).collect::<Vec<String>>();
+
+        let previous = [bank("0001", "一銀行"), bank("0002", "二銀行"), bank("0005", "五銀行"), bank("0009", "九銀行")]
+            .iter()
+            .map(|bank| (bank.code.clone(), bank.clone()))
+            .collect::<HashMap<BankCode, Bank>>();
+        done("0001", 2_000_000_000);
+        done("0002", 1_000_000_000);
+        done("0005", 2_000_000_000);
+        let mut banks = vec![
+            bank("0001", "一銀行"),
+            bank("0002", "二銀行"),
+            bank("0005", "新五銀行"),
+            // Listed before, but a run never got to save its branches.
+            bank("0009", "九銀行"),
+            bank("0010", "十銀行"),
+        ];
+        let scheduled = order(&layout, &mut banks, &previous);
+        assert_eq!(codes(&banks), ["0009", "0010", "0005", "0002", "0001"]);
+        assert_eq!(scheduled, Scheduled { new: 2, changed: 1, unchanged: 2 });
+
+        // Without a previous bank list, every bank is new and they go by code.
+        let mut banks = vec![bank("0005", "五銀行"), bank("0001", "一銀行")];
+        order(&layout, &mut banks, &HashMap::new());
+        assert_eq!(codes(&banks), ["0001", "0005"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{Bank, BankCode, Error};
+
+// JSON Schema of banks.json and the per-bank branch files, which share one format: banks keyed by code.
+pub fn schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "zngn dataset",
+        "description": "Banks keyed by bank code, as saved in banks.json and the per-bank branch files",
+        "type": "object",
+        "propertyNames": { "pattern": "^[0-9]{4}$" },
+        "additionalProperties": { "$ref": "#/$defs/bank" },
+        "$defs": {
+            "bank": {
+                "type": "object",
+                "required": ["name", "phonetic", "code", "search_param", "branches"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "name_en": { "type": "string", "description": "English name, for major banks" },
+                    "phonetic": { "type": "string", "description": "Reading in half-width katakana" },
+                    "code": { "type": "string", "pattern": "^[0-9]{4}$" },
+                    "search_param": { "type": "string", "description": "Parameter zengin.ajtw.net lists the branches under" },
+                    "branches": { "type": "array", "items": { "$ref": "#/$defs/branch" } },
+                    "aliases": { "type": "array", "items": { "$ref": "#/$defs/alias" } },
+                    "detail_url": { "type": "string", "description": "The bank's own page on zengin.ajtw.net, from crawl --enrich" },
+                    "address": { "type": "string", "description": "Head office address, from crawl --enrich" },
+                    "website": { "type": "string", "description": "The bank's website, from crawl --enrich" }
+                },
+                "additionalProperties": false
+            },
+            "branch": {
+                "type": "object",
+                "required": ["name", "phonetic", "code"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "phonetic": { "type": "string" },
+                    "code": { "type": "string", "pattern": "^[0-9]{3}$" }
+                },
+                "additionalProperties": false
+            },
+            "alias": {
+                "type": "object",
+                "required": ["name"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "phonetic": { "type": "string" },
+                    "until": { "type": "string", "format": "date" }
+                },
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+// Where a document breaks the schema, as a JSON Pointer (RFC 6901) into the document.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub pointer: String,
+    pub message: String,
+}
+
+// Checks `document` against `schema()`. Only the keywords the schema uses are understood, and of
+// `pattern` only the `^[0-9]{n}$` form it uses for codes.
+pub fn validate(document: &Value) -> Vec<Violation> {
+    let root = schema();
+    let mut violations = Vec::new();
+    check(&root, &root, document, String::new(), &mut violations);
+    violations
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// The n of a `^[0-9]{n}$` pattern.
+fn digits(pattern: &str) -> Option<usize> {
+    pattern.strip_prefix("^[0-9]{")?.strip_suffix("}$")?.parse().ok()
+}
+
+fn fail(violations: &mut Vec<Violation>, pointer: &str, message: String) {
+    violations.push(Violation {
+        pointer: pointer.to_owned(),
+        message,
+    });
+}
+
+fn check(root: &Value, schema: &Value, value: &Value, pointer: String, violations: &mut Vec<Violation>) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let target = reference.strip_prefix('#').and_then(|path| root.pointer(path)).unwrap_or(&Value::Null);
+        return check(root, target, value, pointer, violations);
+    }
+    if let Some(expected) = schema["type"].as_str() {
+        if type_name(value) != expected {
+            return fail(violations, &pointer, format!("expected {}, found {}", expected, type_name(value)));
+        }
+    }
+    if let (Some(n), Some(text)) = (schema["pattern"].as_str().and_then(digits), value.as_str()) {
+        if text.len() != n || !text.chars().all(|c| c.is_ascii_digit()) {
+            fail(violations, &pointer, format!("{:?} is not {} digits", text, n));
+        }
+    }
+    match value {
+        Value::Object(object) => check_object(root, schema, object, pointer, violations),
+        Value::Array(items) if !schema["items"].is_null() => {
+            for (i, item) in items.iter().enumerate() {
+                check(root, &schema["items"], item, format!("{}/{}", pointer, i), violations);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_object(root: &Value, schema: &Value, object: &Map<String, Value>, pointer: String, violations: &mut Vec<Violation>) {
+    for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(required) {
+            fail(violations, &pointer, format!("missing {}", required));
+        }
+    }
+    for (key, value) in object {
+        let at = format!("{}/{}", pointer, escape(key));
+        if !schema["propertyNames"].is_null() {
+            check(root, &schema["propertyNames"], &Value::String(key.clone()), at.clone(), violations);
+        }
+        match (&schema["properties"][key], &schema["additionalProperties"]) {
+            (Value::Null, Value::Bool(false)) => fail(violations, &at, format!("unexpected field {}", key)),
+            (Value::Null, Value::Null) => {}
+            (Value::Null, additional) => check(root, additional, value, at, violations),
+            (property, _) => check(root, property, value, at, violations),
+        }
+    }
+}
+
+// Reads a dataset file, refusing it with every violation when it doesn't match the schema.
+pub fn load_validated(path: &Path) -> Result<HashMap<BankCode, Bank>, Error> {
+    let file = File::open(path).map_err(Error::OpenBanksFileFailed)?;
+    let document: Value = serde_json::from_reader(file).map_err(Error::LoadBanksFileFailed)?;
+    let violations = validate(&document);
+    if !violations.is_empty() {
+        return Err(Error::SchemaViolations(violations));
+    }
+    serde_json::from_value(document).map_err(Error::LoadBanksFileFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn validate_test() {
+        use serde_json::json;
+        use crate::schema::{validate, Violation};
+        use crate::{to_hashmap, Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let saved = serde_json::to_value(to_hashmap(&[neko])).unwrap();
+        assert_eq!(validate(&saved), Vec::new());
+
+        let broken = json!({
+            "0222": {
+                "name": "ねこ銀行",
+                "phonetic": 1,
+                "code": "0222",
+                "search_param": "0x222",
+                "branches": [{"name": "本店", "code": "01", "phone": "x"}]
+            },
+            "22": {"name": "x", "phonetic": "x", "code": "0022", "search_param": "x", "branches": []}
+        });
+        let violation = |pointer: &str, message: &str| Violation {
+            pointer: pointer.to_owned(),
+            message: message.to_owned(),
+        };
+        let mut violations = validate(&broken);
+        violations.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        assert_eq!(
+            violations,
+            vec![
+                violation("/0222/branches/0", "missing phonetic"),
+                violation("/0222/branches/0/code", "\"01\" is not 3 digits"),
+                violation("/0222/branches/0/phone", "unexpected field phone"),
+                violation("/0222/phonetic", "expected string, found number"),
+                violation("/22", "\"22\" is not 4 digits"),
+            ]
+        );
+    }
+}
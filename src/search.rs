@@ -0,0 +1,297 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::charset;
+use crate::matcher::matches;
+use crate::{Bank, BankCode, Branch, BranchCode};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hit<'a> {
+    Bank(&'a Bank),
+    Branch(&'a Bank, &'a Branch),
+}
+
+/// Which text of a record the query was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    Name,
+    Phonetic,
+    Code,
+    Alias,
+}
+
+/// An occurrence of the query in the matched text, in characters from its start, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+// A hit along with why it matched, for UIs to mark up: the text the query was found in, where in it,
+// and a score from 0 to 1 favouring whole and leading matches over ones in the middle of a long name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit<'a> {
+    pub hit: Hit<'a>,
+    pub field: MatchField,
+    pub text: &'a str,
+    pub score: f32,
+    pub highlights: Vec<Highlight>,
+}
+
+struct Match<'a> {
+    field: MatchField,
+    text: &'a str,
+    score: f32,
+    highlights: Vec<Highlight>,
+}
+
+// How well `query` matches `text`, found by `matches` or by code prefix. None when it doesn't.
+fn explain<'a>(field: MatchField, text: &'a str, query: &str) -> Option<Match<'a>> {
+    if query.is_empty() || !text.contains(query) || (field == MatchField::Code && !text.starts_with(query)) {
+        return None;
+    }
+    let length = query.chars().count();
+    let highlights = text
+        .match_indices(query)
+        .map(|(at, _)| {
+            let start = text[..at].chars().count();
+            Highlight { start, end: start + length }
+        })
+        .collect::<Vec<Highlight>>();
+    let coverage = length as f32 / text.chars().count() as f32;
+    let score = if text == query {
+        1.0
+    } else if text.starts_with(query) {
+        0.5 + 0.5 * coverage
+    } else {
+        0.5 * coverage
+    };
+    Some(Match { field, text, score, highlights })
+}
+
+// The best of the fields a record matched in, or None when it matched in none. An empty query matches
+// everything, as in `search`, just with nothing to highlight.
+fn best<'a>(candidates: impl Iterator<Item = (MatchField, &'a str)>, query: &str) -> Option<Match<'a>> {
+    let numeric = !query.is_empty() && query.chars().all(|c| c.is_ascii_digit());
+    candidates
+        .filter(|(field, _)| *field != MatchField::Code || numeric)
+        .filter_map(|(field, text)| explain(field, text, query))
+        .fold(None, |best: Option<Match<'a>>, found| match best {
+            Some(best) if best.score >= found.score => Some(best),
+            _ => Some(found),
+        })
+}
+
+// `search` with match metadata. Yields the same hits in the same order.
+pub fn search_hits<'a, I>(banks: I, query: &'a str) -> impl Iterator<Item = SearchHit<'a>> + 'a
+where
+    I: IntoIterator<Item = &'a Bank>,
+    I::IntoIter: 'a,
+{
+    let hit = move |hit: Hit<'a>, code: &'a str, name: &'a str, phonetic: &'a str, aliases: Vec<&'a str>| {
+        let fields = [(MatchField::Name, name), (MatchField::Phonetic, phonetic), (MatchField::Code, code)];
+        let aliases = aliases.into_iter().map(|alias| (MatchField::Alias, alias));
+        match best(fields.iter().copied().chain(aliases), query) {
+            Some(Match { field, text, score, highlights }) => SearchHit { hit, field, text, score, highlights },
+            None => SearchHit { hit, field: MatchField::Name, text: name, score: 0.0, highlights: Vec::new() },
+        }
+    };
+    search(banks, query).map(move |found| match found {
+        Hit::Bank(bank) => {
+            let aliases = bank
+                .aliases
+                .iter()
+                .flat_map(|alias| std::iter::once(alias.name.as_str()).chain(alias.phonetic.as_deref()))
+                .collect();
+            hit(found, &bank.code.0, &bank.name, &bank.phonetic, aliases)
+        }
+        Hit::Branch(_, branch) => hit(found, &branch.code, &branch.name, &branch.phonetic, Vec::new()),
+    })
+}
+
+// Hits are produced lazily, in dataset order, so callers can page through them without collecting.
+pub fn search<'a, I>(banks: I, query: &'a str) -> impl Iterator<Item = Hit<'a>> + 'a
+where
+    I: IntoIterator<Item = &'a Bank>,
+    I::IntoIter: 'a,
+{
+    banks.into_iter().flat_map(move |bank| {
+        let bank_hit = matches(&bank.code.0, &bank.name, &bank.phonetic, query)
+            || bank.aliases.iter().any(|alias| alias.matches(query));
+        let branch_hits = bank
+            .branches
+            .iter()
+            .filter(move |branch| matches(&branch.code, &branch.name, &branch.phonetic, query))
+            .map(move |branch| Hit::Branch(bank, branch));
+        bank_hit.then_some(Hit::Bank(bank)).into_iter().chain(branch_hits)
+    })
+}
+
+// Banks having a branch with `query` in its name or reading; `exact` requires the whole name or reading.
+pub fn find_branches<'a>(
+    banks: &'a [Bank],
+    query: &'a str,
+    exact: bool,
+) -> impl Iterator<Item = (&'a Bank, &'a Branch)> + 'a {
+    let matches = move |text: &str| if exact { text == query } else { text.contains(query) };
+    banks
+        .iter()
+        .flat_map(|bank| bank.branches.iter().map(move |branch| (bank, branch)))
+        .filter(move |(_, branch)| matches(&branch.name) || matches(&branch.phonetic))
+}
+
+// Branches of one bank matching `query` by name, reading or code prefix. Readings are stored in the zengin
+// character set, so a query typed as it is spoken, such as とうきょう or うめだ, is normalized to match them too.
+pub fn search_branches<'a>(bank: &'a Bank, query: &'a str) -> impl Iterator<Item = &'a Branch> + 'a {
+    let reading = charset::normalize(query);
+    bank.branches.iter().filter(move |branch| {
+        matches(&branch.code, &branch.name, &branch.phonetic, query) || branch.phonetic.contains(reading.as_str())
+    })
+}
+
+pub fn is_pattern(code: &str) -> bool {
+    code.contains(['*', '?'])
+}
+
+// Glob match on codes: `*` stands for any run of characters and `?` for exactly one.
+pub fn code_matches(code: &str, pattern: &str) -> bool {
+    let code = code.chars().collect::<Vec<char>>();
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    // matched[j]: whether the code consumed so far matches the first j pattern characters.
+    let mut matched = vec![false; pattern.len() + 1];
+    matched[0] = true;
+    for j in 0..pattern.len() {
+        matched[j + 1] = matched[j] && pattern[j] == '*';
+    }
+    for &c in &code {
+        let mut next = vec![false; pattern.len() + 1];
+        for j in 0..pattern.len() {
+            next[j + 1] = match pattern[j] {
+                '*' => next[j] || matched[j + 1],
+                '?' => matched[j],
+                p => matched[j] && p == c,
+            };
+        }
+        matched = next;
+    }
+    matched[pattern.len()]
+}
+
+pub fn lookup_banks<'a>(banks: &'a [Bank], pattern: &str) -> Vec<&'a Bank> {
+    banks.iter().filter(|bank| code_matches(&bank.code.0, pattern)).collect()
+}
+
+pub fn lookup_branches<'a>(bank: &'a Bank, pattern: &str) -> Vec<&'a Branch> {
+    bank.branches.iter().filter(|branch| code_matches(&branch.code, pattern)).collect()
+}
+
+// Codes may be given without their leading zeros.
+pub fn lookup_bank<'a>(banks: &'a [Bank], code: &str) -> Option<&'a Bank> {
+    let code = code.parse::<BankCode>().ok()?;
+    banks.iter().find(|bank| bank.code == code)
+}
+
+pub fn lookup_branch<'a>(bank: &'a Bank, code: &str) -> Option<&'a Branch> {
+    let code = code.parse::<BranchCode>().ok()?;
+    bank.branches.iter().find(|branch| branch.code == code.0)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn search_test() {
+        use crate::search::{search, Hit};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "123".to_owned()));
+        bank.append_branch(Branch::new("ねこ町支店".to_owned(), "ﾈｺﾏﾁ".to_owned(), "456".to_owned()));
+        let banks = vec![bank];
+
+        let hits = search(&banks, "ﾈｺ").collect::<Vec<Hit>>();
+        assert_eq!(hits, vec![Hit::Bank(&banks[0]), Hit::Branch(&banks[0], &banks[0].branches[1])]);
+        assert_eq!(search(&banks, "みけ").collect::<Vec<Hit>>(), vec![Hit::Branch(&banks[0], &banks[0].branches[0])]);
+        assert_eq!(search(&banks, "45").collect::<Vec<Hit>>(), vec![Hit::Branch(&banks[0], &banks[0].branches[1])]);
+        assert_eq!(search(&banks, "0222").collect::<Vec<Hit>>(), vec![Hit::Bank(&banks[0])]);
+    }
+
+    #[test]
+    fn search_hits_test() {
+        use crate::search::{search, search_hits, Highlight, Hit, MatchField};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("みけ支店".to_owned(), "ﾐｹ".to_owned(), "123".to_owned()));
+        bank.append_branch(Branch::new("ねこ町支店".to_owned(), "ﾈｺﾏﾁ".to_owned(), "456".to_owned()));
+        let banks = vec![bank];
+
+        let hits = search_hits(&banks, "ﾈｺ").collect::<Vec<_>>();
+        assert_eq!(hits.iter().map(|hit| hit.hit).collect::<Vec<Hit>>(), search(&banks, "ﾈｺ").collect::<Vec<Hit>>());
+        assert_eq!((hits[0].field, hits[0].text, hits[0].score), (MatchField::Phonetic, "ﾈｺ", 1.0));
+        assert_eq!((hits[1].field, hits[1].text), (MatchField::Phonetic, "ﾈｺﾏﾁ"));
+        assert!(hits[1].score < hits[0].score);
+
+        let hit = search_hits(&banks, "支店").next().unwrap();
+        assert_eq!((hit.field, hit.text), (MatchField::Name, "みけ支店"));
+        assert_eq!(hit.highlights, vec![Highlight { start: 2, end: 4 }]);
+
+        let hit = search_hits(&banks, "45").next().unwrap();
+        assert_eq!((hit.field, hit.highlights.clone()), (MatchField::Code, vec![Highlight { start: 0, end: 2 }]));
+        assert_eq!(search_hits(&banks, "").count(), search(&banks, "").count());
+    }
+
+    #[test]
+    fn find_branches_test() {
+        use crate::search::find_branches;
+        use crate::{Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("梅田支店".to_owned(), "ｳﾒﾀﾞ".to_owned(), "101".to_owned()));
+        neko.append_branch(Branch::new("東梅田支店".to_owned(), "ﾋｶﾞｼｳﾒﾀﾞ".to_owned(), "102".to_owned()));
+        let mut inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        inu.append_branch(Branch::new("梅田支店".to_owned(), "ｳﾒﾀﾞ".to_owned(), "201".to_owned()));
+        let banks = vec![neko, inu];
+
+        let codes = |pairs: &mut dyn Iterator<Item = (&Bank, &Branch)>| {
+            pairs
+                .map(|(bank, branch)| format!("{}-{}", bank.code.0, branch.code))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(codes(&mut find_branches(&banks, "梅田支店", false)), vec!["0222-101", "0222-102", "0111-201"]);
+        assert_eq!(codes(&mut find_branches(&banks, "ｳﾒﾀﾞ", true)), vec!["0222-101", "0111-201"]);
+    }
+
+    #[test]
+    fn search_branches_test() {
+        use crate::search::search_branches;
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("梅田支店".to_owned(), "ｳﾒﾀﾞ".to_owned(), "101".to_owned()));
+        bank.append_branch(Branch::new("東梅田支店".to_owned(), "ﾋｶﾞｼｳﾒﾀﾞ".to_owned(), "102".to_owned()));
+        bank.append_branch(Branch::new("難波支店".to_owned(), "ﾅﾝﾊﾞ".to_owned(), "201".to_owned()));
+
+        let codes = |query| search_branches(&bank, query).map(|branch| branch.code.as_str()).collect::<Vec<&str>>();
+        assert_eq!(codes("うめだ"), vec!["101", "102"]);
+        assert_eq!(codes("ウメダ"), vec!["101", "102"]);
+        assert_eq!(codes("難波"), vec!["201"]);
+        assert_eq!(codes("ひがしうめだ"), vec!["102"]);
+        assert_eq!(codes("10"), vec!["101", "102"]);
+        assert!(codes("しんじゅく").is_empty());
+    }
+
+    #[test]
+    fn code_matches_test() {
+        use crate::search::code_matches;
+
+        assert!(code_matches("0001", "000*"));
+        assert!(code_matches("0001", "0001"));
+        assert!(code_matches("0001", "*1"));
+        assert!(code_matches("0123", "0?2?"));
+        assert!(code_matches("0123", "*"));
+        assert!(!code_matches("0123", "000*"));
+        assert!(!code_matches("0123", "0?2"));
+        assert!(!code_matches("0123", "012"));
+    }
+}
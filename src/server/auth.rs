@@ -0,0 +1,57 @@
+use hyper::header::AUTHORIZATION;
+use hyper::HeaderMap;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(key);
+    }
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+// Compares every byte so response timing does not reveal how much of a key was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Requests pass when no keys are configured, or when they carry one of the keys either as
+// `Authorization: Bearer <key>` or as `X-Api-Key: <key>`.
+pub fn authorized(api_keys: &[String], headers: &HeaderMap) -> bool {
+    if api_keys.is_empty() {
+        return true;
+    }
+    match presented_key(headers) {
+        Some(presented) => api_keys
+            .iter()
+            .any(|key| constant_time_eq(key.as_bytes(), presented.trim().as_bytes())),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn authorized_test() {
+        use hyper::header::AUTHORIZATION;
+        use hyper::HeaderMap;
+        use crate::server::auth::authorized;
+
+        let keys = vec!["s3cret".to_owned()];
+        let mut headers = HeaderMap::new();
+        assert!(authorized(&[], &headers));
+        assert!(!authorized(&keys, &headers));
+
+        headers.insert(AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(authorized(&keys, &headers));
+        headers.insert(AUTHORIZATION, "Bearer s3cre".parse().unwrap());
+        assert!(!authorized(&keys, &headers));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "s3cret".parse().unwrap());
+        assert!(authorized(&keys, &headers));
+    }
+}
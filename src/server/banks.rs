@@ -0,0 +1,81 @@
+use hyper::header::HeaderValue;
+use hyper::{Body, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::filter::Filter;
+use crate::page;
+use crate::server::headers::TOTAL_COUNT;
+use crate::server::{error, json, Snapshot};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Params {
+    /// Only list banks matching a filter, e.g. `code=0* AND branch_count>100`
+    #[serde(rename = "where")]
+    #[param(rename = "where")]
+    filter: Option<String>,
+    /// Page size; every bank from the offset on when omitted
+    limit: Option<usize>,
+    /// Number of banks skipped before the page starts
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BankSummary<'a> {
+    code: &'a str,
+    name: &'a str,
+    /// English name, for major banks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_en: Option<&'a str>,
+    phonetic: &'a str,
+    /// Number of branches
+    branches: usize,
+    /// Head office address, when the dataset was crawled with --enrich
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<&'a str>,
+    /// The bank's website, when the dataset was crawled with --enrich
+    #[serde(skip_serializing_if = "Option::is_none")]
+    website: Option<&'a str>,
+}
+
+/// List banks in dataset order
+#[utoipa::path(
+    get,
+    path = "/banks",
+    operation_id = "banks",
+    params(Params),
+    responses(
+        (status = 200, description = "All banks, or the ones matching the filter; X-Total-Count has the number before paging", body = [BankSummary]),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+        (status = 400, description = "Malformed filter", body = ErrorBody),
+    )
+)]
+pub fn handle(query: &str, snapshot: &Snapshot) -> Response<Body> {
+    let params = match serde_urlencoded::from_str::<Params>(query) {
+        Ok(params) => params,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let filter = match params.filter.as_deref().map(str::parse::<Filter>).transpose() {
+        Ok(filter) => filter,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e),
+    };
+    let banks = snapshot
+        .banks
+        .iter()
+        .filter(|bank| filter.as_ref().is_none_or(|filter| filter.matches(bank)))
+        .map(|bank| BankSummary {
+            code: &bank.code.0,
+            name: &bank.name,
+            name_en: bank.name_en.as_deref(),
+            phonetic: &bank.phonetic,
+            branches: bank.branches.len(),
+            address: bank.address.as_deref(),
+            website: bank.website.as_deref(),
+        });
+    let page = page::paginate(banks, params.offset, params.limit);
+    let mut response = json(StatusCode::OK, &page.items);
+    response.headers_mut().insert(TOTAL_COUNT, HeaderValue::from(page.total));
+    response
+}
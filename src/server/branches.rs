@@ -0,0 +1,91 @@
+use hyper::header::HeaderValue;
+use hyper::{Body, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::page;
+use crate::search;
+use crate::server::headers::TOTAL_COUNT;
+use crate::server::{error, json, Snapshot};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Params {
+    /// Bank code, leading zeros optional
+    bank: String,
+    /// Substring of a branch name or phonetic reading in any kana, or a numeric code prefix; every branch when omitted
+    q: Option<String>,
+    /// Page size; every branch from the offset on when omitted
+    limit: Option<usize>,
+    /// Number of branches skipped before the page starts
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BranchSummary<'a> {
+    code: &'a str,
+    name: &'a str,
+    phonetic: &'a str,
+}
+
+/// Search the branches of one bank
+#[utoipa::path(
+    get,
+    path = "/branches",
+    operation_id = "branches",
+    params(Params),
+    responses(
+        (status = 200, description = "The bank's branches matching q in dataset order; X-Total-Count has the number before paging", body = [BranchSummary]),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+        (status = 400, description = "Missing or malformed parameters", body = ErrorBody),
+        (status = 404, description = "No bank has the code", body = ErrorBody),
+    )
+)]
+pub fn handle(query: &str, snapshot: &Snapshot) -> Response<Body> {
+    let params = match serde_urlencoded::from_str::<Params>(query) {
+        Ok(params) => params,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let bank = match search::lookup_bank(&snapshot.banks, &params.bank) {
+        Some(bank) => bank,
+        None => return error(StatusCode::NOT_FOUND, &format!("no bank with code {}", params.bank)),
+    };
+    let branches = search::search_branches(bank, params.q.as_deref().unwrap_or_default()).map(|branch| BranchSummary {
+        code: &branch.code,
+        name: &branch.name,
+        phonetic: &branch.phonetic,
+    });
+    let page = page::paginate(branches, params.offset, params.limit);
+    let mut response = json(StatusCode::OK, &page.items);
+    response.headers_mut().insert(TOTAL_COUNT, HeaderValue::from(page.total));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn branches_test() {
+        use hyper::StatusCode;
+        use serde_json::Value;
+        use crate::server::branches::handle;
+        use crate::server::Snapshot;
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("梅田支店".to_owned(), "ｳﾒﾀﾞ".to_owned(), "101".to_owned()));
+        bank.append_branch(Branch::new("難波支店".to_owned(), "ﾅﾝﾊﾞ".to_owned(), "201".to_owned()));
+        let snapshot = Snapshot::new(vec![bank]);
+
+        let response = handle("bank=222&q=%E3%81%86%E3%82%81%E3%81%A0", &snapshot);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "1");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let branches: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(branches[0]["code"], "101");
+
+        assert_eq!(handle("bank=0222", &snapshot).headers()["x-total-count"], "2");
+        assert_eq!(handle("bank=0333", &snapshot).status(), StatusCode::NOT_FOUND);
+        assert_eq!(handle("q=x", &snapshot).status(), StatusCode::BAD_REQUEST);
+    }
+}
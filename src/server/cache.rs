@@ -0,0 +1,126 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use hyper::body::Bytes;
+use hyper::{Body, Response, StatusCode};
+use lru::LruCache;
+
+use crate::server::{search, typed, Snapshot};
+
+// A page of search hits, as JSON, and when it was searched; keyed by dataset version and query.
+type Entries = LruCache<(String, String), (Instant, Bytes)>;
+
+// Pages of search hits recently answered, keyed by dataset version and query string, so clients
+// typing the same autocomplete prefixes don't each search the whole dataset. Keying by version keeps
+// a reload from ever serving the old dataset's hits; `clear` frees them as well.
+#[derive(Debug)]
+pub struct SearchCache {
+    // None when caching is turned off.
+    entries: Option<Mutex<Entries>>,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl SearchCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))),
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn clear(&self) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    // Answers a search from the cache when the same query was answered within the TTL. Only pages of
+    // hits are kept; refusals of malformed queries are cheap to make again.
+    pub fn search(&self, query: &str, snapshot: &Snapshot) -> Response<Body> {
+        let entries = match &self.entries {
+            Some(entries) => entries,
+            None => return search::handle(query, snapshot),
+        };
+        let key = (snapshot.version.clone(), query.to_owned());
+        if let Some((at, body)) = entries.lock().unwrap_or_else(PoisonError::into_inner).get(&key) {
+            if at.elapsed() < self.ttl {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return typed(StatusCode::OK, "application/json", Body::from(body.clone()));
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        match search::answer(query, snapshot) {
+            Ok(body) => {
+                let body = Bytes::from(body);
+                entries
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .put(key, (Instant::now(), body.clone()));
+                typed(StatusCode::OK, "application/json", Body::from(body))
+            }
+            Err(response) => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn search_cache_test() {
+        use std::time::Duration;
+        use hyper::StatusCode;
+        use crate::server::cache::SearchCache;
+        use crate::server::Snapshot;
+        use crate::Bank;
+
+        let neko = Snapshot::new(vec![Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned())]);
+        let inu = Snapshot::new(vec![Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0333".to_owned(), "0x333".to_owned())]);
+        let cache = SearchCache::new(2, Duration::from_secs(60));
+        let total = |response: hyper::Response<hyper::Body>| async {
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["total"].as_u64().unwrap()
+        };
+
+        assert_eq!(total(cache.search("q=0", &neko)).await, 1);
+        assert_eq!(total(cache.search("q=0", &neko)).await, 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        // Another dataset version never sees the first one's hits.
+        assert_eq!(total(cache.search("q=0", &inu)).await, 1);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(cache.search("type=bank", &neko).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(cache.search("type=bank", &neko).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(cache.hits(), 1);
+
+        // Least recently used queries go first once the cache is full.
+        cache.search("q=1", &neko);
+        cache.search("q=0", &inu);
+        cache.search("q=0", &neko);
+        assert_eq!(cache.hits(), 2);
+        cache.clear();
+        cache.search("q=0", &inu);
+        assert_eq!(cache.hits(), 2);
+
+        let expiring = SearchCache::new(10, Duration::from_millis(0));
+        expiring.search("q=0", &neko);
+        expiring.search("q=0", &neko);
+        assert_eq!((expiring.hits(), expiring.misses()), (0, 2));
+        let off = SearchCache::new(0, Duration::from_secs(60));
+        off.search("q=0", &neko);
+        off.search("q=0", &neko);
+        assert_eq!((off.hits(), off.misses()), (0, 0));
+    }
+}
@@ -0,0 +1,154 @@
+use hyper::{Body, Response, StatusCode};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::delta;
+use crate::diff;
+use crate::search;
+use crate::server::{error, json, Snapshot, State};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Params {
+    /// Version the client holds: the `to` of the last patch it applied, or `delta::fingerprint` of its copy
+    since: String,
+}
+
+/// Patch from an earlier version of the dataset to the one being served
+#[utoipa::path(
+    get,
+    path = "/changes",
+    operation_id = "changes",
+    params(Params),
+    responses(
+        (status = 200, description = "Patch in the format of `zngn delta`, for `zngn apply-delta`; no operations when the client is up to date", body = Object),
+        (status = 400, description = "Missing since", body = ErrorBody),
+        (status = 410, description = "The version is unknown or no longer kept; download the full dataset instead", body = ErrorBody),
+    )
+)]
+pub fn handle(query: &str, state: &State) -> Response<Body> {
+    let params = match serde_urlencoded::from_str::<Params>(query) {
+        Ok(params) => params,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let current = state.snapshot();
+    match state.version(&params.since) {
+        Some(since) => json(StatusCode::OK, &delta::delta(&since.banks, &current.banks)),
+        None => error(StatusCode::GONE, "unknown or expired version"),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BankParams {
+    /// Bank code, leading zeros optional
+    bank: String,
+    /// An earlier version of the dataset, as for `/changes`
+    since: String,
+}
+
+/// Branches one bank added, removed, renamed or renumbered since an earlier version of the dataset
+#[utoipa::path(
+    get,
+    path = "/bank-changes",
+    operation_id = "bank_changes",
+    params(BankParams),
+    responses(
+        (status = 200, description = "Changes in the format of `zngn diff-bank --output json`; empty when nothing changed", body = Object),
+        (status = 400, description = "Missing bank or since", body = ErrorBody),
+        (status = 404, description = "No bank has the code in either version", body = ErrorBody),
+        (status = 410, description = "The version is unknown or no longer kept", body = ErrorBody),
+    )
+)]
+pub fn bank(query: &str, state: &State) -> Response<Body> {
+    let params = match serde_urlencoded::from_str::<BankParams>(query) {
+        Ok(params) => params,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let current = state.snapshot();
+    let since = match state.version(&params.since) {
+        Some(since) => since,
+        None => return error(StatusCode::GONE, "unknown or expired version"),
+    };
+    let old = search::lookup_bank(&since.banks, &params.bank);
+    let new = search::lookup_bank(&current.banks, &params.bank);
+    if old.is_none() && new.is_none() {
+        return error(StatusCode::NOT_FOUND, &format!("no bank with code {}", params.bank));
+    }
+    json(StatusCode::OK, &diff::diff_bank(old, new))
+}
+
+/// Every bank with its branches, for clients too far behind for `/changes`
+#[utoipa::path(
+    get,
+    path = "/dataset",
+    operation_id = "dataset",
+    responses(
+        (status = 200, description = "The whole dataset; X-Dataset-Version has its version", body = Object),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+    )
+)]
+pub fn dataset(_query: &str, snapshot: &Snapshot) -> Response<Body> {
+    json(StatusCode::OK, &snapshot.banks)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn changes_test() {
+        use hyper::StatusCode;
+        use crate::delta::{apply, Delta};
+        use crate::server::changes::handle;
+        use crate::server::{Config, Snapshot, State};
+        use crate::Bank;
+
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let config = Config {
+            keep_versions: 1,
+            ..Config::default()
+        };
+        let state = State::new(vec![neko.clone()], config);
+        let first = state.snapshot().version.clone();
+        state.swap(Snapshot::new(vec![inu.clone(), neko.clone()]));
+        let second = state.snapshot().version.clone();
+
+        let response = handle(&format!("since={}", first), &state);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let patch: Delta = serde_json::from_slice(&body).unwrap();
+        let mut banks = vec![neko.clone()];
+        apply(&mut banks, &patch).unwrap();
+        assert_eq!(banks, vec![inu.clone(), neko.clone()]);
+
+        state.swap(Snapshot::new(vec![inu]));
+        assert_eq!(handle(&format!("since={}", second), &state).status(), StatusCode::OK);
+        assert_eq!(handle(&format!("since={}", first), &state).status(), StatusCode::GONE);
+        assert_eq!(handle("", &state).status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn bank_changes_test() {
+        use hyper::StatusCode;
+        use serde_json::Value;
+        use crate::server::changes::bank;
+        use crate::server::{Config, Snapshot, State};
+        use crate::{Bank, Branch};
+
+        let mut neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        neko.append_branch(Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()));
+        let state = State::new(vec![neko.clone()], Config::default());
+        let first = state.snapshot().version.clone();
+        neko.append_branch(Branch::new("駅前支店".to_owned(), "ｴｷﾏｴ".to_owned(), "002".to_owned()));
+        state.swap(Snapshot::new(vec![neko]));
+
+        let response = bank(&format!("bank=222&since={}", first), &state);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let changes: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(changes[0]["change"], "branch_added");
+        assert_eq!(changes[0]["code"], "002");
+        assert_eq!(bank(&format!("bank=0111&since={}", first), &state).status(), StatusCode::NOT_FOUND);
+        assert_eq!(bank("bank=0222&since=x", &state).status(), StatusCode::GONE);
+    }
+}
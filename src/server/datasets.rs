@@ -0,0 +1,99 @@
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::server::{json, Snapshot, State};
+
+// Splits /v/<name>/banks into the dataset name and the path within it. Other paths ask for the
+// dataset being served.
+pub fn split(path: &str) -> (Option<&str>, &str) {
+    let rest = match path.strip_prefix("/v/") {
+        Some(rest) => rest,
+        None => return (None, path),
+    };
+    match rest.find('/') {
+        Some(at) if at > 0 => (Some(&rest[..at]), &rest[at..]),
+        _ => (None, path),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Named<'a> {
+    /// Name to ask for it by, in /v/<name>/ or X-Dataset; absent for the dataset served by default
+    name: Option<&'a str>,
+    /// `delta::fingerprint` of the dataset
+    version: &'a str,
+    /// Number of banks in the dataset
+    banks: usize,
+    /// Unix time the dataset was crawled, when known
+    generated_at: Option<u64>,
+}
+
+fn named<'a>(name: Option<&'a str>, snapshot: &'a Snapshot) -> Named<'a> {
+    Named {
+        name,
+        version: &snapshot.version,
+        banks: snapshot.banks.len(),
+        generated_at: snapshot.generated_at,
+    }
+}
+
+/// Datasets this server answers from: the one served by default and any named ones
+#[utoipa::path(
+    get,
+    path = "/datasets",
+    operation_id = "datasets",
+    responses((status = 200, description = "The default dataset first, then named ones by name", body = [Named]))
+)]
+pub fn handle(state: &State) -> Response<Body> {
+    let current = state.snapshot();
+    let datasets = std::iter::once(named(None, &current))
+        .chain(state.named.iter().map(|(name, snapshot)| named(Some(name), snapshot)))
+        .collect::<Vec<Named>>();
+    json(StatusCode::OK, &datasets)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn named_dataset_test() {
+        use hyper::{Body, Request, StatusCode};
+        use serde_json::Value;
+        use crate::dataset::Dataset;
+        use crate::server::datasets::split;
+        use crate::server::{route, Config, State};
+        use crate::Bank;
+
+        assert_eq!(split("/v/2024-06-01/banks"), (Some("2024-06-01"), "/banks"));
+        assert_eq!(split("/banks"), (None, "/banks"));
+        assert_eq!(split("/v//banks"), (None, "/v//banks"));
+
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        let state = State::new(vec![neko.clone()], Config::default())
+            .with_dataset("staging", Dataset::new(vec![neko, inu]));
+        let banks = |request: Request<Body>| {
+            let response = route(&request, &state);
+            async move {
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let (status, live) = banks(get("/banks")).await;
+        assert_eq!((status, live.as_array().map(Vec::len)), (StatusCode::OK, Some(1)));
+        let (_, staging) = banks(get("/v/staging/banks")).await;
+        assert_eq!(staging.as_array().map(Vec::len), Some(2));
+        let request = Request::get("/banks").header("x-dataset", "staging").body(Body::empty()).unwrap();
+        assert_eq!(banks(request).await.1.as_array().map(Vec::len), Some(2));
+        assert_eq!(banks(get("/v/prod/banks")).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(banks(get("/v/staging/metrics")).await.0, StatusCode::BAD_REQUEST);
+
+        let (_, datasets) = banks(get("/datasets")).await;
+        assert_eq!(datasets[0]["name"], Value::Null);
+        assert_eq!(datasets[1]["name"], "staging");
+        assert_eq!(datasets[1]["banks"], 2);
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use hyper::header::{
+    HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+    CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, ORIGIN, REFERRER_POLICY,
+    VARY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use hyper::{Body, HeaderMap, Method, Response, StatusCode};
+
+use crate::server::{Config, Snapshot};
+
+// Size of a whole listing when the body only holds one page of it.
+pub const TOTAL_COUNT: &str = "x-total-count";
+// `delta::fingerprint` of the dataset a response was answered from.
+pub const DATASET_VERSION: &str = "x-dataset-version";
+// Name of the dataset a request asks for, as an alternative to the /v/<name>/ prefix.
+pub const DATASET: &str = "x-dataset";
+// When that dataset was crawled, as an HTTP date, when known.
+pub const DATASET_GENERATED_AT: &str = "x-dataset-generated-at";
+
+// How long browsers may cache a preflight answer.
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+fn allowed_origin(config: &Config, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+    let origin = origin?;
+    if config.allowed_origins.iter().any(|allowed| allowed == "*") {
+        return Some(HeaderValue::from_static("*"));
+    }
+    let origin_str = origin.to_str().ok()?;
+    config
+        .allowed_origins
+        .iter()
+        .find(|allowed| allowed.as_str() == origin_str)
+        .map(|_| origin.clone())
+}
+
+// Answer to an `OPTIONS` request sent by a browser before a cross-origin call.
+pub fn preflight(config: &Config, request: &HeaderMap) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    if allowed_origin(config, request.get(ORIGIN)).is_some() {
+        let headers = response.headers_mut();
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<&str>>()
+            .join(", ");
+        if let Ok(methods) = HeaderValue::from_str(&methods) {
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Some(requested) = request.get(ACCESS_CONTROL_REQUEST_HEADERS) {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+        }
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE));
+    }
+    response
+}
+
+// Adds CORS, caching and security headers to every response.
+pub fn apply(config: &Config, request: &HeaderMap, response: &mut Response<Body>) {
+    let status = response.status();
+    let cacheable = status.is_success() || status == StatusCode::NOT_MODIFIED;
+    let headers = response.headers_mut();
+    if let Some(origin) = allowed_origin(config, request.get(ORIGIN)) {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static("ETag, X-Total-Count, X-Dataset-Version, X-Dataset-Generated-At"));
+    }
+    if !config.allowed_origins.is_empty() {
+        headers.insert(VARY, HeaderValue::from_static("Origin"));
+    }
+    // Without a max age clients may still keep responses, but must revalidate them with the ETag.
+    let cache_control = match config.cache_max_age {
+        Some(max_age) if cacheable => format!("public, max-age={}", max_age),
+        None if cacheable => "no-cache".to_owned(),
+        _ => "no-store".to_owned(),
+    };
+    if let Ok(cache_control) = HeaderValue::from_str(&cache_control) {
+        headers.insert(CACHE_CONTROL, cache_control);
+    }
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+}
+
+// Whether the client's cached copy still matches the snapshot. If-None-Match wins over
+// If-Modified-Since, as RFC 7232 requires.
+pub fn fresh(request: &HeaderMap, snapshot: &Snapshot) -> bool {
+    if let Some(tags) = request.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return tags
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == snapshot.etag);
+    }
+    match request
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+    {
+        // HTTP dates have second precision.
+        Some(since) => snapshot.loaded_at.duration_since(since).map_or(true, |d| d < Duration::from_secs(1)),
+        None => false,
+    }
+}
+
+fn last_modified(snapshot: &Snapshot) -> Option<HeaderValue> {
+    let secs = snapshot.loaded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    HeaderValue::from_str(&httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(secs))).ok()
+}
+
+// Validators for conditional requests, and the version of the dataset so clients notice it changed.
+pub fn validators(snapshot: &Snapshot, response: &mut Response<Body>) {
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&snapshot.etag) {
+        headers.insert(ETAG, etag);
+    }
+    if let Some(last_modified) = last_modified(snapshot) {
+        headers.insert(LAST_MODIFIED, last_modified);
+    }
+    if let Ok(version) = HeaderValue::from_str(&snapshot.version) {
+        headers.insert(DATASET_VERSION, version);
+    }
+    let generated_at = snapshot.generated_at.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+    if let Some(generated_at) = generated_at.and_then(|at| HeaderValue::from_str(&httpdate::fmt_http_date(at)).ok()) {
+        headers.insert(DATASET_GENERATED_AT, generated_at);
+    }
+}
+
+pub fn not_modified(snapshot: &Snapshot) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    validators(snapshot, &mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn cors_test() {
+        use hyper::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, ORIGIN};
+        use hyper::{Body, HeaderMap, Response};
+        use crate::server::headers::apply;
+        use crate::server::Config;
+
+        let config = Config {
+            allowed_origins: vec!["https://example.com".to_owned()],
+            cache_max_age: Some(60),
+            ..Config::default()
+        };
+        let mut request = HeaderMap::new();
+        request.insert(ORIGIN, "https://example.com".parse().unwrap());
+        let mut response = Response::new(Body::empty());
+        apply(&config, &request, &mut response);
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=60");
+
+        request.insert(ORIGIN, "https://evil.example".parse().unwrap());
+        let mut response = Response::new(Body::empty());
+        apply(&config, &request, &mut response);
+        assert!(response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[test]
+    fn fresh_test() {
+        use hyper::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+        use hyper::HeaderMap;
+        use crate::server::headers::fresh;
+        use crate::server::Snapshot;
+
+        let snapshot = Snapshot::new(Vec::new());
+        let mut request = HeaderMap::new();
+        assert!(!fresh(&request, &snapshot));
+
+        request.insert(IF_NONE_MATCH, format!("\"stale\", W/{}", snapshot.etag).parse().unwrap());
+        assert!(fresh(&request, &snapshot));
+        request.insert(IF_NONE_MATCH, "\"stale\"".parse().unwrap());
+        assert!(!fresh(&request, &snapshot));
+
+        let mut request = HeaderMap::new();
+        request.insert(IF_MODIFIED_SINCE, httpdate::fmt_http_date(snapshot.loaded_at).parse().unwrap());
+        assert!(fresh(&request, &snapshot));
+    }
+}
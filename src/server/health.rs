@@ -0,0 +1,92 @@
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::server::{json, State};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Health {
+    /// Whether the probe passed
+    ok: bool,
+    /// Banks in the dataset being served; absent from the liveness probe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    banks: Option<usize>,
+    /// Seconds since the dataset was crawled, or loaded when the crawl time is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    age_seconds: Option<u64>,
+    /// Why the server isn't ready
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Liveness probe: the process is up and answering
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    operation_id = "healthz",
+    responses((status = 200, description = "The server is running", body = Health))
+)]
+pub fn live() -> Response<Body> {
+    json(StatusCode::OK, &Health { ok: true, banks: None, age_seconds: None, reason: None })
+}
+
+/// Readiness probe: a dataset is loaded and, with a maximum age configured, recent enough
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    operation_id = "readyz",
+    responses(
+        (status = 200, description = "Ready to answer lookups", body = Health),
+        (status = 503, description = "The dataset is empty or older than the maximum age", body = Health),
+    )
+)]
+pub fn ready(state: &State) -> Response<Body> {
+    let snapshot = state.snapshot();
+    let age = snapshot.age();
+    let reason = if snapshot.banks.is_empty() {
+        Some("no dataset is loaded".to_owned())
+    } else {
+        state
+            .config
+            .ready_max_age
+            .filter(|max_age| age > *max_age)
+            .map(|max_age| format!("the dataset is older than {} seconds", max_age.as_secs()))
+    };
+    let status = if reason.is_none() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let health = Health {
+        ok: reason.is_none(),
+        banks: Some(snapshot.banks.len()),
+        age_seconds: Some(age.as_secs()),
+        reason,
+    };
+    json(status, &health)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn ready_test() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+        use hyper::StatusCode;
+        use crate::server::health::{live, ready};
+        use crate::server::{Config, State};
+        use crate::Bank;
+
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let config = Config {
+            ready_max_age: Some(Duration::from_secs(3600)),
+            ..Config::default()
+        };
+        assert_eq!(live().status(), StatusCode::OK);
+        assert_eq!(ready(&State::new(Vec::new(), config.clone())).status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let state = State::new(vec![neko.clone()], config.clone()).dated(Some(now - 60));
+        assert_eq!(ready(&state).status(), StatusCode::OK);
+        let state = State::new(vec![neko.clone()], config).dated(Some(now - 7200));
+        assert_eq!(ready(&state).status(), StatusCode::SERVICE_UNAVAILABLE);
+        // Without a maximum age, any dataset will do.
+        let state = State::new(vec![neko], Config::default()).dated(Some(now - 7200));
+        assert_eq!(ready(&state).status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, UNIX_EPOCH};
+
+use hyper::{Body, Method, Response, StatusCode};
+
+use crate::logging;
+use crate::server::{typed, State, ROUTES};
+
+// Upper bounds of the latency histogram buckets, in seconds.
+const BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0];
+
+#[derive(Debug, Default)]
+struct Latency {
+    // Requests at or under each of `BUCKETS`.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    seconds: f64,
+}
+
+// Requests served since the server started, by path and status. Paths outside the API are counted
+// together as "other", so scanners probing random paths can't grow the label set.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(&'static str, u16), u64>>,
+    latencies: Mutex<BTreeMap<&'static str, Latency>>,
+    // Requests being answered right now, which a shutdown waits for.
+    in_flight: AtomicUsize,
+}
+
+fn label(path: &str) -> &'static str {
+    ROUTES.iter().find(|route| **route == path).copied().unwrap_or("other")
+}
+
+impl Metrics {
+    pub fn started(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.requests.lock().unwrap_or_else(PoisonError::into_inner).values().sum()
+    }
+
+    // Counts a finished request, which `started` counted as in flight.
+    pub fn record(&self, path: &str, status: StatusCode, elapsed: Duration) {
+        let path = label(path);
+        let seconds = elapsed.as_secs_f64();
+        let _ = self.in_flight.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| count.checked_sub(1));
+        *self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((path, status.as_u16()))
+            .or_default() += 1;
+        let mut latencies = self.latencies.lock().unwrap_or_else(PoisonError::into_inner);
+        let latency = latencies.entry(path).or_default();
+        for (bucket, bound) in latency.buckets.iter_mut().zip(BUCKETS.iter()) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        latency.count += 1;
+        latency.seconds += seconds;
+    }
+
+    // The Prometheus text format.
+    fn render(&self, state: &State) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "# HELP zngn_http_requests_total Requests served, by path and status.");
+        let _ = writeln!(text, "# TYPE zngn_http_requests_total counter");
+        for ((path, status), count) in self.requests.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            let _ = writeln!(text, "zngn_http_requests_total{{path=\"{}\",status=\"{}\"}} {}", path, status, count);
+        }
+        let _ = writeln!(text, "# HELP zngn_http_request_duration_seconds Time to answer requests, by path.");
+        let _ = writeln!(text, "# TYPE zngn_http_request_duration_seconds histogram");
+        for (path, latency) in self.latencies.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            let name = "zngn_http_request_duration_seconds";
+            for (bound, count) in BUCKETS.iter().zip(latency.buckets.iter()) {
+                let _ = writeln!(text, "{}_bucket{{path=\"{}\",le=\"{}\"}} {}", name, path, bound, count);
+            }
+            let _ = writeln!(text, "{}_bucket{{path=\"{}\",le=\"+Inf\"}} {}", name, path, latency.count);
+            let _ = writeln!(text, "{}_sum{{path=\"{}\"}} {}", name, path, latency.seconds);
+            let _ = writeln!(text, "{}_count{{path=\"{}\"}} {}", name, path, latency.count);
+        }
+        let _ = writeln!(text, "# HELP zngn_http_requests_in_flight Requests being answered.");
+        let _ = writeln!(text, "# TYPE zngn_http_requests_in_flight gauge");
+        let _ = writeln!(text, "zngn_http_requests_in_flight {}", self.in_flight());
+        let _ = writeln!(text, "# HELP zngn_search_cache_hits_total Searches answered from the cache.");
+        let _ = writeln!(text, "# TYPE zngn_search_cache_hits_total counter");
+        let _ = writeln!(text, "zngn_search_cache_hits_total {}", state.search_cache.hits());
+        let _ = writeln!(text, "# HELP zngn_search_cache_misses_total Searches the cache could not answer.");
+        let _ = writeln!(text, "# TYPE zngn_search_cache_misses_total counter");
+        let _ = writeln!(text, "zngn_search_cache_misses_total {}", state.search_cache.misses());
+        let snapshot = state.snapshot();
+        let loaded = snapshot.loaded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let _ = writeln!(text, "# HELP zngn_dataset_banks Banks in the dataset being served.");
+        let _ = writeln!(text, "# TYPE zngn_dataset_banks gauge");
+        let _ = writeln!(text, "zngn_dataset_banks {}", snapshot.banks.len());
+        let _ = writeln!(text, "# HELP zngn_dataset_loaded_timestamp_seconds When the dataset being served was loaded.");
+        let _ = writeln!(text, "# TYPE zngn_dataset_loaded_timestamp_seconds gauge");
+        let _ = writeln!(text, "zngn_dataset_loaded_timestamp_seconds {}", loaded);
+        text
+    }
+}
+
+// One line per request: method, path with query, status and how long answering took.
+pub fn log(method: &Method, path: &str, status: StatusCode, elapsed: Duration) {
+    logging::info(&format!(
+        "{} {} {} {:.1}ms",
+        method,
+        path,
+        status.as_u16(),
+        elapsed.as_secs_f64() * 1000.0
+    ));
+}
+
+/// Request counts and latencies by path, and the size and age of the dataset, for Prometheus
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    operation_id = "metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+pub fn handle(state: &State) -> Response<Body> {
+    typed(
+        StatusCode::OK,
+        "text/plain; version=0.0.4; charset=utf-8",
+        Body::from(state.metrics.render(state)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn metrics_test() {
+        use std::time::Duration;
+        use hyper::StatusCode;
+        use crate::server::metrics::handle;
+        use crate::server::{Config, State};
+        use crate::Bank;
+
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let state = State::new(vec![neko], Config::default());
+        state.metrics.record("/banks", StatusCode::OK, Duration::from_millis(3));
+        state.metrics.record("/banks", StatusCode::OK, Duration::from_millis(30));
+        state.metrics.record("/wp-login.php", StatusCode::NOT_FOUND, Duration::from_millis(1));
+
+        let response = handle(&state);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("zngn_http_requests_total{path=\"/banks\",status=\"200\"} 2\n"));
+        assert!(text.contains("zngn_http_requests_total{path=\"other\",status=\"404\"} 1\n"));
+        assert!(text.contains("zngn_http_request_duration_seconds_bucket{path=\"/banks\",le=\"0.005\"} 1\n"));
+        assert!(text.contains("zngn_http_request_duration_seconds_bucket{path=\"/banks\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("zngn_http_request_duration_seconds_count{path=\"/banks\"} 2\n"));
+        assert!(text.contains("zngn_dataset_banks 1\n"));
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyper::header::{HeaderValue, CONTENT_TYPE, WWW_AUTHENTICATE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::cancel::CancellationToken;
+use crate::dataset::Dataset;
+use crate::delta;
+use crate::layout::Layout;
+use crate::logging;
+use crate::{Bank, Error};
+
+mod auth;
+mod banks;
+mod branches;
+mod cache;
+mod changes;
+mod datasets;
+mod headers;
+mod health;
+mod metrics;
+mod reload;
+mod search;
+mod stats;
+mod version;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Serve a Swagger UI page for the OpenAPI document at /docs.
+    pub swagger_ui: bool,
+    // Origins allowed to call the API from a browser; "*" allows any.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<Method>,
+    // Lets clients and proxies cache successful responses for this many seconds.
+    pub cache_max_age: Option<u64>,
+    // When non-empty, every endpoint except the API documentation requires one of these keys.
+    pub api_keys: Vec<String>,
+    // Poll the output directory this often and reload the dataset once a new crawl has finished.
+    pub watch_interval: Option<Duration>,
+    // Earlier datasets kept after reloads, so /changes can answer clients a few versions behind.
+    pub keep_versions: usize,
+    // /readyz fails once the dataset is older than this, so an orchestrator notices crawls have stopped.
+    pub ready_max_age: Option<Duration>,
+    // How long a shutdown waits for requests under way before dropping them.
+    pub drain_timeout: Duration,
+    // Search pages kept for repeated queries, least recently used dropped first; 0 turns caching off.
+    pub search_cache_size: usize,
+    // How long a cached search page is served before the query is searched again.
+    pub search_cache_ttl: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            swagger_ui: false,
+            allowed_origins: Vec::new(),
+            allowed_methods: vec![Method::GET, Method::OPTIONS],
+            cache_max_age: None,
+            api_keys: Vec::new(),
+            watch_interval: None,
+            keep_versions: 10,
+            ready_max_age: None,
+            drain_timeout: Duration::from_secs(30),
+            search_cache_size: 1024,
+            search_cache_ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+// One loaded dataset, with the validators clients use to skip downloading it again.
+#[derive(Debug)]
+pub struct Snapshot {
+    banks: Vec<Bank>,
+    etag: String,
+    loaded_at: SystemTime,
+    // Unix time the banks were crawled, from the manifest, when known.
+    generated_at: Option<u64>,
+    // `delta::fingerprint` of the banks, which clients name in /changes?since=.
+    version: String,
+}
+
+impl Snapshot {
+    fn new(banks: Vec<Bank>) -> Self {
+        let digest = Sha256::digest(serde_json::to_vec(&banks).unwrap_or_default());
+        let etag = format!("\"{:x}\"", digest);
+        let version = delta::fingerprint(&banks);
+        Self {
+            banks,
+            etag,
+            loaded_at: SystemTime::now(),
+            generated_at: None,
+            version,
+        }
+    }
+
+    fn dated(mut self, generated_at: Option<u64>) -> Self {
+        self.generated_at = generated_at;
+        self
+    }
+
+    // How old the banks are: since they were crawled, or else since they were loaded.
+    fn age(&self) -> Duration {
+        let generated = self.generated_at.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        SystemTime::now()
+            .duration_since(generated.unwrap_or(self.loaded_at))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub struct State {
+    snapshot: RwLock<Arc<Snapshot>>,
+    // Previous snapshots, newest first.
+    history: RwLock<VecDeque<Arc<Snapshot>>>,
+    // Further snapshots served under /v/<name>/, such as a crawl waiting to be promoted.
+    named: BTreeMap<String, Arc<Snapshot>>,
+    config: Config,
+    source: Option<Layout>,
+    metrics: metrics::Metrics,
+    search_cache: cache::SearchCache,
+}
+
+impl State {
+    pub fn new(banks: Vec<Bank>, config: Config) -> Self {
+        Self {
+            snapshot: RwLock::new(Arc::new(Snapshot::new(banks))),
+            history: RwLock::new(VecDeque::new()),
+            named: BTreeMap::new(),
+            search_cache: cache::SearchCache::new(config.search_cache_size, config.search_cache_ttl),
+            config,
+            source: None,
+            metrics: metrics::Metrics::default(),
+        }
+    }
+
+    // Records when the initial dataset was crawled, as a `Dataset` knows it.
+    pub fn dated(mut self, generated_at: Option<u64>) -> Self {
+        let snapshot = self.snapshot.get_mut().unwrap_or_else(PoisonError::into_inner);
+        // Nothing has taken the snapshot yet while the state is being built.
+        if let Some(snapshot) = Arc::get_mut(snapshot) {
+            snapshot.generated_at = generated_at;
+        }
+        self
+    }
+
+    // Serves `dataset` as well, under /v/<name>/ or to requests naming it in X-Dataset.
+    pub fn with_dataset(mut self, name: &str, dataset: Dataset) -> Self {
+        let snapshot = Snapshot::new(dataset.banks).dated(dataset.generated_at);
+        self.named.insert(name.to_owned(), Arc::new(snapshot));
+        self
+    }
+
+    // Lets the dataset be reloaded from `layout` while serving.
+    pub fn reloadable(mut self, layout: Layout) -> Self {
+        self.source = Some(layout);
+        self
+    }
+
+    // The current snapshot; requests keep using the one they started with even if a reload swaps it.
+    fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    // The snapshot with this version, if it is the current one or still kept.
+    fn version(&self, version: &str) -> Option<Arc<Snapshot>> {
+        let current = self.snapshot();
+        if current.version == version {
+            return Some(current);
+        }
+        self.history.read().unwrap_or_else(PoisonError::into_inner).iter().find(|snapshot| snapshot.version == version).cloned()
+    }
+
+    // Swaps in a new dataset, keeping the one it replaces in the history unless nothing changed.
+    fn swap(&self, snapshot: Snapshot) -> Arc<Snapshot> {
+        let snapshot = Arc::new(snapshot);
+        let previous = std::mem::replace(&mut *self.snapshot.write().unwrap_or_else(PoisonError::into_inner), snapshot.clone());
+        let mut history = self.history.write().unwrap_or_else(PoisonError::into_inner);
+        if previous.version != snapshot.version {
+            history.retain(|kept| kept.version != previous.version);
+            history.push_front(previous);
+        }
+        history.truncate(self.config.keep_versions);
+        self.search_cache.clear();
+        snapshot
+    }
+
+    // Loads the dataset again and swaps it in, keeping the old one if loading fails.
+    pub fn reload(&self) -> Result<Option<Arc<Snapshot>>, Error> {
+        let layout = match &self.source {
+            Some(layout) => layout,
+            None => return Ok(None),
+        };
+        let dataset = Dataset::open(layout)?;
+        Ok(Some(self.swap(Snapshot::new(dataset.banks).dated(dataset.generated_at))))
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "zngn", description = "Lookup service for zengin bank and branch codes"),
+    paths(
+        banks::handle,
+        branches::handle,
+        search::handle,
+        changes::dataset,
+        changes::handle,
+        changes::bank,
+        reload::handle,
+        metrics::handle,
+        health::live,
+        health::ready,
+        version::handle,
+        stats::handle,
+        datasets::handle
+    ),
+    components(schemas(
+        ErrorBody,
+        banks::BankSummary,
+        branches::BranchSummary,
+        search::Page,
+        search::HitBody,
+        search::Kind,
+        crate::search::MatchField,
+        crate::search::Highlight,
+        reload::Reloaded,
+        health::Health,
+        version::Version,
+        stats::Stats,
+        stats::Counts,
+        datasets::Named
+    ))
+)]
+struct ApiDoc;
+
+pub fn openapi_json() -> String {
+    ApiDoc::openapi().to_pretty_json().unwrap_or_default()
+}
+
+// Swagger UI itself is loaded from a CDN so the binary does not have to bundle it.
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>zngn API</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+// Set field by field rather than through `Response::builder`, whose result would have to be unwrapped.
+fn typed(status: StatusCode, content_type: &'static str, body: Body) -> Response<Body> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(body) => typed(status, "application/json", Body::from(body)),
+        Err(_) => typed(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "application/json",
+            Body::from(r#"{"error":"failed to encode the response"}"#),
+        ),
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, &ErrorBody { error: message })
+}
+
+fn unauthorized() -> Response<Body> {
+    let mut response = error(StatusCode::UNAUTHORIZED, "missing or invalid API key");
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+// Probes are answered without a key, since orchestrators send none.
+fn public(path: &str) -> bool {
+    path == "/openapi.json" || path == "/docs" || path == "/healthz" || path == "/readyz"
+}
+
+// Paths `route` answers, which /metrics counts requests by.
+const ROUTES: [&str; 15] = [
+    "/banks",
+    "/branches",
+    "/search",
+    "/dataset",
+    "/version",
+    "/stats",
+    "/datasets",
+    "/changes",
+    "/bank-changes",
+    "/reload",
+    "/metrics",
+    "/healthz",
+    "/readyz",
+    "/openapi.json",
+    "/docs",
+];
+
+fn route(request: &Request<Body>, state: &State) -> Response<Body> {
+    let (name, path) = datasets::split(request.uri().path());
+    if request.method() != Method::OPTIONS
+        && !public(path)
+        && !auth::authorized(&state.config.api_keys, request.headers())
+    {
+        return unauthorized();
+    }
+    let name = name.or_else(|| request.headers().get(headers::DATASET).and_then(|name| name.to_str().ok()));
+    let query = request.uri().query().unwrap_or("");
+    let dataset: Option<fn(&str, &Snapshot) -> Response<Body>> = match (request.method(), path) {
+        (&Method::GET, "/banks") => Some(banks::handle),
+        (&Method::GET, "/branches") => Some(branches::handle),
+        (&Method::GET, "/search") => Some(search::handle),
+        (&Method::GET, "/dataset") => Some(changes::dataset),
+        (&Method::GET, "/version") => Some(version::handle),
+        (&Method::GET, "/stats") => Some(stats::handle),
+        _ => None,
+    };
+    if let Some(handle) = dataset {
+        let snapshot = match name {
+            Some(name) => match state.named.get(name) {
+                Some(snapshot) => snapshot.clone(),
+                None => return error(StatusCode::NOT_FOUND, &format!("no dataset named {}", name)),
+            },
+            None => state.snapshot(),
+        };
+        if headers::fresh(request.headers(), &snapshot) {
+            return headers::not_modified(&snapshot);
+        }
+        let mut response = if path == "/search" {
+            state.search_cache.search(query, &snapshot)
+        } else {
+            handle(query, &snapshot)
+        };
+        if response.status().is_success() {
+            headers::validators(&snapshot, &mut response);
+        }
+        return response;
+    }
+    // Other endpoints are about the dataset being served, which named ones are only compared with.
+    if name.is_some() && request.method() != Method::OPTIONS {
+        return error(StatusCode::BAD_REQUEST, "only data endpoints can be asked of a named dataset");
+    }
+    match (request.method(), path) {
+        (&Method::OPTIONS, _) => headers::preflight(&state.config, request.headers()),
+        (&Method::GET, "/datasets") => datasets::handle(state),
+        (&Method::GET, "/changes") => changes::handle(query, state),
+        (&Method::GET, "/bank-changes") => changes::bank(query, state),
+        (&Method::POST, "/reload") => reload::handle(state),
+        (&Method::GET, "/metrics") => metrics::handle(state),
+        (&Method::GET, "/healthz") => health::live(),
+        (&Method::GET, "/readyz") => health::ready(state),
+        (&Method::GET, "/openapi.json") => typed(StatusCode::OK, "application/json", Body::from(openapi_json())),
+        (&Method::GET, "/docs") if state.config.swagger_ui => {
+            typed(StatusCode::OK, "text/html; charset=utf-8", Body::from(SWAGGER_UI))
+        }
+        _ => error(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+// Resolves on SIGTERM, as orchestrators send to stop a service, or on Ctrl-C.
+async fn terminated() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+// Serves until SIGTERM or Ctrl-C; see `serve_until`.
+pub async fn serve(addr: SocketAddr, state: State) -> Result<(), Error> {
+    serve_until(addr, state, terminated()).await
+}
+
+// Serves until `shutdown` resolves, then stops accepting connections and waits up to the drain
+// timeout for requests under way to be answered. The log is flushed before returning.
+pub async fn serve_until<F>(addr: SocketAddr, state: State, shutdown: F) -> Result<(), Error>
+where
+    F: Future<Output = ()>,
+{
+    let state = Arc::new(state);
+    if let (Some(interval), Some(layout)) = (state.config.watch_interval, state.source.clone()) {
+        tokio::spawn(reload::watch(state.clone(), layout, interval));
+    }
+    let service_state = state.clone();
+    let make_service = make_service_fn(move |_| {
+        let state = service_state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let state = state.clone();
+                async move {
+                    let started = Instant::now();
+                    state.metrics.started();
+                    let mut response = route(&request, &state);
+                    headers::apply(&state.config, request.headers(), &mut response);
+                    let elapsed = started.elapsed();
+                    let uri = request.uri();
+                    let target = uri.path_and_query().map(|target| target.as_str()).unwrap_or_else(|| uri.path());
+                    metrics::log(request.method(), target, response.status(), elapsed);
+                    state.metrics.record(datasets::split(uri.path()).1, response.status(), elapsed);
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let stopping = CancellationToken::new();
+    let stopped = stopping.clone();
+    let server = Server::try_bind(&addr)
+        .map_err(Error::ServeFailed)?
+        .serve(make_service)
+        .with_graceful_shutdown(async move { stopped.cancelled().await });
+    tokio::pin!(server);
+    tokio::pin!(shutdown);
+    let served = tokio::select! {
+        served = &mut server => served,
+        _ = &mut shutdown => {
+            logging::info(&format!(
+                "shutting down, waiting up to {}s for {} requests under way",
+                state.config.drain_timeout.as_secs(),
+                state.metrics.in_flight()
+            ));
+            stopping.cancel();
+            match tokio::time::timeout(state.config.drain_timeout, &mut server).await {
+                Ok(served) => served,
+                Err(_) => {
+                    logging::warn(&format!("dropped {} requests still under way", state.metrics.in_flight()));
+                    Ok(())
+                }
+            }
+        }
+    };
+    logging::info(&format!("stopped after serving {} requests", state.metrics.total()));
+    logging::flush();
+    served.map_err(Error::ServeFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn shutdown_test() {
+        use std::net::TcpListener;
+        use tokio::sync::oneshot;
+        use crate::server::{serve_until, Config, State};
+
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_until(addr, State::new(Vec::new(), Config::default()), async {
+            let _ = stopped.await;
+        }));
+        let url = format!("http://{}/healthz", addr);
+        let mut response = reqwest::get(url.as_str()).await;
+        for _ in 0..50 {
+            if response.is_ok() {
+                break;
+            }
+            tokio::time::delay_for(std::time::Duration::from_millis(20)).await;
+            response = reqwest::get(url.as_str()).await;
+        }
+        assert!(response.unwrap().status().is_success());
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(reqwest::get(url.as_str()).await.is_err());
+    }
+}
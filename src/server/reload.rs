@@ -0,0 +1,77 @@
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+use tokio::time::interval;
+
+use crate::layout::Layout;
+use crate::lock;
+use crate::logging;
+use crate::server::{error, json, State};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Reloaded {
+    /// Number of banks in the dataset now being served
+    banks: usize,
+    /// Version of the dataset now being served, for `/changes?since=`
+    version: String,
+}
+
+/// Load the dataset from disk again and swap it in without dropping requests
+#[utoipa::path(
+    post,
+    path = "/reload",
+    operation_id = "reload",
+    responses(
+        (status = 200, description = "The new dataset is being served", body = Reloaded),
+        (status = 500, description = "Loading failed and the previous dataset is still served", body = ErrorBody),
+    )
+)]
+pub fn handle(state: &State) -> Response<Body> {
+    match state.reload() {
+        Ok(Some(snapshot)) => json(
+            StatusCode::OK,
+            &Reloaded {
+                banks: snapshot.banks.len(),
+                version: snapshot.version.clone(),
+            },
+        ),
+        Ok(None) => error(StatusCode::NOT_IMPLEMENTED, "this server was not started from an output directory"),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &format!("reload failed: {}", e)),
+    }
+}
+
+fn modified(layout: &Layout) -> Option<SystemTime> {
+    fs::metadata(layout.banks_file()).and_then(|m| m.modified()).ok()
+}
+
+// Reloads once the saved bank list changes and no crawl holds the lock, so a half written
+// snapshot is never picked up.
+pub async fn watch(state: Arc<State>, layout: Layout, period: Duration) {
+    let mut loaded = modified(&layout);
+    let mut crawled = false;
+    let mut ticks = interval(period);
+    loop {
+        ticks.tick().await;
+        if lock::is_held(&layout) {
+            crawled = true;
+            continue;
+        }
+        let current = modified(&layout);
+        if current == loaded && !crawled {
+            continue;
+        }
+        match state.reload() {
+            Ok(snapshot) => logging::info(&format!(
+                "reloaded {} banks",
+                snapshot.map(|snapshot| snapshot.banks.len()).unwrap_or_default()
+            )),
+            Err(e) => logging::error(&format!("reload failed, still serving the previous dataset: {}", e)),
+        }
+        loaded = current;
+        crawled = false;
+    }
+}
@@ -0,0 +1,169 @@
+use hyper::{Body, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::filter::Filter;
+use crate::page;
+use crate::search::{self, Highlight, Hit, MatchField, SearchHit};
+use crate::server::{error, typed, Snapshot};
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Bank,
+    Branch,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Params {
+    /// Substring of a name or phonetic reading, or a numeric code prefix
+    q: String,
+    /// Only return hits of this kind
+    #[serde(rename = "type")]
+    #[param(rename = "type", inline)]
+    kind: Option<Kind>,
+    /// Page size, at most 100
+    #[param(default = 20, maximum = 100)]
+    limit: Option<usize>,
+    /// Number of hits skipped before the page starts
+    #[serde(default)]
+    offset: usize,
+    /// Only search banks matching a filter, e.g. `name~銀行 AND branch_count>100`
+    #[serde(rename = "where")]
+    #[param(rename = "where")]
+    filter: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HitBody<'a> {
+    kind: Kind,
+    bank_code: &'a str,
+    bank_name: &'a str,
+    branch_code: Option<&'a str>,
+    branch_name: Option<&'a str>,
+    phonetic: &'a str,
+    field: MatchField,
+    /// The text the query was found in: the name, reading or code of the hit, or a former name of the bank
+    matched: &'a str,
+    /// From 0 to 1, higher for whole and leading matches
+    score: f32,
+    /// Where the query occurs in `matched`
+    highlights: Vec<Highlight>,
+}
+
+impl<'a> From<SearchHit<'a>> for HitBody<'a> {
+    fn from(hit: SearchHit<'a>) -> Self {
+        let (kind, bank, branch) = match hit.hit {
+            Hit::Bank(bank) => (Kind::Bank, bank, None),
+            Hit::Branch(bank, branch) => (Kind::Branch, bank, Some(branch)),
+        };
+        Self {
+            kind,
+            bank_code: &bank.code.0,
+            bank_name: &bank.name,
+            branch_code: branch.map(|branch| branch.code.as_str()),
+            branch_name: branch.map(|branch| &*branch.name),
+            phonetic: branch.map_or(&bank.phonetic, |branch| &branch.phonetic),
+            field: hit.field,
+            matched: hit.text,
+            score: hit.score,
+            highlights: hit.highlights,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<'a> {
+    /// Number of hits across all pages
+    total: usize,
+    limit: usize,
+    offset: usize,
+    results: Vec<HitBody<'a>>,
+}
+
+/// Search banks and branches by name, phonetic reading or code
+#[utoipa::path(
+    get,
+    path = "/search",
+    operation_id = "search",
+    params(Params),
+    responses(
+        (status = 200, description = "One page of hits", body = Page),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+        (status = 400, description = "Missing or malformed parameters", body = ErrorBody),
+    )
+)]
+pub fn handle(query: &str, snapshot: &Snapshot) -> Response<Body> {
+    match answer(query, snapshot) {
+        Ok(body) => typed(StatusCode::OK, "application/json", Body::from(body)),
+        Err(response) => response,
+    }
+}
+
+// The JSON of one page of hits, or the response refusing the query.
+#[allow(clippy::result_large_err)]
+pub fn answer(query: &str, snapshot: &Snapshot) -> Result<Vec<u8>, Response<Body>> {
+    let params = serde_urlencoded::from_str::<Params>(query).map_err(|e| error(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    if params.q.is_empty() {
+        return Err(error(StatusCode::BAD_REQUEST, "q is required"));
+    }
+    let filter = params
+        .filter
+        .as_deref()
+        .map(str::parse::<Filter>)
+        .transpose()
+        .map_err(|e| error(StatusCode::BAD_REQUEST, &e))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let banks = snapshot
+        .banks
+        .iter()
+        .filter(|bank| filter.as_ref().is_none_or(|filter| filter.matches(bank)));
+    let hits = search::search_hits(banks, &params.q).filter(|hit| {
+        matches!(
+            (params.kind, hit.hit),
+            (None, _) | (Some(Kind::Bank), Hit::Bank(_)) | (Some(Kind::Branch), Hit::Branch(..))
+        )
+    });
+    let hits = page::paginate(hits, params.offset, Some(limit));
+    let page = Page {
+        total: hits.total,
+        limit,
+        offset: params.offset,
+        results: hits.items.into_iter().map(HitBody::from).collect(),
+    };
+    serde_json::to_vec(&page).map_err(|_| error(StatusCode::INTERNAL_SERVER_ERROR, "failed to encode the response"))
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn paginated_search_test() {
+        use hyper::StatusCode;
+        use serde_json::Value;
+        use crate::server::search::handle;
+        use crate::server::Snapshot;
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        bank.append_branch(Branch::new("ねこ町支店".to_owned(), "ﾈｺﾏﾁ".to_owned(), "456".to_owned()));
+        bank.append_branch(Branch::new("ねこ台支店".to_owned(), "ﾈｺﾀﾞｲ".to_owned(), "457".to_owned()));
+        let snapshot = Snapshot::new(vec![bank]);
+
+        let response = handle("q=%E3%81%AD%E3%81%93&type=branch&limit=1&offset=1", &snapshot);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let page: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["results"][0]["branch_code"], "457");
+        assert_eq!(page["results"][0]["field"], "name");
+        assert_eq!(page["results"][0]["highlights"], serde_json::json!([{"start": 0, "end": 2}]));
+
+        assert_eq!(handle("q=x&where=branch_count~1", &snapshot).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(handle("type=branch", &snapshot).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(handle("q=x&type=atm", &snapshot).status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::category::Category;
+use crate::server::{json, Snapshot};
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct Counts {
+    /// Number of banks
+    banks: usize,
+    /// Number of branches of those banks
+    branches: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Stats<'a> {
+    /// `delta::fingerprint` of the dataset being served
+    version: &'a str,
+    /// Number of banks in the dataset
+    banks: usize,
+    /// Number of branches in the dataset
+    branches: usize,
+    /// Banks with no branches, usually ones the crawl did not reach
+    banks_without_branches: usize,
+    /// Unix time the dataset was crawled, when known
+    generated_at: Option<u64>,
+    /// Unix time the server loaded the dataset
+    loaded_at: u64,
+    /// Seconds since the dataset was crawled, or loaded when the crawl time is unknown
+    age_seconds: u64,
+    /// Banks and branches by kind of institution, keyed by category slug such as shinkin
+    categories: BTreeMap<&'static str, Counts>,
+}
+
+/// Counts of the dataset being served, for dashboards watching its health
+#[utoipa::path(
+    get,
+    path = "/stats",
+    operation_id = "stats",
+    responses(
+        (status = 200, description = "Counts and age of the dataset", body = Stats),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+    )
+)]
+pub fn handle(_query: &str, snapshot: &Snapshot) -> Response<Body> {
+    let mut categories = BTreeMap::<&'static str, Counts>::new();
+    for bank in &snapshot.banks {
+        let counts = categories.entry(Category::of(bank).slug()).or_default();
+        counts.banks += 1;
+        counts.branches += bank.branches.len();
+    }
+    let stats = Stats {
+        version: &snapshot.version,
+        banks: snapshot.banks.len(),
+        branches: snapshot.banks.iter().map(|bank| bank.branches.len()).sum(),
+        banks_without_branches: snapshot.banks.iter().filter(|bank| bank.branches.is_empty()).count(),
+        generated_at: snapshot.generated_at,
+        loaded_at: snapshot.loaded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        age_seconds: snapshot.age().as_secs(),
+        categories,
+    };
+    json(StatusCode::OK, &stats)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn stats_test() {
+        use serde_json::Value;
+        use crate::server::stats::handle;
+        use crate::server::Snapshot;
+        use crate::{Bank, Branch};
+
+        let mut mizuho = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x1".to_owned());
+        mizuho.branches = vec![
+            Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()),
+            Branch::new("丸の内中央支店".to_owned(), "ﾏﾙﾉｳﾁﾁﾕｳｵｳ".to_owned(), "004".to_owned()),
+        ];
+        let mut jonan = Bank::new("城南信用金庫".to_owned(), "ｼﾞﾖｳﾅﾝｼﾝｷﾝ".to_owned(), "1344".to_owned(), "0x1344".to_owned());
+        jonan.branches = vec![Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned())];
+        let neko = Bank::new("ねこ信用金庫".to_owned(), "ﾈｺｼﾝｷﾝ".to_owned(), "1999".to_owned(), "0x1999".to_owned());
+        let snapshot = Snapshot::new(vec![mizuho, jonan, neko]).dated(Some(1_700_000_000));
+
+        let response = handle("", &snapshot);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let stats: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["version"], snapshot.version.as_str());
+        assert_eq!((stats["banks"].as_u64(), stats["branches"].as_u64()), (Some(3), Some(3)));
+        assert_eq!(stats["banks_without_branches"], 1);
+        assert_eq!(stats["generated_at"], 1_700_000_000);
+        assert!(stats["age_seconds"].as_u64().unwrap() > 0);
+        assert_eq!(stats["categories"]["city"], serde_json::json!({"banks": 1, "branches": 2}));
+        assert_eq!(stats["categories"]["shinkin"], serde_json::json!({"banks": 2, "branches": 1}));
+        assert!(stats["categories"].get("regional").is_none());
+    }
+}
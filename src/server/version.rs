@@ -0,0 +1,70 @@
+use std::time::UNIX_EPOCH;
+
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::dataset::SCHEMA_VERSION;
+use crate::server::{json, Snapshot};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Version<'a> {
+    /// `delta::fingerprint` of the dataset being served, as in X-Dataset-Version and `/changes?since=`
+    version: &'a str,
+    /// Unix time the dataset was crawled, when known
+    generated_at: Option<u64>,
+    /// Unix time the server loaded the dataset
+    loaded_at: u64,
+    /// Number of banks in the dataset
+    banks: usize,
+    /// Version of the dataset format
+    schema_version: u32,
+    /// Version of the zngn serving it
+    server: &'static str,
+}
+
+/// Version of the dataset being served, to tell when the data behind the other endpoints changed
+#[utoipa::path(
+    get,
+    path = "/version",
+    operation_id = "version",
+    responses(
+        (status = 200, description = "The dataset version; X-Dataset-Version carries it on every data endpoint", body = Version),
+        (status = 304, description = "The dataset has not changed since the ETag in If-None-Match"),
+    )
+)]
+pub fn handle(_query: &str, snapshot: &Snapshot) -> Response<Body> {
+    let version = Version {
+        version: &snapshot.version,
+        generated_at: snapshot.generated_at,
+        loaded_at: snapshot.loaded_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        banks: snapshot.banks.len(),
+        schema_version: SCHEMA_VERSION,
+        server: env!("CARGO_PKG_VERSION"),
+    };
+    json(StatusCode::OK, &version)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn version_test() {
+        use serde_json::Value;
+        use crate::server::headers::{validators, DATASET_GENERATED_AT, DATASET_VERSION};
+        use crate::server::version::handle;
+        use crate::server::Snapshot;
+        use crate::Bank;
+
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let snapshot = Snapshot::new(vec![neko]).dated(Some(1_700_000_000));
+        let mut response = handle("", &snapshot);
+        validators(&snapshot, &mut response);
+        assert_eq!(response.headers()[DATASET_VERSION], snapshot.version.as_str());
+        assert_eq!(response.headers()[DATASET_GENERATED_AT], "Tue, 14 Nov 2023 22:13:20 GMT");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let version: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(version["version"], snapshot.version.as_str());
+        assert_eq!(version["generated_at"], 1_700_000_000);
+        assert_eq!(version["banks"], 1);
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+// For a site that wants a session: a page fetched before the first form is sent, whose cookies go
+// with every later request and whose hidden form fields, such as a CSRF token, go with every form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionSource {
+    // Relative to the source's base, or a full URL.
+    pub form_page: String,
+    // Hidden fields copied into forms, by name; every hidden field of the page when empty.
+    pub token_fields: Vec<String>,
+    // Statuses the site answers a stale session with; the page is fetched again and the form resent.
+    pub refresh_on: Vec<u16>,
+}
+
+impl Default for SessionSource {
+    fn default() -> Self {
+        Self {
+            form_page: String::new(),
+            token_fields: Vec::new(),
+            refresh_on: vec![403, 419],
+        }
+    }
+}
+
+// Cookies the site set, by host, sent back on later requests to that host. Expiry, paths and other
+// attributes are ignored: a crawl is short and only ever talks to the one site.
+#[derive(Debug, Default)]
+pub struct CookieJar(Mutex<BTreeMap<String, BTreeMap<String, String>>>);
+
+impl CookieJar {
+    pub fn store(&self, url: &Url, headers: &HeaderMap) {
+        let host = match url.host_str() {
+            Some(host) => host,
+            None => return,
+        };
+        let mut jar = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for header in headers.get_all(SET_COOKIE) {
+            let pair = header.to_str().ok().and_then(|cookie| cookie.split(';').next());
+            if let Some((name, value)) = pair.and_then(|pair| pair.split_once('=')) {
+                let cookies = jar.entry(host.to_owned()).or_default();
+                cookies.insert(name.trim().to_owned(), value.trim().to_owned());
+            }
+        }
+    }
+
+    // The Cookie header for a request to `url`, if the host set any.
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let jar = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let cookies = jar.get(url.host_str()?).filter(|cookies| !cookies.is_empty())?;
+        Some(cookies.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<String>>().join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn cookie_jar_test() {
+        use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+        use reqwest::Url;
+        use crate::session::CookieJar;
+
+        let jar = CookieJar::default();
+        let site = Url::parse("https://zengin.ajtw.net/ginkou.php").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("PHPSESSID=abc; path=/; HttpOnly"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("lang=ja"));
+        jar.store(&site, &headers);
+        assert_eq!(jar.header(&site).as_deref(), Some("PHPSESSID=abc; lang=ja"));
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("PHPSESSID=def"));
+        jar.store(&site, &headers);
+        assert_eq!(jar.header(&site).as_deref(), Some("PHPSESSID=def; lang=ja"));
+        assert_eq!(jar.header(&Url::parse("https://www.example.com/").unwrap()), None);
+    }
+}
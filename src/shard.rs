@@ -0,0 +1,139 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::{Bank, BankCode, Error};
+
+// One of `count` parts of the bank list, numbered from 1, for crawling it on several machines at once.
+// A bank's part follows from its code alone, so workers agree on it without talking to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    pub fn contains(&self, code: &BankCode) -> bool {
+        // FNV-1a, which unlike std's hasher is the same on every machine and release.
+        let hash = code
+            .0
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+// "i/n", e.g. 2/4 for the second of four parts.
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s.split_once('/').ok_or_else(|| format!("expected i/n, got {}", s))?;
+        let index = index.trim().parse::<usize>().map_err(|_| format!("expected i/n, got {}", s))?;
+        let count = count.trim().parse::<usize>().map_err(|_| format!("expected i/n, got {}", s))?;
+        if index == 0 || index > count {
+            return Err(format!("shard {} is not between 1 and {}", index, count));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+// The part a crawl into the output directory covered, recorded in shard.json; None for a whole crawl.
+pub fn load(layout: &Layout) -> Result<Option<Shard>, Error> {
+    match fs::read(layout.shard_file()) {
+        Ok(json) => serde_json::from_slice(&json).map(Some).map_err(Error::invalid(layout.shard_file())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::FileFailed { path: layout.shard_file(), source: e }),
+    }
+}
+
+pub fn save(layout: &Layout, shard: Option<Shard>) -> Result<(), Error> {
+    match shard {
+        Some(shard) => {
+            let json = serde_json::to_vec(&shard).map_err(Error::SerializeFailed)?;
+            fs::write(layout.shard_file(), json).map_err(Error::file(layout.shard_file()))
+        }
+        None => match fs::remove_file(layout.shard_file()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::FileFailed { path: layout.shard_file(), source: e }),
+            _ => Ok(()),
+        },
+    }
+}
+
+// Combines the banks of partial snapshots, ordered by code. Snapshots crawled with --shard have to be
+// every part of one split, each once; a bank found in two snapshots means they overlap and is refused.
+pub fn merge(parts: Vec<(Option<Shard>, Vec<Bank>)>) -> Result<Vec<Bank>, Error> {
+    let shards = parts.iter().filter_map(|(shard, _)| *shard).collect::<Vec<Shard>>();
+    if !shards.is_empty() {
+        let count = shards[0].count;
+        let mut indices = shards.iter().filter(|shard| shard.count == count).map(|shard| shard.index).collect::<Vec<usize>>();
+        indices.sort_unstable();
+        indices.dedup();
+        if shards.len() != parts.len() || indices.len() != count || shards.len() != count {
+            let found = shards.iter().map(Shard::to_string).collect::<Vec<String>>().join(", ");
+            return Err(Error::MergeFailed(format!(
+                "expected each of {} shards once, got {}",
+                count,
+                if found.is_empty() { "none".to_owned() } else { found }
+            )));
+        }
+    }
+    let mut merged = parts.into_iter().flat_map(|(_, banks)| banks).collect::<Vec<Bank>>();
+    merged.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+    if let Some(pair) = merged.windows(2).find(|pair| pair[0].code == pair[1].code) {
+        return Err(Error::MergeFailed(format!("bank {} is in more than one snapshot", pair[0].code.0)));
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn shard_test() {
+        use crate::shard::{merge, Shard};
+        use crate::{Bank, BankCode, Error};
+
+        assert_eq!("2/4".parse::<Shard>(), Ok(Shard { index: 2, count: 4 }));
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+
+        // Every bank falls in exactly one of the parts.
+        let shards = (1..=3).map(|index| Shard { index, count: 3 }).collect::<Vec<Shard>>();
+        let codes = (0..300).map(|code| BankCode(format!("{:04}", code))).collect::<Vec<BankCode>>();
+        for code in &codes {
+            assert_eq!(shards.iter().filter(|shard| shard.contains(code)).count(), 1);
+        }
+        assert!(shards.iter().all(|shard| codes.iter().filter(|code| shard.contains(code)).count() > 50));
+
+        let bank = |code: &str| Bank::new(format!("銀行{}", code), "ｷﾞﾝｺｳ".to_owned(), code.to_owned(), String::new());
+        let parts = |banks: &[&str]| {
+            shards
+                .iter()
+                .map(|shard| (Some(*shard), banks.iter().filter(|code| shard.contains(&BankCode(code.to_string()))).map(|code| bank(code)).collect()))
+                .collect::<Vec<(Option<Shard>, Vec<Bank>)>>()
+        };
+        let merged = merge(parts(&["0005", "0001", "0138", "9900"])).unwrap();
+        assert_eq!(merged.iter().map(|bank| bank.code.0.as_str()).collect::<Vec<&str>>(), ["0001", "0005", "0138", "9900"]);
+
+        let mut missing = parts(&["0001"]);
+        missing.pop();
+        assert!(matches!(merge(missing), Err(Error::MergeFailed(_))));
+        let mut doubled = parts(&["0001"]);
+        doubled[1].0 = doubled[0].0;
+        assert!(matches!(merge(doubled), Err(Error::MergeFailed(_))));
+        let overlapping = vec![(None, vec![bank("0001")]), (None, vec![bank("0001"), bank("0005")])];
+        assert!(matches!(merge(overlapping), Err(Error::MergeFailed(_))));
+        assert_eq!(merge(vec![(None, vec![bank("0005")]), (None, vec![bank("0001")])]).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::cancel::CancellationToken;
+use crate::client::ZnginClient;
+use crate::dataset::Dataset;
+use crate::layout::Layout;
+use crate::lock;
+use crate::logging;
+use crate::sync::{self, Pulled, Remote};
+use crate::Error;
+
+// Where `SharedDataset::refresh` gets a newer dataset from.
+#[derive(Debug, Clone)]
+pub enum Refresh {
+    // The output directory of `zngn crawl`; skipped while a crawl holds its lock.
+    Directory(Layout),
+    // A crawl of the site, kept in memory only.
+    Crawl(ZnginClient),
+    // A server started with `zngn serve`, by patch when it still knows the current version.
+    Remote(Remote),
+}
+
+// A dataset an embedding application reads from many tasks while a refresh replaces it. Readers
+// get the dataset current when they asked and keep it for as long as they hold it, so a lookup never
+// sees half of one dataset and half of the next. Clones share the dataset.
+#[derive(Debug, Clone)]
+pub struct SharedDataset {
+    current: Arc<RwLock<Arc<Dataset>>>,
+}
+
+impl SharedDataset {
+    pub fn new(dataset: Dataset) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(dataset))),
+        }
+    }
+
+    pub fn open(layout: &Layout) -> Result<Self, Error> {
+        Dataset::open(layout).map(Self::new)
+    }
+
+    pub fn get(&self) -> Arc<Dataset> {
+        self.current.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    // Swaps in `dataset` and returns the one it replaced.
+    pub fn replace(&self, dataset: Dataset) -> Arc<Dataset> {
+        std::mem::replace(&mut *self.current.write().unwrap_or_else(PoisonError::into_inner), Arc::new(dataset))
+    }
+
+    // Fetches the dataset from `source` and swaps it in when it differs from the current one, which
+    // is kept when fetching fails. Returns whether it was swapped.
+    pub async fn refresh(&self, source: &Refresh) -> Result<bool, Error> {
+        let current = self.get();
+        let dataset = match source {
+            Refresh::Directory(layout) => {
+                if lock::is_held(layout) {
+                    return Ok(false);
+                }
+                Dataset::open(layout)?
+            }
+            Refresh::Crawl(client) => client.fetch_dataset().await?,
+            Refresh::Remote(remote) => {
+                let mut banks = Some(current.banks.clone());
+                match sync::pull(remote, &mut banks).await? {
+                    Pulled::UpToDate => return Ok(false),
+                    Pulled::Patched(_) | Pulled::Downloaded => {}
+                }
+                Dataset::new(banks.unwrap_or_default()).generated(remote.url())
+            }
+        };
+        if dataset.banks == current.banks {
+            return Ok(false);
+        }
+        self.replace(dataset);
+        Ok(true)
+    }
+
+    // Calls `refresh` every `period` in the background until the returned refresher is stopped. An
+    // output directory is only read again once its bank list has changed. Failures are logged and the
+    // current dataset kept.
+    pub fn spawn_refresher(&self, source: Refresh, period: Duration) -> Refresher {
+        let shared = self.clone();
+        let cancel = CancellationToken::new();
+        let stopped = cancel.clone();
+        let task = tokio::spawn(async move {
+            let modified = |layout: &Layout| fs::metadata(layout.banks_file()).and_then(|m| m.modified()).ok();
+            let mut loaded: Option<SystemTime> = match &source {
+                Refresh::Directory(layout) => modified(layout),
+                _ => None,
+            };
+            let mut ticks = interval(period);
+            // The first tick is immediate, and the dataset was only just loaded.
+            ticks.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    _ = stopped.cancelled() => return,
+                }
+                if let Refresh::Directory(layout) = &source {
+                    let current = modified(layout);
+                    if current == loaded || lock::is_held(layout) {
+                        continue;
+                    }
+                    loaded = current;
+                }
+                match shared.refresh(&source).await {
+                    Ok(true) => logging::info(&format!("refreshed the dataset, now {} banks", shared.get().len())),
+                    Ok(false) => {}
+                    Err(e) => logging::error(&format!("refresh failed, keeping the current dataset: {}", e)),
+                }
+            }
+        });
+        Refresher { cancel, task }
+    }
+}
+
+// The background task of `SharedDataset::spawn_refresher`. Dropping it leaves the task running.
+#[derive(Debug)]
+pub struct Refresher {
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl Refresher {
+    // Stops refreshing, waiting for a refresh under way to finish.
+    pub async fn stop(self) {
+        self.cancel.cancel();
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn refresh_test() {
+        use std::path::PathBuf;
+        use std::time::Duration;
+        use crate::dataset::Dataset;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::shared::{Refresh, SharedDataset};
+        use crate::{save_banks, Bank};
+
+        let out = std::env::temp_dir().join(format!("zngn-shared-{}", std::process::id()));
+        let layout = Layout::new(PathBuf::from(&out), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let neko = Bank::new("ねこ銀行".to_owned(), "ﾈｺ".to_owned(), "0222".to_owned(), "0x222".to_owned());
+        let inu = Bank::new("いぬ銀行".to_owned(), "ｲﾇ".to_owned(), "0111".to_owned(), "0x111".to_owned());
+        save_banks(std::slice::from_ref(&neko), &layout).unwrap();
+
+        let shared = SharedDataset::open(&layout).unwrap();
+        let before = shared.get();
+        let source = Refresh::Directory(layout.clone());
+        assert!(!shared.refresh(&source).await.unwrap());
+
+        save_banks(&[neko, inu], &layout).unwrap();
+        assert!(shared.refresh(&source).await.unwrap());
+        assert_eq!(shared.get().len(), 2);
+        // Readers holding the previous dataset keep it.
+        assert_eq!(before.len(), 1);
+
+        let old = shared.replace(Dataset::new(Vec::new()));
+        assert_eq!(old.len(), 2);
+        let refresher = shared.clone().spawn_refresher(source, Duration::from_millis(10));
+        // The bank list didn't change, so the refresher leaves the replaced dataset alone.
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        assert!(shared.get().is_empty());
+        refresher.stop().await;
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::layout::Layout;
+use crate::Error;
+
+// Keys and signatures are stored as a single line of hex.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    let text = text.trim();
+    if text.len() != N * 2 || !text.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn read_key<const N: usize>(path: &Path) -> Result<[u8; N], Error> {
+    let text = fs::read_to_string(path).map_err(Error::file(path))?;
+    from_hex(&text).ok_or_else(|| Error::InvalidKey(path.to_path_buf()))
+}
+
+// The public key file sits next to the private one.
+pub fn public_key_file(private_key: &Path) -> PathBuf {
+    let mut name = private_key.as_os_str().to_owned();
+    name.push(".pub");
+    PathBuf::from(name)
+}
+
+// Writes a new private key to `path` and its public key to `public_key_file(path)`.
+pub fn generate(path: &Path) -> Result<PathBuf, Error> {
+    let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+    fs::write(path, to_hex(&key.to_bytes()) + "\n").map_err(Error::file(path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(Error::file(path))?;
+    }
+    let public = public_key_file(path);
+    fs::write(&public, to_hex(key.verifying_key().as_bytes()) + "\n").map_err(Error::file(&public))?;
+    Ok(public)
+}
+
+// Signs manifest.json, which in turn pins every file of the snapshot by checksum.
+pub fn sign(layout: &Layout, private_key: &Path) -> Result<(), Error> {
+    let key = SigningKey::from_bytes(&read_key(private_key)?);
+    let manifest = fs::read(layout.manifest_file()).map_err(Error::file(layout.manifest_file()))?;
+    let signature = key.sign(&manifest);
+    fs::write(layout.signature_file(), to_hex(&signature.to_bytes()) + "\n").map_err(Error::file(layout.signature_file()))
+}
+
+pub fn verify(layout: &Layout, public_key: &Path) -> Result<(), Error> {
+    let key = VerifyingKey::from_bytes(&read_key(public_key)?).map_err(|_| Error::InvalidKey(public_key.to_path_buf()))?;
+    let manifest = fs::read(layout.manifest_file()).map_err(Error::file(layout.manifest_file()))?;
+    let signature = fs::read_to_string(layout.signature_file()).map_err(Error::file(layout.signature_file()))?;
+    let signature = from_hex(&signature).map(|bytes| Signature::from_bytes(&bytes));
+    match signature {
+        Some(signature) if key.verify_strict(&manifest, &signature).is_ok() => Ok(()),
+        _ => Err(Error::BadSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sign_test() {
+        use std::fs;
+        use std::path::PathBuf;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::signing::{generate, sign, verify};
+        use crate::Error;
+
+        let out = std::env::temp_dir().join(format!("zngn-signing-{}", std::process::id()));
+        fs::create_dir_all(&out).unwrap();
+        let layout = Layout::new(PathBuf::from(&out), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let (key, other) = (out.join("key"), out.join("other"));
+        let public = generate(&key).unwrap();
+        let other_public = generate(&other).unwrap();
+
+        fs::write(layout.manifest_file(), r#"{"files":{}}"#).unwrap();
+        sign(&layout, &key).unwrap();
+        verify(&layout, &public).unwrap();
+        assert!(matches!(verify(&layout, &other_public), Err(Error::BadSignature)));
+        assert!(matches!(verify(&layout, &key.with_file_name("missing")), Err(Error::FileFailed { .. })));
+
+        fs::write(layout.manifest_file(), r#"{"files":{"x":{}}}"#).unwrap();
+        assert!(matches!(verify(&layout, &public), Err(Error::BadSignature)));
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::collate::{self, gojuon_row, SortKey, SortOrder, GOJUON_ROWS};
+use crate::{Bank, Error};
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; color: #222; }
+nav a { margin-right: .8em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: .25em .6em; border-bottom: 1px solid #ddd; }
+td.code { font-family: monospace; }
+.reading { color: #666; }
+#search { width: 100%; font-size: 1.1em; padding: .3em; box-sizing: border-box; }
+";
+
+const SEARCH_JS: &str = include_str!("../data/site/search.js");
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub title: String,
+    // search.wasm built from wasm/; adds a search box to the index page that runs in the browser.
+    pub search: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            title: "Zengin bank codes".to_owned(),
+            search: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SiteReport {
+    pub pages: usize,
+    pub bytes: usize,
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// `root` leads from the page back to the top of the site, for the stylesheet and links.
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        root,
+        body
+    )
+}
+
+// Tabs and line breaks separate fields and records, so any inside a name become spaces.
+fn field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+// The records search.wasm searches, in the line format its `parse` reads.
+pub fn search_index(banks: &[Bank]) -> String {
+    let mut index = String::new();
+    for bank in banks {
+        let aliases = bank
+            .aliases
+            .iter()
+            .flat_map(|alias| std::iter::once(alias.name.as_str()).chain(alias.phonetic.as_deref()))
+            .map(|alias| field(alias).replace('|', " "))
+            .collect::<Vec<String>>();
+        let _ = writeln!(
+            index,
+            "B\t{}\t{}\t{}\t{}",
+            field(&bank.code.0),
+            field(&bank.name),
+            field(&bank.phonetic),
+            aliases.join("|")
+        );
+        for branch in &bank.branches {
+            let _ = writeln!(index, "R\t{}\t{}\t{}", field(&branch.code), field(&branch.name), field(&branch.phonetic));
+        }
+    }
+    index
+}
+
+// Section labels: the gojūon rows, then banks whose reading starts with a letter or digit.
+fn sections() -> Vec<String> {
+    GOJUON_ROWS
+        .chars()
+        .map(|kana| format!("{}行", kana))
+        .chain(std::iter::once("英数".to_owned()))
+        .collect()
+}
+
+fn index(banks: &[Bank], options: &Options) -> String {
+    let labels = sections();
+    let mut grouped: Vec<Vec<&Bank>> = vec![Vec::new(); labels.len()];
+    for bank in banks {
+        grouped[gojuon_row(&bank.phonetic).unwrap_or(labels.len() - 1)].push(bank);
+    }
+    let mut body = format!("<h1>{}</h1>\n<nav>", escape(&options.title));
+    for (i, label) in labels.iter().enumerate().filter(|(i, _)| !grouped[*i].is_empty()) {
+        let _ = write!(body, "<a href=\"#row-{}\">{}</a>", i, label);
+    }
+    body.push_str("</nav>\n");
+    if options.search.is_some() {
+        body.push_str(
+            "<p><input id=\"search\" type=\"search\" placeholder=\"name, reading or code\" disabled></p>\n\
+             <ol id=\"hits\"></ol>\n<script src=\"search.js\"></script>\n",
+        );
+    }
+    for (i, banks) in grouped.iter().enumerate().filter(|(_, banks)| !banks.is_empty()) {
+        let _ = write!(
+            body,
+            "<h2 id=\"row-{}\">{}</h2>\n<table>\n<tr><th>code</th><th>name</th><th>reading</th><th>branches</th></tr>\n",
+            i, labels[i]
+        );
+        for bank in banks {
+            let _ = writeln!(
+                body,
+                "<tr><td class=\"code\">{code}</td><td><a href=\"banks/{code}.html\">{}</a></td>\
+                 <td class=\"reading\">{}</td><td>{}</td></tr>",
+                escape(&bank.name),
+                escape(&bank.phonetic),
+                bank.branches.len(),
+                code = escape(&bank.code.0),
+            );
+        }
+        body.push_str("</table>\n");
+    }
+    page(&options.title, "", &body)
+}
+
+fn bank_page(bank: &Bank, options: &Options) -> String {
+    let mut body = format!(
+        "<nav><a href=\"../index.html\">{}</a></nav>\n<h1>{} <span class=\"reading\">{}</span></h1>\n<p>code <span class=\"code\">{}</span></p>\n",
+        escape(&options.title),
+        escape(&bank.name),
+        escape(&bank.phonetic),
+        escape(&bank.code.0)
+    );
+    if !bank.aliases.is_empty() {
+        let names = bank.aliases.iter().map(|alias| escape(&alias.name)).collect::<Vec<String>>();
+        let _ = writeln!(body, "<p>formerly {}</p>", names.join(", "));
+    }
+    body.push_str("<table>\n<tr><th>code</th><th>name</th><th>reading</th></tr>\n");
+    for branch in &bank.branches {
+        let _ = writeln!(
+            body,
+            "<tr id=\"b-{code}\"><td class=\"code\">{code}</td><td>{}</td><td class=\"reading\">{}</td></tr>",
+            escape(&branch.name),
+            escape(&branch.phonetic),
+            code = escape(&branch.code),
+        );
+    }
+    body.push_str("</table>\n");
+    page(&format!("{} - {}", bank.name, options.title), "../", &body)
+}
+
+fn write_page(path: &Path, html: &str, report: &mut SiteReport) -> Result<(), Error> {
+    fs::write(path, html).map_err(Error::ExportFailed)?;
+    report.pages += 1;
+    report.bytes += html.len();
+    Ok(())
+}
+
+// Renders `banks` as plain HTML files under `dir`: index.html listing every bank by gojūon row, and
+// banks/<code>.html with the branches of each. The pages link to each other relatively, so the
+// directory can be served from anywhere or opened straight from disk. With `options.search`, the
+// module is copied in along with its index and script.
+pub fn build(banks: &[Bank], dir: &Path, options: &Options) -> Result<SiteReport, Error> {
+    let mut banks = banks.to_vec();
+    collate::sort_banks(&mut banks, SortKey::Phonetic, SortOrder::Asc);
+    for bank in &mut banks {
+        collate::sort_branches(&mut bank.branches, SortKey::Code, SortOrder::Asc);
+    }
+    fs::create_dir_all(dir.join("banks")).map_err(Error::ExportFailed)?;
+    // Copied first, so a missing module fails before any page is written.
+    if let Some(module) = &options.search {
+        fs::copy(module, dir.join("search.wasm")).map_err(Error::file(module))?;
+    }
+    let mut report = SiteReport::default();
+    fs::write(dir.join("style.css"), STYLE).map_err(Error::ExportFailed)?;
+    write_page(&dir.join("index.html"), &index(&banks, options), &mut report)?;
+    for bank in &banks {
+        let path = dir.join("banks").join(format!("{}.html", bank.code.0));
+        write_page(&path, &bank_page(bank, options), &mut report)?;
+    }
+    if options.search.is_some() {
+        fs::write(dir.join("search-index.tsv"), search_index(&banks)).map_err(Error::ExportFailed)?;
+        fs::write(dir.join("search.js"), SEARCH_JS).map_err(Error::ExportFailed)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn build_test() {
+        use std::fs;
+        use crate::site::{build, Options};
+        use crate::{Bank, Branch};
+
+        let dir = std::env::temp_dir().join(format!("zngn-site-{}", std::process::id()));
+        let mut mizuho = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned());
+        mizuho.append_branch(Branch::new("R&D<支店>".to_owned(), "ｱｰﾙｱﾝﾄﾞﾃﾞｲ".to_owned(), "123".to_owned()));
+        let paypay = Bank::new("ＰａｙＰａｙ銀行".to_owned(), "PAYPAY".to_owned(), "0033".to_owned(), "x33".to_owned());
+        let aozora = Bank::new("あおぞら銀行".to_owned(), "ｱｵｿﾞﾗ".to_owned(), "0398".to_owned(), "x398".to_owned());
+
+        let banks = [mizuho, paypay, aozora];
+        let report = build(&banks, &dir, &Options::default()).unwrap();
+        assert_eq!(report.pages, 4);
+        let index = fs::read_to_string(dir.join("index.html")).unwrap();
+        let (a, ma, latin) = (index.find("あ行").unwrap(), index.find("ま行").unwrap(), index.find("英数").unwrap());
+        assert!(a < ma && ma < latin);
+        assert!(index.contains("<a href=\"banks/0001.html\">みずほ銀行</a>"));
+        assert!(!index.contains("か行"));
+        let bank = fs::read_to_string(dir.join("banks/0001.html")).unwrap();
+        assert!(bank.contains("<tr id=\"b-123\"><td class=\"code\">123</td><td>R&amp;D&lt;支店&gt;</td>"));
+        assert!(bank.contains("href=\"../style.css\""));
+        assert!(!dir.join("search.js").exists());
+
+        let module = dir.join("module.wasm");
+        fs::write(&module, b"\0asm").unwrap();
+        let options = Options {
+            search: Some(module),
+            ..Options::default()
+        };
+        build(&banks, &dir, &options).unwrap();
+        assert!(fs::read_to_string(dir.join("index.html")).unwrap().contains("<script src=\"search.js\">"));
+        let index = fs::read_to_string(dir.join("search-index.tsv")).unwrap();
+        assert!(index.starts_with("B\t0033\tＰａｙＰａｙ銀行\tPAYPAY\t\n"));
+        assert!(index.contains("B\t0001\tみずほ銀行\tﾐｽﾞﾎ\t\nR\t123\tR&D<支店>\tｱｰﾙｱﾝﾄﾞﾃﾞｲ\n"));
+        assert_eq!(fs::read(dir.join("search.wasm")).unwrap(), b"\0asm");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
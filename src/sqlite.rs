@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, params_from_iter, Connection};
+
+use crate::{prepare_parent_dir, Bank, Error};
+
+const SCHEMA: &str = "
+CREATE TABLE banks (
+    code TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    phonetic TEXT NOT NULL
+);
+CREATE TABLE branches (
+    bank_code TEXT NOT NULL REFERENCES banks (code),
+    code TEXT NOT NULL,
+    name TEXT NOT NULL,
+    phonetic TEXT NOT NULL,
+    PRIMARY KEY (bank_code, code)
+);
+CREATE TABLE bank_aliases (
+    bank_code TEXT NOT NULL REFERENCES banks (code),
+    name TEXT NOT NULL,
+    phonetic TEXT,
+    until TEXT
+);
+";
+
+// External content tables over the rows above. The trigram tokenizer copes with Japanese text having no
+// word boundaries, so any substring of three or more characters matches, e.g.
+// `SELECT * FROM branches_fts WHERE branches_fts MATCH '東京営'`.
+const FTS_SCHEMA: &str = "
+CREATE VIRTUAL TABLE banks_fts USING fts5 (
+    name, phonetic, content = 'banks', content_rowid = 'rowid', tokenize = 'trigram'
+);
+CREATE VIRTUAL TABLE branches_fts USING fts5 (
+    name, phonetic, content = 'branches', content_rowid = 'rowid', tokenize = 'trigram'
+);
+INSERT INTO banks_fts (rowid, name, phonetic) SELECT rowid, name, phonetic FROM banks;
+INSERT INTO branches_fts (rowid, name, phonetic) SELECT rowid, name, phonetic FROM branches;
+";
+
+// A new database at `path`, replacing any file there.
+fn create(path: &Path) -> Result<Connection, Error> {
+    prepare_parent_dir(path);
+    if path.exists() {
+        fs::remove_file(path).map_err(Error::ExportFailed)?;
+    }
+    Connection::open(path).map_err(Error::SqliteExportFailed)
+}
+
+pub fn export(banks: &[Bank], path: &Path, fts: bool) -> Result<(), Error> {
+    let mut conn = create(path)?;
+    let tx = conn.transaction().map_err(Error::SqliteExportFailed)?;
+    tx.execute_batch(SCHEMA).map_err(Error::SqliteExportFailed)?;
+    {
+        let mut insert_bank = tx
+            .prepare("INSERT INTO banks (code, name, phonetic) VALUES (?1, ?2, ?3)")
+            .map_err(Error::SqliteExportFailed)?;
+        let mut insert_alias = tx
+            .prepare("INSERT INTO bank_aliases (bank_code, name, phonetic, until) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(Error::SqliteExportFailed)?;
+        let mut insert_branch = tx
+            .prepare("INSERT INTO branches (bank_code, code, name, phonetic) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(Error::SqliteExportFailed)?;
+        for bank in banks {
+            insert_bank
+                .execute(params![bank.code.0, bank.name, bank.phonetic])
+                .map_err(Error::SqliteExportFailed)?;
+            for alias in &bank.aliases {
+                insert_alias
+                    .execute(params![bank.code.0, alias.name, alias.phonetic, alias.until])
+                    .map_err(Error::SqliteExportFailed)?;
+            }
+            for branch in &bank.branches {
+                insert_branch
+                    .execute(params![bank.code.0, branch.code, branch.name, branch.phonetic])
+                    .map_err(Error::SqliteExportFailed)?;
+            }
+        }
+    }
+    if fts {
+        tx.execute_batch(FTS_SCHEMA).map_err(Error::SqliteExportFailed)?;
+    }
+    tx.commit().map_err(Error::SqliteExportFailed)
+}
+
+// A table `results` of text columns named by `headers`, for command results other than the dataset.
+pub fn export_rows(headers: &[String], rows: &[Vec<String>], path: &Path) -> Result<(), Error> {
+    let mut conn = create(path)?;
+    let tx = conn.transaction().map_err(Error::SqliteExportFailed)?;
+    let quoted = headers
+        .iter()
+        .map(|header| format!("\"{}\"", header.replace('"', "\"\"")))
+        .collect::<Vec<String>>();
+    let columns = quoted.iter().map(|column| format!("{} TEXT", column)).collect::<Vec<String>>();
+    tx.execute_batch(&format!("CREATE TABLE results ({});", columns.join(", ")))
+        .map_err(Error::SqliteExportFailed)?;
+    {
+        let placeholders = (1..=headers.len()).map(|i| format!("?{}", i)).collect::<Vec<String>>();
+        let mut insert = tx
+            .prepare(&format!("INSERT INTO results ({}) VALUES ({})", quoted.join(", "), placeholders.join(", ")))
+            .map_err(Error::SqliteExportFailed)?;
+        for row in rows {
+            insert.execute(params_from_iter(row)).map_err(Error::SqliteExportFailed)?;
+        }
+    }
+    tx.commit().map_err(Error::SqliteExportFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn fts_export_test() {
+        use rusqlite::Connection;
+        use crate::sqlite::export;
+        use crate::{Bank, Branch};
+
+        let path = std::env::temp_dir().join(format!("zngn-sqlite-{}.db", std::process::id()));
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x001".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        bank.append_branch(Branch::new("京都中央支店".to_owned(), "ｷﾖｳﾄﾁﾕｳｵｳ".to_owned(), "521".to_owned()));
+        export(&[bank], &path, true).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let code: String = conn
+            .query_row(
+                "SELECT code FROM branches WHERE rowid IN (SELECT rowid FROM branches_fts WHERE branches_fts MATCH '東京営')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(code, "001");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
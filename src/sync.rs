@@ -0,0 +1,121 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+
+use crate::delta::{self, Delta};
+use crate::{Bank, Error};
+
+// Header the server sends the dataset version in; see `server::headers::DATASET_VERSION`.
+const DATASET_VERSION: &str = "x-dataset-version";
+
+// A zngn server started with `zngn serve`.
+#[derive(Debug, Clone)]
+pub struct Remote {
+    client: Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Pulled {
+    UpToDate,
+    // The remote sent a patch, already checked against the versions it names.
+    Patched(Delta),
+    // The local copy was missing or too old for a patch, so the whole dataset was downloaded.
+    Downloaded,
+}
+
+impl Remote {
+    pub fn new(url: &str, api_key: Option<String>) -> Result<Self, Error> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(Error::SyncFailed(format!("{} is not the http(s) URL of a zngn server", url)));
+        }
+        Ok(Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_owned(),
+            api_key,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        let request = self.client.get(&format!("{}{}", self.url, path));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        request.send().await.map_err(|source| Error::RemoteFailed {
+            url: self.url.clone(),
+            source,
+        })
+    }
+
+    async fn body(&self, response: Response) -> Result<Vec<u8>, Error> {
+        let status = response.status();
+        let body = response.bytes().await.map_err(|source| Error::RemoteFailed {
+            url: self.url.clone(),
+            source,
+        })?;
+        if !status.is_success() {
+            let message = String::from_utf8_lossy(&body);
+            return Err(Error::SyncFailed(format!("{} answered {}: {}", self.url, status, message)));
+        }
+        Ok(body.to_vec())
+    }
+
+    // The patch from `since` to the served dataset, or None when the server no longer has `since`.
+    pub async fn changes(&self, since: &str) -> Result<Option<Delta>, Error> {
+        let response = self.send(self.get("/changes").query(&[("since", since)])).await?;
+        if response.status() == StatusCode::GONE {
+            return Ok(None);
+        }
+        let body = self.body(response).await?;
+        serde_json::from_slice(&body).map(Some).map_err(Error::LoadBanksFileFailed)
+    }
+
+    // The whole served dataset, checked against the version the server claims for it.
+    pub async fn dataset(&self) -> Result<Vec<Bank>, Error> {
+        let response = self.send(self.get("/dataset")).await?;
+        let version = response
+            .headers()
+            .get(DATASET_VERSION)
+            .and_then(|version| version.to_str().ok())
+            .map(str::to_owned);
+        let body = self.body(response).await?;
+        let mut banks: Vec<Bank> = serde_json::from_slice(&body).map_err(Error::LoadBanksFileFailed)?;
+        // The served banks carry aliases from the server's alias tables, which aren't part of the snapshot.
+        for bank in &mut banks {
+            bank.aliases.clear();
+        }
+        banks.sort_by(|a, b| a.code.0.cmp(&b.code.0));
+        let found = delta::fingerprint(&banks);
+        match version {
+            Some(version) if version == found => Ok(banks),
+            version => Err(Error::SyncFailed(format!(
+                "dataset from {} has version {}, not {}",
+                self.url,
+                found,
+                version.unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+// Brings `local` up to the remote dataset, by a patch when the server still knows the local version.
+pub async fn pull(remote: &Remote, local: &mut Option<Vec<Bank>>) -> Result<Pulled, Error> {
+    if let Some(banks) = local {
+        if let Some(delta) = remote.changes(&delta::fingerprint(banks)).await? {
+            if delta.ops.is_empty() {
+                return Ok(Pulled::UpToDate);
+            }
+            delta::apply(banks, &delta)?;
+            return Ok(Pulled::Patched(delta));
+        }
+    }
+    *local = Some(remote.dataset().await?);
+    Ok(Pulled::Downloaded)
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+use reqwest::Url;
+use tokio::sync::Semaphore;
+use tokio::time::delay_for;
+
+use crate::logging;
+use crate::Error;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+// Windows are in Japan time, the site's, which has no daylight saving.
+const JST_OFFSET_MINUTES: u32 = 9 * 60;
+
+// How hard one host may be hit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    // Sleep a random duration up to this long before each request.
+    pub jitter: Duration,
+    // Bytes per second.
+    pub max_bandwidth: Option<u64>,
+    // Requests in flight at the same time.
+    pub concurrency: Option<usize>,
+}
+
+// Limits for one host, written `host:key=value,...` with the keys jitter_ms, max_bandwidth and
+// concurrency, e.g. `mirror.example.com:jitter_ms=0,concurrency=32`. Keys left out are unlimited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostLimits {
+    pub host: String,
+    pub limits: Limits,
+}
+
+// A --max-bandwidth value. 0 would stop every download, so it is refused rather than taken as no cap.
+pub fn parse_bandwidth(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(0) => Err("must be at least 1; leave it out for no cap".to_owned()),
+        Ok(bandwidth) => Ok(bandwidth),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl FromStr for HostLimits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, settings) = s.split_once(':').ok_or_else(|| format!("expected host:key=value,...: {}", s))?;
+        let mut limits = Limits::default();
+        for setting in settings.split(',').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting.split_once('=').ok_or_else(|| format!("expected key=value: {}", setting))?;
+            let number = value.parse::<u64>().map_err(|_| format!("not a number: {}", setting))?;
+            match key {
+                "jitter_ms" => limits.jitter = Duration::from_millis(number),
+                "max_bandwidth" if number > 0 => limits.max_bandwidth = Some(number),
+                "max_bandwidth" => return Err("max_bandwidth must be at least 1; leave it out for no cap".to_owned()),
+                "concurrency" if number > 0 => limits.concurrency = Some(number as usize),
+                "concurrency" => return Err("concurrency must be at least 1".to_owned()),
+                _ => return Err(format!("unknown limit: {}", key)),
+            }
+        }
+        Ok(Self {
+            host: host.to_owned(),
+            limits,
+        })
+    }
+}
+
+// The time of day requests may be sent in, written `HH:MM-HH:MM` in Japan time, e.g. `01:00-05:00`.
+// A window ending before it starts runs past midnight, e.g. `22:00-06:00`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    // Minutes since midnight.
+    start: u32,
+    end: u32,
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let minutes = |time: &str| {
+            let (hours, minutes) = time.trim().split_once(':')?;
+            let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+            Some(hours * 60 + minutes).filter(|_| hours <= 24 && minutes < 60 && hours * 60 + minutes <= MINUTES_PER_DAY)
+        };
+        let (start, end) = s.split_once('-').ok_or_else(|| format!("expected HH:MM-HH:MM: {}", s))?;
+        match (minutes(start), minutes(end)) {
+            (Some(start), Some(end)) if start != end => Ok(Self { start, end }),
+            (Some(_), Some(_)) => Err(format!("the window is empty: {}", s)),
+            _ => Err(format!("expected HH:MM-HH:MM: {}", s)),
+        }
+    }
+}
+
+impl Window {
+    // How long from `now`, in seconds since the epoch, until the window opens; zero inside it.
+    pub fn until_open(&self, now: u64) -> Duration {
+        let seconds = (now + u64::from(JST_OFFSET_MINUTES) * 60) % (u64::from(MINUTES_PER_DAY) * 60);
+        let minute = (seconds / 60) as u32;
+        let inside = if self.start < self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            minute >= self.start || minute < self.end
+        };
+        if inside {
+            return Duration::from_secs(0);
+        }
+        let wait = (self.start + MINUTES_PER_DAY - minute) % MINUTES_PER_DAY;
+        Duration::from_secs(u64::from(wait) * 60 - seconds % 60)
+    }
+}
+
+#[derive(Debug)]
+struct Transferred {
+    started_at: Instant,
+    bytes: u64,
+}
+
+#[derive(Debug)]
+struct Host {
+    limits: Limits,
+    transferred: Mutex<Transferred>,
+    slots: Option<Semaphore>,
+}
+
+impl Host {
+    fn new(limits: Limits) -> Self {
+        Self {
+            slots: limits.concurrency.map(Semaphore::new),
+            limits,
+            transferred: Mutex::new(Transferred {
+                started_at: Instant::now(),
+                bytes: 0,
+            }),
+        }
+    }
+}
+
+// Held while a request is in flight; frees its host's concurrency slot when dropped.
+#[derive(Debug)]
+pub struct Slot(Option<Arc<Host>>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(slots) = self.0.as_ref().and_then(|host| host.slots.as_ref()) {
+            slots.add_permits(1);
+        }
+    }
+}
+
+// Paces requests per host. Hosts without limits of their own share the default ones; the request
+// budget counts requests to every host together.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    max_requests: Option<usize>,
+    default: Arc<Host>,
+    hosts: Arc<HashMap<String, Arc<Host>>>,
+    requests: Arc<AtomicUsize>,
+    window: Option<Window>,
+    // Set while requests wait for the window, so the pause is logged once.
+    paused: Arc<AtomicBool>,
+}
+
+impl Throttle {
+    pub fn new(jitter: Duration, max_bandwidth: Option<u64>, max_requests: Option<usize>) -> Self {
+        let limits = Limits {
+            jitter,
+            max_bandwidth,
+            concurrency: None,
+        };
+        Self {
+            max_requests,
+            default: Arc::new(Host::new(limits)),
+            hosts: Arc::new(HashMap::new()),
+            requests: Arc::new(AtomicUsize::new(0)),
+            window: None,
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Holds requests outside `window` until it opens again.
+    pub fn with_window(mut self, window: Option<Window>) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_hosts(mut self, hosts: Vec<HostLimits>) -> Self {
+        self.hosts = Arc::new(
+            hosts
+                .into_iter()
+                .map(|host| (host.host, Arc::new(Host::new(host.limits))))
+                .collect(),
+        );
+        self
+    }
+
+    // Caps the requests in flight to hosts without limits of their own.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        let limits = Limits {
+            concurrency: Some(concurrency.max(1)),
+            ..self.default.limits.clone()
+        };
+        self.default = Arc::new(Host::new(limits));
+        self
+    }
+
+    fn host(&self, url: &str) -> &Arc<Host> {
+        let host = Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_owned));
+        host.and_then(|host| self.hosts.get(&host)).unwrap_or(&self.default)
+    }
+
+    // Waits until a request to `url` may be sent; keep the slot until its response has been read.
+    pub async fn wait(&self, url: &str) -> Result<Slot, Error> {
+        self.wait_for_window().await;
+        if let Some(max_requests) = self.max_requests {
+            if self.requests.fetch_add(1, Ordering::SeqCst) >= max_requests {
+                return Err(Error::RequestBudgetExhausted);
+            }
+        }
+        let host = self.host(url).clone();
+        if let Some(slots) = &host.slots {
+            slots.acquire().await.forget();
+        }
+        let slot = Slot(Some(host.clone()));
+        // The waits below can run past the end of the window, which is checked again after them.
+        loop {
+            let jitter = host.limits.jitter.as_millis() as u64;
+            if jitter > 0 {
+                let millis = rand::thread_rng().gen_range(0..=jitter);
+                delay_for(Duration::from_millis(millis)).await;
+            }
+            if let Some(max_bandwidth) = host.limits.max_bandwidth {
+                let delay = {
+                    let transferred = host.transferred.lock().unwrap_or_else(PoisonError::into_inner);
+                    overdraft(transferred.bytes, max_bandwidth, transferred.started_at.elapsed())
+                };
+                delay_for(delay).await;
+            }
+            if !self.wait_for_window().await {
+                return Ok(slot);
+            }
+        }
+    }
+
+    // Sleeps until the crawl window is open. Returns whether it was closed.
+    async fn wait_for_window(&self) -> bool {
+        let window = match &self.window {
+            Some(window) => window,
+            None => return false,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let pause = window.until_open(now);
+        if pause == Duration::from_secs(0) {
+            return false;
+        }
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            logging::info(&format!("outside the crawl window, pausing for {} minutes", pause.as_secs() / 60 + 1));
+        }
+        delay_for(pause).await;
+        if self.paused.swap(false, Ordering::SeqCst) {
+            logging::info("the crawl window opened, resuming");
+        }
+        true
+    }
+
+    pub fn consume(&self, url: &str, bytes: usize) {
+        let mut transferred = self.host(url).transferred.lock().unwrap_or_else(PoisonError::into_inner);
+        transferred.bytes += bytes as u64;
+    }
+}
+
+// How long to hold off so that `bytes` received over `elapsed` stays under `max_bandwidth` bytes/sec.
+fn overdraft(bytes: u64, max_bandwidth: u64, elapsed: Duration) -> Duration {
+    if max_bandwidth == 0 {
+        return Duration::from_secs(0);
+    }
+    let allowed_after = Duration::from_secs_f64(bytes as f64 / max_bandwidth as f64);
+    allowed_after.checked_sub(elapsed).unwrap_or_else(|| Duration::from_secs(0))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn overdraft_test() {
+        use std::time::Duration;
+        use crate::throttle::overdraft;
+
+        assert_eq!(overdraft(1000, 100, Duration::from_secs(4)), Duration::from_secs(6));
+        assert_eq!(overdraft(1000, 100, Duration::from_secs(12)), Duration::from_secs(0));
+        assert_eq!(overdraft(1000, 0, Duration::from_secs(0)), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn window_test() {
+        use std::time::Duration;
+        use crate::throttle::Window;
+
+        // 2024-06-01 00:00 JST, a Saturday.
+        let midnight = 1717167600;
+        let at = |hours: u64, minutes: u64| midnight + hours * 3600 + minutes * 60;
+        let night = "01:00-05:00".parse::<Window>().unwrap();
+        assert_eq!(night.until_open(at(0, 30)), Duration::from_secs(30 * 60));
+        assert_eq!(night.until_open(at(1, 0)), Duration::from_secs(0));
+        assert_eq!(night.until_open(at(4, 59)), Duration::from_secs(0));
+        assert_eq!(night.until_open(at(5, 0)), Duration::from_secs(20 * 3600));
+        assert_eq!(night.until_open(at(0, 30) + 15), Duration::from_secs(30 * 60 - 15));
+
+        let overnight = "22:00-06:00".parse::<Window>().unwrap();
+        assert_eq!(overnight.until_open(at(23, 0)), Duration::from_secs(0));
+        assert_eq!(overnight.until_open(at(3, 0)), Duration::from_secs(0));
+        assert_eq!(overnight.until_open(at(12, 0)), Duration::from_secs(10 * 3600));
+
+        assert!("01:00".parse::<Window>().is_err());
+        assert!("01:00-25:00".parse::<Window>().is_err());
+        assert!("03:00-03:00".parse::<Window>().is_err());
+    }
+
+    #[tokio::test]
+    async fn host_limits_test() {
+        use std::time::Duration;
+        use crate::throttle::{parse_bandwidth, HostLimits, Limits, Throttle};
+
+        let mirror = "mirror.example.com:jitter_ms=0,concurrency=1".parse::<HostLimits>().unwrap();
+        assert_eq!(
+            mirror.limits,
+            Limits {
+                jitter: Duration::from_millis(0),
+                max_bandwidth: None,
+                concurrency: Some(1),
+            }
+        );
+        assert!("mirror.example.com".parse::<HostLimits>().is_err());
+        assert!("mirror.example.com:burst=3".parse::<HostLimits>().is_err());
+        assert!("mirror.example.com:concurrency=0".parse::<HostLimits>().is_err());
+        assert!("mirror.example.com:max_bandwidth=0".parse::<HostLimits>().is_err());
+        assert!(parse_bandwidth("0").is_err() && parse_bandwidth("-1").is_err());
+        assert_eq!(parse_bandwidth("10000000"), Ok(10_000_000));
+
+        let throttle = Throttle::new(Duration::from_millis(0), None, None).with_hosts(vec![mirror]);
+        let slot = throttle.wait("https://mirror.example.com/ginkou.php").await.unwrap();
+        let second = tokio::time::timeout(Duration::from_millis(50), throttle.wait("https://mirror.example.com/x"));
+        assert!(second.await.is_err());
+        // Other hosts aren't held up by the mirror's limit.
+        throttle.wait("https://zengin.ajtw.net/ginkou.php").await.unwrap();
+        drop(slot);
+        throttle.wait("https://mirror.example.com/x").await.unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{charset, yucho, Bank, BankCode, BranchCode};
+
+// Longest receiver name a zengin transfer record holds, in half-width characters.
+pub const NAME_LENGTH: usize = 30;
+
+// 預金種目, with the code a transfer record stores it as.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    // 普通
+    Ordinary,
+    // 当座
+    Current,
+    // 貯蓄
+    Savings,
+    // その他
+    Other,
+}
+
+impl AccountType {
+    pub fn code(self) -> u8 {
+        match self {
+            AccountType::Ordinary => 1,
+            AccountType::Current => 2,
+            AccountType::Savings => 4,
+            AccountType::Other => 9,
+        }
+    }
+}
+
+impl FromStr for AccountType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ordinary" | "1" => Ok(AccountType::Ordinary),
+            "current" | "2" => Ok(AccountType::Current),
+            "savings" | "4" => Ok(AccountType::Savings),
+            "other" | "9" => Ok(AccountType::Other),
+            _ => Err(format!("unknown account type: {}", s)),
+        }
+    }
+}
+
+// A transfer destination as it was entered.
+#[derive(Debug, Clone)]
+pub struct Destination<'a> {
+    pub bank_code: &'a str,
+    pub branch_code: &'a str,
+    pub account_type: AccountType,
+    pub account: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Problem {
+    pub field: &'static str,
+    pub message: String,
+}
+
+// The destination as a transfer record would carry it, and whatever keeps it from being sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct Checked {
+    pub bank_code: String,
+    pub bank_name: Option<String>,
+    pub branch_code: String,
+    pub branch_name: Option<String>,
+    pub account_type: AccountType,
+    pub account: String,
+    pub name: String,
+    pub problems: Vec<Problem>,
+}
+
+impl Checked {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+// Checks the codes against the dataset and the account number and name against the format of a
+// zengin transfer record.
+pub fn check(banks: &[Bank], destination: &Destination) -> Checked {
+    let mut problems = Vec::new();
+    let mut problem = |field, message| problems.push(Problem { field, message });
+
+    // Codes are compared, and carried on, zero-padded; a malformed one is reported as given.
+    let bank_code = destination.bank_code.parse::<BankCode>();
+    let branch_code = destination.branch_code.parse::<BranchCode>();
+    let bank = bank_code.as_ref().ok().and_then(|code| banks.iter().find(|bank| bank.code == *code));
+    let branch = match (bank, &branch_code) {
+        (Some(bank), Ok(code)) => bank.branches.iter().find(|branch| branch.code == code.0),
+        _ => None,
+    };
+    match (&bank_code, bank) {
+        (Err(e), _) => problem("bank", e.clone()),
+        (Ok(code), None) => problem("bank", format!("no bank with code {}", code)),
+        _ => {}
+    }
+    match (&branch_code, bank, branch) {
+        (Err(e), _, _) => problem("branch", e.clone()),
+        (Ok(code), Some(bank), None) => problem("branch", format!("no branch with code {} in bank {}", code, bank.code)),
+        _ => {}
+    }
+    let bank_code = bank_code.map_or_else(|_| destination.bank_code.to_owned(), |code| code.0);
+    let branch_code = branch_code.map_or_else(|_| destination.branch_code.to_owned(), |code| code.0);
+
+    let digits = yucho::digits(destination.account);
+    let account = match digits {
+        Some(digits) if !digits.is_empty() && digits.len() <= 7 => format!("{:0>7}", digits),
+        _ => {
+            problem("account", format!("account number {:?} is not up to 7 digits", destination.account));
+            destination.account.to_owned()
+        }
+    };
+    if bank_code == yucho::BANK_CODE {
+        if let Err(crate::Error::InvalidYuchoNumber(reason)) = yucho::from_zengin(&branch_code, &account) {
+            problem("account", reason);
+        }
+    }
+
+    let name = charset::normalize(destination.name);
+    let disallowed = charset::check(&name)
+        .iter()
+        .map(|disallowed| disallowed.character)
+        .collect::<String>();
+    if name.is_empty() {
+        problem("name", "the receiver name is empty".to_owned());
+    } else if !disallowed.is_empty() {
+        problem("name", format!("characters not allowed in a receiver name: {}", disallowed));
+    }
+    if name.chars().count() > NAME_LENGTH {
+        problem("name", format!("the receiver name is longer than {} characters", NAME_LENGTH));
+    }
+
+    Checked {
+        bank_code,
+        bank_name: bank.map(|bank| bank.name.clone()),
+        branch_code,
+        branch_name: branch.map(|branch| branch.name.to_string()),
+        account_type: destination.account_type,
+        account,
+        name,
+        problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_test() {
+        use crate::transfer::{check, AccountType, Destination};
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "x1".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+        let banks = [bank];
+
+        let destination = Destination {
+            bank_code: "1",
+            branch_code: "001",
+            account_type: AccountType::Ordinary,
+            account: "12-345",
+            name: "たろう",
+        };
+        let checked = check(&banks, &destination);
+        assert!(checked.is_valid());
+        assert_eq!(checked.bank_code, "0001");
+        assert_eq!(checked.account, "0012345");
+        assert_eq!(checked.name, "ﾀﾛｳ");
+        assert_eq!(checked.branch_name.as_deref(), Some("東京営業部"));
+
+        let destination = Destination {
+            branch_code: "002",
+            account: "12345678",
+            name: "山田ﾀﾛｳ",
+            ..destination
+        };
+        let fields = check(&banks, &destination)
+            .problems
+            .iter()
+            .map(|problem| problem.field)
+            .collect::<Vec<&str>>();
+        assert_eq!(fields, vec!["branch", "account", "name"]);
+    }
+}
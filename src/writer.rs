@@ -0,0 +1,205 @@
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures::stream::{StreamExt, iter as siter};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+use crate::dedup::{self, Conflict, Policy};
+use crate::layout::Layout;
+use crate::marker::{clear_done, mark_done};
+use crate::plan::{self, Item};
+use crate::progress::ProgressObserver;
+use crate::retry::RetryQueue;
+use crate::{Bank, Error};
+
+#[derive(Debug)]
+pub struct WriteReport {
+    pub files: usize,
+    pub bytes: usize,
+    pub elapsed: Duration,
+}
+
+impl WriteReport {
+    fn bytes_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes as f64 / secs
+        } else {
+            self.bytes as f64
+        }
+    }
+}
+
+impl fmt::Display for WriteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "wrote {} files ({} bytes) in {:.2}s, {:.1} KiB/s",
+            self.files,
+            self.bytes,
+            self.elapsed.as_secs_f64(),
+            self.bytes_per_sec() / 1024.0,
+        )
+    }
+}
+
+pub async fn save_branch_files(banks: &[Bank], layout: &Layout, concurrency: usize) -> Result<WriteReport, Error> {
+    let started_at = Instant::now();
+    let results = siter(banks.iter())
+        .map(|bank| async move {
+            let bytes = bank.save_as_file(layout).await?;
+            mark_done(layout, bank).await?;
+            plan::record(layout, &Item::Branches { bank_code: bank.code.clone() }).await?;
+            Ok(bytes)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<Result<usize, Error>>>()
+        .await;
+    let mut report = WriteReport {
+        files: 0,
+        bytes: 0,
+        elapsed: Duration::default(),
+    };
+    for result in results {
+        report.bytes += result?;
+        report.files += 1;
+    }
+    report.elapsed = started_at.elapsed();
+    Ok(report)
+}
+
+type Written = Result<(usize, Vec<Conflict>), Error>;
+
+// Saves each bank a crawl fetches as soon as it is finished and records it in the plan, so a crawl
+// cut off halfway keeps what it got that far. Register it as the last `ProgressObserver`, after the
+// retry queue, so the failures of a bank are queued by the time it is saved, and `finish` it once the
+// banks are fetched. Banks some requests failed for are saved with the branches found but not marked
+// done, so resuming fetches them again.
+pub struct BankWriter {
+    // Where the plan is, and where the files go, which for a crawl is the staged output directory.
+    layout: Layout,
+    target: Layout,
+    policy: Policy,
+    queue: Arc<RetryQueue>,
+    slots: Arc<Semaphore>,
+    started_at: Instant,
+    tasks: Mutex<Vec<JoinHandle<Written>>>,
+}
+
+impl BankWriter {
+    pub fn new(layout: Layout, target: Layout, policy: Policy, queue: Arc<RetryQueue>, concurrency: usize) -> Self {
+        Self {
+            layout,
+            target,
+            policy,
+            queue,
+            slots: Arc::new(Semaphore::new(concurrency.max(1))),
+            started_at: Instant::now(),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Waits for the banks finished so far to be saved, with the branch conflicts found in them.
+    pub async fn finish(&self) -> Result<(WriteReport, Vec<Conflict>), Error> {
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(PoisonError::into_inner));
+        let mut report = WriteReport {
+            files: 0,
+            bytes: 0,
+            elapsed: Duration::default(),
+        };
+        let mut conflicts = Vec::new();
+        for task in tasks {
+            let (bytes, found) = task
+                .await
+                .map_err(|e| Error::SaveBankFileFailed(io::Error::other(e.to_string())))??;
+            report.bytes += bytes;
+            report.files += 1;
+            conflicts.extend(found);
+        }
+        report.elapsed = self.started_at.elapsed();
+        Ok((report, conflicts))
+    }
+}
+
+async fn write(mut bank: Bank, layout: Layout, target: Layout, policy: Policy, queue: Arc<RetryQueue>, slots: Arc<Semaphore>) -> Written {
+    let _slot = slots.acquire().await;
+    let conflicts = dedup::dedup_branches(&mut bank, policy)?;
+    let bytes = bank.save_as_file(&target).await?;
+    if queue.incomplete_banks().contains(&bank.code) {
+        // A marker left by an earlier crawl would skip the bank when resuming.
+        clear_done(&layout, &bank).await?;
+        clear_done(&target, &bank).await?;
+    } else {
+        mark_done(&target, &bank).await?;
+        plan::record(&layout, &Item::Branches { bank_code: bank.code.clone() }).await?;
+    }
+    Ok((bytes, conflicts))
+}
+
+impl ProgressObserver for BankWriter {
+    fn bank_finished(&self, bank: &Bank, _position: usize, _total: usize) {
+        let task = tokio::spawn(write(
+            bank.clone(),
+            self.layout.clone(),
+            self.target.clone(),
+            self.policy,
+            self.queue.clone(),
+            self.slots.clone(),
+        ));
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner).push(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn bank_writer_test() {
+        use std::fs;
+        use std::sync::Arc;
+        use crate::dedup::Policy;
+        use crate::layout::{Layout, DEFAULT_TEMPLATE};
+        use crate::marker::done_at;
+        use crate::plan::{self, Item, Plan};
+        use crate::progress::ProgressObserver;
+        use crate::retry::{FailedRequest, RetryQueue};
+        use crate::writer::BankWriter;
+        use crate::{Bank, BankCode, Branch};
+
+        let dir = std::env::temp_dir().join(format!("zngn-writer-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let layout = Layout::new(dir.clone(), DEFAULT_TEMPLATE.to_owned()).unwrap();
+        let target = plan::staged(&layout);
+        let bank = |code: &str| {
+            let mut bank = Bank::new(format!("銀行{}", code), "ｷﾞﾝｺｳ".to_owned(), code.to_owned(), String::new());
+            bank.branches = vec![
+                Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()),
+                Branch::new("本店".to_owned(), "ﾎﾝﾃﾝ".to_owned(), "001".to_owned()),
+            ];
+            bank
+        };
+        let mut plan = Plan::start(&layout, "あ".chars(), None).unwrap();
+        plan::stage(&layout, &[bank("0001"), bank("0005")], None).unwrap();
+        plan.listed(&layout, &[bank("0001"), bank("0005")], &Default::default()).await.unwrap();
+        let queue = Arc::new(RetryQueue::load(layout.retry_queue_file()).unwrap());
+        queue.push(FailedRequest::Branches { bank_code: BankCode("0005".to_owned()), search_key: 'あ' }).unwrap();
+
+        let writer = BankWriter::new(layout.clone(), target.clone(), Policy::KeepFirst, queue, 1);
+        writer.bank_finished(&bank("0001"), 0, 2);
+        writer.bank_finished(&bank("0005"), 1, 2);
+        let (report, conflicts) = writer.finish().await.unwrap();
+        assert_eq!(report.files, 2);
+        assert!(conflicts.is_empty());
+        // Saved where it was told, deduplicated, and logged as soon as it is, unless requests for it failed.
+        assert_eq!(crate::load_branch_file(&target, &bank("0001")).unwrap().branches.len(), 1);
+        assert!(target.branch_file(&bank("0005")).exists() && !layout.branch_file(&bank("0001")).exists());
+        assert!(done_at(&target, &bank("0001")).is_some() && done_at(&target, &bank("0005")).is_none());
+        let plan = Plan::load(&layout).unwrap().unwrap();
+        assert!(plan.is_done(&Item::Branches { bank_code: BankCode("0001".to_owned()) }));
+        assert_eq!(plan.pending_banks(), [&BankCode("0005".to_owned())]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
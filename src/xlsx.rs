@@ -0,0 +1,117 @@
+use std::io::Write;
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::{Bank, Error};
+
+const BANK_HEADERS: [&str; 6] = ["code", "name", "name_en", "phonetic", "aliases", "branches"];
+const BRANCH_HEADERS: [&str; 5] = ["bank_code", "bank_name", "code", "name", "phonetic"];
+
+// Headers in bold with a filter on them, kept in view while scrolling. Code columns are formatted as
+// text, so Excel neither drops their leading zeros nor those of codes typed in later.
+fn sheet<'a>(workbook: &'a mut Workbook, name: &str, headers: &[&str], code_columns: &[u16]) -> Result<&'a mut Worksheet, XlsxError> {
+    let bold = Format::new().set_bold();
+    let text = Format::new().set_num_format("@");
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(name)?;
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold)?;
+    }
+    for col in code_columns {
+        sheet.set_column_format(*col, &text)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(sheet)
+}
+
+fn workbook(banks: &[Bank]) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let text = Format::new().set_num_format("@");
+
+    let sheet_banks = sheet(&mut workbook, "banks", &BANK_HEADERS, &[0])?;
+    for (row, bank) in (1..).zip(banks) {
+        let aliases = bank
+            .aliases
+            .iter()
+            .map(|alias| alias.name.as_str())
+            .collect::<Vec<&str>>()
+            .join("|");
+        sheet_banks.write_string_with_format(row, 0, &bank.code.0, &text)?;
+        sheet_banks.write_string(row, 1, &bank.name)?;
+        sheet_banks.write_string(row, 2, bank.name_en.as_deref().unwrap_or_default())?;
+        sheet_banks.write_string(row, 3, &bank.phonetic)?;
+        sheet_banks.write_string(row, 4, aliases)?;
+        sheet_banks.write_number(row, 5, bank.branches.len() as f64)?;
+    }
+    sheet_banks.autofilter(0, 0, banks.len() as u32, BANK_HEADERS.len() as u16 - 1)?;
+    sheet_banks.autofit();
+
+    let sheet_branches = sheet(&mut workbook, "branches", &BRANCH_HEADERS, &[0, 2])?;
+    let mut row = 0;
+    for bank in banks {
+        for branch in &bank.branches {
+            row += 1;
+            sheet_branches.write_string_with_format(row, 0, &bank.code.0, &text)?;
+            sheet_branches.write_string(row, 1, &bank.name)?;
+            sheet_branches.write_string_with_format(row, 2, &*branch.code, &text)?;
+            sheet_branches.write_string(row, 3, &*branch.name)?;
+            sheet_branches.write_string(row, 4, &*branch.phonetic)?;
+        }
+    }
+    sheet_branches.autofilter(0, 0, row, BRANCH_HEADERS.len() as u16 - 1)?;
+    sheet_branches.autofit();
+
+    workbook.save_to_buffer()
+}
+
+// A workbook with a sheet of banks and a sheet of branches, one row each.
+pub fn write<W: Write>(banks: &[Bank], mut writer: W) -> Result<(), Error> {
+    let bytes = workbook(banks).map_err(Error::XlsxExportFailed)?;
+    writer.write_all(&bytes).map_err(Error::ExportFailed)?;
+    writer.flush().map_err(Error::ExportFailed)
+}
+
+// A workbook with a single sheet of `rows` under `headers`, for command results other than the dataset.
+// Every cell is text, codes included.
+pub fn write_rows<W: Write>(headers: &[String], rows: &[Vec<String>], mut writer: W) -> Result<(), Error> {
+    let rows_workbook = || -> Result<Vec<u8>, XlsxError> {
+        let mut workbook = Workbook::new();
+        let names = headers.iter().map(String::as_str).collect::<Vec<&str>>();
+        let columns = (0..headers.len() as u16).collect::<Vec<u16>>();
+        let sheet = sheet(&mut workbook, "results", &names, &columns)?;
+        for (row, cells) in (1..).zip(rows) {
+            for (col, cell) in (0..).zip(cells) {
+                sheet.write_string(row, col, cell)?;
+            }
+        }
+        if !headers.is_empty() {
+            sheet.autofilter(0, 0, rows.len() as u32, headers.len() as u16 - 1)?;
+        }
+        sheet.autofit();
+        workbook.save_to_buffer()
+    };
+    let bytes = rows_workbook().map_err(Error::XlsxExportFailed)?;
+    writer.write_all(&bytes).map_err(Error::ExportFailed)?;
+    writer.flush().map_err(Error::ExportFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn xlsx_export_test() {
+        use crate::xlsx::write;
+        use crate::{Bank, Branch};
+
+        let mut bank = Bank::new("みずほ銀行".to_owned(), "ﾐｽﾞﾎ".to_owned(), "0001".to_owned(), "0x001".to_owned());
+        bank.append_branch(Branch::new("東京営業部".to_owned(), "ﾄｳｷﾖｳ".to_owned(), "001".to_owned()));
+
+        let mut out = Vec::new();
+        write(&[bank], &mut out).unwrap();
+        // An xlsx file is a zip archive of XML parts, whose names are stored uncompressed.
+        assert_eq!(&out[..2], b"PK");
+        let has_part = |name: &[u8]| out.windows(name.len()).any(|window| window == name);
+        assert!(has_part(b"xl/worksheets/sheet1.xml"));
+        assert!(has_part(b"xl/worksheets/sheet2.xml"));
+        assert!(has_part(b"xl/sharedStrings.xml"));
+    }
+}
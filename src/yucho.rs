@@ -0,0 +1,157 @@
+use serde::Serialize;
+
+use crate::Error;
+
+// Japan Post Bank's code in the zengin system.
+pub const BANK_CODE: &str = "9900";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    // 通常貯金, 記号 starting with 1.
+    Ordinary,
+    // 振替口座, 記号 starting with 0.
+    Transfer,
+}
+
+impl Kind {
+    // 預金種目 of the account as other banks see it.
+    pub fn account_type(self) -> &'static str {
+        match self {
+            Kind::Ordinary => "普通",
+            Kind::Transfer => "当座",
+        }
+    }
+}
+
+// An account as it is written on a transfer to Japan Post Bank from another bank.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Zengin {
+    pub kind: Kind,
+    pub branch_code: String,
+    pub number: String,
+}
+
+// What a zengin destination tells about the passbook's 記号・番号. Only the first three digits of
+// the 記号 survive the conversion, so the 記号 itself can only be checked against, not rebuilt.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Yucho {
+    pub kind: Kind,
+    pub kigo_prefix: String,
+    pub bango: String,
+}
+
+impl Yucho {
+    pub fn matches(&self, kigo: &str) -> bool {
+        digits(kigo).is_some_and(|kigo| kigo.len() == 5 && kigo.starts_with(&self.kigo_prefix))
+    }
+}
+
+// Accepts full-width digits and ignores hyphens and spaces, as the numbers are often copied from forms.
+pub(crate) fn digits(s: &str) -> Option<String> {
+    s.chars()
+        .filter(|c| !matches!(c, '-' | '－' | ' ' | '　'))
+        .map(|c| match c {
+            '0'..='9' => Some(c),
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32),
+            _ => None,
+        })
+        .collect()
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidYuchoNumber(reason)
+}
+
+// 記号・番号 from a passbook into the zengin branch code and account number.
+pub fn to_zengin(kigo: &str, bango: &str) -> Result<Zengin, Error> {
+    let kigo = digits(kigo)
+        .filter(|kigo| kigo.len() == 5)
+        .ok_or_else(|| invalid(format!("記号 {:?} is not 5 digits", kigo)))?;
+    let bango = digits(bango)
+        .filter(|bango| !bango.is_empty())
+        .ok_or_else(|| invalid(format!("番号 {:?} is not a number", bango)))?;
+    let (kind, suffix) = match &kigo[..1] {
+        "1" => (Kind::Ordinary, '8'),
+        "0" => (Kind::Transfer, '9'),
+        _ => return Err(invalid(format!("記号 {} starts with neither 0 nor 1", kigo))),
+    };
+    let number = match kind {
+        // The last digit of an 8 digit 番号 is always 1 and is dropped.
+        Kind::Ordinary => {
+            if bango.len() > 8 {
+                return Err(invalid(format!("番号 {} is longer than 8 digits", bango)));
+            }
+            let bango = format!("{:0>8}", bango);
+            if !bango.ends_with('1') {
+                return Err(invalid(format!("番号 {} does not end with 1", bango)));
+            }
+            bango[..7].to_owned()
+        }
+        Kind::Transfer => {
+            if bango.len() > 6 {
+                return Err(invalid(format!("番号 {} is longer than 6 digits", bango)));
+            }
+            format!("{:0>7}", bango)
+        }
+    };
+    Ok(Zengin {
+        kind,
+        branch_code: format!("{}{}", &kigo[1..3], suffix),
+        number,
+    })
+}
+
+// The zengin branch code and account number back into what the passbook shows.
+pub fn from_zengin(branch_code: &str, number: &str) -> Result<Yucho, Error> {
+    let branch_code = digits(branch_code)
+        .filter(|code| code.len() == 3)
+        .ok_or_else(|| invalid(format!("branch code {:?} is not 3 digits", branch_code)))?;
+    let number = digits(number)
+        .filter(|number| !number.is_empty() && number.len() <= 7)
+        .ok_or_else(|| invalid(format!("account number {:?} is not up to 7 digits", number)))?;
+    let number = format!("{:0>7}", number);
+    match &branch_code[2..] {
+        "8" => Ok(Yucho {
+            kind: Kind::Ordinary,
+            kigo_prefix: format!("1{}", &branch_code[..2]),
+            bango: format!("{}1", number),
+        }),
+        "9" => {
+            if !number.starts_with('0') {
+                return Err(invalid(format!("account number {} is too long for a 振替口座", number)));
+            }
+            Ok(Yucho {
+                kind: Kind::Transfer,
+                kigo_prefix: format!("0{}", &branch_code[..2]),
+                bango: number.trim_start_matches('0').to_owned(),
+            })
+        }
+        _ => Err(invalid(format!("branch code {} is not a Japan Post Bank branch", branch_code))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn round_trip_test() {
+        use crate::yucho::{from_zengin, to_zengin, Kind};
+
+        let zengin = to_zengin("10180", "12345671").unwrap();
+        assert_eq!((zengin.kind, zengin.branch_code.as_str(), zengin.number.as_str()), (Kind::Ordinary, "018", "1234567"));
+        let yucho = from_zengin(&zengin.branch_code, &zengin.number).unwrap();
+        assert_eq!(yucho.bango, "12345671");
+        assert!(yucho.matches("10180"));
+        assert!(!yucho.matches("10280"));
+
+        let zengin = to_zengin("００１２０", "１２３４５").unwrap();
+        assert_eq!((zengin.kind, zengin.branch_code.as_str(), zengin.number.as_str()), (Kind::Transfer, "019", "0012345"));
+        let yucho = from_zengin("019", "12345").unwrap();
+        assert_eq!((yucho.kigo_prefix.as_str(), yucho.bango.as_str()), ("001", "12345"));
+        assert!(yucho.matches("00120"));
+
+        assert!(to_zengin("10180", "12345670").is_err());
+        assert!(to_zengin("20180", "12345671").is_err());
+        assert!(from_zengin("001", "1234567").is_err());
+    }
+}
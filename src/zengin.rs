@@ -0,0 +1,268 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::charset;
+use crate::export::{Exporter, Options};
+use crate::layout::Layout;
+use crate::{Bank, Error};
+
+// Width of the bank and branch name fields of a zengin transfer record, in half-width characters.
+pub const NAME_WIDTH: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    Anywhere,
+    Prefix,
+    Suffix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum When {
+    Always,
+    // Only while the name is wider than its field.
+    TooLong,
+}
+
+// Rewrites part of a reading, e.g. drops a trailing ｼﾃﾝ or shortens ｼﾝﾖｳｷﾝｺ to ｼﾝｷﾝ. Patterns are
+// matched against the reading in the zengin character set, so they may be written in any kana.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default = "anywhere")]
+    pub position: Position,
+    #[serde(default = "too_long")]
+    pub when: When,
+}
+
+fn anywhere() -> Position {
+    Position::Anywhere
+}
+
+fn too_long() -> When {
+    When::TooLong
+}
+
+impl Rule {
+    fn new(pattern: &str, replacement: &str, position: Position, when: When) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            replacement: replacement.to_owned(),
+            position,
+            when,
+        }
+    }
+
+    fn apply(&self, text: &str) -> Option<String> {
+        let pattern = charset::normalize(&self.pattern);
+        let replacement = charset::normalize(&self.replacement);
+        if pattern.is_empty() {
+            return None;
+        }
+        match self.position {
+            Position::Anywhere if text.contains(&pattern) => Some(text.replacen(&pattern, &replacement, 1)),
+            Position::Prefix => text.strip_prefix(&pattern).map(|rest| format!("{}{}", replacement, rest)),
+            Position::Suffix => text.strip_suffix(&pattern).map(|rest| format!("{}{}", rest, replacement)),
+            Position::Anywhere => None,
+        }
+    }
+}
+
+// Abbreviations applied, in order, to bank and branch readings before they are cut to width. Banks
+// differ in the ones they accept, so an abbreviations.json in the output directory replaces either
+// list, e.g. {"branch": [{"pattern": "ｼﾃﾝ", "position": "suffix", "when": "always"}]}.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Abbreviations {
+    pub bank: Vec<Rule>,
+    pub branch: Vec<Rule>,
+}
+
+impl Default for Abbreviations {
+    fn default() -> Self {
+        Self {
+            bank: vec![
+                Rule::new("ｼﾝﾖｳｷﾝｺ", "ｼﾝｷﾝ", Position::Anywhere, When::TooLong),
+                Rule::new("ｼﾝﾖｳｸﾐｱｲ", "ｼﾝｸﾐ", Position::Anywhere, When::TooLong),
+                Rule::new("ﾉｳｷﾞﾖｳｷﾖｳﾄﾞｳｸﾐｱｲ", "ﾉｳｷﾖｳ", Position::Anywhere, When::TooLong),
+                Rule::new("ｷﾞﾝｺｳ", "", Position::Suffix, When::TooLong),
+            ],
+            branch: vec![
+                Rule::new("ｼﾃﾝ", "", Position::Suffix, When::Always),
+                Rule::new("ｴｲｷﾞﾖｳﾌﾞ", "ｴｲ", Position::Anywhere, When::TooLong),
+                Rule::new("ｼﾕﾂﾁﾖｳｼﾖ", "ｼﾕﾂ", Position::Anywhere, When::TooLong),
+            ],
+        }
+    }
+}
+
+impl Abbreviations {
+    pub fn load(layout: &Layout) -> Result<Self, Error> {
+        let path = layout.abbreviations_file();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::read(&path)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::file(path))?;
+        serde_json::from_reader(file).map_err(Error::invalid(path))
+    }
+}
+
+// A name as a fixed-width kana field takes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fitted {
+    pub text: String,
+    // Whether a rule for names too long was applied.
+    pub abbreviated: bool,
+    // Whether characters were cut off to fit.
+    pub truncated: bool,
+}
+
+// Writes a reading in the zengin character set, applies the rules and cuts it to `width` characters.
+// Rules for names too long are applied one at a time until the name fits. A voiced or semi-voiced
+// mark is a character of its own, so a kana whose mark would be cut off goes too, rather than leaving
+// the name with a different sound. Characters outside the set are dropped.
+pub fn fit(phonetic: &str, width: usize, rules: &[Rule]) -> Fitted {
+    let mut text = charset::normalize(phonetic)
+        .chars()
+        .filter(|c| charset::is_allowed(*c))
+        .collect::<String>();
+    for rule in rules.iter().filter(|rule| rule.when == When::Always) {
+        text = rule.apply(&text).unwrap_or(text);
+    }
+    let mut abbreviated = false;
+    for rule in rules.iter().filter(|rule| rule.when == When::TooLong) {
+        if text.trim_end().chars().count() <= width {
+            break;
+        }
+        if let Some(shorter) = rule.apply(&text) {
+            text = shorter;
+            abbreviated = true;
+        }
+    }
+    let chars = text.trim_end().chars().collect::<Vec<char>>();
+    if chars.len() <= width {
+        return Fitted {
+            text: chars.into_iter().collect(),
+            abbreviated,
+            truncated: false,
+        };
+    }
+    let mut end = width;
+    if matches!(chars[end], 'ﾞ' | 'ﾟ') {
+        end -= 1;
+    }
+    Fitted {
+        text: chars[..end].iter().collect::<String>().trim_end().to_owned(),
+        abbreviated,
+        truncated: true,
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ZenginRow<'a> {
+    bank_code: &'a str,
+    bank_name_kana: &'a str,
+    branch_code: &'a str,
+    branch_name_kana: &'a str,
+}
+
+fn write_rows(banks: &[Bank], rules: &Abbreviations, writer: &mut dyn Write) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for bank in banks {
+        let bank_name = fit(&bank.phonetic, NAME_WIDTH, &rules.bank);
+        for branch in &bank.branches {
+            writer.serialize(ZenginRow {
+                bank_code: &bank.code.0,
+                bank_name_kana: &bank_name.text,
+                branch_code: &branch.code,
+                branch_name_kana: &fit(&branch.phonetic, NAME_WIDTH, &rules.branch).text,
+            })?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Bank and branch names as transfer files need them: one csv row per branch with both codes and both
+// readings fitted to the 15 character kana fields of a zengin record, abbreviated as the options say.
+#[derive(Debug, Clone, Copy)]
+pub struct ZenginExporter;
+
+impl Exporter for ZenginExporter {
+    fn name(&self) -> &'static str {
+        "zengin"
+    }
+
+    fn write_banks(&self, banks: &[Bank], options: &Options, writer: &mut dyn Write) -> Result<(), Error> {
+        write_rows(banks, &options.abbreviations, writer).map_err(|e| Error::ExportFailed(e.into()))
+    }
+
+    fn write_records(&self, _: &[Value], _: &mut dyn Write) -> Result<(), Error> {
+        Err(Error::ExportFailed(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "zengin exports only take the dataset",
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn fit_test() {
+        use crate::zengin::{fit, Fitted, NAME_WIDTH};
+
+        let fitted = |text: &str, truncated| Fitted {
+            text: text.to_owned(),
+            abbreviated: false,
+            truncated,
+        };
+        assert_eq!(fit("ﾐｽﾞﾎ", NAME_WIDTH, &[]), fitted("ﾐｽﾞﾎ", false));
+        assert_eq!(fit("みずほ", NAME_WIDTH, &[]), fitted("ﾐｽﾞﾎ", false));
+        assert_eq!(fit("ｷｬﾋﾟﾀﾙ", NAME_WIDTH, &[]), fitted("ｷﾔﾋﾟﾀﾙ", false));
+        assert_eq!(fit("ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲｷﾞﾝｺｳ", NAME_WIDTH, &[]), fitted("ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲｷﾞ", true));
+        assert_eq!(fit("ｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾞ", NAME_WIDTH, &[]), fitted("ｱｲｳｴｵｶｷｸｹｺｻｼｽｾ", true));
+        assert_eq!(fit("ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ ｷﾞﾝｺｳ", NAME_WIDTH, &[]), fitted("ﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ", true));
+        assert_eq!(fit("ﾄｳｷﾖｳ本店", NAME_WIDTH, &[]), fitted("ﾄｳｷﾖｳ", false));
+    }
+
+    #[test]
+    fn abbreviations_test() {
+        use crate::zengin::{fit, Abbreviations, Position, Rule, When, NAME_WIDTH};
+
+        let rules = Abbreviations::default();
+        let bank = |phonetic: &str| fit(phonetic, NAME_WIDTH, &rules.bank);
+        let branch = |phonetic: &str| fit(phonetic, NAME_WIDTH, &rules.branch);
+        assert_eq!(branch("ｳﾒﾀﾞｼﾃﾝ").text, "ｳﾒﾀﾞ");
+        assert!(!branch("ｳﾒﾀﾞｼﾃﾝ").abbreviated);
+        assert_eq!(branch("ｼﾃﾝ").text, "");
+        assert_eq!(branch("ﾄｳｷﾖｳｴｲｷﾞﾖｳﾌﾞ").text, "ﾄｳｷﾖｳｴｲｷﾞﾖｳﾌﾞ");
+        let long = branch("ﾏﾙﾉｳﾁﾁﾕｳｵｳｴｲｷﾞﾖｳﾌﾞ");
+        assert_eq!((long.text.as_str(), long.abbreviated, long.truncated), ("ﾏﾙﾉｳﾁﾁﾕｳｵｳｴｲ", true, false));
+        assert_eq!(bank("ｱｻﾋｶﾜｼﾝﾖｳｷﾝｺ").text, "ｱｻﾋｶﾜｼﾝﾖｳｷﾝｺ");
+        assert_eq!(bank("ﾄｳｷﾖｳﾋｶﾞｼｼﾝﾖｳｷﾝｺ").text, "ﾄｳｷﾖｳﾋｶﾞｼｼﾝｷﾝ");
+
+        let rules = serde_json::from_str::<Abbreviations>(
+            r#"{"branch": [{"pattern": "しゅっちょうじょ", "replacement": "(ｼﾕﾂ)", "position": "suffix", "when": "always"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(rules.bank, Abbreviations::default().bank);
+        assert_eq!(rules.branch, vec![Rule {
+            pattern: "しゅっちょうじょ".to_owned(),
+            replacement: "(ｼﾕﾂ)".to_owned(),
+            position: Position::Suffix,
+            when: When::Always,
+        }]);
+        assert_eq!(fit("ｴｷﾏｴｼﾕﾂﾁﾖｳｼﾞﾖ", NAME_WIDTH, &rules.branch).text, "ｴｷﾏｴ(ｼﾕﾂ)");
+    }
+}
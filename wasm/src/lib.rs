@@ -0,0 +1,158 @@
+// Searches the index `zngn site --search` writes next to the pages, from JavaScript in the browser.
+// There is no wasm-bindgen glue: strings cross the boundary as UTF-8 bytes in linear memory, which
+// search.js copies in through `alloc` and reads back through `output_ptr` and `output_len`.
+
+use std::cell::RefCell;
+
+#[path = "../../src/matcher.rs"]
+mod matcher;
+
+use matcher::matches;
+
+#[derive(Debug, Default, PartialEq)]
+struct Branch {
+    code: String,
+    name: String,
+    phonetic: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Bank {
+    code: String,
+    name: String,
+    phonetic: String,
+    // Former names and their readings.
+    aliases: Vec<String>,
+    branches: Vec<Branch>,
+}
+
+// The index is one record per line, fields separated by tabs: a bank as
+// `B <code> <name> <phonetic> <aliases separated by |>`, followed by its branches as
+// `R <code> <name> <phonetic>`.
+fn parse(index: &str) -> Vec<Bank> {
+    let mut banks: Vec<Bank> = Vec::new();
+    for line in index.lines() {
+        let fields = line.split('\t').collect::<Vec<&str>>();
+        match fields.as_slice() {
+            ["B", code, name, phonetic, aliases] => banks.push(Bank {
+                code: code.to_string(),
+                name: name.to_string(),
+                phonetic: phonetic.to_string(),
+                aliases: aliases.split('|').filter(|alias| !alias.is_empty()).map(str::to_owned).collect(),
+                branches: Vec::new(),
+            }),
+            ["R", code, name, phonetic] => {
+                if let Some(bank) = banks.last_mut() {
+                    bank.branches.push(Branch {
+                        code: code.to_string(),
+                        name: name.to_string(),
+                        phonetic: phonetic.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    banks
+}
+
+// Up to `limit` hits in the order of `zngn search`, one per line:
+// `<bank code> <branch code, empty for a bank> <name> <phonetic> <bank name>`.
+fn search(banks: &[Bank], query: &str, limit: usize) -> (usize, String) {
+    let hits = banks.iter().flat_map(|bank| {
+        let bank_hit = matches(&bank.code, &bank.name, &bank.phonetic, query)
+            || bank.aliases.iter().any(|alias| alias.contains(query));
+        let branch_hits = bank
+            .branches
+            .iter()
+            .filter(move |branch| matches(&branch.code, &branch.name, &branch.phonetic, query))
+            .map(move |branch| (bank, Some(branch)));
+        bank_hit.then_some((bank, None)).into_iter().chain(branch_hits)
+    });
+    let mut found = 0;
+    let mut output = String::new();
+    for (bank, branch) in hits.take(limit) {
+        let (code, name, phonetic) = match branch {
+            Some(branch) => (branch.code.as_str(), branch.name.as_str(), branch.phonetic.as_str()),
+            None => ("", bank.name.as_str(), bank.phonetic.as_str()),
+        };
+        output.push_str(&[bank.code.as_str(), code, name, phonetic, bank.name.as_str()].join("\t"));
+        output.push('\n');
+        found += 1;
+    }
+    (found, output)
+}
+
+thread_local! {
+    static BANKS: RefCell<Vec<Bank>> = const { RefCell::new(Vec::new()) };
+    static OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+unsafe fn text<'a>(ptr: *const u8, len: usize) -> &'a str {
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap_or_default()
+}
+
+#[no_mangle]
+pub extern "C" fn alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// # Safety
+/// `ptr` must come from `alloc(len)` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dealloc(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// # Safety
+/// `ptr` must point to `len` initialized bytes, e.g. written after `alloc(len)`.
+#[no_mangle]
+pub unsafe extern "C" fn load(ptr: *const u8, len: usize) -> usize {
+    let banks = parse(text(ptr, len));
+    let count = banks.len();
+    BANKS.with(|loaded| *loaded.borrow_mut() = banks);
+    count
+}
+
+/// # Safety
+/// `ptr` must point to `len` initialized bytes of UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn search_index(ptr: *const u8, len: usize, limit: usize) -> usize {
+    let query = text(ptr, len);
+    let (found, output) = BANKS.with(|banks| search(&banks.borrow(), query, limit));
+    OUTPUT.with(|kept| *kept.borrow_mut() = output);
+    found
+}
+
+#[no_mangle]
+pub extern "C" fn output_ptr() -> *const u8 {
+    OUTPUT.with(|output| output.borrow().as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn output_len() -> usize {
+    OUTPUT.with(|output| output.borrow().len())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn search_test() {
+        use crate::{parse, search};
+
+        let index = "B\t0001\tみずほ銀行\tﾐｽﾞﾎ\t第一勧業銀行|ﾀﾞｲｲﾁｶﾝｷﾞﾖｳ\n\
+                     R\t001\t東京営業部\tﾄｳｷﾖｳ\n\
+                     R\t472\t梅田支店\tｳﾒﾀﾞ\n\
+                     B\t0005\t三菱ＵＦＪ銀行\tﾐﾂﾋﾞｼﾕ-ｴﾌｼﾞｴｲ\t\n";
+        let banks = parse(index);
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks[0].branches.len(), 2);
+        assert_eq!(search(&banks, "梅田", 10), (1, "0001\t472\t梅田支店\tｳﾒﾀﾞ\tみずほ銀行\n".to_owned()));
+        assert_eq!(search(&banks, "第一勧業", 10).0, 1);
+        assert_eq!(search(&banks, "000", 10).0, 2);
+        assert_eq!(search(&banks, "銀行", 1).0, 1);
+    }
+}